@@ -18,8 +18,73 @@ fn main() -> std::io::Result<()> {
     for &shell in clap_complete::Shell::value_variants() {
         clap_complete::generate_to(shell, &mut Cli::command(), BIN_NAME, compl_dir)?;
     }
+    complete_publish_topics(compl_dir)?;
 
     clap_mangen::generate_to(Cli::command(), man_dir)?;
 
     Ok(())
 }
+
+/// Complete the topic of the publish subcommand with the topics known to the broker via `mqttui complete-topics`.
+///
+/// clap only generates static completions so the generated scripts are extended.
+fn complete_publish_topics(compl_dir: &std::path::Path) -> std::io::Result<()> {
+    use std::io::Write;
+
+    const BASH: &str = r#"
+# Complete the topic of publish with the topics known to the broker
+_mqttui_publish_topics() {
+    _mqttui "$@"
+    local cur="${COMP_WORDS[COMP_CWORD]}" word publish="" positionals=0 i
+    [[ $cur == -* ]] && return 0
+    for ((i = 1; i < COMP_CWORD; i++)); do
+        word="${COMP_WORDS[i]}"
+        if [[ -z $publish ]]; then
+            [[ $word == publish || $word == pub || $word == p ]] && publish=1
+            continue
+        fi
+        case "$word" in
+            -f | --file | --payload-file | -q | --qos | --timeout | -b | --broker | -u | --username | --password | --password-command | -i | --client-id | --client-cert | --client-key | --ca-cert | --tls-server-name) ((i++)) ;;
+            -*) ;;
+            *) ((positionals++)) ;;
+        esac
+    done
+    if [[ -n $publish && $positionals -eq 0 ]]; then
+        local IFS=$'\n'
+        COMPREPLY=($(compgen -W "$(mqttui complete-topics "$cur" 2>/dev/null)" -- "$cur"))
+    fi
+}
+complete -F _mqttui_publish_topics -o nosort -o bashdefault -o default mqttui
+"#;
+    const FISH: &str = r#"
+# Complete the topic of publish with the topics known to the broker
+complete -c mqttui -n "__fish_mqttui_using_subcommand publish; or __fish_mqttui_using_subcommand pub; or __fish_mqttui_using_subcommand p" -f -a "(mqttui complete-topics (commandline -ct) 2>/dev/null)"
+"#;
+    const ZSH_FUNCTION: &str = r#"
+(( $+functions[_mqttui_publish_topics] )) ||
+_mqttui_publish_topics() {
+    local -a topics
+    topics=(${(f)"$(mqttui complete-topics "$PREFIX" 2>/dev/null)"})
+    compadd -a topics
+}
+
+"#;
+    const ZSH_TOPIC: &str = "'::topic -- Topic to publish to:'";
+    const ZSH_FOOTER: &str = "if [ \"$funcstack[1]\" = \"_mqttui\" ]; then";
+
+    for (file, addition) in [("mqttui.bash", BASH), ("mqttui.fish", FISH)] {
+        std::fs::OpenOptions::new()
+            .append(true)
+            .open(compl_dir.join(file))?
+            .write_all(addition.as_bytes())?;
+    }
+
+    let zsh_path = compl_dir.join("_mqttui");
+    let zsh = std::fs::read_to_string(&zsh_path)?
+        .replace(
+            ZSH_TOPIC,
+            "'::topic -- Topic to publish to:_mqttui_publish_topics'",
+        )
+        .replacen(ZSH_FOOTER, &format!("{ZSH_FUNCTION}{ZSH_FOOTER}"), 1);
+    std::fs::write(zsh_path, zsh)
+}
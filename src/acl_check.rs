@@ -0,0 +1,190 @@
+use std::path::Path;
+use std::time::Duration;
+
+use anyhow::Context;
+use rumqttc::v5::mqttbytes::v5::{Packet, PubAck, PubAckReason, SubAck, SubscribeReasonCode};
+use rumqttc::v5::mqttbytes::QoS;
+use rumqttc::v5::{AsyncClient, ConnectionError, Event, EventLoop, StateError};
+use rumqttc::Outgoing;
+
+/// Read the topics to check, one per line. Empty lines are ignored.
+pub fn read_topics(path: &Path) -> anyhow::Result<Vec<String>> {
+    let content = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+    Ok(parse_topics(&content))
+}
+
+fn parse_topics(content: &str) -> Vec<String> {
+    content
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(ToOwned::to_owned)
+        .collect()
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum Access {
+    Allowed,
+    /// Contains the reason code of the broker
+    Denied(String),
+    /// Wildcards are only valid for subscribing
+    Skipped,
+    Timeout,
+}
+
+impl Access {
+    fn subscribe(reason: SubscribeReasonCode) -> Self {
+        match reason {
+            SubscribeReasonCode::Success(_) => Self::Allowed,
+            reason => Self::Denied(format!("{reason:?}")),
+        }
+    }
+
+    fn publish(reason: PubAckReason) -> Self {
+        match reason {
+            PubAckReason::Success | PubAckReason::NoMatchingSubscribers => Self::Allowed,
+            reason => Self::Denied(format!("{reason:?}")),
+        }
+    }
+
+    const fn is_denied(&self) -> bool {
+        matches!(self, Self::Denied(_))
+    }
+}
+
+impl std::fmt::Display for Access {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Allowed => f.write_str("allowed"),
+            Self::Denied(reason) => write!(f, "denied ({reason})"),
+            Self::Skipped => f.write_str("skipped"),
+            Self::Timeout => f.write_str("timeout"),
+        }
+    }
+}
+
+/// Wait for the acknowledgement of the next subscribe or publish sent to the broker
+async fn acknowledgement(eventloop: &mut EventLoop) -> Access {
+    let mut pkid = None;
+    loop {
+        let event = match eventloop.poll().await {
+            Ok(event) => event,
+            // rumqttc handles failure reason codes as errors of the connection
+            Err(ConnectionError::MqttState(StateError::SubFail { reason })) => {
+                return Access::subscribe(reason);
+            }
+            Err(ConnectionError::MqttState(StateError::PubAckFail { reason })) => {
+                return Access::publish(reason);
+            }
+            Err(err) => {
+                // rumqttc reconnects
+                eprintln!("Connection Error: {err}");
+                tokio::time::sleep(Duration::from_millis(25)).await;
+                continue;
+            }
+        };
+        match event {
+            Event::Outgoing(Outgoing::Subscribe(id) | Outgoing::Publish(id)) => pkid = Some(id),
+            Event::Incoming(Packet::SubAck(SubAck {
+                pkid: id,
+                return_codes,
+                ..
+            })) if pkid == Some(id) => {
+                return return_codes
+                    .first()
+                    .copied()
+                    .map_or(Access::Timeout, Access::subscribe);
+            }
+            Event::Incoming(Packet::PubAck(PubAck {
+                pkid: id, reason, ..
+            })) if pkid == Some(id) => {
+                return Access::publish(reason);
+            }
+            _ => {}
+        }
+    }
+}
+
+async fn check(
+    client: &AsyncClient,
+    eventloop: &mut EventLoop,
+    topic: &str,
+    timeout: Duration,
+) -> anyhow::Result<(Access, Access)> {
+    client.subscribe(topic, QoS::AtLeastOnce).await?;
+    let subscribe = tokio::time::timeout(timeout, acknowledgement(eventloop))
+        .await
+        .unwrap_or(Access::Timeout);
+    if subscribe == Access::Allowed {
+        // Dont receive the own publish and everything else on the topic
+        client.unsubscribe(topic).await?;
+    }
+
+    if topic.contains(['+', '#']) {
+        return Ok((subscribe, Access::Skipped));
+    }
+    client
+        .publish(topic, QoS::AtLeastOnce, false, Vec::new())
+        .await?;
+    let publish = tokio::time::timeout(timeout, acknowledgement(eventloop))
+        .await
+        .unwrap_or(Access::Timeout);
+    Ok((subscribe, publish))
+}
+
+/// Subscribe and publish to each topic and print whether the broker allowed it
+pub async fn acl_check(
+    client: AsyncClient,
+    mut eventloop: EventLoop,
+    topics: Vec<String>,
+    timeout: Duration,
+) -> anyhow::Result<()> {
+    let mut denied = 0_usize;
+    for topic in &topics {
+        let (subscribe, publish) = check(&client, &mut eventloop, topic, timeout).await?;
+        denied += usize::from(subscribe.is_denied()) + usize::from(publish.is_denied());
+        println!(
+            "{topic:50} Subscribe: {:30} Publish: {publish}",
+            subscribe.to_string()
+        );
+    }
+
+    // Send the disconnect to the broker
+    client.disconnect().await?;
+    let _ = tokio::time::timeout(Duration::from_secs(1), async {
+        while let Ok(event) = eventloop.poll().await {
+            if matches!(event, Event::Outgoing(Outgoing::Disconnect)) {
+                break;
+            }
+        }
+    })
+    .await;
+
+    println!(
+        "Checked {} topics, {denied} operations were denied",
+        topics.len()
+    );
+    Ok(())
+}
+
+#[test]
+fn topics_and_access_work() {
+    assert_eq!(
+        parse_topics("foo/bar\n\n  sensors/#  \n"),
+        ["foo/bar", "sensors/#"]
+    );
+    assert_eq!(
+        Access::subscribe(SubscribeReasonCode::Success(QoS::AtMostOnce)),
+        Access::Allowed
+    );
+    assert_eq!(
+        Access::subscribe(SubscribeReasonCode::NotAuthorized).to_string(),
+        "denied (NotAuthorized)"
+    );
+    assert_eq!(
+        Access::publish(PubAckReason::NoMatchingSubscribers),
+        Access::Allowed
+    );
+    assert!(Access::publish(PubAckReason::NotAuthorized).is_denied());
+}
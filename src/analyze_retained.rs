@@ -0,0 +1,128 @@
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use rumqttc::{Client, Connection};
+
+use crate::diff::{collect_retained, Retained};
+use crate::{exit, format};
+
+/// Retained topics sharing the same first levels
+#[derive(Debug, PartialEq, Eq)]
+struct Group {
+    /// Like `foo/bar/#` or the topic itself when it has no more levels than grouped by
+    prefix: String,
+    topics: usize,
+    bytes: usize,
+    /// Topic with the biggest payload within the group
+    largest: String,
+}
+
+/// Group the topics by their first levels, the groups with the most bytes first
+fn groups(retained: &Retained, depth: usize) -> Vec<Group> {
+    let mut groups = BTreeMap::<String, Group>::new();
+    for (topic, payload) in retained {
+        let levels = topic.split('/').collect::<Vec<_>>();
+        let prefix = if levels.len() > depth {
+            format!("{}/#", levels[..depth].join("/"))
+        } else {
+            topic.clone()
+        };
+        let group = groups.entry(prefix.clone()).or_insert_with(|| Group {
+            prefix,
+            topics: 0,
+            bytes: 0,
+            largest: topic.clone(),
+        });
+        group.topics += 1;
+        group.bytes += payload.len();
+        if payload.len() > retained[&group.largest].len() {
+            group.largest.clone_from(topic);
+        }
+    }
+    let mut groups = groups.into_values().collect::<Vec<_>>();
+    groups.sort_by(|a, b| b.bytes.cmp(&a.bytes).then_with(|| a.prefix.cmp(&b.prefix)));
+    groups
+}
+
+/// Collect the retained messages until none arrived for the settle duration and report the prefixes with the most bytes
+pub fn analyze_retained(
+    client: &Client,
+    connection: Connection,
+    settle: Duration,
+    depth: usize,
+    top: usize,
+) -> anyhow::Result<()> {
+    let retained = collect_retained(client, connection, settle);
+    if retained.is_empty() {
+        return Err(exit::fail(
+            exit::Code::NoMatch,
+            "No retained message was received",
+        ));
+    }
+    let total_bytes = retained.values().map(Vec::len).sum::<usize>();
+    let groups = groups(&retained, depth);
+
+    println!(
+        "{:50} {:>7} {:>10} {:>6}  Largest topic",
+        "Prefix", "Topics", "Size", "Share"
+    );
+    for group in groups.iter().take(top) {
+        #[allow(clippy::cast_precision_loss)]
+        let share = group.bytes as f64 * 100.0 / total_bytes.max(1) as f64;
+        println!(
+            "{:50} {:>7} {:>10} {share:>5.1}%  {}",
+            group.prefix,
+            group.topics,
+            format::bytes(group.bytes as u64),
+            group.largest,
+        );
+    }
+    if groups.len() > top {
+        println!("… and {} more prefixes", groups.len() - top);
+    }
+    exit::chatter!(
+        "{} retained topics with {} in {} prefixes",
+        retained.len(),
+        format::bytes(total_bytes as u64),
+        groups.len()
+    );
+    Ok(())
+}
+
+#[test]
+fn groups_works() {
+    let retained = Retained::from([
+        ("a".to_owned(), b"1".to_vec()),
+        ("a/b".to_owned(), b"12".to_vec()),
+        ("a/c/d".to_owned(), b"12345".to_vec()),
+        ("z/y".to_owned(), b"123".to_vec()),
+    ]);
+    assert_eq!(
+        groups(&retained, 1),
+        [
+            Group {
+                prefix: "a/#".to_owned(),
+                topics: 2,
+                bytes: 7,
+                largest: "a/c/d".to_owned(),
+            },
+            Group {
+                prefix: "z/#".to_owned(),
+                topics: 1,
+                bytes: 3,
+                largest: "z/y".to_owned(),
+            },
+            Group {
+                prefix: "a".to_owned(),
+                topics: 1,
+                bytes: 1,
+                largest: "a".to_owned(),
+            },
+        ]
+    );
+    let prefixes = groups(&retained, 2)
+        .into_iter()
+        .map(|group| group.prefix)
+        .collect::<Vec<_>>();
+    assert_eq!(prefixes, ["a/c/#", "z/y", "a/b", "a"]);
+}
@@ -0,0 +1,322 @@
+use std::cmp::Ordering;
+use std::io::BufRead;
+use std::path::Path;
+use std::str::FromStr;
+use std::thread::sleep;
+use std::time::{Duration, Instant};
+
+use anyhow::Context;
+use rumqttc::{Client, Connection, RecvTimeoutError};
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::mqtt::topic_filter;
+use crate::payload::{JsonSelector, Payload};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Operator {
+    Equal,
+    NotEqual,
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+}
+
+impl Operator {
+    /// Longer operators first as `<` is the start of `<=`
+    const ALL: [(&'static str, Self); 6] = [
+        ("==", Self::Equal),
+        ("!=", Self::NotEqual),
+        ("<=", Self::LessOrEqual),
+        (">=", Self::GreaterOrEqual),
+        ("<", Self::Less),
+        (">", Self::Greater),
+    ];
+
+    fn compare(self, actual: &Value, expected: &Value) -> bool {
+        let ordering = match (actual, expected) {
+            (Value::Number(actual), Value::Number(expected)) => actual
+                .as_f64()
+                .zip(expected.as_f64())
+                .and_then(|(actual, expected)| actual.partial_cmp(&expected)),
+            (Value::String(actual), Value::String(expected)) => Some(actual.cmp(expected)),
+            (actual, expected) => (actual == expected).then_some(Ordering::Equal),
+        };
+        match self {
+            Self::Equal => ordering == Some(Ordering::Equal),
+            Self::NotEqual => ordering != Some(Ordering::Equal),
+            Self::Less => ordering == Some(Ordering::Less),
+            Self::LessOrEqual => matches!(ordering, Some(Ordering::Less | Ordering::Equal)),
+            Self::Greater => ordering == Some(Ordering::Greater),
+            Self::GreaterOrEqual => {
+                matches!(ordering, Some(Ordering::Greater | Ordering::Equal))
+            }
+        }
+    }
+}
+
+/// A jq-like path into a JSON payload optionally compared with a JSON value like `.state == "on"`
+#[derive(Debug, PartialEq, Eq)]
+pub struct Expectation {
+    path: Vec<JsonSelector>,
+    comparison: Option<(Operator, Value)>,
+}
+
+impl Expectation {
+    /// Without a comparison the value has to exist and must not be `null` or `false`
+    pub fn matches(&self, json: &Value) -> bool {
+        let Some(actual) = JsonSelector::get_json(json, &self.path) else {
+            return false;
+        };
+        match &self.comparison {
+            None => !matches!(actual, Value::Null | Value::Bool(false)),
+            Some((operator, expected)) => operator.compare(actual, expected),
+        }
+    }
+}
+
+fn parse_path(mut rest: &str) -> anyhow::Result<(Vec<JsonSelector>, &str)> {
+    let mut path = Vec::new();
+    loop {
+        if let Some(after) = rest.strip_prefix('[') {
+            let (inner, after) = after
+                .split_once(']')
+                .context("Missing ] after [ in the path")?;
+            let selector = if inner.starts_with('"') {
+                JsonSelector::ObjectKey(
+                    serde_json::from_str(inner).context("Invalid quoted key in the path")?,
+                )
+            } else {
+                JsonSelector::ArrayIndex(inner.trim().parse().with_context(|| {
+                    format!("Array index has to be a positive number, not {inner}")
+                })?)
+            };
+            path.push(selector);
+            rest = after;
+        } else if let Some(after) = rest.strip_prefix('.') {
+            rest = after;
+        } else {
+            let end = rest
+                .find(|char: char| !(char.is_alphanumeric() || char == '_' || char == '-'))
+                .unwrap_or(rest.len());
+            if end == 0 {
+                return Ok((path, rest));
+            }
+            path.push(JsonSelector::ObjectKey(rest[..end].to_owned()));
+            rest = &rest[end..];
+        }
+    }
+}
+
+impl FromStr for Expectation {
+    type Err = anyhow::Error;
+
+    fn from_str(expression: &str) -> anyhow::Result<Self> {
+        let rest = expression
+            .trim()
+            .strip_prefix('.')
+            .context("The expression has to start with a path like .state")?;
+        let (path, rest) = parse_path(rest)?;
+        let rest = rest.trim_start();
+        if rest.is_empty() {
+            return Ok(Self {
+                path,
+                comparison: None,
+            });
+        }
+        let (operator, value) = Operator::ALL
+            .into_iter()
+            .find_map(|(text, operator)| rest.strip_prefix(text).map(|value| (operator, value)))
+            .with_context(|| {
+                format!("Expected a comparison like == != < <= > >= but got {rest}")
+            })?;
+        let value = serde_json::from_str(value.trim()).with_context(|| {
+            format!("The value to compare with has to be JSON like \"on\" or 42, not {value}")
+        })?;
+        Ok(Self {
+            path,
+            comparison: Some((operator, value)),
+        })
+    }
+}
+
+/// Wait for a message on the topic which matches the expectation
+pub struct Assertion {
+    pub topic: String,
+    /// The expression as given for the output
+    expect_json: Option<String>,
+    expectation: Option<Expectation>,
+}
+
+impl Assertion {
+    pub fn new(topic: String, expect_json: Option<String>) -> anyhow::Result<Self> {
+        let expectation = expect_json
+            .as_deref()
+            .map(Expectation::from_str)
+            .transpose()
+            .with_context(|| format!("Invalid expectation for {topic}"))?;
+        Ok(Self {
+            topic,
+            expect_json,
+            expectation,
+        })
+    }
+
+    /// Any message on the topic matches when there is no expectation
+    fn matches(&self, payload: &[u8]) -> bool {
+        self.expectation.as_ref().map_or(true, |expectation| {
+            serde_json::from_slice(payload).is_ok_and(|json| expectation.matches(&json))
+        })
+    }
+}
+
+impl std::fmt::Display for Assertion {
+    fn fmt(&self, fmt: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            fmt,
+            "{:50} {}",
+            self.topic,
+            self.expect_json.as_deref().unwrap_or("any message")
+        )
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct FileEntry {
+    topic: String,
+    expect_json: Option<String>,
+}
+
+/// Read newline-delimited JSON with one assertion per line
+pub fn read_file(path: &Path) -> anyhow::Result<Vec<Assertion>> {
+    let file =
+        std::fs::File::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+    let mut assertions = Vec::new();
+    for (index, line) in std::io::BufReader::new(file).lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let line_number = index.saturating_add(1);
+        let entry = serde_json::from_str::<FileEntry>(&line)
+            .with_context(|| format!("Failed to parse line {line_number}"))?;
+        let assertion = Assertion::new(entry.topic, entry.expect_json)
+            .with_context(|| format!("Invalid assertion on line {line_number}"))?;
+        assertions.push(assertion);
+    }
+    anyhow::ensure!(
+        !assertions.is_empty(),
+        "{} contains no assertions",
+        path.display()
+    );
+    Ok(assertions)
+}
+
+/// Wait until every assertion matched a message and fail when they did not within the timeout
+pub fn assert(
+    client: &Client,
+    mut connection: Connection,
+    assertions: Vec<Assertion>,
+    timeout: Duration,
+) -> anyhow::Result<()> {
+    let total = assertions.len();
+    // The last payload not matching is shown when the assertion fails
+    let mut pending = assertions
+        .into_iter()
+        .map(|assertion| (assertion, None))
+        .collect::<Vec<(Assertion, Option<Vec<u8>>)>>();
+    let start = Instant::now();
+    loop {
+        let remaining = timeout.saturating_sub(start.elapsed());
+        match connection.recv_timeout(remaining) {
+            Ok(Ok(rumqttc::Event::Incoming(rumqttc::Packet::Publish(publish)))) => {
+                if publish.dup {
+                    continue;
+                }
+                pending.retain_mut(|(assertion, last)| {
+                    if !topic_filter::matches(&assertion.topic, &publish.topic) {
+                        return true;
+                    }
+                    if assertion.matches(&publish.payload) {
+                        println!("Passed {assertion}");
+                        return false;
+                    }
+                    *last = Some(publish.payload.to_vec());
+                    true
+                });
+                if pending.is_empty() {
+                    client.disconnect()?;
+                }
+            }
+            Ok(Ok(rumqttc::Event::Outgoing(rumqttc::Outgoing::Disconnect)))
+            | Err(RecvTimeoutError::Disconnected) => break,
+            Ok(Ok(_)) => {}
+            Ok(Err(err)) => {
+                eprintln!("Connection Error: {err}");
+                sleep(Duration::from_millis(25));
+            }
+            Err(RecvTimeoutError::Timeout) => {
+                _ = client.disconnect();
+                break;
+            }
+        }
+    }
+
+    for (assertion, last) in &pending {
+        let last = last.as_ref().map_or_else(
+            || "no message received".to_owned(),
+            |payload| format!("last payload: {}", Payload::unlimited(payload.clone())),
+        );
+        println!("Failed {assertion}, {last}");
+    }
+    anyhow::ensure!(
+        pending.is_empty(),
+        "{} of {total} assertions did not pass within {} seconds",
+        pending.len(),
+        timeout.as_secs_f32()
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+fn check(expression: &str, json: &str) -> bool {
+    let expectation = expression.parse::<Expectation>().unwrap();
+    expectation.matches(&serde_json::from_str(json).unwrap())
+}
+
+#[test]
+fn parse_expressions() {
+    let expectation = r#".state.list[1]["with space"] >= 2.5"#.parse::<Expectation>().unwrap();
+    assert_eq!(
+        expectation,
+        Expectation {
+            path: vec![
+                JsonSelector::ObjectKey("state".to_owned()),
+                JsonSelector::ObjectKey("list".to_owned()),
+                JsonSelector::ArrayIndex(1),
+                JsonSelector::ObjectKey("with space".to_owned()),
+            ],
+            comparison: Some((Operator::GreaterOrEqual, serde_json::json!(2.5))),
+        }
+    );
+    assert!("state == 1".parse::<Expectation>().is_err());
+    assert!(".state = 1".parse::<Expectation>().is_err());
+    assert!(".state == on".parse::<Expectation>().is_err());
+    assert!(".list[x]".parse::<Expectation>().is_err());
+}
+
+#[test]
+fn expressions_match() {
+    assert!(check(r#".state == "on""#, r#"{"state": "on"}"#));
+    assert!(!check(r#".state == "on""#, r#"{"state": "off"}"#));
+    assert!(!check(r#".state == "on""#, "{}"));
+    assert!(check(r#".state != "on""#, r#"{"state": "off"}"#));
+    assert!(check(".battery >= 20", r#"{"battery": 20.0}"#));
+    assert!(check(".battery < 20", r#"{"battery": 5}"#));
+    assert!(!check(".battery < 20", r#"{"battery": "5"}"#));
+    assert!(check(".list[0].ok", r#"{"list": [{"ok": 1}]}"#));
+    assert!(!check(".list[0].ok", r#"{"list": [{"ok": false}]}"#));
+    assert!(check(". == 42", "42"));
+}
@@ -0,0 +1,70 @@
+use std::thread::{self, sleep};
+use std::time::Duration;
+
+use rumqttc::{Client, Connection};
+
+use crate::mqtt::Time;
+
+/// Forward all messages received on the source connection to the destination.
+///
+/// Messages already below the prefix are not forwarded again to prevent loops when both brokers are the same.
+pub fn bridge(
+    source: Connection,
+    destination: &Client,
+    mut destination_connection: Connection,
+    prefix: &str,
+    verbose: bool,
+) {
+    thread::Builder::new()
+        .name("bridge destination".to_owned())
+        .spawn(move || {
+            for notification in destination_connection.iter() {
+                match notification {
+                    Ok(rumqttc::Event::Outgoing(rumqttc::Outgoing::Disconnect)) => break,
+                    Ok(_) => {}
+                    Err(err) => {
+                        eprintln!("Destination Connection Error: {err}");
+                        sleep(Duration::from_millis(25));
+                    }
+                }
+            }
+        })
+        .expect("should be able to spawn a thread");
+
+    forward(source, destination, prefix, verbose);
+}
+
+fn forward(mut source: Connection, destination: &Client, prefix: &str, verbose: bool) {
+    for notification in source.iter() {
+        match notification {
+            Ok(rumqttc::Event::Outgoing(rumqttc::Outgoing::Disconnect)) => break,
+            Ok(rumqttc::Event::Incoming(rumqttc::Packet::Publish(publish))) => {
+                if publish.dup || (!prefix.is_empty() && publish.topic.starts_with(prefix)) {
+                    continue;
+                }
+                let topic = format!("{prefix}{}", publish.topic);
+                if verbose {
+                    let time = Time::new_now(publish.retain);
+                    let size = publish.payload.len();
+                    eprintln!(
+                        "{time:12} {:50} -> {topic:50} Payload({size:>3})",
+                        publish.topic
+                    );
+                }
+                if let Err(err) = destination.publish(
+                    topic,
+                    publish.qos,
+                    publish.retain,
+                    publish.payload.to_vec(),
+                ) {
+                    eprintln!("Failed to forward: {err}");
+                }
+            }
+            Ok(_) => {}
+            Err(err) => {
+                eprintln!("Source Connection Error: {err}");
+                sleep(Duration::from_millis(25));
+            }
+        }
+    }
+}
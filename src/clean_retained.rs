@@ -1,50 +1,168 @@
-use std::thread::sleep;
+use std::io::IsTerminal;
+use std::num::NonZeroU32;
+use std::thread::{self, sleep, JoinHandle};
 use std::time::Duration;
 
 use rumqttc::{Client, Connection, QoS};
 
+use crate::exit::chatter;
 use crate::format;
 use crate::payload::Payload;
 
-pub fn clean_retained(client: &Client, mut connection: Connection, dry_run: bool) {
+/// Protect the broker and the retained messages from wiping huge topic trees by accident
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Safeguards {
+    /// Maximum amount of topics cleaned per second
+    pub rate_limit: Option<NonZeroU32>,
+    /// Cleaning more topics requires an explicit confirmation
+    pub max_topics: Option<usize>,
+}
+
+impl Safeguards {
+    /// Time between cleaning two topics
+    pub fn interval(&self) -> Option<Duration> {
+        self.rate_limit
+            .map(|rate_limit| Duration::from_secs(1) / rate_limit.get())
+    }
+
+    pub fn exceeds_max_topics(&self, amount: usize) -> bool {
+        self.max_topics
+            .is_some_and(|max_topics| amount > max_topics)
+    }
+}
+
+/// Ask on the terminal whether the topics exceeding the threshold should be cleaned anyway
+fn confirm(amount: usize, max_topics: usize) -> anyhow::Result<()> {
+    anyhow::ensure!(
+        std::io::stdin().is_terminal(),
+        "Cleaning {amount} topics exceeds --max-topics {max_topics}. Raise the limit to clean them."
+    );
+    eprint!("Clean {amount} topics which is more than --max-topics {max_topics}? Type the amount of topics to confirm: ");
+    let mut answer = String::new();
+    std::io::stdin().read_line(&mut answer)?;
+    anyhow::ensure!(
+        answer.trim() == amount.to_string(),
+        "Aborted, no topic was cleaned"
+    );
+    Ok(())
+}
+
+/// The request channel is bounded so clean while the connection is progressing.
+/// The connection also has to progress while waiting for the confirmation to not miss its keep alive.
+fn spawn_cleaner(
+    client: Client,
+    topics: Vec<String>,
+    safeguards: Safeguards,
+) -> JoinHandle<anyhow::Result<()>> {
+    thread::Builder::new()
+        .name("clean retained".to_owned())
+        .spawn(move || {
+            if let Some(max_topics) = safeguards.max_topics {
+                if safeguards.exceeds_max_topics(topics.len()) {
+                    if let Err(err) = confirm(topics.len(), max_topics) {
+                        client.disconnect()?;
+                        return Err(err);
+                    }
+                }
+            }
+            let interval = safeguards.interval();
+            for topic in topics {
+                if client.publish(topic, QoS::ExactlyOnce, true, []).is_err() {
+                    break;
+                }
+                if let Some(interval) = interval {
+                    sleep(interval);
+                }
+            }
+            Ok(())
+        })
+        .expect("should be able to spawn a thread")
+}
+
+/// Collect the retained topics until there are no more and clean them afterwards
+pub fn clean_retained(
+    client: &Client,
+    mut connection: Connection,
+    dry_run: bool,
+    safeguards: Safeguards,
+) -> anyhow::Result<()> {
+    let mut topics = Vec::new();
     let mut amount: usize = 0;
+    let mut collecting = true;
+    // Cleaning is done once the broker completed every publish
+    let mut outstanding: usize = 0;
+    let mut cleaner = None;
     for notification in connection.iter() {
         match notification {
             Ok(rumqttc::Event::Outgoing(rumqttc::Outgoing::Disconnect)) => break,
-            Ok(rumqttc::Event::Outgoing(rumqttc::Outgoing::PingReq)) => {
-                client.disconnect().unwrap();
-            }
-            Ok(rumqttc::Event::Incoming(rumqttc::Packet::Publish(publish))) => {
+            Ok(rumqttc::Event::Incoming(rumqttc::Packet::Publish(publish))) if collecting => {
                 if publish.payload.is_empty() {
-                    // That's probably myself cleaning up
+                    // Nothing to clean
                     continue;
                 }
-                if !publish.retain {
-                    client.disconnect().unwrap();
+                if publish.retain {
+                    let topic = &publish.topic;
+                    {
+                        let qos = format::qos(publish.qos);
+                        let size = publish.payload.len();
+                        let payload = Payload::unlimited(publish.payload.to_vec());
+                        println!("QoS:{qos:11} {topic:50} Payload({size:>3}): {payload}");
+                    }
+                    topics.push(publish.topic);
                     continue;
                 }
-                let topic = &publish.topic;
-                {
-                    let qos = format::qos(publish.qos);
-                    let size = publish.payload.len();
-                    let payload = Payload::unlimited(publish.payload.into());
-                    println!("QoS:{qos:11} {topic:50} Payload({size:>3}): {payload}");
-                }
-                amount += 1;
-                if !dry_run {
-                    client.publish(topic, QoS::ExactlyOnce, true, []).unwrap();
+            }
+            Ok(rumqttc::Event::Outgoing(rumqttc::Outgoing::PingReq)) if collecting => {}
+            Ok(rumqttc::Event::Incoming(rumqttc::Packet::PubComp(_))) if !collecting => {
+                outstanding = outstanding.saturating_sub(1);
+                if outstanding == 0 {
+                    client.disconnect()?;
                 }
+                continue;
             }
-            Ok(_) => {}
+            Ok(_) => continue,
             Err(err) => {
-                eprintln!("Connection Error: {err}");
+                chatter!("Connection Error: {err}");
                 sleep(Duration::from_millis(25));
+                continue;
             }
         }
+
+        // A non retained message or no message until the keep alive ends the collecting
+        collecting = false;
+        amount = topics.len();
+        if dry_run || amount == 0 {
+            client.disconnect()?;
+        } else {
+            outstanding = amount;
+            cleaner = Some(spawn_cleaner(
+                client.clone(),
+                std::mem::take(&mut topics),
+                safeguards,
+            ));
+        }
+    }
+    // Publishing fails without the connection so the cleaner can not be stuck on a full request channel
+    drop(connection);
+    if let Some(cleaner) = cleaner {
+        cleaner.join().expect("clean retained thread panicked")?;
     }
     if dry_run {
         println!("Dry run: would have cleaned {amount} topics");
     } else {
         println!("Cleaned {amount} topics");
     }
+    Ok(())
+}
+
+#[test]
+fn safeguards_work() {
+    let safeguards = Safeguards {
+        rate_limit: NonZeroU32::new(4),
+        max_topics: Some(100),
+    };
+    assert_eq!(safeguards.interval(), Some(Duration::from_millis(250)));
+    assert!(!safeguards.exceeds_max_topics(100));
+    assert!(safeguards.exceeds_max_topics(101));
+    assert!(!Safeguards::default().exceeds_max_topics(usize::MAX));
 }
@@ -1,10 +1,66 @@
 use anyhow::Context;
-use clap::{Args, Parser, Subcommand, ValueHint};
+use clap::{Args, Parser, Subcommand, ValueEnum, ValueHint};
 use url::Url;
 
 #[allow(clippy::doc_markdown)]
 #[derive(Debug, Subcommand)]
 pub enum Subcommands {
+    /// Forward messages from the broker to another broker.
+    ///
+    /// Subscribes to the topics on the broker given via --broker and publishes every received message to the broker given via --to.
+    /// QoS and the retain flag of the received messages are kept.
+    /// Messages already below the prefix are not forwarded again to prevent loops when both brokers are the same.
+    #[command(visible_alias = "b")]
+    Bridge {
+        /// Topics to forward
+        #[arg(
+            env = "MQTTUI_TOPIC",
+            value_hint = ValueHint::Other,
+            value_parser = parse_topic_filter,
+            default_value = "#",
+        )]
+        topic: Vec<String>,
+
+        /// URL of the broker to forward the messages to.
+        ///
+        /// Uses the same format and connection options as --broker.
+        #[arg(
+            long,
+            value_hint = ValueHint::Url,
+            value_name = "URL",
+        )]
+        to: Broker,
+
+        /// Prefix the topics on the destination like 'mirrored/'
+        #[arg(long, value_hint = ValueHint::Other, default_value = "")]
+        prefix: String,
+
+        /// Username to access the destination broker instead of --username
+        #[arg(
+            long,
+            env = "MQTTUI_TO_USERNAME",
+            value_hint = ValueHint::Username,
+            value_name = "STRING",
+            requires = "to_password",
+        )]
+        to_username: Option<String>,
+
+        /// Password to access the destination broker instead of --password
+        #[arg(
+            long,
+            env = "MQTTUI_TO_PASSWORD",
+            value_hint = ValueHint::Other,
+            value_name = "STRING",
+            hide_env_values = true,
+            requires = "to_username",
+        )]
+        to_password: Option<String>,
+
+        /// Log every forwarded message to stderr
+        #[arg(short, long)]
+        verbose: bool,
+    },
+
     /// Clean retained messages from the broker.
     ///
     /// This works by subscribing to the topic and waiting for messages with the retained flag.
@@ -15,7 +71,7 @@ pub enum Subcommands {
         /// Topic which gets cleaned.
         ///
         /// Supports filters like 'foo/bar/#'.
-        #[arg(value_hint = ValueHint::Other)]
+        #[arg(value_hint = ValueHint::Other, value_parser = parse_topic_filter)]
         topic: String,
 
         /// When there is no message received for the given time the operation is considered done
@@ -30,6 +86,223 @@ pub enum Subcommands {
         /// Dont clean topics, only log them
         #[arg(long)]
         dry_run: bool,
+
+        /// Clean at most the given amount of topics per second to not overwhelm the broker
+        #[arg(
+            long,
+            env = "MQTTUI_CLEAN_RATE_LIMIT",
+            value_hint = ValueHint::Other,
+            value_name = "TOPICS_PER_SECOND",
+        )]
+        rate_limit: Option<std::num::NonZeroU32>,
+
+        /// Ask for an explicit confirmation before cleaning more than the given amount of topics.
+        ///
+        /// The topics are collected first and cleaned afterwards.
+        /// Without a terminal to confirm on the clean fails instead.
+        #[arg(
+            long,
+            env = "MQTTUI_CLEAN_MAX_TOPICS",
+            value_hint = ValueHint::Other,
+            value_name = "AMOUNT",
+        )]
+        max_topics: Option<usize>,
+    },
+
+    /// Report the amount and size of retained messages grouped by their topic prefix.
+    ///
+    /// Collects the retained messages until no further retained message arrives within the settle time.
+    /// The prefixes with the most bytes are listed first together with their largest topic.
+    /// Helps to find stale retained data to remove with clean-retained.
+    AnalyzeRetained {
+        /// Topics to analyze
+        #[arg(
+            env = "MQTTUI_TOPIC",
+            value_hint = ValueHint::Other,
+            value_parser = parse_topic_filter,
+            default_value = "#",
+        )]
+        topic: Vec<String>,
+
+        /// When there is no retained message received for the given time the collection is considered done
+        #[arg(
+            long,
+            value_hint = ValueHint::Other,
+            value_name = "SECONDS",
+            default_value_t = 3.0,
+        )]
+        settle: f32,
+
+        /// Amount of topic levels the prefixes are grouped by.
+        ///
+        /// With 2 `home/kitchen/temp` is counted for `home/kitchen/#`.
+        #[arg(
+            long,
+            value_hint = ValueHint::Other,
+            value_name = "LEVELS",
+            default_value = "1",
+        )]
+        depth: std::num::NonZeroUsize,
+
+        /// Amount of prefixes to list
+        #[arg(
+            long,
+            value_hint = ValueHint::Other,
+            value_name = "AMOUNT",
+            default_value_t = 20,
+        )]
+        top: usize,
+    },
+
+    /// Move retained messages from one topic prefix to another.
+    ///
+    /// This works by subscribing to everything below the source prefix and waiting for messages with the retained flag.
+    /// Each of them is published retained again with the source prefix replaced by the destination prefix.
+    /// With --delete-source the original topic is cleaned afterwards.
+    /// Ends on the first non retained message or when the timeout is reached.
+    ///
+    /// `mqttui move sensors/living-room sensors/kitchen` moves `sensors/living-room/temp` to `sensors/kitchen/temp`.
+    #[command(visible_alias = "mv")]
+    Move {
+        /// Topic prefix of the retained messages to move
+        #[arg(value_hint = ValueHint::Other)]
+        from: String,
+
+        /// Topic prefix to move the retained messages to
+        #[arg(value_hint = ValueHint::Other)]
+        to: String,
+
+        /// When there is no message received for the given time the operation is considered done
+        #[arg(
+            long,
+            value_hint = ValueHint::Other,
+            value_name = "SECONDS",
+            default_value_t = 5.0,
+        )]
+        timeout: f32,
+
+        /// Clean the original topics after they were copied
+        #[arg(long)]
+        delete_source: bool,
+
+        /// Dont publish anything, only log what would be moved
+        #[arg(long)]
+        dry_run: bool,
+    },
+
+    /// Compare the retained messages of the broker with another broker.
+    ///
+    /// Collects the retained messages of both brokers until no further retained message arrives within the timeout.
+    /// Then prints the topics only existing on one of them and the topics with different payloads.
+    /// When there are differences the exit code indicates a failure.
+    Diff {
+        /// Topics to compare
+        #[arg(
+            env = "MQTTUI_TOPIC",
+            value_hint = ValueHint::Other,
+            value_parser = parse_topic_filter,
+            default_value = "#",
+        )]
+        topic: Vec<String>,
+
+        /// URL of the broker to compare with.
+        ///
+        /// Uses the same format and connection options as --broker.
+        #[arg(
+            long,
+            value_hint = ValueHint::Url,
+            value_name = "URL",
+        )]
+        other: Broker,
+
+        /// Username to access the other broker instead of --username
+        #[arg(
+            long,
+            env = "MQTTUI_OTHER_USERNAME",
+            value_hint = ValueHint::Username,
+            value_name = "STRING",
+            requires = "other_password",
+        )]
+        other_username: Option<String>,
+
+        /// Password to access the other broker instead of --password
+        #[arg(
+            long,
+            env = "MQTTUI_OTHER_PASSWORD",
+            value_hint = ValueHint::Other,
+            value_name = "STRING",
+            hide_env_values = true,
+            requires = "other_username",
+        )]
+        other_password: Option<String>,
+
+        /// When there is no retained message received for the given time the collection is considered done
+        #[arg(
+            long,
+            value_hint = ValueHint::Other,
+            value_name = "SECONDS",
+            default_value_t = 5.0,
+        )]
+        timeout: f32,
+    },
+
+    /// Save the retained messages of the broker to a JSON file.
+    ///
+    /// Collects the retained messages until no further retained message arrives within the settle time.
+    /// The file contains an object with the topics as keys.
+    /// Payloads are stored as text when they are valid UTF-8 and as `{"base64": "…"}` otherwise.
+    /// Useful for backups and for assertions about the state of the broker in CI.
+    Snapshot {
+        /// Topics to save
+        #[arg(
+            env = "MQTTUI_TOPIC",
+            value_hint = ValueHint::Other,
+            value_parser = parse_topic_filter,
+            default_value = "#",
+        )]
+        topic: Vec<String>,
+
+        /// When there is no retained message received for the given time the collection is considered done
+        #[arg(
+            long,
+            value_hint = ValueHint::Other,
+            value_name = "SECONDS",
+            default_value_t = 3.0,
+        )]
+        settle: f32,
+
+        /// File to write the snapshot to. Prints to stdout when not given.
+        #[arg(long, value_hint = ValueHint::FilePath, value_name = "FILE")]
+        out: Option<std::path::PathBuf>,
+    },
+
+    /// Publish the retained messages of a snapshot file back to the broker.
+    ///
+    /// Every payload of a file created with the snapshot subcommand is published retained.
+    /// Prints the acknowledgements as newline-delimited JSON like publish --file.
+    Restore {
+        /// Snapshot file created with the snapshot subcommand
+        #[arg(value_hint = ValueHint::FilePath, value_name = "FILE")]
+        file: std::path::PathBuf,
+
+        /// Restore the topics below the given topic.
+        ///
+        /// `--prefix backup` restores `foo/bar` as `backup/foo/bar`.
+        #[arg(long, value_hint = ValueHint::Other, value_name = "TOPIC")]
+        prefix: Option<String>,
+
+        /// Fail when the publishes are not acknowledged by the broker within the given time
+        #[arg(
+            long,
+            value_hint = ValueHint::Other,
+            value_name = "SECONDS",
+            default_value_t = 5.0,
+        )]
+        timeout: f32,
+
+        /// Dont publish anything, only log what would be restored
+        #[arg(long)]
+        dry_run: bool,
     },
 
     /// Log values from subscribed topics to stdout
@@ -39,6 +312,7 @@ pub enum Subcommands {
         #[arg(
             env = "MQTTUI_TOPIC",
             value_hint = ValueHint::Other,
+            value_parser = parse_topic_filter,
             default_value = "#",
         )]
         topic: Vec<String>,
@@ -47,74 +321,430 @@ pub enum Subcommands {
         #[arg(short, long)]
         json: bool,
 
-        /// Show full MQTT communication
-        #[arg(short, long)]
-        verbose: bool,
+        /// How the time a message was received at is printed.
+        ///
+        /// Retained messages have no receive time and are still printed as RETAINED (null in JSON).
+        #[arg(
+            long,
+            env = "MQTTUI_TIMESTAMP",
+            value_enum,
+            value_name = "FORMAT",
+            default_value = "local"
+        )]
+        timestamp: Timestamp,
+
+        #[command(flatten)]
+        decoding: DecodingArgs,
+
+        /// Disconnect and exit after the given amount of messages were printed
+        #[arg(long, short = 'n', value_hint = ValueHint::Other, value_name = "N")]
+        count: Option<std::num::NonZeroUsize>,
+
+        /// Disconnect and exit after the given time.
+        ///
+        /// Combined with `--count` whatever is reached first ends the log.
+        #[arg(long, value_hint = ValueHint::Other, value_name = "SECONDS")]
+        duration: Option<f32>,
+
+        /// Show full MQTT communication
+        #[arg(short, long)]
+        verbose: bool,
+    },
+
+    /// Wait for the first message on the given topic(s) and return its payload to stdout.
+    ///
+    /// Returns exactly one payload of the first received message on the given topic(s).
+    /// The topic of the received message is printed to stderr.
+    /// This means that you can handle stdout and stderr separately.
+    ///
+    /// This can be helpful for scripting to get the current temperature reading and pipe it to somewhere else:
+    ///
+    /// `echo "The temperature is $(mqttui read-one room/temp)"`
+    ///
+    /// The output is the exact payload in its binary form.
+    /// This might be valid ASCII / Unicode but could also be something not intended to be displayed on a terminal.
+    /// For a human readable format use `--pretty` or `mqttui log`.
+    ///
+    /// When more than one message is awaited via `--count` or `--per-topic` all of them are printed to stdout as a JSON array.
+    #[command(visible_alias = "r", visible_alias = "read")]
+    ReadOne {
+        /// Topics to watch
+        #[arg(
+            env = "MQTTUI_TOPIC",
+            value_hint = ValueHint::Other,
+            value_parser = parse_topic_filter,
+            default_value = "#",
+        )]
+        topic: Vec<String>,
+
+        /// Do not return on a retained message on connection, wait for another message to arrive
+        #[arg(long, short = 'r')]
+        ignore_retained: bool,
+
+        /// Parse the payload and print it in a human readable pretty form.
+        ///
+        /// This might not be useful for piping the data.
+        #[arg(short, long)]
+        pretty: bool,
+
+        /// Wait for the given amount of messages before returning
+        #[arg(
+            long,
+            short = 'n',
+            value_hint = ValueHint::Other,
+            value_name = "N",
+            default_value = "1",
+        )]
+        count: std::num::NonZeroUsize,
+
+        /// Return once every given topic received one message.
+        ///
+        /// Only the first message of each topic is returned.
+        /// Requires topics without wildcards like '+' or '#' as it is unknown which topics would be expected otherwise.
+        #[arg(long, conflicts_with = "count")]
+        per_topic: bool,
+    },
+
+    /// Wait for messages matching expectations and fail when they do not arrive within the timeout.
+    ///
+    /// Useful as a step of integration tests in CI, for example for device firmware.
+    /// Expectations are jq-like paths into JSON payloads optionally compared with a JSON value like `.state == "on"` or `.battery >= 20`.
+    /// Without a comparison the value has to exist and must not be `null` or `false`.
+    /// Retained messages count as well.
+    ///
+    /// `mqttui assert --topic devices/lamp/state --expect-json '.state == "on"'`
+    Assert {
+        /// Topic to wait for a matching message on
+        #[arg(
+            long,
+            value_hint = ValueHint::Other,
+            value_parser = parse_topic_filter,
+            required_unless_present = "file",
+        )]
+        topic: Option<String>,
+
+        /// Expectation on the JSON payload like `.state == "on"`.
+        ///
+        /// Any message on the topic passes when not given.
+        #[arg(long, requires = "topic", value_hint = ValueHint::Other, value_name = "EXPRESSION")]
+        expect_json: Option<String>,
+
+        /// Read multiple assertions from newline-delimited JSON which all have to pass.
+        ///
+        /// One assertion per line like `{"topic": "devices/lamp/state", "expect_json": ".state == \"on\""}`.
+        #[arg(
+            long,
+            conflicts_with = "topic",
+            value_hint = ValueHint::FilePath,
+            value_name = "FILE",
+        )]
+        file: Option<std::path::PathBuf>,
+
+        /// Fail when not every assertion passed within the given time
+        #[arg(
+            long,
+            value_hint = ValueHint::Other,
+            value_name = "SECONDS",
+            default_value_t = 10.0,
+        )]
+        timeout: f32,
+    },
+
+    /// Publish messages of virtual devices continuously as described by a scenario file.
+    ///
+    /// Useful to demo dashboards or load test rule engines without real hardware.
+    /// The JSON file contains devices with a topic, an interval in seconds and a payload template.
+    /// `{name}` in the topic and payload is replaced by the next value of the generator with that name.
+    /// Generators are `{"random_walk": {"start": 21, "step": 0.2, "min": 15, "max": 30}}`, `{"random": {"min": 0, "max": 100}}` and `{"cycle": ["on", "off"]}`.
+    /// Numbers have one decimal unless `decimals` is given.
+    /// Devices can be created multiple times with `instances` and `{instance}` is replaced by their number.
+    /// Optionally `retain` and `qos` can be set per device.
+    ///
+    /// `{"devices": [{"topic": "sim/room-{instance}/temperature", "interval": 5, "instances": 3, "payload": "{\"temperature\": {temp}}", "values": {"temp": {"random_walk": {"start": 21, "step": 0.2, "min": 15, "max": 30}}}}]}`
+    Simulate {
+        /// Scenario file describing the devices
+        #[arg(value_hint = ValueHint::FilePath, value_name = "FILE")]
+        file: std::path::PathBuf,
+
+        /// Print every published message
+        #[arg(short, long)]
+        verbose: bool,
+    },
+
+    /// Publish a value quickly.
+    ///
+    /// Waits for the acknowledgement of the broker according to the QoS and prints the result as JSON to stdout.
+    /// When the broker does not acknowledge the publish within the timeout the exit code indicates a failure.
+    #[command(visible_alias = "p", visible_alias = "pub")]
+    Publish {
+        /// Topic to publish to
+        #[arg(value_hint = ValueHint::Other, required_unless_present = "file")]
+        topic: Option<String>,
+
+        /// Payload to be published.
+        ///
+        /// Reads from stdin when not specified.
+        /// This allows file content to be sent via pipes like this (bash):
+        ///
+        /// `mqttui publish some/topic </etc/hostname`
+        ///
+        /// `cowsay "I was here" | mqttui publish some/topic`
+        #[arg(value_hint = ValueHint::Unknown)]
+        payload: Option<String>,
+
+        /// Publish multiple messages in order from a file containing newline-delimited JSON.
+        ///
+        /// Each line is a JSON object with a topic and a payload.
+        /// String payloads are published as they are, other JSON values are published as JSON.
+        /// Retain and QoS are optional and fall back to --retain and --qos.
+        ///
+        /// `{"topic": "foo/bar", "payload": "Banana?", "retain": true, "qos": 2}`
+        ///
+        /// `{"topic": "foo/baz", "payload": {"state": "on"}}`
+        #[arg(
+            short,
+            long,
+            value_hint = ValueHint::FilePath,
+            value_name = "FILEPATH",
+            conflicts_with_all = ["topic", "payload"],
+        )]
+        file: Option<std::path::PathBuf>,
+
+        /// Publish the content of the file as payload without any changes.
+        ///
+        /// Useful for binary payloads like firmware images as shells might alter piped content.
+        #[arg(
+            long,
+            value_hint = ValueHint::FilePath,
+            value_name = "FILEPATH",
+            conflicts_with_all = ["payload", "file"],
+        )]
+        payload_file: Option<std::path::PathBuf>,
+
+        /// Decode the payload from base64 before publishing it.
+        ///
+        /// Applies to the payload argument, stdin and --payload-file.
+        /// Whitespace like line breaks is ignored.
+        ///
+        /// `mqttui publish some/topic --payload-base64 AAEC/w==`
+        #[arg(long, conflicts_with = "file")]
+        payload_base64: bool,
+
+        /// Publish the MQTT message retained
+        #[arg(short, long, env = "MQTTUI_RETAIN")]
+        retain: bool,
+
+        /// Quality of Service (QoS) level to publish with
+        #[arg(
+            short,
+            long,
+            env = "MQTTUI_QOS",
+            value_hint = ValueHint::Other,
+            value_parser = clap::value_parser!(u8).range(0..=2),
+            default_value_t = 1,
+        )]
+        qos: u8,
+
+        /// Fail when the publish is not acknowledged by the broker within the given time
+        #[arg(
+            long,
+            value_hint = ValueHint::Other,
+            value_name = "SECONDS",
+            default_value_t = 5.0,
+        )]
+        timeout: f32,
+
+        /// Show full MQTT communication and the publishes awaiting their acknowledgement
+        #[arg(short, long)]
+        verbose: bool,
+    },
+
+    /// Print the topics of retained messages starting with the prefix, one per line.
+    ///
+    /// The shell completions use it to complete the topic of the publish subcommand from the broker.
+    /// Ends on the first non retained message or when the timeout is reached.
+    ///
+    /// `mqttui complete-topics zigbee2mqtt/living`
+    CompleteTopics {
+        /// Start of the topics like 'zigbee2mqtt/living'
+        #[arg(value_hint = ValueHint::Other, default_value = "")]
+        prefix: String,
+
+        /// When there is no message received for the given time all retained topics are considered received
+        #[arg(
+            long,
+            value_hint = ValueHint::Other,
+            value_name = "SECONDS",
+            default_value_t = 1.0,
+        )]
+        timeout: f32,
+    },
+
+    /// Send a request via MQTT 5 and wait for its response.
+    ///
+    /// Subscribes to the response topic and publishes the payload with the response topic and correlation data set.
+    /// The payload of the first message on the response topic with the same correlation data is printed to stdout.
+    /// When there is no response within the timeout the exit code indicates a failure.
+    ///
+    /// `mqttui request devices/lamp/rpc '{"method": "getStatus"}'`
+    Request {
+        /// Topic to publish the request to
+        #[arg(value_hint = ValueHint::Other)]
+        topic: String,
+
+        /// Payload of the request.
+        ///
+        /// Reads from stdin when not specified.
+        #[arg(value_hint = ValueHint::Unknown)]
+        payload: Option<String>,
+
+        /// Topic the response is expected on.
+        ///
+        /// Defaults to a random topic below `mqttui/response/`.
+        #[arg(long, value_hint = ValueHint::Other)]
+        response_topic: Option<String>,
+
+        /// Quality of Service (QoS) level to publish the request and subscribe to the response with
+        #[arg(
+            short,
+            long,
+            env = "MQTTUI_QOS",
+            value_hint = ValueHint::Other,
+            value_parser = clap::value_parser!(u8).range(0..=2),
+            default_value_t = 1,
+        )]
+        qos: u8,
+
+        /// Fail when there is no response within the given time
+        #[arg(
+            long,
+            value_hint = ValueHint::Other,
+            value_name = "SECONDS",
+            default_value_t = 5.0,
+        )]
+        timeout: f32,
+
+        /// Parse the response payload and print it in a human readable pretty form
+        #[arg(short, long)]
+        pretty: bool,
+    },
+
+    /// Check which topics the broker allows to subscribe and publish to.
+    ///
+    /// Each topic is subscribed to via MQTT 5 and an empty, not retained message is published to it with QoS 1.
+    /// The reason codes of the acknowledgements are reported per topic.
+    /// Topic filters with wildcards are only subscribed to.
+    /// Useful to verify the access control list (ACL) of the broker after changing it.
+    ///
+    /// `mqttui acl-check --topics topics.txt`
+    AclCheck {
+        /// File with one topic per line. Empty lines are ignored.
+        #[arg(long, value_hint = ValueHint::FilePath, value_name = "FILE")]
+        topics: std::path::PathBuf,
+
+        /// Consider an operation timed out when it is not acknowledged within the given time
+        #[arg(
+            long,
+            value_hint = ValueHint::Other,
+            value_name = "SECONDS",
+            default_value_t = 5.0,
+        )]
+        timeout: f32,
+    },
+
+    /// Measure the round trip time of MQTT pings to the broker.
+    ///
+    /// A ping is sent every second until the given amount of responses was received.
+    ///
+    /// With --tls-info the certificate chain presented by the broker is printed with its subjects, issuers, validity and names.
+    /// Certificates which expire soon are warned about.
+    Ping {
+        /// Amount of pings to wait for
+        #[arg(
+            long,
+            short = 'n',
+            value_hint = ValueHint::Other,
+            value_name = "N",
+            default_value = "3",
+        )]
+        count: std::num::NonZeroUsize,
+
+        /// Print the TLS certificate chain of the broker
+        #[arg(long)]
+        tls_info: bool,
+    },
+
+    /// Find MQTT brokers in the local network which announce themselves via mDNS.
+    ///
+    /// Browses for `_mqtt._tcp` and `_secure-mqtt._tcp` services and lists their names and URLs usable with --broker.
+    /// The interactive mode offers the discovered brokers when switching the broker with `b`.
+    Discover {
+        /// Seconds to wait for responses
+        #[arg(
+            long,
+            value_hint = ValueHint::Other,
+            value_name = "SECONDS",
+            default_value_t = 3.0,
+        )]
+        timeout: f32,
+    },
+
+    /// Store the username and password of a profile in the keychain of the operating system.
+    ///
+    /// Use them later with `--profile <PROFILE>` instead of passing the password in plaintext.
+    /// The username is taken from --username and the password from --password when given.
+    /// Otherwise they are asked for on the terminal or read line by line from stdin.
+    Login {
+        /// Name of the profile like `prod`
+        #[arg(value_hint = ValueHint::Other, value_name = "PROFILE")]
+        name: String,
     },
 
-    /// Wait for the first message on the given topic(s) and return its payload to stdout.
+    /// Keep a history of the subscribed topics and provide it to other tools via a unix socket.
     ///
-    /// Returns exactly one payload of the first received message on the given topic(s).
-    /// The topic of the received message is printed to stderr.
-    /// This means that you can handle stdout and stderr separately.
+    /// Each line sent to the socket is a JSON request which is answered with a single line of JSON like `{"ok": true, "result": …}` or `{"ok": false, "error": "…"}`.
     ///
-    /// This can be helpful for scripting to get the current temperature reading and pipe it to somewhere else:
+    /// `{"command": "topics"}` lists all topics with messages.
     ///
-    /// `echo "The temperature is $(mqttui read-one room/temp)"`
+    /// `{"command": "get", "topic": "foo/bar"}` returns all messages of the topic.
     ///
-    /// The output is the exact payload in its binary form.
-    /// This might be valid ASCII / Unicode but could also be something not intended to be displayed on a terminal.
-    /// For a human readable format use `--pretty` or `mqttui log`.
-    #[command(visible_alias = "r", visible_alias = "read")]
-    ReadOne {
+    /// `{"command": "publish", "topic": "foo/bar", "payload": "Banana?", "qos": 1, "retain": false}` publishes a message. QoS and retain are optional.
+    ///
+    /// Only supported on unix.
+    Serve {
         /// Topics to watch
         #[arg(
             env = "MQTTUI_TOPIC",
             value_hint = ValueHint::Other,
+            value_parser = parse_topic_filter,
             default_value = "#",
         )]
         topic: Vec<String>,
 
-        /// Do not return on a retained message on connection, wait for another message to arrive
-        #[arg(long, short = 'r')]
-        ignore_retained: bool,
-
-        /// Parse the payload and print it in a human readable pretty form.
-        ///
-        /// This might not be useful for piping the data.
-        #[arg(short, long)]
-        pretty: bool,
-    },
-
-    /// Publish a value quickly
-    #[command(visible_alias = "p", visible_alias = "pub")]
-    Publish {
-        /// Topic to publish to
-        #[arg(value_hint = ValueHint::Other)]
-        topic: String,
+        /// Path of the unix socket to listen on
+        #[arg(
+            long,
+            env = "MQTTUI_SOCKET",
+            value_hint = ValueHint::FilePath,
+            value_name = "FILEPATH",
+        )]
+        socket: std::path::PathBuf,
 
-        /// Payload to be published.
-        ///
-        /// Reads from stdin when not specified.
-        /// This allows file content to be sent via pipes like this (bash):
-        ///
-        /// `mqttui publish some/topic </etc/hostname`
+        /// Keep only the given amount of messages per topic.
         ///
-        /// `cowsay "I was here" | mqttui publish some/topic`
-        #[arg(value_hint = ValueHint::Unknown)]
-        payload: Option<String>,
-
-        /// Publish the MQTT message retained
-        #[arg(short, long, env = "MQTTUI_RETAIN")]
-        retain: bool,
-
-        /// Show full MQTT communication
-        #[arg(short, long)]
-        verbose: bool,
+        /// Older messages are removed when a new one arrives which keeps the memory bounded.
+        #[arg(
+            long,
+            env = "MQTTUI_HISTORY_LIMIT",
+            value_hint = ValueHint::Other,
+            value_name = "MESSAGES",
+            default_value = "1000",
+        )]
+        history_limit: std::num::NonZeroUsize,
     },
 }
 
-#[allow(clippy::doc_markdown)]
+#[allow(clippy::doc_markdown, clippy::struct_excessive_bools)]
 #[derive(Debug, Parser)]
 #[command(about, version)]
 pub struct Cli {
@@ -125,6 +755,7 @@ pub struct Cli {
     #[arg(
         env = "MQTTUI_TOPIC",
         value_hint = ValueHint::Other,
+        value_parser = parse_topic_filter,
         default_value = "#",
     )]
     pub topic: Vec<String>,
@@ -134,6 +765,7 @@ pub struct Cli {
     /// Payloads bigger than that are truncated and not inspected for formats like JSON or MessagePack.
     /// Only their beginning up to the specified amount of bytes can be viewed.
     /// Increasing this value might result in higher memory consumption especially over time.
+    /// Can be changed at runtime in the settings popup with the key `,`.
     #[arg(
         long,
         env = "MQTTUI_PAYLOAD_SIZE_LIMIT",
@@ -142,13 +774,298 @@ pub struct Cli {
     )]
     pub payload_size_limit: usize,
 
+    /// Keep only the given amount of messages per topic in the history.
+    ///
+    /// Older messages are removed when a new one arrives which keeps the memory bounded on long running sessions.
+    /// By default the history grows without a limit.
+    #[arg(
+        long,
+        env = "MQTTUI_HISTORY_LIMIT",
+        value_hint = ValueHint::Other,
+        value_name = "MESSAGES",
+    )]
+    pub history_limit: Option<std::num::NonZeroUsize>,
+
+    /// Keep per-minute min, max and average of numeric messages removed by the history limit.
+    ///
+    /// The graph shows them in front of the remaining messages so it still covers the long term.
+    #[arg(long, env = "MQTTUI_HISTORY_COMPACTION", requires = "history_limit")]
+    pub history_compaction: bool,
+
+    /// Clean at most the given amount of topics per second when cleaning retained topics with Delete
+    #[arg(
+        long,
+        env = "MQTTUI_CLEAN_RATE_LIMIT",
+        value_hint = ValueHint::Other,
+        value_name = "TOPICS_PER_SECOND",
+    )]
+    pub clean_rate_limit: Option<std::num::NonZeroU32>,
+
+    /// Require to confirm again with Y before cleaning more than the given amount of retained topics with Delete
+    #[arg(
+        long,
+        env = "MQTTUI_CLEAN_MAX_TOPICS",
+        value_hint = ValueHint::Other,
+        value_name = "AMOUNT",
+    )]
+    pub clean_max_topics: Option<usize>,
+
+    /// Ignore topics matching the given filter.
+    ///
+    /// Matching messages are neither stored nor displayed.
+    /// Supports filters like 'foo/+/heartbeat' or 'foo/#'.
+    /// Can be specified multiple times.
+    #[arg(
+        long,
+        value_hint = ValueHint::Other,
+        value_name = "FILTER",
+        value_parser = parse_topic_filter,
+    )]
+    pub ignore_topic: Vec<String>,
+
+    /// Serve the latest payloads read-only as JSON via HTTP on the given address like '127.0.0.1:8080'.
+    ///
+    /// `/topics` returns the latest message of every topic, `/topics/<topic>` the one of the given topic.
+    /// There is no authentication so only listen on addresses reachable by trusted clients.
+    #[arg(
+        long,
+        env = "MQTTUI_HTTP_LISTEN",
+        value_hint = ValueHint::Other,
+        value_name = "ADDRESS",
+    )]
+    pub http_listen: Option<std::net::SocketAddr>,
+
+    /// Title of the terminal window while the interactive terminal UI is running.
+    ///
+    /// `{broker}` and `{topics}` are replaced with the broker and the amount of topics.
+    /// An empty value keeps the title untouched.
+    /// tmux uses it as the pane title which can be shown in the status line via `#{pane_title}`.
+    #[arg(
+        long,
+        env = "MQTTUI_TERMINAL_TITLE",
+        value_hint = ValueHint::Other,
+        value_name = "FORMAT",
+        default_value = "mqttui {broker} ({topics} topics)",
+    )]
+    pub terminal_title: String,
+
+    /// Columns of the history table shown besides the value.
+    ///
+    /// They can be toggled at runtime in the history table with the keys 1 (time), 2 (QoS), 3 (size) and 4 (retain).
+    #[arg(
+        long,
+        env = "MQTTUI_HISTORY_COLUMNS",
+        value_name = "COLUMN",
+        value_delimiter = ',',
+        default_value = "time,qos"
+    )]
+    pub history_columns: Vec<HistoryColumn>,
+
+    /// Show the time of messages as their age like `5s ago` in the history table and the age of the last message next to the topics.
+    ///
+    /// Can be toggled at runtime with the key a.
+    #[arg(long, env = "MQTTUI_RELATIVE_TIME")]
+    pub relative_time: bool,
+
+    /// Summary shown next to topics without messages of their own in the topic tree
+    #[arg(
+        long,
+        env = "MQTTUI_BRANCH_SUMMARY",
+        value_name = "SUMMARY",
+        default_value = "counts"
+    )]
+    pub branch_summary: BranchSummary,
+
+    /// Collapse consecutive identical payloads in the history table into one row with a repeat counter.
+    ///
+    /// Can be toggled at runtime in the history table with the key d.
+    #[arg(long, env = "MQTTUI_COLLAPSE_DUPLICATES")]
+    pub collapse_duplicates: bool,
+
+    /// Keep the selected history entry when new messages arrive instead of following the newest one.
+    ///
+    /// Can be toggled at runtime in the history table with the key p.
+    #[arg(long, env = "MQTTUI_PIN_SELECTION")]
+    pub pin_selection: bool,
+
+    /// Skip retained messages which are byte-identical to the latest retained message of the topic.
+    ///
+    /// Brokers send the retained messages again on every reconnect.
+    /// Without this they are added to the history again and inflate the message counts.
+    #[arg(long, env = "MQTTUI_SKIP_RETAINED_DUPLICATES")]
+    pub skip_retained_duplicates: bool,
+
+    /// Accessibility mode: the focused selection is bold and underlined instead of only colored, dimmed text gets more contrast and nothing blinks.
+    ///
+    /// Can be changed at runtime in the settings opened with the key ,.
+    #[arg(long, env = "MQTTUI_ACCESSIBLE")]
+    pub accessible: bool,
+
+    /// Briefly highlight topics in the topic tree when a message arrives for them.
+    ///
+    /// Collapsed topics are highlighted for the topics below them.
+    /// Can be changed at runtime in the settings opened with the key ,.
+    #[arg(long, env = "MQTTUI_ARRIVAL_PULSE")]
+    pub arrival_pulse: bool,
+
+    /// Append a line describing the focus and selection like `Topic foo/bar, 3 messages, latest: 42` to the file whenever it changes.
+    ///
+    /// Screen readers can follow the file like with `tail -f` while the interactive terminal UI is running.
+    #[arg(
+        long,
+        env = "MQTTUI_ANNOUNCE_FILE",
+        value_hint = ValueHint::FilePath,
+        value_name = "FILE",
+    )]
+    pub announce_file: Option<std::path::PathBuf>,
+
+    /// Pin a value to the dashboard and start with it instead of the topic tree.
+    ///
+    /// Either a topic like 'home/livingroom/temp' or a filter with wildcards followed by sum, avg, min or max like 'home/+/humidity avg'.
+    /// Filters with wildcards default to the average of the latest value of every matching topic.
+    /// Can be specified multiple times.
+    /// More values can be pinned at runtime with the key w and the dashboard is shown with the key D.
+    #[arg(
+        long,
+        value_hint = ValueHint::Other,
+        value_name = "EXPRESSION",
+    )]
+    pub dashboard: Vec<String>,
+
+    /// Print the messages like the log subcommand instead of starting the interactive terminal UI.
+    ///
+    /// This is the default when stdout is not a terminal like when running under a process supervisor or in CI.
+    #[arg(long, env = "MQTTUI_NO_INTERACTIVE")]
+    pub no_interactive: bool,
+
+    #[command(flatten)]
+    pub decoding: DecodingArgs,
+
+    /// Suppress progress, warnings and connection errors on stderr. The error ending the command is still printed.
+    ///
+    /// Independent of it the exit code tells what went wrong:
+    /// 1 other errors, 2 invalid arguments, 3 connection failed, 4 authentication failed,
+    /// 5 timeout, 6 no message received and 7 partial success.
+    #[arg(long, env = "MQTTUI_QUIET", global = true)]
+    pub quiet: bool,
+
     // Keep at the end to not mix the next_help_heading with other options
     #[command(flatten, next_help_heading = "MQTT Connection")]
     pub mqtt_connection: MqttConnection,
 }
 
+/// Column of the history table in the interactive terminal UI
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum HistoryColumn {
+    /// Time of the message with milliseconds
+    Time,
+    /// Time of the message without milliseconds which needs less space
+    ShortTime,
+    Qos,
+    /// Size of the payload in bytes
+    Size,
+    /// Whether the message was retained
+    Retain,
+}
+
+/// Summary of the topics below a branch in the topic tree of the interactive terminal UI
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum BranchSummary {
+    /// Amount of topics and messages below
+    #[default]
+    Counts,
+    /// Time of the latest message below
+    LatestUpdate,
+    /// Bytes of all the payloads below
+    Bytes,
+    /// Nothing
+    Hidden,
+}
+
+/// Format of the receive time of messages in the log output
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Timestamp {
+    /// Local wall clock time. JSON includes the date.
+    Local,
+    /// UTC wall clock time
+    Utc,
+    /// ISO 8601 date and time in UTC like 2024-05-01T10:00:00.123Z
+    Iso,
+    /// Milliseconds since the Unix epoch
+    EpochMs,
+    /// Seconds since the log started
+    Relative,
+}
+
+/// Arguments related to decoding the payloads.
+#[derive(Debug, Clone, Args)]
+pub struct DecodingArgs {
+    /// Decrypt payloads of topics matching the filter before they are inspected.
+    ///
+    /// Format: FILTER=ALGORITHM:KEY[:IV] with the key and the optional fixed IV / nonce in hex.
+    /// Without a fixed IV the payload is expected to start with the IV followed by the ciphertext.
+    /// Supported algorithms: aes-128-cbc, aes-256-cbc (PKCS#7 padding), aes-128-gcm, aes-256-gcm and chacha20-poly1305.
+    /// Can be specified multiple times, the first matching filter is used.
+    ///
+    /// Passing keys via command line is insecure as they can be read from the history!
+    /// You should pass them via environment variable.
+    #[arg(
+        long,
+        env = "MQTTUI_DECRYPT",
+        value_hint = ValueHint::Other,
+        value_name = "FILTER=ALGORITHM:KEY[:IV]",
+        value_delimiter = ',',
+        hide_env_values = true,
+    )]
+    pub decrypt: Vec<String>,
+
+    /// Pipe every received payload through the given shell command.
+    ///
+    /// The payload is passed via stdin and the stdout of the command is displayed instead.
+    /// The topic is available to the command via the `MQTT_TOPIC` environment variable.
+    /// When the command fails the original payload is used.
+    /// This allows for decoding formats mqttui does not support natively:
+    ///
+    /// `--transform 'protoc --decode_raw'`
+    #[arg(long, env = "MQTTUI_TRANSFORM", value_hint = ValueHint::CommandString, value_name = "COMMAND")]
+    pub transform: Option<String>,
+
+    /// Decode payloads of topics matching the filter with a Rhai script.
+    ///
+    /// The script gets the raw payload as `payload` (a blob of bytes) and the `topic`.
+    /// The value of the last expression is displayed like JSON.
+    /// Can be specified multiple times, the first matching filter is used.
+    ///
+    /// `--decoder 'sensor/+/raw=decode.rhai'` with decode.rhai containing
+    /// `#{ temperature: (payload[0] * 256 + payload[1]) / 10.0 }`
+    #[arg(
+        long,
+        value_hint = ValueHint::Other,
+        value_name = "FILTER=SCRIPT",
+        value_parser = parse_decoder,
+    )]
+    pub decoder: Vec<(String, std::path::PathBuf)>,
+
+    /// Decode fixed-format binary payloads of topics matching the filter with a template file.
+    ///
+    /// Every line of the template is a field in the form `OFFSET TYPE NAME`.
+    /// Types are u8, i8, u16, i16, u32, i32, u64, i64, f32 and f64 with the endianness like `u16le` or `u16be`.
+    /// The fields are displayed like JSON. The interactive mode can graph the numbers by selecting them.
+    /// Can be specified multiple times, the first matching filter is used.
+    ///
+    /// `--binary-template 'sensor/+/raw=sensor.template'` with sensor.template containing
+    /// `0 i16be temperature` and `2 u8 battery`
+    #[arg(
+        long,
+        value_hint = ValueHint::Other,
+        value_name = "FILTER=FILE",
+        value_parser = parse_binary_template,
+    )]
+    pub binary_template: Vec<(String, std::path::PathBuf)>,
+}
+
 /// Arguments related to the MQTT connection.
-#[derive(Debug, Args)]
+#[derive(Debug, Clone, Args)]
 pub struct MqttConnection {
     /// URL which represents how to connect to the MQTT broker.
     ///
@@ -161,6 +1078,7 @@ pub struct MqttConnection {
     /// `ws://localhost:9001/path`
     /// `wss://localhost/path`
     /// `wss://localhost:9001/path`
+    /// `unix:///run/mosquitto/mqtt.sock`
     #[arg(
         short,
         long,
@@ -175,13 +1093,13 @@ pub struct MqttConnection {
     /// Username to access the mqtt broker.
     ///
     /// Anonymous access when not supplied.
+    /// Requires a password via --password, --password-command, --profile or --netrc.
     #[arg(
         short,
         long,
         env = "MQTTUI_USERNAME",
         value_hint = ValueHint::Username,
         value_name = "STRING",
-        requires = "password",
         global = true,
     )]
     pub username: Option<String>,
@@ -204,7 +1122,47 @@ pub struct MqttConnection {
     )]
     pub password: Option<String>,
 
-    /// Specify the client id to connect with
+    /// Command which prints the password to access the mqtt broker.
+    ///
+    /// The first line of its output is used as the password.
+    /// The command is run when connecting so the password never has to be part of the environment or the shell history.
+    ///
+    /// Example: `pass show mqtt/prod`
+    #[arg(
+        long,
+        env = "MQTTUI_PASSWORD_COMMAND",
+        value_hint = ValueHint::CommandString,
+        value_name = "COMMAND",
+        requires = "username",
+        conflicts_with = "password",
+        global = true,
+    )]
+    pub password_command: Option<String>,
+
+    /// Use the credentials stored with `mqttui login <PROFILE>` in the keychain of the operating system.
+    ///
+    /// The stored username is used when no --username is given.
+    /// Ignored when a password is given via --password or --password-command.
+    #[arg(
+        long,
+        env = "MQTTUI_PROFILE",
+        value_hint = ValueHint::Other,
+        value_name = "PROFILE",
+        global = true,
+    )]
+    pub profile: Option<String>,
+
+    /// Look up the credentials for the broker host in the netrc file.
+    ///
+    /// Uses the file given by the NETRC environment variable or ~/.netrc.
+    /// The entry of the broker host (or the default entry) provides the username and password when they are not given otherwise.
+    #[arg(long, env = "MQTTUI_NETRC", global = true)]
+    pub netrc: bool,
+
+    /// Specify the client id to connect with.
+    ///
+    /// Brokers disconnect the existing connection when the same client id connects again.
+    /// mqttui warns when another instance on this machine is connected to the same broker with the same client id.
     #[arg(
         short = 'i',
         long,
@@ -243,17 +1201,90 @@ pub struct MqttConnection {
     )]
     pub client_key: Option<std::path::PathBuf>,
 
+    /// Path to a CA certificate file to trust additionally to the certificates of the system.
+    ///
+    /// Allows TLS connections to brokers with self-signed certificates without --insecure.
+    /// The file can contain multiple PEM encoded certificates.
+    #[arg(
+        long,
+        env = "MQTTUI_CA_CERTIFICATE",
+        value_hint = ValueHint::FilePath,
+        value_name = "FILEPATH",
+        global = true,
+    )]
+    pub ca_cert: Option<std::path::PathBuf>,
+
+    /// Name to verify the TLS certificate of the broker against instead of the host of --broker.
+    ///
+    /// Useful when the broker is reached via an IP address or a tunnel while its certificate is issued for a DNS name.
+    #[arg(
+        long,
+        env = "MQTTUI_TLS_SERVER_NAME",
+        value_hint = ValueHint::Hostname,
+        value_name = "NAME",
+        global = true,
+    )]
+    pub tls_server_name: Option<String>,
+
     /// Allow insecure TLS connections
     #[arg(long, global = true)]
     pub insecure: bool,
+
+    /// Seconds between pings when nothing else is sent.
+    ///
+    /// The broker assumes the client is gone when it does not hear from it for one and a half times this duration.
+    /// Shorter values detect broken connections sooner, for example on flaky cellular links.
+    /// MQTT 5 connections require at least 5 seconds.
+    /// Defaults to 60 seconds.
+    #[arg(
+        long,
+        env = "MQTTUI_KEEP_ALIVE",
+        value_hint = ValueHint::Other,
+        value_name = "SECONDS",
+        global = true,
+    )]
+    pub keep_alive: Option<std::num::NonZeroU16>,
+
+    /// Seconds to establish the connection to the broker including TLS and the acknowledgement of the broker
+    #[arg(
+        long,
+        env = "MQTTUI_CONNECTION_TIMEOUT",
+        value_hint = ValueHint::Other,
+        value_name = "SECONDS",
+        global = true,
+        default_value = "5",
+    )]
+    pub connection_timeout: std::num::NonZeroU64,
+
+    /// Maximum size of MQTT packets in bytes which are sent or received.
+    ///
+    /// Bigger incoming packets end the connection with an error.
+    /// Strict brokers close the connection on packets exceeding their limit so knowing it beforehand helps with a clear error.
+    /// Unlimited by default.
+    #[arg(
+        long,
+        env = "MQTTUI_MAX_PACKET_SIZE",
+        value_hint = ValueHint::Other,
+        value_name = "BYTES",
+        global = true,
+    )]
+    pub max_packet_size: Option<std::num::NonZeroU32>,
 }
 
 #[derive(Debug, Clone)]
 pub enum Broker {
-    Tcp { host: String, port: u16 },
-    Ssl { host: String, port: u16 },
+    Tcp {
+        host: String,
+        port: u16,
+    },
+    Ssl {
+        host: String,
+        port: u16,
+    },
     WebSocket(Url),
     WebSocketSsl(Url),
+    /// Path of a Unix domain socket
+    Unix(std::path::PathBuf),
 }
 
 impl core::str::FromStr for Broker {
@@ -261,6 +1292,19 @@ impl core::str::FromStr for Broker {
 
     fn from_str(input: &str) -> Result<Self, Self::Err> {
         let url = Url::parse(input)?;
+        if url.scheme() == "unix" {
+            anyhow::ensure!(
+                url.host_str().unwrap_or_default().is_empty(),
+                "Unix sockets use an absolute path like unix:///run/mosquitto/mqtt.sock but no host"
+            );
+            anyhow::ensure!(url.query().is_none(), "URL query is not used");
+            let path = url
+                .to_file_path()
+                .ok()
+                .filter(|path| path.file_name().is_some())
+                .context("Unix sockets require the path of the socket")?;
+            return Ok(Self::Unix(path));
+        }
         anyhow::ensure!(url.has_host(), "Broker requires a Host");
 
         if matches!(url.scheme(), "mqtt" | "mqtts") {
@@ -278,13 +1322,18 @@ impl core::str::FromStr for Broker {
 
         anyhow::ensure!(url.port() != Some(0), "Port can not be 0");
 
+        // IPv6 literals without the brackets of the URL syntax
+        let host = match url.host().context("Broker requires a Host")? {
+            url::Host::Ipv6(address) => address.to_string(),
+            host => host.to_string(),
+        };
         let broker = match url.scheme() {
             "mqtt" => Self::Tcp {
-                host: url.host_str().context("Broker requires a Host")?.to_owned(),
+                host,
                 port: url.port().unwrap_or(1883),
             },
             "mqtts" => Self::Ssl {
-                host: url.host_str().context("Broker requires a Host")?.to_owned(),
+                host,
                 port: url.port().unwrap_or(8883),
             },
             "ws" => Self::WebSocket(url),
@@ -300,24 +1349,144 @@ impl core::fmt::Display for Broker {
     fn fmt(&self, fmt: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         match self {
             Self::Tcp { host, port } => {
-                if *port == 1883 {
-                    write!(fmt, "mqtt://{host}")
-                } else {
-                    write!(fmt, "mqtt://{host}@{port}")
-                }
+                write!(fmt, "mqtt://")?;
+                fmt_host(fmt, host, *port, 1883)
             }
             Self::Ssl { host, port } => {
-                if *port == 8883 {
-                    write!(fmt, "mqtts://{host}")
-                } else {
-                    write!(fmt, "mqtts://{host}@{port}")
-                }
+                write!(fmt, "mqtts://")?;
+                fmt_host(fmt, host, *port, 8883)
             }
             Self::WebSocket(url) | Self::WebSocketSsl(url) => url.fmt(fmt),
+            Self::Unix(path) => write!(fmt, "unix://{}", path.display()),
         }
     }
 }
 
+fn fmt_host(
+    fmt: &mut core::fmt::Formatter<'_>,
+    host: &str,
+    port: u16,
+    default_port: u16,
+) -> core::fmt::Result {
+    if host.contains(':') {
+        write!(fmt, "[{host}]")?;
+    } else {
+        write!(fmt, "{host}")?;
+    }
+    if port != default_port {
+        write!(fmt, "@{port}")?;
+    }
+    Ok(())
+}
+
+#[test]
+fn parse_unix_broker_works() {
+    let broker = "unix:///run/mosquitto/mqtt.sock".parse::<Broker>().unwrap();
+    assert!(
+        matches!(&broker, Broker::Unix(path) if path == std::path::Path::new("/run/mosquitto/mqtt.sock"))
+    );
+    assert_eq!(broker.to_string(), "unix:///run/mosquitto/mqtt.sock");
+    for invalid in [
+        "unix://localhost/mqtt.sock",
+        "unix:///",
+        "unix:///mqtt.sock?foo",
+    ] {
+        assert!(invalid.parse::<Broker>().is_err(), "{invalid}");
+    }
+}
+
+#[test]
+fn parse_ipv6_broker_works() {
+    let broker = "mqtt://[::1]:1884".parse::<Broker>().unwrap();
+    assert!(matches!(&broker, Broker::Tcp { host, port: 1884 } if host == "::1"));
+    assert_eq!(broker.to_string(), "mqtt://[::1]@1884");
+    let broker = "mqtts://[2001:db8::1]".parse::<Broker>().unwrap();
+    assert!(matches!(&broker, Broker::Ssl { host, port: 8883 } if host == "2001:db8::1"));
+    assert_eq!(broker.to_string(), "mqtts://[2001:db8::1]");
+}
+
+/// Validate the syntax of a topic filter before it is subscribed to.
+///
+/// Otherwise the broker rejects the subscription or closes the connection and the result is just no messages.
+///
+/// # Errors
+///
+/// When the filter is empty or contains wildcards at invalid positions.
+pub fn parse_topic_filter(filter: &str) -> anyhow::Result<String> {
+    anyhow::ensure!(!filter.is_empty(), "The topic filter can not be empty");
+    anyhow::ensure!(
+        !filter.contains('\0'),
+        "The topic filter can not contain the null character"
+    );
+    let levels = filter.split('/').collect::<Vec<_>>();
+    for (index, level) in levels.iter().enumerate() {
+        if level.contains('#') {
+            anyhow::ensure!(
+                *level == "#" && index == levels.len() - 1,
+                "The multi-level wildcard '#' has to be a whole level at the end of the topic filter like 'foo/#' but got '{filter}'"
+            );
+        }
+        if level.contains('+') {
+            anyhow::ensure!(
+                *level == "+",
+                "The single-level wildcard '+' has to be a whole level like 'foo/+/bar' but got '{filter}'"
+            );
+        }
+    }
+    Ok(filter.to_owned())
+}
+
+#[test]
+fn parse_topic_filter_works() {
+    for valid in ["#", "foo", "foo/#", "+/bar/+", "foo//bar", "/", "$SYS/#"] {
+        assert!(parse_topic_filter(valid).is_ok(), "{valid}");
+    }
+    for invalid in [
+        "",
+        "foo#",
+        "foo/#/bar",
+        "#/foo",
+        "foo/bar+",
+        "+foo/bar",
+        "a\0b",
+    ] {
+        assert!(parse_topic_filter(invalid).is_err(), "{invalid}");
+    }
+}
+
+/// Parse the decoder argument in the form `FILTER=SCRIPT`
+fn parse_decoder(arg: &str) -> anyhow::Result<(String, std::path::PathBuf)> {
+    let (filter, path) = arg
+        .rsplit_once('=')
+        .context("Expected the form FILTER=SCRIPT like 'sensor/+/raw=decode.rhai'")?;
+    anyhow::ensure!(
+        !filter.is_empty() && !path.is_empty(),
+        "Neither the topic filter nor the script path can be empty"
+    );
+    Ok((filter.to_owned(), path.into()))
+}
+
+/// Parse the binary template argument in the form `FILTER=FILE`
+fn parse_binary_template(arg: &str) -> anyhow::Result<(String, std::path::PathBuf)> {
+    let (filter, path) = arg
+        .rsplit_once('=')
+        .context("Expected the form FILTER=FILE like 'sensor/+/raw=sensor.template'")?;
+    anyhow::ensure!(
+        !filter.is_empty() && !path.is_empty(),
+        "Neither the topic filter nor the template path can be empty"
+    );
+    Ok((filter.to_owned(), path.into()))
+}
+
+#[test]
+fn parse_decoder_works() {
+    let (filter, path) = parse_decoder("foo/+/bar=scripts/decode.rhai").unwrap();
+    assert_eq!(filter, "foo/+/bar");
+    assert_eq!(path, std::path::PathBuf::from("scripts/decode.rhai"));
+    assert!(parse_decoder("decode.rhai").is_err());
+    assert!(parse_decoder("=decode.rhai").is_err());
+}
+
 #[test]
 fn verify() {
     use clap::CommandFactory;
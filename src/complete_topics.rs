@@ -0,0 +1,50 @@
+use std::collections::BTreeSet;
+
+use rumqttc::{Client, Connection};
+
+/// Subscribe to everything below the last complete level of the prefix
+pub fn topic_filter(prefix: &str) -> String {
+    prefix
+        .rsplit_once('/')
+        .map_or_else(|| "#".to_owned(), |(base, _)| format!("{base}/#"))
+}
+
+/// Print the topics of retained messages starting with the prefix sorted one per line.
+///
+/// Ends on the first non retained message or when no message was received within the keep alive.
+pub fn complete_topics(
+    client: &Client,
+    mut connection: Connection,
+    prefix: &str,
+) -> anyhow::Result<()> {
+    let mut topics = BTreeSet::new();
+    for notification in connection.iter() {
+        match notification? {
+            rumqttc::Event::Outgoing(rumqttc::Outgoing::Disconnect) => break,
+            rumqttc::Event::Outgoing(rumqttc::Outgoing::PingReq) => client.disconnect()?,
+            rumqttc::Event::Incoming(rumqttc::Packet::Publish(publish)) => {
+                if !publish.retain {
+                    client.disconnect()?;
+                    continue;
+                }
+                if publish.topic.starts_with(prefix) {
+                    topics.insert(publish.topic);
+                }
+            }
+            _ => {}
+        }
+    }
+    for topic in topics {
+        println!("{topic}");
+    }
+    Ok(())
+}
+
+#[test]
+fn topic_filter_works() {
+    assert_eq!(topic_filter(""), "#");
+    assert_eq!(topic_filter("zigbee"), "#");
+    assert_eq!(topic_filter("zigbee2mqtt/"), "zigbee2mqtt/#");
+    assert_eq!(topic_filter("zigbee2mqtt/living"), "zigbee2mqtt/#");
+    assert_eq!(topic_filter("a/b/c"), "a/b/#");
+}
@@ -0,0 +1,188 @@
+use std::path::PathBuf;
+
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+
+/// Settings saved in the settings popup of the interactive terminal UI.
+///
+/// They are used as environment variables when the variables are not set already.
+/// Arguments and environment variables therefore take precedence over the file.
+#[derive(Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub payload_size_limit: Option<usize>,
+    /// Messages kept per topic, 0 for no limit
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub history_limit: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub history_columns: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub relative_time: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub collapse_duplicates: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pin_selection: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub branch_summary: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub accessible: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub announce_file: Option<PathBuf>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub arrival_pulse: Option<bool>,
+}
+
+impl Config {
+    /// Environment variables of the arguments the settings correspond to
+    fn env(&self) -> Vec<(&'static str, String)> {
+        let mut env = Vec::new();
+        if let Some(limit) = self.payload_size_limit {
+            env.push(("MQTTUI_PAYLOAD_SIZE_LIMIT", limit.to_string()));
+        }
+        if let Some(limit) = self.history_limit.filter(|limit| *limit > 0) {
+            env.push(("MQTTUI_HISTORY_LIMIT", limit.to_string()));
+        }
+        if let Some(columns) = &self.history_columns {
+            env.push(("MQTTUI_HISTORY_COLUMNS", columns.join(",")));
+        }
+        if let Some(relative_time) = self.relative_time {
+            env.push(("MQTTUI_RELATIVE_TIME", relative_time.to_string()));
+        }
+        if let Some(collapse) = self.collapse_duplicates {
+            env.push(("MQTTUI_COLLAPSE_DUPLICATES", collapse.to_string()));
+        }
+        if let Some(pin) = self.pin_selection {
+            env.push(("MQTTUI_PIN_SELECTION", pin.to_string()));
+        }
+        if let Some(summary) = &self.branch_summary {
+            env.push(("MQTTUI_BRANCH_SUMMARY", summary.clone()));
+        }
+        if let Some(accessible) = self.accessible {
+            env.push(("MQTTUI_ACCESSIBLE", accessible.to_string()));
+        }
+        if let Some(path) = &self.announce_file {
+            env.push(("MQTTUI_ANNOUNCE_FILE", path.display().to_string()));
+        }
+        if let Some(pulse) = self.arrival_pulse {
+            env.push(("MQTTUI_ARRIVAL_PULSE", pulse.to_string()));
+        }
+        env
+    }
+
+    /// Take over the settings which are set in the other config
+    fn update(&mut self, other: &Self) {
+        fn update<T: Clone>(value: &mut Option<T>, other: Option<&T>) {
+            if let Some(other) = other {
+                *value = Some(other.clone());
+            }
+        }
+        update(
+            &mut self.payload_size_limit,
+            other.payload_size_limit.as_ref(),
+        );
+        update(&mut self.history_limit, other.history_limit.as_ref());
+        update(&mut self.history_columns, other.history_columns.as_ref());
+        update(&mut self.relative_time, other.relative_time.as_ref());
+        update(
+            &mut self.collapse_duplicates,
+            other.collapse_duplicates.as_ref(),
+        );
+        update(&mut self.pin_selection, other.pin_selection.as_ref());
+        update(&mut self.branch_summary, other.branch_summary.as_ref());
+        update(&mut self.accessible, other.accessible.as_ref());
+        update(&mut self.announce_file, other.announce_file.as_ref());
+        update(&mut self.arrival_pulse, other.arrival_pulse.as_ref());
+    }
+}
+
+/// `$XDG_CONFIG_HOME/mqttui/config.json` or the platform equivalent
+pub fn path() -> Option<PathBuf> {
+    let dir = if cfg!(windows) {
+        std::env::var_os("APPDATA").map(PathBuf::from)
+    } else {
+        std::env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+    }?;
+    Some(dir.join("mqttui").join("config.json"))
+}
+
+fn load() -> anyhow::Result<Config> {
+    let Some(path) = path() else {
+        return Ok(Config::default());
+    };
+    let content = match std::fs::read_to_string(&path) {
+        Ok(content) => content,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(Config::default()),
+        Err(err) => {
+            return Err(err).with_context(|| format!("Failed to read {}", path.display()));
+        }
+    };
+    serde_json::from_str(&content).with_context(|| format!("Invalid config {}", path.display()))
+}
+
+/// Provide the saved settings as environment variables which are not set already.
+///
+/// Has to be called before other threads are started.
+pub fn load_into_env() -> anyhow::Result<()> {
+    for (key, value) in load()?.env() {
+        if std::env::var_os(key).is_none() {
+            std::env::set_var(key, value);
+        }
+    }
+    Ok(())
+}
+
+/// Write the settings into the config file while keeping the ones not set in the given config.
+///
+/// Returns the path the settings were written to
+pub fn save(config: &Config) -> anyhow::Result<PathBuf> {
+    let path = path().context("Unable to determine the config directory")?;
+    let mut merged = load()?;
+    merged.update(config);
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)
+            .with_context(|| format!("Failed to create {}", dir.display()))?;
+    }
+    let json = serde_json::to_string_pretty(&merged).expect("Should be able to format config");
+    std::fs::write(&path, json + "\n")
+        .with_context(|| format!("Failed to write {}", path.display()))?;
+    Ok(path)
+}
+
+#[test]
+fn env_of_config() {
+    let config = serde_json::from_str::<Config>(
+        r#"{"payload_size_limit": 100000, "history_columns": ["short-time", "size"], "relative_time": true}"#,
+    )
+    .unwrap();
+    assert_eq!(
+        config.env(),
+        [
+            ("MQTTUI_PAYLOAD_SIZE_LIMIT", "100000".to_owned()),
+            ("MQTTUI_HISTORY_COLUMNS", "short-time,size".to_owned()),
+            ("MQTTUI_RELATIVE_TIME", "true".to_owned()),
+        ]
+    );
+    let config =
+        serde_json::from_str::<Config>(r#"{"history_limit": 0, "pin_selection": true}"#).unwrap();
+    assert_eq!(config.env(), [("MQTTUI_PIN_SELECTION", "true".to_owned())]);
+    assert!(serde_json::from_str::<Config>(r#"{"theme": "dark"}"#).is_err());
+    assert_eq!(serde_json::to_string(&Config::default()).unwrap(), "{}");
+}
+
+#[test]
+fn update_keeps_unset() {
+    let mut config = Config {
+        relative_time: Some(true),
+        accessible: Some(true),
+        ..Config::default()
+    };
+    config.update(&Config {
+        relative_time: Some(false),
+        ..Config::default()
+    });
+    assert_eq!(config.relative_time, Some(false));
+    assert_eq!(config.accessible, Some(true));
+}
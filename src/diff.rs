@@ -0,0 +1,144 @@
+use std::collections::BTreeMap;
+use std::thread::{self, sleep};
+use std::time::{Duration, Instant};
+
+use rumqttc::{Client, Connection, RecvTimeoutError};
+
+use crate::cli::Broker;
+use crate::payload::Payload;
+
+pub type Retained = BTreeMap<String, Vec<u8>>;
+
+#[derive(Debug, PartialEq, Eq)]
+enum Difference<'a> {
+    OnlyFirst(&'a str, &'a [u8]),
+    OnlySecond(&'a str, &'a [u8]),
+    Payload(&'a str, &'a [u8], &'a [u8]),
+}
+
+fn compare<'a>(first: &'a Retained, second: &'a Retained) -> Vec<Difference<'a>> {
+    let mut differences = Vec::new();
+    for (topic, payload) in first {
+        match second.get(topic) {
+            None => differences.push(Difference::OnlyFirst(topic, payload)),
+            Some(other) if other != payload => {
+                differences.push(Difference::Payload(topic, payload, other));
+            }
+            Some(_) => {}
+        }
+    }
+    for (topic, payload) in second {
+        if !first.contains_key(topic) {
+            differences.push(Difference::OnlySecond(topic, payload));
+        }
+    }
+    differences.sort_by_key(|difference| match difference {
+        Difference::OnlyFirst(topic, _)
+        | Difference::OnlySecond(topic, _)
+        | Difference::Payload(topic, _, _) => *topic,
+    });
+    differences
+}
+
+/// Collect retained messages until no further retained message arrives within the timeout
+pub fn collect_retained(
+    client: &Client,
+    mut connection: Connection,
+    timeout: Duration,
+) -> Retained {
+    let mut retained = Retained::new();
+    let mut last_retained = Instant::now();
+    loop {
+        let remaining = timeout.saturating_sub(last_retained.elapsed());
+        match connection.recv_timeout(remaining) {
+            Ok(Ok(rumqttc::Event::Incoming(rumqttc::Packet::Publish(publish)))) => {
+                if publish.retain {
+                    last_retained = Instant::now();
+                    retained.insert(publish.topic, publish.payload.into());
+                }
+            }
+            Ok(Ok(rumqttc::Event::Outgoing(rumqttc::Outgoing::Disconnect)))
+            | Err(RecvTimeoutError::Disconnected) => break,
+            Ok(Ok(_)) => {}
+            Ok(Err(err)) => {
+                eprintln!("Connection Error: {err}");
+                sleep(Duration::from_millis(25));
+            }
+            Err(RecvTimeoutError::Timeout) => {
+                _ = client.disconnect();
+                break;
+            }
+        }
+    }
+    retained
+}
+
+pub fn diff(
+    first_broker: &Broker,
+    first_client: &Client,
+    first_connection: Connection,
+    second_broker: &Broker,
+    second_client: Client,
+    second_connection: Connection,
+    timeout: Duration,
+) -> anyhow::Result<()> {
+    let second = thread::Builder::new()
+        .name("diff second broker".to_owned())
+        .spawn(move || collect_retained(&second_client, second_connection, timeout))
+        .expect("should be able to spawn a thread");
+    let first = collect_retained(first_client, first_connection, timeout);
+    let second = second
+        .join()
+        .expect("collecting retained messages panicked");
+
+    let first_broker = first_broker.to_string();
+    let second_broker = second_broker.to_string();
+    let differences = compare(&first, &second);
+    for difference in &differences {
+        match difference {
+            Difference::OnlyFirst(topic, payload) => {
+                let payload = Payload::unlimited(payload.to_vec());
+                println!("Only on {first_broker:30} {topic:50} Payload: {payload}");
+            }
+            Difference::OnlySecond(topic, payload) => {
+                let payload = Payload::unlimited(payload.to_vec());
+                println!("Only on {second_broker:30} {topic:50} Payload: {payload}");
+            }
+            Difference::Payload(topic, first, second) => {
+                let first = Payload::unlimited(first.to_vec());
+                let second = Payload::unlimited(second.to_vec());
+                println!("Different payload   {topic:50} {first} -> {second}");
+            }
+        }
+    }
+    let topics = first.len().max(second.len());
+    anyhow::ensure!(
+        differences.is_empty(),
+        "{} of about {topics} retained topics differ",
+        differences.len()
+    );
+    println!("All {topics} retained topics are the same");
+    Ok(())
+}
+
+#[test]
+fn compare_works() {
+    let first = Retained::from([
+        ("both".to_owned(), b"same".to_vec()),
+        ("changed".to_owned(), b"A".to_vec()),
+        ("only-first".to_owned(), b"1".to_vec()),
+    ]);
+    let second = Retained::from([
+        ("both".to_owned(), b"same".to_vec()),
+        ("changed".to_owned(), b"B".to_vec()),
+        ("only-second".to_owned(), b"2".to_vec()),
+    ]);
+    assert_eq!(
+        compare(&first, &second),
+        [
+            Difference::Payload("changed", b"A", b"B"),
+            Difference::OnlyFirst("only-first", b"1"),
+            Difference::OnlySecond("only-second", b"2"),
+        ]
+    );
+}
@@ -0,0 +1,347 @@
+use std::collections::BTreeMap;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, UdpSocket};
+use std::time::{Duration, Instant};
+
+use anyhow::Context;
+
+use crate::exit;
+
+const MDNS_ADDRESS: Ipv4Addr = Ipv4Addr::new(224, 0, 0, 251);
+const MDNS_PORT: u16 = 5353;
+
+const SERVICE_MQTT: &str = "_mqtt._tcp.local";
+const SERVICE_SECURE_MQTT: &str = "_secure-mqtt._tcp.local";
+
+const TYPE_A: u16 = 1;
+const TYPE_PTR: u16 = 12;
+const TYPE_AAAA: u16 = 28;
+const TYPE_SRV: u16 = 33;
+const CLASS_IN: u16 = 1;
+/// Ask for unicast responses to the port of the query. See RFC 6762 section 5.4.
+const UNICAST_RESPONSE: u16 = 0x8000;
+
+/// MQTT broker announced via DNS-SD
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Service {
+    /// Instance name like `Living room hub`
+    pub name: String,
+    pub secure: bool,
+    /// Host name like `hub.local`
+    pub host: String,
+    pub port: u16,
+    pub addresses: Vec<IpAddr>,
+}
+
+impl Service {
+    /// Broker URL usable with --broker. Prefers an IPv4 address as `.local` host names are often not resolvable.
+    pub fn url(&self) -> String {
+        let scheme = if self.secure { "mqtts" } else { "mqtt" };
+        let address = self
+            .addresses
+            .iter()
+            .find(|address| address.is_ipv4())
+            .or_else(|| self.addresses.first());
+        match address {
+            Some(IpAddr::V4(address)) => format!("{scheme}://{address}:{}", self.port),
+            Some(IpAddr::V6(address)) => format!("{scheme}://[{address}]:{}", self.port),
+            None => format!("{scheme}://{}:{}", self.host, self.port),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum Record {
+    Ptr {
+        owner: String,
+        target: String,
+    },
+    Srv {
+        owner: String,
+        port: u16,
+        target: String,
+    },
+    Address {
+        owner: String,
+        address: IpAddr,
+    },
+}
+
+fn push_name(packet: &mut Vec<u8>, name: &str) {
+    for label in name.split('.') {
+        #[allow(clippy::cast_possible_truncation)]
+        packet.push(label.len() as u8);
+        packet.extend_from_slice(label.as_bytes());
+    }
+    packet.push(0);
+}
+
+/// DNS query for the PTR records of the MQTT services
+fn query() -> Vec<u8> {
+    let mut packet = Vec::new();
+    // id, flags, 2 questions, no answer, authority or additional records
+    packet.extend_from_slice(&[0, 0, 0, 0, 0, 2, 0, 0, 0, 0, 0, 0]);
+    for service in [SERVICE_MQTT, SERVICE_SECURE_MQTT] {
+        push_name(&mut packet, service);
+        packet.extend_from_slice(&TYPE_PTR.to_be_bytes());
+        packet.extend_from_slice(&(CLASS_IN | UNICAST_RESPONSE).to_be_bytes());
+    }
+    packet
+}
+
+struct Reader<'a> {
+    packet: &'a [u8],
+    position: usize,
+}
+
+impl Reader<'_> {
+    fn bytes(&mut self, amount: usize) -> Option<&[u8]> {
+        let bytes = self
+            .packet
+            .get(self.position..self.position.checked_add(amount)?)?;
+        self.position += amount;
+        Some(bytes)
+    }
+
+    fn u16(&mut self) -> Option<u16> {
+        let bytes = self.bytes(2)?;
+        Some(u16::from_be_bytes([bytes[0], bytes[1]]))
+    }
+
+    /// Domain name which might be compressed with pointers to earlier names in the packet
+    fn name(&mut self) -> Option<String> {
+        let mut labels = Vec::new();
+        let mut position = self.position;
+        let mut jumped = false;
+        // Limit the pointers followed to not loop forever on malicious packets
+        for _ in 0..128 {
+            let length = *self.packet.get(position)?;
+            if length == 0 {
+                if !jumped {
+                    self.position = position + 1;
+                }
+                return Some(labels.join("."));
+            } else if length & 0xC0 == 0xC0 {
+                let low = *self.packet.get(position + 1)?;
+                if !jumped {
+                    self.position = position + 2;
+                }
+                jumped = true;
+                position = usize::from(u16::from_be_bytes([length & 0x3F, low]));
+            } else if length < 64 {
+                let start = position + 1;
+                let end = start + usize::from(length);
+                labels.push(String::from_utf8_lossy(self.packet.get(start..end)?).into_owned());
+                position = end;
+            } else {
+                return None;
+            }
+        }
+        None
+    }
+}
+
+/// Records of all sections of a DNS response. Records of other types are skipped.
+fn parse(packet: &[u8]) -> Option<Vec<Record>> {
+    let mut reader = Reader {
+        packet,
+        position: 4,
+    };
+    let questions = reader.u16()?;
+    let records = (0..3).try_fold(0_usize, |sum, _| Some(sum + usize::from(reader.u16()?)))?;
+    for _ in 0..questions {
+        reader.name()?;
+        reader.bytes(4)?; // type and class
+    }
+
+    let mut result = Vec::new();
+    for _ in 0..records {
+        let owner = reader.name()?;
+        let kind = reader.u16()?;
+        reader.bytes(6)?; // class and time to live
+        let length = usize::from(reader.u16()?);
+        let data_start = reader.position;
+        match kind {
+            TYPE_PTR => {
+                let target = reader.name()?;
+                result.push(Record::Ptr { owner, target });
+            }
+            TYPE_SRV => {
+                reader.bytes(4)?; // priority and weight
+                let port = reader.u16()?;
+                let target = reader.name()?;
+                result.push(Record::Srv {
+                    owner,
+                    port,
+                    target,
+                });
+            }
+            TYPE_A if length == 4 => {
+                let bytes: [u8; 4] = reader.bytes(4)?.try_into().ok()?;
+                let address = IpAddr::V4(Ipv4Addr::from(bytes));
+                result.push(Record::Address { owner, address });
+            }
+            TYPE_AAAA if length == 16 => {
+                let bytes: [u8; 16] = reader.bytes(16)?.try_into().ok()?;
+                let address = IpAddr::V6(Ipv6Addr::from(bytes));
+                result.push(Record::Address { owner, address });
+            }
+            _ => {}
+        }
+        reader.position = data_start.checked_add(length)?;
+    }
+    Some(result)
+}
+
+/// Combine the records of all responses into the announced services sorted by their name
+fn services(records: &[Record]) -> Vec<Service> {
+    let mut services = BTreeMap::new();
+    for record in records {
+        let Record::Ptr { owner, target } = record else {
+            continue;
+        };
+        let secure = if owner.eq_ignore_ascii_case(SERVICE_SECURE_MQTT) {
+            true
+        } else if owner.eq_ignore_ascii_case(SERVICE_MQTT) {
+            false
+        } else {
+            continue;
+        };
+        let Some((port, host)) = records.iter().find_map(|record| match record {
+            Record::Srv {
+                owner,
+                port,
+                target: host,
+            } if owner.eq_ignore_ascii_case(target) => Some((*port, host)),
+            _ => None,
+        }) else {
+            continue;
+        };
+        let mut addresses = records
+            .iter()
+            .filter_map(|record| match record {
+                Record::Address { owner, address } if owner.eq_ignore_ascii_case(host) => {
+                    Some(*address)
+                }
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        addresses.sort_unstable();
+        addresses.dedup();
+        let name = target
+            .get(..target.len().saturating_sub(owner.len() + 1))
+            .filter(|name| !name.is_empty())
+            .unwrap_or(target)
+            .to_owned();
+        services.insert(
+            target.to_lowercase(),
+            Service {
+                name,
+                secure,
+                host: host.clone(),
+                port,
+                addresses,
+            },
+        );
+    }
+    services.into_values().collect()
+}
+
+/// Browse the local network via multicast DNS for the given duration
+pub fn discover(duration: Duration) -> anyhow::Result<Vec<Service>> {
+    let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0))
+        .context("Failed to open a UDP socket for mDNS")?;
+    socket
+        .send_to(&query(), (MDNS_ADDRESS, MDNS_PORT))
+        .context("Failed to send the mDNS query")?;
+
+    let deadline = Instant::now() + duration;
+    let mut records = Vec::new();
+    let mut buffer = [0; 9000];
+    loop {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            break;
+        }
+        socket.set_read_timeout(Some(remaining))?;
+        match socket.recv_from(&mut buffer) {
+            Ok((length, _)) => records.extend(parse(&buffer[..length]).unwrap_or_default()),
+            Err(err)
+                if matches!(
+                    err.kind(),
+                    std::io::ErrorKind::WouldBlock | std::io::ErrorKind::TimedOut
+                ) =>
+            {
+                break;
+            }
+            Err(err) => return Err(err).context("Failed to receive mDNS responses"),
+        }
+    }
+    Ok(services(&records))
+}
+
+pub fn show(duration: Duration) -> anyhow::Result<()> {
+    let services = discover(duration)?;
+    if services.is_empty() {
+        return Err(exit::fail(
+            exit::Code::NoMatch,
+            "No MQTT broker announced itself via mDNS",
+        ));
+    }
+    println!("{:30} {:40} Host", "Name", "URL");
+    for service in &services {
+        println!("{:30} {:40} {}", service.name, service.url(), service.host);
+    }
+    exit::chatter!("Found {} brokers", services.len());
+    Ok(())
+}
+
+#[test]
+fn query_works() {
+    let query = query();
+    assert_eq!(&query[..12], &[0, 0, 0, 0, 0, 2, 0, 0, 0, 0, 0, 0]);
+    let question = b"\x0c_secure-mqtt\x04_tcp\x05local\x00\x00\x0c\x80\x01";
+    assert!(query.ends_with(question));
+    assert_eq!(parse(&query).unwrap(), []);
+}
+
+#[test]
+#[allow(clippy::cast_possible_truncation)]
+fn response_works() {
+    let mut packet = vec![0, 0, 0x84, 0, 0, 0, 0, 3, 0, 0, 0, 1];
+    // PTR _mqtt._tcp.local -> Living room._mqtt._tcp.local
+    push_name(&mut packet, SERVICE_MQTT);
+    packet.extend_from_slice(&[0, 12, 0, 1, 0, 0, 0, 120]);
+    let instance = b"\x0bLiving room\xc0\x0c";
+    packet.extend_from_slice(&[0, instance.len() as u8]);
+    packet.extend_from_slice(instance);
+    // SRV with the instance name compressed -> hub.local:1883
+    let instance_position = packet.len() - instance.len();
+    packet.extend_from_slice(&[0xc0, instance_position as u8]);
+    packet.extend_from_slice(&[0, 33, 0x80, 1, 0, 0, 0, 120]);
+    let target = b"\x03hub\xc0\x17";
+    packet.extend_from_slice(&[0, 6 + target.len() as u8, 0, 0, 0, 0, 0x07, 0x5b]);
+    packet.extend_from_slice(target);
+    // A hub.local -> 192.168.1.2
+    let host_position = packet.len() - target.len();
+    packet.extend_from_slice(&[0xc0, host_position as u8]);
+    packet.extend_from_slice(&[0, 1, 0x80, 1, 0, 0, 0, 120, 0, 4, 192, 168, 1, 2]);
+    // TXT which is ignored
+    packet.extend_from_slice(&[0xc0, instance_position as u8]);
+    packet.extend_from_slice(&[0, 16, 0, 1, 0, 0, 0, 120, 0, 1, 0]);
+
+    let records = parse(&packet).unwrap();
+    assert_eq!(records.len(), 3);
+    assert_eq!(
+        services(&records),
+        [Service {
+            name: "Living room".to_owned(),
+            secure: false,
+            host: "hub.local".to_owned(),
+            port: 1883,
+            addresses: vec![IpAddr::V4(Ipv4Addr::new(192, 168, 1, 2))],
+        }]
+    );
+    assert_eq!(services(&records)[0].url(), "mqtt://192.168.1.2:1883");
+
+    assert!(parse(&packet[..packet.len() - 3]).is_none());
+}
@@ -0,0 +1,112 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use rumqttc::v5::mqttbytes::v5::ConnectReturnCode as ConnectReturnCodeV5;
+use rumqttc::ConnectReturnCode;
+
+static QUIET: AtomicBool = AtomicBool::new(false);
+
+/// Suppress the human readable progress and warnings on stderr
+pub fn set_quiet(quiet: bool) {
+    QUIET.store(quiet, Ordering::Relaxed);
+}
+
+pub fn is_quiet() -> bool {
+    QUIET.load(Ordering::Relaxed)
+}
+
+/// Like `eprintln!` but suppressed with `--quiet`
+macro_rules! chatter {
+    ($($arg:tt)*) => {
+        if !$crate::exit::is_quiet() {
+            eprintln!($($arg)*);
+        }
+    };
+}
+#[allow(clippy::redundant_pub_crate)]
+pub(crate) use chatter;
+
+/// Exit codes of failures scripts might want to handle differently.
+///
+/// Other errors exit with 1, invalid arguments with 2.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Code {
+    /// The broker could not be reached or refused the connection
+    Connection = 3,
+    /// The broker rejected the credentials or the client is not authorized
+    Auth = 4,
+    /// The broker did not acknowledge everything within the timeout
+    Timeout = 5,
+    /// No message was received
+    NoMatch = 6,
+    /// Only some of the expected messages or acknowledgements arrived
+    Partial = 7,
+}
+
+/// Error ending the command with a specific exit code
+#[derive(Debug)]
+struct Failure {
+    code: Code,
+    message: String,
+}
+
+impl std::fmt::Display for Failure {
+    fn fmt(&self, fmt: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        fmt.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for Failure {}
+
+/// Create an error which ends the process with the given exit code
+pub fn fail(code: Code, message: impl Into<String>) -> anyhow::Error {
+    anyhow::Error::new(Failure {
+        code,
+        message: message.into(),
+    })
+}
+
+/// The exit code of the error based on its cause
+pub fn code(err: &anyhow::Error) -> u8 {
+    let code = err.chain().find_map(|cause| {
+        if let Some(failure) = cause.downcast_ref::<Failure>() {
+            return Some(failure.code);
+        }
+        if let Some(err) = cause.downcast_ref::<rumqttc::ConnectionError>() {
+            return Some(match err {
+                rumqttc::ConnectionError::ConnectionRefused(
+                    ConnectReturnCode::BadUserNamePassword | ConnectReturnCode::NotAuthorized,
+                ) => Code::Auth,
+                _ => Code::Connection,
+            });
+        }
+        if let Some(err) = cause.downcast_ref::<rumqttc::v5::ConnectionError>() {
+            return Some(match err {
+                rumqttc::v5::ConnectionError::ConnectionRefused(
+                    ConnectReturnCodeV5::BadUserNamePassword
+                    | ConnectReturnCodeV5::NotAuthorized
+                    | ConnectReturnCodeV5::Banned
+                    | ConnectReturnCodeV5::BadAuthenticationMethod,
+                ) => Code::Auth,
+                _ => Code::Connection,
+            });
+        }
+        None
+    });
+    code.map_or(1, |code| code as u8)
+}
+
+#[test]
+fn code_works() {
+    assert_eq!(code(&anyhow::anyhow!("something")), 1);
+    assert_eq!(code(&fail(Code::Timeout, "too slow")), 5);
+    let refused = anyhow::Error::new(rumqttc::ConnectionError::ConnectionRefused(
+        ConnectReturnCode::NotAuthorized,
+    ))
+    .context("Failed to connect");
+    assert_eq!(code(&refused), 4);
+    let unavailable = anyhow::Error::new(rumqttc::ConnectionError::ConnectionRefused(
+        ConnectReturnCode::ServiceUnavailable,
+    ));
+    assert_eq!(code(&unavailable), 3);
+    assert_eq!(code(&fail(Code::Partial, "2 of 3").context("Publish")), 7);
+}
@@ -8,9 +8,59 @@ pub const fn qos(qos: QoS) -> &'static str {
     }
 }
 
+/// Age like `3s ago` in the biggest fitting unit
+pub fn age(seconds: i64) -> String {
+    let seconds = seconds.max(0);
+    if seconds < 60 {
+        format!("{seconds}s ago")
+    } else if seconds < 60 * 60 {
+        format!("{}m ago", seconds / 60)
+    } else if seconds < 24 * 60 * 60 {
+        format!("{}h ago", seconds / (60 * 60))
+    } else {
+        format!("{}d ago", seconds / (24 * 60 * 60))
+    }
+}
+
+/// Amount of bytes like `1.5 KiB` in the biggest fitting binary unit
+pub fn bytes(bytes: u64) -> String {
+    const UNITS: [&str; 6] = ["KiB", "MiB", "GiB", "TiB", "PiB", "EiB"];
+    if bytes < 1024 {
+        return format!("{bytes} B");
+    }
+    #[allow(clippy::cast_precision_loss)]
+    let mut value = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    format!("{value:.1} {}", UNITS[unit])
+}
+
+#[test]
+fn formats_age() {
+    assert_eq!(age(-2), "0s ago");
+    assert_eq!(age(3), "3s ago");
+    assert_eq!(age(59), "59s ago");
+    assert_eq!(age(60), "1m ago");
+    assert_eq!(age(5 * 60 + 59), "5m ago");
+    assert_eq!(age(2 * 60 * 60), "2h ago");
+    assert_eq!(age(3 * 24 * 60 * 60), "3d ago");
+}
+
 #[test]
 fn formats_qos() {
     assert_eq!("AtLeastOnce", qos(QoS::AtLeastOnce));
     assert_eq!("AtMostOnce", qos(QoS::AtMostOnce));
     assert_eq!("ExactlyOnce", qos(QoS::ExactlyOnce));
 }
+
+#[test]
+fn formats_bytes() {
+    assert_eq!(bytes(0), "0 B");
+    assert_eq!(bytes(1023), "1023 B");
+    assert_eq!(bytes(1536), "1.5 KiB");
+    assert_eq!(bytes(5 * 1024 * 1024), "5.0 MiB");
+    assert_eq!(bytes(u64::MAX), "16.0 EiB");
+}
@@ -0,0 +1,36 @@
+use std::fs::File;
+use std::io::Write;
+use std::path::Path;
+
+use anyhow::Context;
+
+/// Appends a line describing the focus and selection to a file whenever it changes.
+///
+/// Screen readers can follow the file while the terminal UI itself is only drawn.
+pub struct Announcer {
+    file: File,
+    last: String,
+}
+
+impl Announcer {
+    pub fn new(path: &Path) -> anyhow::Result<Self> {
+        let file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .with_context(|| format!("Failed to open the announce file {}", path.display()))?;
+        Ok(Self {
+            file,
+            last: String::new(),
+        })
+    }
+
+    pub fn announce(&mut self, text: String) -> std::io::Result<()> {
+        if text == self.last {
+            return Ok(());
+        }
+        writeln!(self.file, "{text}")?;
+        self.last = text;
+        Ok(())
+    }
+}
@@ -0,0 +1,58 @@
+use std::io::Write;
+
+use chrono::NaiveDateTime;
+
+use crate::interactive::mqtt_history::MqttHistory;
+
+/// Ring the terminal bell when the watched topic receives a message to not miss rare events
+pub struct Bell {
+    pub topic: String,
+    /// Time of the newest message of the topic which was already noticed
+    last: Option<NaiveDateTime>,
+}
+
+impl Bell {
+    /// Only messages arriving from now on ring the bell
+    pub fn new(topic: String, history: &MqttHistory) -> Self {
+        let last = latest(&topic, history);
+        Self { topic, last }
+    }
+
+    /// Returns `true` when a message arrived since the last check
+    pub fn check(&mut self, history: &MqttHistory) -> bool {
+        let latest = latest(&self.topic, history);
+        let arrived = latest > self.last;
+        self.last = self.last.max(latest);
+        arrived
+    }
+}
+
+/// Retained messages are not new so they are ignored
+fn latest(topic: &str, history: &MqttHistory) -> Option<NaiveDateTime> {
+    history
+        .get(topic)?
+        .iter()
+        .rev()
+        .find_map(|entry| entry.time.as_optional().copied())
+}
+
+pub fn ring() -> std::io::Result<()> {
+    let mut stdout = std::io::stdout();
+    stdout.write_all(b"\x07")?;
+    stdout.flush()
+}
+
+#[test]
+fn check_works() {
+    let mut history = MqttHistory::example();
+    let mut bell = Bell::new("foo/bar".to_owned(), &history);
+    assert!(!bell.check(&history));
+
+    std::thread::sleep(std::time::Duration::from_millis(2));
+    history.add(
+        "foo/bar".to_owned(),
+        crate::mqtt::HistoryEntry::example("open", crate::mqtt::Time::new_now(false)),
+    );
+    assert!(bell.check(&history));
+    assert!(!bell.check(&history));
+}
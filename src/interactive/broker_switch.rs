@@ -0,0 +1,203 @@
+use std::sync::mpsc;
+use std::time::Duration;
+
+use ratatui::layout::{Alignment, Rect};
+use ratatui::style::{Color, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Clear, Paragraph, Wrap};
+use ratatui::Frame;
+
+use crate::cli::Broker;
+use crate::discover;
+use crate::interactive::mqtt_history::STYLE_DARKGRAY;
+use crate::interactive::ui::{self, STYLE_BOLD};
+
+const PROMPT: &str = "Broker: ";
+
+/// How long to wait for brokers announcing themselves via mDNS
+const DISCOVERY_DURATION: Duration = Duration::from_secs(2);
+
+/// Connect to another broker without restarting and losing the received messages
+pub struct Popup {
+    pub input: String,
+    /// Keep the received messages or start with an empty history
+    pub keep_history: bool,
+    /// Why the last attempt to connect failed
+    pub error: Option<String>,
+    /// Brokers found via mDNS
+    discovered: anyhow::Result<Vec<discover::Service>>,
+    /// Pending discovery in the background
+    discovery: Option<mpsc::Receiver<anyhow::Result<Vec<discover::Service>>>>,
+    selected: Option<usize>,
+}
+
+impl Popup {
+    pub const fn new() -> Self {
+        Self {
+            input: String::new(),
+            keep_history: true,
+            error: None,
+            discovered: Ok(Vec::new()),
+            discovery: None,
+            selected: None,
+        }
+    }
+
+    /// Also look for brokers in the local network which can be chosen instead of typing their URL
+    pub fn with_discovery() -> Self {
+        let (sender, receiver) = mpsc::channel();
+        std::thread::spawn(move || _ = sender.send(discover::discover(DISCOVERY_DURATION)));
+        Self {
+            discovery: Some(receiver),
+            ..Self::new()
+        }
+    }
+
+    fn update_discovery(&mut self) {
+        let Some(receiver) = &self.discovery else {
+            return;
+        };
+        match receiver.try_recv() {
+            Ok(result) => {
+                self.discovered = result;
+                self.discovery = None;
+            }
+            Err(mpsc::TryRecvError::Empty) => {}
+            Err(mpsc::TryRecvError::Disconnected) => self.discovery = None,
+        }
+    }
+
+    pub fn select_next(&mut self) {
+        let Ok(discovered) = &self.discovered else {
+            return;
+        };
+        if discovered.is_empty() {
+            return;
+        }
+        let index = self
+            .selected
+            .map_or(0, |index| index.saturating_add(1).min(discovered.len() - 1));
+        self.selected = Some(index);
+        self.input = discovered[index].url();
+    }
+
+    pub fn select_previous(&mut self) {
+        let Ok(discovered) = &self.discovered else {
+            return;
+        };
+        if let Some(index) = self.selected {
+            let index = index.saturating_sub(1);
+            self.selected = Some(index);
+            self.input = discovered[index].url();
+        }
+    }
+
+    pub fn broker(&self) -> anyhow::Result<Broker> {
+        self.input.trim().parse()
+    }
+
+    fn discovered_lines(&self) -> Vec<Line<'_>> {
+        let mut lines = vec![
+            Line::raw(""),
+            Line::styled("Discovered via mDNS", STYLE_BOLD),
+        ];
+        match &self.discovered {
+            _ if self.discovery.is_some() => {
+                lines.push(Line::styled("Searching…", STYLE_DARKGRAY));
+            }
+            Ok(discovered) if discovered.is_empty() => {
+                lines.push(Line::styled("No broker announced itself", STYLE_DARKGRAY));
+            }
+            Ok(discovered) => {
+                for (index, service) in discovered.iter().enumerate() {
+                    let line = Line::raw(format!("{}  {}", service.name, service.url()));
+                    if self.selected == Some(index) {
+                        lines.push(line.style(Style::new().fg(Color::Black).bg(Color::LightGreen)));
+                    } else {
+                        lines.push(line);
+                    }
+                }
+                lines.push(Line::styled(
+                    "↑↓ to choose a discovered broker",
+                    STYLE_DARKGRAY,
+                ));
+            }
+            Err(err) => lines.push(Line::styled(format!("{err:#}"), STYLE_DARKGRAY)),
+        }
+        lines
+    }
+
+    pub fn draw(&mut self, frame: &mut Frame) {
+        self.update_discovery();
+
+        let mut lines = vec![
+            Line::styled(
+                "URL like mqtt://localhost:1883 or mqtts://broker.example",
+                STYLE_DARKGRAY,
+            ),
+            Line::raw(""),
+            Line::from(vec![
+                Span::styled(PROMPT, STYLE_BOLD),
+                Span::raw(self.input.as_str()),
+            ]),
+            Line::raw(""),
+            Line::raw(if self.keep_history {
+                "[x] Keep the received messages"
+            } else {
+                "[ ] Keep the received messages"
+            }),
+        ];
+        lines.extend(self.discovered_lines());
+        if let Some(error) = &self.error {
+            lines.push(Line::raw(""));
+            lines.push(Line::styled(error.as_str(), Style::new().fg(Color::Red)));
+        }
+
+        let area = popup_area(frame.size(), lines.len());
+        let block = Block::bordered()
+            .border_style(Style::new().fg(Color::LightGreen))
+            .title_alignment(Alignment::Center)
+            .title("Switch broker");
+        frame.render_widget(Clear, area); // clear the background of the popup
+        frame.render_widget(
+            Paragraph::new(lines)
+                .block(block)
+                .wrap(Wrap { trim: false }),
+            area,
+        );
+
+        #[allow(clippy::cast_possible_truncation)]
+        let cursor_x = area
+            .x
+            .saturating_add(1)
+            .saturating_add(PROMPT.len() as u16)
+            .saturating_add(ui::width(&self.input) as u16);
+        frame.set_cursor(
+            cursor_x.min(area.right().saturating_sub(2)),
+            area.y.saturating_add(3),
+        );
+    }
+}
+
+/// Centered area with some space for a wrapped error message
+fn popup_area(area: Rect, lines: usize) -> Rect {
+    #[allow(clippy::cast_possible_truncation)]
+    let height = (lines as u16)
+        .saturating_add(4)
+        .min(area.height.saturating_sub(4));
+    let width = area.width.saturating_sub(8).min(80);
+    Rect {
+        x: area.width.saturating_sub(width) / 2,
+        y: area.height.saturating_sub(height) / 2,
+        width,
+        height,
+    }
+}
+
+#[test]
+fn broker_works() {
+    let mut popup = Popup::new();
+    assert!(popup.broker().is_err());
+    popup.input = " mqtts://localhost ".to_owned();
+    assert!(matches!(popup.broker(), Ok(Broker::Ssl { port: 8883, .. })));
+}
@@ -1,35 +1,128 @@
+use std::collections::BTreeMap;
+
 use ratatui::layout::{Alignment, Rect};
 use ratatui::style::{Color, Modifier, Style};
-use ratatui::text::{Line, Text};
+use ratatui::text::{Line, Span};
 use ratatui::widgets::{Block, Clear, Paragraph};
 use ratatui::Frame;
 
-pub fn draw_popup(frame: &mut Frame, topic: &str) {
-    let block = Block::bordered()
-        .border_style(Style::new().fg(Color::Red))
-        .title_alignment(Alignment::Center)
-        .title("Clean retained topics");
-    let text = vec![
-        Line::raw("Clean the following topic and all relative below?"),
-        Line::styled(
-            topic,
-            Style::new().add_modifier(Modifier::BOLD | Modifier::ITALIC),
-        ),
-        Line::raw(""),
-        Line::raw("Confirm with Enter, abort with Esc"),
-    ];
-    let text = Text::from(text);
-    let area = popup_area(frame.size(), text.width());
-    let paragraph = Paragraph::new(text)
-        .block(block)
-        .alignment(Alignment::Center);
-    frame.render_widget(Clear, area); // clear the background of the popup
-    frame.render_widget(paragraph, area);
+use crate::interactive::mqtt_history::{MqttHistory, STYLE_DARKGRAY};
+
+/// Confirmation of cleaning the topics and all relative below which previews the topics affected.
+pub struct Popup {
+    pub topics: Vec<String>,
+    /// The threshold of topics which was exceeded and requires to confirm again
+    pub extra_confirmation: Option<usize>,
+    scroll: usize,
+}
+
+impl Popup {
+    pub const fn new(topics: Vec<String>) -> Self {
+        Self {
+            topics,
+            extra_confirmation: None,
+            scroll: 0,
+        }
+    }
+
+    pub fn scroll_up(&mut self, amount: usize) -> bool {
+        let before = self.scroll;
+        self.scroll = self.scroll.saturating_sub(amount);
+        before != self.scroll
+    }
+
+    /// Limited to the available lines on the next draw
+    pub fn scroll_down(&mut self, amount: usize) {
+        self.scroll = self.scroll.saturating_add(amount);
+    }
+
+    /// All topics which will be cleaned with the payload size of their last message
+    pub fn affected(&self, history: &MqttHistory) -> BTreeMap<String, usize> {
+        self.topics
+            .iter()
+            .flat_map(|topic| history.get_topics_below(topic))
+            .map(|topic| {
+                let size = history
+                    .get(&topic)
+                    .and_then(|entries| entries.last())
+                    .map_or(0, |entry| entry.payload_size);
+                (topic, size)
+            })
+            .collect()
+    }
+
+    pub fn draw(&mut self, frame: &mut Frame, history: &MqttHistory) {
+        let affected = self.affected(history);
+        let header = Line::raw(format!("Clean the following {} topics?", affected.len()));
+        let footer = self.extra_confirmation.map_or_else(
+            || Line::raw("Confirm with Enter, abort with Esc, scroll with ↑↓"),
+            |max_topics| {
+                Line::styled(
+                    format!("More than {max_topics} topics! Confirm again with Y, abort with any other key"),
+                    Style::new().fg(Color::Red).add_modifier(Modifier::BOLD),
+                )
+            },
+        );
+        let list = affected
+            .into_iter()
+            .map(|(topic, size)| {
+                Line::from(vec![
+                    Span::styled(
+                        topic,
+                        Style::new().add_modifier(Modifier::BOLD | Modifier::ITALIC),
+                    ),
+                    Span::styled(format!(" ({size} bytes)"), STYLE_DARKGRAY),
+                ])
+            })
+            .collect::<Vec<_>>();
+
+        let text_width = list
+            .iter()
+            .chain([&header, &footer])
+            .map(Line::width)
+            .max()
+            .unwrap_or_default();
+        // header, empty line, list, empty line, footer
+        let area = popup_area(frame.size(), text_width, list.len().saturating_add(4));
+        let block = Block::bordered()
+            .border_style(Style::new().fg(Color::Red))
+            .title_alignment(Alignment::Center)
+            .title("Clean retained topics");
+        let inner = block.inner(area);
+        frame.render_widget(Clear, area); // clear the background of the popup
+        frame.render_widget(block, area);
+
+        let list_height = inner.height.saturating_sub(4);
+        self.scroll = self
+            .scroll
+            .min(list.len().saturating_sub(usize::from(list_height)));
+        let list = list
+            .into_iter()
+            .skip(self.scroll)
+            .take(usize::from(list_height))
+            .collect::<Vec<_>>();
+
+        let mut lines = vec![header, Line::raw("")];
+        lines.extend(list);
+        lines.push(Line::raw(""));
+        let paragraph = Paragraph::new(lines).alignment(Alignment::Center);
+        frame.render_widget(paragraph, inner);
+        let footer_area = Rect {
+            y: inner.bottom().saturating_sub(1),
+            height: 1.min(inner.height),
+            ..inner
+        };
+        frame.render_widget(
+            Paragraph::new(footer).alignment(Alignment::Center),
+            footer_area,
+        );
+    }
 }
 
 /// helper function to create a centered area using up certain percentage of the available `area`.
-fn popup_area(area: Rect, text_width: usize) -> Rect {
-    let height = area.height.min(6);
+fn popup_area(area: Rect, text_width: usize, text_height: usize) -> Rect {
+    #[allow(clippy::cast_possible_truncation)]
+    let height = (text_height.saturating_add(2) as u16).min(area.height.saturating_sub(4));
     let max_width = area.width.saturating_sub(4);
     #[allow(clippy::cast_possible_truncation)]
     let width = text_width.saturating_add(14).min(max_width as usize) as u16;
@@ -40,3 +133,14 @@ fn popup_area(area: Rect, text_width: usize) -> Rect {
         height,
     }
 }
+
+#[test]
+fn affected_lists_topics_below_once() {
+    let history = MqttHistory::example();
+    let popup = Popup::new(vec!["foo".to_owned(), "foo/bar".to_owned()]);
+    let affected = popup.affected(&history);
+    assert_eq!(
+        affected.into_iter().collect::<Vec<_>>(),
+        [("foo/bar".to_owned(), 1), ("foo/test".to_owned(), 1)]
+    );
+}
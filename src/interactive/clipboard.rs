@@ -0,0 +1,13 @@
+use std::io::Write;
+
+use base64::Engine;
+
+/// Copy the text to the clipboard of the terminal emulator via the OSC 52 escape sequence.
+///
+/// This also works over SSH but some terminals (or tmux without `set-clipboard on`) ignore it.
+pub fn copy(text: &str) -> std::io::Result<()> {
+    let encoded = base64::engine::general_purpose::STANDARD.encode(text);
+    let mut stdout = std::io::stdout();
+    write!(stdout, "\x1b]52;c;{encoded}\x07")?;
+    stdout.flush()
+}
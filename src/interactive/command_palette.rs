@@ -0,0 +1,440 @@
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use ratatui::layout::{Alignment, Rect};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Clear, List, ListItem, ListState, Paragraph};
+use ratatui::Frame;
+
+use crate::interactive::mqtt_history::STYLE_DARKGRAY;
+use crate::interactive::ui::{self, ElementInFocus, STYLE_BOLD};
+use crate::interactive::App;
+use crate::payload::Payload;
+
+const PROMPT: &str = "> ";
+
+/// An action of the TUI which is run by its key binding
+#[derive(Debug, Clone, Copy)]
+pub struct Action {
+    pub name: &'static str,
+    /// Shown next to the name to make the key binding discoverable
+    pub key: &'static str,
+    pub code: KeyCode,
+}
+
+impl Action {
+    const fn new(name: &'static str, key: &'static str, code: KeyCode) -> Self {
+        Self { name, key, code }
+    }
+
+    pub const fn key_event(&self) -> KeyEvent {
+        KeyEvent::new(self.code, KeyModifiers::NONE)
+    }
+}
+
+/// All actions which are currently possible in the element in focus
+#[allow(clippy::too_many_lines)]
+pub fn actions(app: &App) -> Vec<Action> {
+    let mut actions = Vec::new();
+    match app.focus {
+        ElementInFocus::TopicOverview => {
+            actions.push(Action::new("Search topics", "/", KeyCode::Char('/')));
+            if app.topic_overview.last_search().is_some() {
+                actions.push(Action::new("Next match", "n", KeyCode::Char('n')));
+                actions.push(Action::new("Previous match", "N", KeyCode::Char('N')));
+            }
+            if let Some(topic) = app.topic_overview.get_selected() {
+                actions.push(Action::new("Mark topic", "m", KeyCode::Char('m')));
+                if app
+                    .mqtt_thread
+                    .is_runtime_subscription(&format!("{topic}/#"))
+                {
+                    actions.push(Action::new(
+                        "Unsubscribe from topics below",
+                        "s",
+                        KeyCode::Char('s'),
+                    ));
+                } else {
+                    actions.push(Action::new(
+                        "Subscribe to topics below",
+                        "s",
+                        KeyCode::Char('s'),
+                    ));
+                }
+                if app.bell.as_ref().is_some_and(|bell| bell.topic == topic) {
+                    actions.push(Action::new("Stop the bell", "B", KeyCode::Char('B')));
+                } else {
+                    actions.push(Action::new(
+                        "Ring the bell on messages of the topic",
+                        "B",
+                        KeyCode::Char('B'),
+                    ));
+                }
+            }
+            if !app.topic_overview.marked.is_empty() {
+                actions.push(Action::new("Unmark all topics", "Esc", KeyCode::Esc));
+            }
+            if !app.targeted_topics().is_empty() {
+                actions.push(Action::new("Clean retained topics", "Del", KeyCode::Delete));
+                actions.push(Action::new(
+                    "Forget topics locally",
+                    "f",
+                    KeyCode::Char('f'),
+                ));
+                actions.push(Action::new("Ignore topics", "i", KeyCode::Char('i')));
+            }
+            if app.is_selected_truncated() {
+                actions.push(Action::new(
+                    "Request the full payload",
+                    "R",
+                    KeyCode::Char('R'),
+                ));
+            }
+            if app.topic_overview.get_selected().is_some() {
+                if app.is_selected_on_dashboard() {
+                    actions.push(Action::new(
+                        "Unpin from the dashboard",
+                        "w",
+                        KeyCode::Char('w'),
+                    ));
+                } else {
+                    actions.push(Action::new("Pin to the dashboard", "w", KeyCode::Char('w')));
+                }
+            }
+            actions.push(Action::new("Dashboard", "D", KeyCode::Char('D')));
+            if app.selected_tasmota_device().is_some() {
+                actions.push(Action::new("Tasmota command", "t", KeyCode::Char('t')));
+            }
+            if app.has_zigbee2mqtt() {
+                actions.push(Action::new("Zigbee2MQTT devices", "z", KeyCode::Char('z')));
+            }
+            if app.topic_overview.hide_system_topics {
+                actions.push(Action::new("Show $ topics", "$", KeyCode::Char('$')));
+            } else if app.has_system_topics() {
+                actions.push(Action::new("Hide $ topics", "$", KeyCode::Char('$')));
+            }
+            actions.push(Action::new(
+                "Subscribe to a topic filter",
+                "+",
+                KeyCode::Char('+'),
+            ));
+            if app.topic_overview.flat {
+                actions.push(Action::new("Show the topic tree", "T", KeyCode::Char('T')));
+                actions.push(Action::new("Sort by next column", "o", KeyCode::Char('o')));
+                actions.push(Action::new("Reverse sort order", "O", KeyCode::Char('O')));
+            } else {
+                actions.push(Action::new(
+                    "Show a flat table of all topics",
+                    "T",
+                    KeyCode::Char('T'),
+                ));
+            }
+            actions.push(Action::new(
+                "Add a marker at the current time",
+                "M",
+                KeyCode::Char('M'),
+            ));
+            actions.push(Action::new("Switch broker", "b", KeyCode::Char('b')));
+            actions.push(Action::new(
+                "Payload size histogram and biggest payloads",
+                "S",
+                KeyCode::Char('S'),
+            ));
+            actions.push(Action::new("Settings", ",", KeyCode::Char(',')));
+            actions.push(Action::new("Toggle relative time", "a", KeyCode::Char('a')));
+            if app.is_aggregate_selected() {
+                actions.push(Action::new(
+                    "Graph sum, average, min or max of children",
+                    "g",
+                    KeyCode::Char('g'),
+                ));
+            } else {
+                actions.push(Action::new(
+                    "Graph value, rate or size",
+                    "g",
+                    KeyCode::Char('g'),
+                ));
+            }
+            if app.can_switch_to_payload() {
+                actions.push(Action::new("Switch to payload", "Tab", KeyCode::Tab));
+            } else if app.can_switch_to_history_table() {
+                actions.push(Action::new("Switch to history", "Tab", KeyCode::Tab));
+            }
+        }
+        ElementInFocus::Payload => {
+            if matches!(
+                app.get_selected_payload().as_deref(),
+                Some(Payload::Json(_) | Payload::MessagePack(_))
+            ) {
+                actions.push(Action::new("Search payload", "/", KeyCode::Char('/')));
+                if !app.details.payload.search.is_empty() {
+                    actions.push(Action::new("Next match", "n", KeyCode::Char('n')));
+                    actions.push(Action::new("Previous match", "N", KeyCode::Char('N')));
+                }
+                actions.push(Action::new("Copy jq path", "y", KeyCode::Char('y')));
+                actions.push(Action::new("Copy JSON Pointer", "Y", KeyCode::Char('Y')));
+            }
+            if matches!(
+                app.get_selected_payload().as_deref(),
+                Some(Payload::Binary(_))
+            ) && app
+                .details
+                .payload
+                .binary_state
+                .selected_address()
+                .is_some()
+            {
+                actions.push(Action::new("Next value type", "v", KeyCode::Char('v')));
+                actions.push(Action::new("Toggle endianness", "e", KeyCode::Char('e')));
+            }
+            if matches!(
+                app.get_selected_payload().as_deref(),
+                Some(Payload::String(_))
+            ) {
+                actions.push(Action::new("Previous CSV column", "←", KeyCode::Left));
+                actions.push(Action::new("Next CSV column", "→", KeyCode::Right));
+            }
+            actions.push(Action::new(
+                "Convert selected value",
+                "c",
+                KeyCode::Char('c'),
+            ));
+            if app.can_switch_to_history_table() {
+                actions.push(Action::new("Switch to history", "Tab", KeyCode::Tab));
+            }
+            actions.push(Action::new("Switch to topics", "S-Tab", KeyCode::BackTab));
+        }
+        ElementInFocus::HistoryTable => {
+            if !app.details.payload.json_state.selected().is_empty() {
+                actions.push(Action::new(
+                    "Show the whole payloads instead of the selected JSON value",
+                    "x",
+                    KeyCode::Char('x'),
+                ));
+            }
+            if app.details.pinned {
+                actions.push(Action::new(
+                    "Follow newest message",
+                    "p",
+                    KeyCode::Char('p'),
+                ));
+            } else {
+                actions.push(Action::new("Pin selected message", "p", KeyCode::Char('p')));
+            }
+            if app.compare.is_some() {
+                actions.push(Action::new("Unpin comparison", "P", KeyCode::Char('P')));
+            } else {
+                actions.push(Action::new(
+                    "Pin message to compare with the latest",
+                    "P",
+                    KeyCode::Char('P'),
+                ));
+            }
+            if app.is_selected_truncated() {
+                actions.push(Action::new(
+                    "Request the full payload",
+                    "R",
+                    KeyCode::Char('R'),
+                ));
+            }
+            actions.push(Action::new(
+                "Add a marker at the current time",
+                "M",
+                KeyCode::Char('M'),
+            ));
+            actions.push(Action::new(
+                "Collapse consecutive duplicate messages",
+                "d",
+                KeyCode::Char('d'),
+            ));
+            actions.push(Action::new("Toggle relative time", "a", KeyCode::Char('a')));
+            actions.push(Action::new(
+                "Graph value, rate or size",
+                "g",
+                KeyCode::Char('g'),
+            ));
+            actions.push(Action::new("Toggle time column", "1", KeyCode::Char('1')));
+            actions.push(Action::new("Toggle QoS column", "2", KeyCode::Char('2')));
+            actions.push(Action::new("Toggle size column", "3", KeyCode::Char('3')));
+            actions.push(Action::new("Toggle retain column", "4", KeyCode::Char('4')));
+            actions.push(Action::new("Switch to topics", "Tab", KeyCode::Tab));
+        }
+        _ => {}
+    }
+    actions.push(Action::new("Toggle MQTT packet log", "F12", KeyCode::F(12)));
+    actions.push(Action::new("Quit", "q", KeyCode::Char('q')));
+    actions
+}
+
+/// Whether all chars of the query appear in the name in the same order
+fn fuzzy_matches(name: &str, query: &str) -> bool {
+    let mut name = name.chars().flat_map(char::to_lowercase);
+    query
+        .chars()
+        .flat_map(char::to_lowercase)
+        .filter(|char| !char.is_whitespace())
+        .all(|query_char| name.any(|char| char == query_char))
+}
+
+/// Fuzzy searchable list of all actions to keep them discoverable
+pub struct Palette {
+    actions: Vec<Action>,
+    pub input: String,
+    selected: usize,
+    /// Focus to return to, also the one the action is run in
+    pub previous: Box<ElementInFocus>,
+}
+
+impl Palette {
+    pub fn new(actions: Vec<Action>, previous: ElementInFocus) -> Self {
+        Self {
+            actions,
+            input: String::new(),
+            selected: 0,
+            previous: Box::new(previous),
+        }
+    }
+
+    /// Actions matching the input. The ones containing the input as it is come first.
+    fn matching(&self) -> Vec<&Action> {
+        let query = self.input.to_lowercase();
+        let (mut contained, fuzzy): (Vec<_>, Vec<_>) = self
+            .actions
+            .iter()
+            .filter(|action| fuzzy_matches(action.name, &query))
+            .partition(|action| action.name.to_lowercase().contains(query.trim()));
+        contained.extend(fuzzy);
+        contained
+    }
+
+    pub fn selected_action(&self) -> Option<Action> {
+        self.matching().get(self.selected).copied().copied()
+    }
+
+    pub fn push(&mut self, char: char) {
+        self.input.push(char);
+        self.selected = 0;
+    }
+
+    pub fn pop(&mut self) {
+        self.input.pop();
+        self.selected = 0;
+    }
+
+    pub fn select_next(&mut self) {
+        let amount = self.matching().len();
+        self.selected = self
+            .selected
+            .saturating_add(1)
+            .min(amount.saturating_sub(1));
+    }
+
+    pub fn select_previous(&mut self) {
+        self.selected = self.selected.saturating_sub(1);
+    }
+
+    pub fn draw(&self, frame: &mut Frame) {
+        let matching = self.matching();
+        let items = matching
+            .iter()
+            .map(|action| {
+                ListItem::new(Line::from(vec![
+                    Span::raw(action.name),
+                    Span::raw(" "),
+                    Span::styled(format!("({})", action.key), STYLE_DARKGRAY),
+                ]))
+            })
+            .collect::<Vec<_>>();
+
+        let area = frame.size();
+        #[allow(clippy::cast_possible_truncation)]
+        let height = (self.actions.len() as u16)
+            .saturating_add(3)
+            .min(area.height.saturating_sub(2));
+        let width = area.width.saturating_sub(8).min(60);
+        let area = Rect {
+            x: area.width.saturating_sub(width) / 2,
+            y: area.y.saturating_add(1),
+            width,
+            height,
+        };
+        let block = Block::bordered()
+            .border_style(Style::new().fg(Color::LightGreen))
+            .title_alignment(Alignment::Center)
+            .title("Commands");
+        let inner = block.inner(area);
+        frame.render_widget(Clear, area); // clear the background of the popup
+        frame.render_widget(block, area);
+
+        let input_area = Rect { height: 1, ..inner };
+        frame.render_widget(
+            Paragraph::new(Line::from(vec![
+                Span::styled(PROMPT, STYLE_BOLD),
+                Span::raw(self.input.as_str()),
+            ])),
+            input_area,
+        );
+        #[allow(clippy::cast_possible_truncation)]
+        let cursor_x = inner
+            .x
+            .saturating_add(PROMPT.len() as u16)
+            .saturating_add(ui::width(&self.input) as u16);
+        frame.set_cursor(cursor_x.min(inner.right().saturating_sub(1)), inner.y);
+
+        let list_area = Rect {
+            y: inner.y.saturating_add(1),
+            height: inner.height.saturating_sub(1),
+            ..inner
+        };
+        let list = List::new(items).highlight_style(
+            Style::new()
+                .fg(Color::Black)
+                .bg(Color::LightGreen)
+                .add_modifier(Modifier::BOLD),
+        );
+        let mut state = ListState::default().with_selected(Some(self.selected));
+        frame.render_stateful_widget(list, list_area, &mut state);
+    }
+}
+
+#[test]
+fn fuzzy_matches_works() {
+    assert!(fuzzy_matches("Toggle relative time", ""));
+    assert!(fuzzy_matches("Toggle relative time", "reltime"));
+    assert!(fuzzy_matches("Toggle relative time", "Rel Time"));
+    assert!(!fuzzy_matches("Toggle relative time", "timerel"));
+    assert!(!fuzzy_matches("Quit", "quiet"));
+}
+
+#[test]
+fn matching_prefers_contained() {
+    let palette = Palette::new(
+        vec![
+            Action::new("Search topics", "/", KeyCode::Char('/')),
+            Action::new("Toggle size column", "3", KeyCode::Char('3')),
+        ],
+        ElementInFocus::TopicOverview,
+    );
+    assert_eq!(palette.matching().len(), 2);
+
+    let mut palette = palette;
+    palette.push('s');
+    palette.push('i');
+    palette.push('z');
+    let names = palette
+        .matching()
+        .iter()
+        .map(|action| action.name)
+        .collect::<Vec<_>>();
+    assert_eq!(names, ["Toggle size column"]);
+
+    palette.pop();
+    palette.pop();
+    palette.push('o');
+    // "so" is contained in neither but fuzzy matches both
+    let names = palette
+        .matching()
+        .iter()
+        .map(|action| action.name)
+        .collect::<Vec<_>>();
+    assert_eq!(names, ["Search topics", "Toggle size column"]);
+}
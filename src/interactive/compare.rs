@@ -0,0 +1,187 @@
+use std::sync::Arc;
+
+use ratatui::layout::{Alignment, Rect};
+use ratatui::style::{Color, Style};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, BorderType, Paragraph};
+use ratatui::Frame;
+
+use crate::interactive::ui::BORDERS_TOP_RIGHT;
+use crate::mqtt::{HistoryEntry, Time};
+use crate::payload::Payload;
+
+/// Above this amount of compared line pairs the lines are compared by their index only
+const MAX_DIFF_CELLS: usize = 1_000_000;
+
+/// A message pinned with `P` to compare it with the latest message of the selected topic
+pub struct Pinned {
+    pub topic: String,
+    pub time: Time,
+    pub payload: Arc<Payload>,
+}
+
+impl Pinned {
+    pub fn new(topic: String, entry: &HistoryEntry) -> Self {
+        Self {
+            topic,
+            time: entry.time,
+            payload: Arc::clone(&entry.payload),
+        }
+    }
+
+    /// Show the pinned message side-by-side with the latest message and highlight the differing lines
+    pub fn draw(&self, frame: &mut Frame, area: Rect, topic: &str, latest: &HistoryEntry) {
+        let pinned_lines = lines(&self.payload);
+        let latest_lines = lines(&latest.payload);
+        let (pinned_changed, latest_changed) = changed_lines(&pinned_lines, &latest_lines);
+
+        let half = area.width / 2;
+        let pinned_area = Rect {
+            width: half,
+            ..area
+        };
+        let latest_area = Rect {
+            x: area.x.saturating_add(half),
+            width: area.width.saturating_sub(half),
+            ..area
+        };
+        draw_side(
+            frame,
+            pinned_area,
+            format!("Pinned {} {}", self.topic, self.time),
+            &pinned_lines,
+            &pinned_changed,
+            Style::new().fg(Color::Red),
+        );
+        draw_side(
+            frame,
+            latest_area,
+            format!("Latest {topic} {}", latest.time),
+            &latest_lines,
+            &latest_changed,
+            Style::new().fg(Color::Green),
+        );
+    }
+}
+
+fn draw_side(
+    frame: &mut Frame,
+    area: Rect,
+    title: String,
+    lines: &[String],
+    changed: &[bool],
+    changed_style: Style,
+) {
+    let text = lines
+        .iter()
+        .zip(changed)
+        .map(|(line, changed)| {
+            if *changed {
+                Line::styled(line.as_str(), changed_style)
+            } else {
+                Line::raw(line.as_str())
+            }
+        })
+        .collect::<Vec<_>>();
+    let block = Block::new()
+        .border_type(BorderType::Rounded)
+        .borders(BORDERS_TOP_RIGHT)
+        .title_alignment(Alignment::Center)
+        .title(title);
+    frame.render_widget(Paragraph::new(text).block(block), area);
+}
+
+/// Lines of the payload which are compared with each other
+fn lines(payload: &Payload) -> Vec<String> {
+    let text = match payload {
+        Payload::Binary(data) => {
+            return data
+                .chunks(16)
+                .map(|chunk| {
+                    chunk
+                        .iter()
+                        .map(|byte| format!("{byte:02x}"))
+                        .collect::<Vec<_>>()
+                        .join(" ")
+                })
+                .collect();
+        }
+        Payload::Json(_) | Payload::MessagePack(_) => {
+            serde_json::to_string_pretty(payload).unwrap_or_default()
+        }
+        Payload::String(str) => str.to_string(),
+    };
+    text.lines().map(ToOwned::to_owned).collect()
+}
+
+/// Mark the lines of both sides which are not part of their longest common subsequence
+fn changed_lines(left: &[String], right: &[String]) -> (Vec<bool>, Vec<bool>) {
+    if left.len().saturating_mul(right.len()) > MAX_DIFF_CELLS {
+        let changed = |lines: &[String], other: &[String]| {
+            lines
+                .iter()
+                .enumerate()
+                .map(|(index, line)| other.get(index) != Some(line))
+                .collect()
+        };
+        return (changed(left, right), changed(right, left));
+    }
+
+    // common[i][j] is the length of the longest common subsequence of left[i..] and right[j..]
+    let mut common = vec![vec![0_usize; right.len() + 1]; left.len() + 1];
+    for i in (0..left.len()).rev() {
+        for j in (0..right.len()).rev() {
+            common[i][j] = if left[i] == right[j] {
+                common[i + 1][j + 1] + 1
+            } else {
+                common[i + 1][j].max(common[i][j + 1])
+            };
+        }
+    }
+
+    let mut left_changed = vec![true; left.len()];
+    let mut right_changed = vec![true; right.len()];
+    let (mut i, mut j) = (0, 0);
+    while i < left.len() && j < right.len() {
+        if left[i] == right[j] {
+            left_changed[i] = false;
+            right_changed[j] = false;
+            i += 1;
+            j += 1;
+        } else if common[i + 1][j] >= common[i][j + 1] {
+            i += 1;
+        } else {
+            j += 1;
+        }
+    }
+    (left_changed, right_changed)
+}
+
+#[test]
+fn lines_of_payloads() {
+    let json = Payload::Json(serde_json::json!({"a": 1, "b": [true]}));
+    assert_eq!(
+        lines(&json),
+        ["{", "  \"a\": 1,", "  \"b\": [", "    true", "  ]", "}"]
+    );
+    let binary = Payload::Binary((0..18).collect::<Vec<u8>>().into());
+    assert_eq!(
+        lines(&binary),
+        ["00 01 02 03 04 05 06 07 08 09 0a 0b 0c 0d 0e 0f", "10 11"]
+    );
+    assert_eq!(lines(&Payload::String("a\nb".into())), ["a", "b"]);
+}
+
+#[test]
+fn changed_lines_works() {
+    let to_lines = |text: &str| text.split(' ').map(ToOwned::to_owned).collect::<Vec<_>>();
+    let left = to_lines("a b c d");
+    let right = to_lines("a x c d e");
+    let (left_changed, right_changed) = changed_lines(&left, &right);
+    assert_eq!(left_changed, [false, true, false, false]);
+    assert_eq!(right_changed, [false, true, false, false, true]);
+
+    let (left_changed, right_changed) = changed_lines(&left, &[]);
+    assert_eq!(left_changed, [true; 4]);
+    assert!(right_changed.is_empty());
+}
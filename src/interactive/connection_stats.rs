@@ -0,0 +1,230 @@
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+/// Timespan over which the throughput is averaged
+const WINDOW: Duration = Duration::from_secs(5);
+/// Connections which drop within this time after being established are considered short-lived
+const SHORT_LIVED: Duration = Duration::from_secs(10);
+/// Consecutive short-lived connections after which another client with the same client id is assumed
+const TAKEOVER_THRESHOLD: usize = 3;
+
+#[derive(Default)]
+pub struct ConnectionStats {
+    latency: Option<Duration>,
+    ping_sent: Option<Instant>,
+    received: VecDeque<(Instant, usize)>,
+    reconnects: usize,
+    connected_since: Option<Instant>,
+    /// Consecutive connections which dropped shortly after being established
+    short_lived: usize,
+    /// Messages waiting to be inserted into the history
+    queued: usize,
+    /// Messages dropped as the queue was full
+    dropped: usize,
+    /// Bytes not stored as the payload was identical to an earlier one
+    deduplicated: usize,
+    /// Retained messages sent again by the broker which were identical to the stored ones
+    skipped_retained: usize,
+}
+
+impl ConnectionStats {
+    /// The initial `ConnAck` is handled before the [`MqttThread`](super::mqtt_thread::MqttThread) starts so every `ConnAck` here is a reconnect.
+    pub fn on_connack(&mut self) {
+        self.reconnects = self.reconnects.saturating_add(1);
+        self.ping_sent = None;
+        self.connected_since = Some(Instant::now());
+    }
+
+    pub fn on_connection_err(&mut self) {
+        self.on_connection_err_at(Instant::now());
+    }
+
+    fn on_connection_err_at(&mut self, now: Instant) {
+        if let Some(since) = self.connected_since.take() {
+            if now.duration_since(since) < SHORT_LIVED {
+                self.short_lived = self.short_lived.saturating_add(1);
+            } else {
+                self.short_lived = 0;
+            }
+        }
+    }
+
+    /// The broker drops a session when another client connects with the same client id.
+    /// When both clients reconnect they keep taking over the session from each other.
+    pub fn takeover_suspected(&self) -> bool {
+        self.short_lived >= TAKEOVER_THRESHOLD
+            && self
+                .connected_since
+                .map_or(true, |since| since.elapsed() < SHORT_LIVED)
+    }
+
+    pub fn on_ping_request(&mut self) {
+        self.ping_sent = Some(Instant::now());
+    }
+
+    pub fn on_ping_response(&mut self) {
+        if let Some(sent) = self.ping_sent.take() {
+            self.latency = Some(sent.elapsed());
+        }
+    }
+
+    pub fn on_publish(&mut self, payload_size: usize) {
+        let now = Instant::now();
+        while self
+            .received
+            .front()
+            .is_some_and(|(time, _)| now.duration_since(*time) > WINDOW)
+        {
+            self.received.pop_front();
+        }
+        self.received.push_back((now, payload_size));
+    }
+
+    pub fn on_deduplicated(&mut self, payload_size: usize) {
+        self.deduplicated = self.deduplicated.saturating_add(payload_size);
+    }
+
+    /// Queued messages which were not inserted as they were identical retained messages
+    pub fn on_skipped_retained(&mut self, amount: usize) {
+        self.queued = self.queued.saturating_sub(amount);
+        self.skipped_retained = self.skipped_retained.saturating_add(amount);
+    }
+
+    pub fn on_queued(&mut self) {
+        self.queued = self.queued.saturating_add(1);
+    }
+
+    pub fn on_inserted(&mut self, amount: usize) {
+        self.queued = self.queued.saturating_sub(amount);
+    }
+
+    pub fn on_dropped(&mut self) {
+        self.dropped = self.dropped.saturating_add(1);
+    }
+
+    /// Warning when the history can not keep up with the incoming messages
+    pub fn ingest_warning(&self) -> Option<String> {
+        /// Below this the queue is considered a regular batch
+        const BEHIND_THRESHOLD: usize = 1000;
+        match (self.queued >= BEHIND_THRESHOLD, self.dropped > 0) {
+            (false, false) => None,
+            (true, false) => Some(format!("{} behind", self.queued)),
+            (false, true) => Some(format!("{} dropped", self.dropped)),
+            (true, true) => Some(format!("{} behind, {} dropped", self.queued, self.dropped)),
+        }
+    }
+
+    /// Returns (messages per second, bytes per second)
+    #[allow(clippy::cast_precision_loss)]
+    pub fn throughput(&self) -> (f64, f64) {
+        let now = Instant::now();
+        let (messages, bytes) = self
+            .received
+            .iter()
+            .filter(|(time, _)| now.duration_since(*time) <= WINDOW)
+            .fold((0_usize, 0_usize), |(messages, bytes), (_, size)| {
+                (messages.saturating_add(1), bytes.saturating_add(*size))
+            });
+        let seconds = WINDOW.as_secs_f64();
+        (messages as f64 / seconds, bytes as f64 / seconds)
+    }
+}
+
+impl std::fmt::Display for ConnectionStats {
+    fn fmt(&self, fmt: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let (messages, bytes) = self.throughput();
+        let kibibytes = bytes / 1024.0;
+        write!(fmt, "{messages:.1} msg/s {kibibytes:.1} KiB/s")?;
+        if let Some(latency) = self.latency {
+            write!(fmt, " ping {}ms", latency.as_millis())?;
+        }
+        if self.reconnects > 0 {
+            write!(fmt, " reconnects {}", self.reconnects)?;
+        }
+        if self.deduplicated > 0 {
+            #[allow(clippy::cast_precision_loss)]
+            let kibibytes = self.deduplicated as f64 / 1024.0;
+            write!(fmt, " dedup {kibibytes:.1} KiB")?;
+        }
+        if self.skipped_retained > 0 {
+            write!(fmt, " skipped retained {}", self.skipped_retained)?;
+        }
+        Ok(())
+    }
+}
+
+#[test]
+fn latency_from_ping() {
+    let mut stats = ConnectionStats::default();
+    assert_eq!(stats.latency, None);
+    stats.on_ping_response();
+    assert_eq!(stats.latency, None);
+    stats.on_ping_request();
+    stats.on_ping_response();
+    assert!(stats.latency.is_some());
+}
+
+#[test]
+fn throughput_over_window() {
+    let mut stats = ConnectionStats::default();
+    stats.on_publish(1024);
+    stats.on_publish(4096);
+    let (messages, bytes) = stats.throughput();
+    assert!((messages - 0.4).abs() < 0.01);
+    assert!((bytes - 1024.0).abs() < 0.01);
+}
+
+#[test]
+fn ingest_warning_works() {
+    let mut stats = ConnectionStats::default();
+    stats.on_queued();
+    assert_eq!(stats.ingest_warning(), None);
+    for _ in 0..1500 {
+        stats.on_queued();
+    }
+    assert_eq!(stats.ingest_warning().as_deref(), Some("1501 behind"));
+    stats.on_dropped();
+    assert_eq!(
+        stats.ingest_warning().as_deref(),
+        Some("1501 behind, 1 dropped")
+    );
+    stats.on_inserted(1501);
+    assert_eq!(stats.ingest_warning().as_deref(), Some("1 dropped"));
+}
+
+#[test]
+fn takeover_after_short_lived_connections() {
+    let mut stats = ConnectionStats::default();
+    let start = Instant::now();
+    for _ in 0..TAKEOVER_THRESHOLD {
+        assert!(!stats.takeover_suspected());
+        stats.connected_since = Some(start);
+        stats.on_connection_err_at(start + Duration::from_secs(1));
+    }
+    assert!(stats.takeover_suspected());
+
+    stats.connected_since = Some(start);
+    stats.on_connection_err_at(start + SHORT_LIVED);
+    assert!(!stats.takeover_suspected());
+}
+
+#[test]
+fn display_works() {
+    let mut stats = ConnectionStats::default();
+    assert_eq!(stats.to_string(), "0.0 msg/s 0.0 KiB/s");
+    stats.on_connack();
+    stats.latency = Some(Duration::from_millis(42));
+    assert_eq!(
+        stats.to_string(),
+        "0.0 msg/s 0.0 KiB/s ping 42ms reconnects 1"
+    );
+    stats.on_deduplicated(2048);
+    assert_eq!(
+        stats.to_string(),
+        "0.0 msg/s 0.0 KiB/s ping 42ms reconnects 1 dedup 2.0 KiB"
+    );
+    stats.on_queued();
+    stats.on_skipped_retained(1);
+    assert!(stats.to_string().ends_with(" skipped retained 1"));
+    assert_eq!(stats.queued, 0);
+}
@@ -0,0 +1,204 @@
+use chrono::{DateTime, Local};
+use ratatui::layout::{Alignment, Constraint, Rect};
+use ratatui::style::{Color, Style};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, Clear, Row, Table, TableState};
+use ratatui::Frame;
+
+use crate::format;
+use crate::interactive::mqtt_history::STYLE_DARKGRAY;
+use crate::interactive::ui::STYLE_BOLD;
+
+/// Show the selected value in other number bases and units
+pub struct Popup {
+    value: String,
+    conversions: Vec<(&'static str, String)>,
+    state: TableState,
+}
+
+impl Popup {
+    /// Returns `None` when there is nothing to convert the value into
+    pub fn new(value: String) -> Option<Self> {
+        let conversions = conversions(&value);
+        if conversions.is_empty() {
+            return None;
+        }
+        Some(Self {
+            value,
+            conversions,
+            state: TableState::new().with_selected(Some(0)),
+        })
+    }
+
+    pub fn select_next(&mut self) {
+        let index = self.state.selected().map_or(0, |index| {
+            index
+                .saturating_add(1)
+                .min(self.conversions.len().saturating_sub(1))
+        });
+        self.state.select(Some(index));
+    }
+
+    pub fn select_previous(&mut self) {
+        let index = self
+            .state
+            .selected()
+            .map_or(0, |index| index.saturating_sub(1));
+        self.state.select(Some(index));
+    }
+
+    pub fn selected_conversion(&self) -> Option<&str> {
+        let (_, converted) = self.conversions.get(self.state.selected()?)?;
+        Some(converted)
+    }
+
+    pub fn draw(&mut self, frame: &mut Frame) {
+        let rows = self
+            .conversions
+            .iter()
+            .map(|(name, converted)| Row::new([*name, converted.as_str()]));
+        let table = Table::new(rows, [Constraint::Length(14), Constraint::Fill(1)])
+            .highlight_style(Style::new().fg(Color::Black).bg(Color::LightGreen))
+            .header(Row::new(["", self.value.as_str()]).style(STYLE_BOLD))
+            .block(
+                Block::bordered()
+                    .border_style(Style::new().fg(Color::LightGreen))
+                    .title_alignment(Alignment::Center)
+                    .title("Convert")
+                    .title_bottom(Line::styled("Enter to copy", STYLE_DARKGRAY)),
+            );
+
+        let frame_area = frame.size();
+        #[allow(clippy::cast_possible_truncation)]
+        let height = (self.conversions.len() as u16)
+            .saturating_add(3)
+            .min(frame_area.height);
+        let width = frame_area.width.saturating_sub(8).min(60);
+        let area = Rect {
+            x: frame_area.width.saturating_sub(width) / 2,
+            y: frame_area.height.saturating_sub(height) / 2,
+            width,
+            height,
+        };
+        frame.render_widget(Clear, area); // clear the background of the popup
+        frame.render_stateful_widget(table, area, &mut self.state);
+    }
+}
+
+/// Integers like `42`, `-7`, `0x2a`, `0b101` or `0o17`
+fn parse_integer(value: &str) -> Option<i128> {
+    let (negative, digits) = value
+        .strip_prefix('-')
+        .map_or((false, value), |digits| (true, digits));
+    let (radix, digits) = [("0x", 16), ("0X", 16), ("0b", 2), ("0o", 8)]
+        .into_iter()
+        .find_map(|(prefix, radix)| digits.strip_prefix(prefix).map(|digits| (radix, digits)))
+        .unwrap_or((10, digits));
+    if digits.starts_with(['+', '-']) {
+        return None;
+    }
+    let number = i128::from_str_radix(digits, radix).ok()?;
+    Some(if negative { -number } else { number })
+}
+
+/// Timestamps between 2001 and 2286 as seconds or milliseconds since the Unix epoch
+fn parse_epoch(number: f64) -> Option<(&'static str, DateTime<Local>)> {
+    let (name, millis) = if (1e9..1e10).contains(&number) {
+        ("Unix seconds", number * 1000.0)
+    } else if (1e12..1e13).contains(&number) {
+        ("Unix millis", number)
+    } else {
+        return None;
+    };
+    #[allow(clippy::cast_possible_truncation)]
+    let datetime = DateTime::from_timestamp_millis(millis.round() as i64)?;
+    Some((name, datetime.with_timezone(&Local)))
+}
+
+fn conversions(value: &str) -> Vec<(&'static str, String)> {
+    let value = value.trim();
+    let integer = parse_integer(value);
+    #[allow(clippy::cast_precision_loss)]
+    let Some(number) = integer
+        .map(|integer| integer as f64)
+        .or_else(|| value.parse::<f64>().ok())
+        .filter(|number| number.is_finite())
+    else {
+        return Vec::new();
+    };
+
+    let mut conversions = Vec::new();
+    if let Some(integer) = integer {
+        let sign = if integer < 0 { "-" } else { "" };
+        let absolute = integer.unsigned_abs();
+        conversions.push(("Decimal", integer.to_string()));
+        conversions.push(("Hexadecimal", format!("{sign}{absolute:#x}")));
+        conversions.push(("Binary", format!("{sign}{absolute:#b}")));
+        if let Ok(bytes) = u64::try_from(integer) {
+            conversions.push(("Bytes", format::bytes(bytes)));
+        }
+    }
+    if let Some((name, datetime)) = parse_epoch(number) {
+        conversions.push((
+            name,
+            datetime.format("%Y-%m-%d %H:%M:%S%.3f %:z").to_string(),
+        ));
+    }
+    let fahrenheit = number.mul_add(9.0 / 5.0, 32.0);
+    let celsius = (number - 32.0) * 5.0 / 9.0;
+    conversions.push(("°C to °F", format!("{fahrenheit:.1} °F")));
+    conversions.push(("°F to °C", format!("{celsius:.1} °C")));
+    conversions
+}
+
+#[test]
+fn parse_integer_works() {
+    assert_eq!(parse_integer("42"), Some(42));
+    assert_eq!(parse_integer("-7"), Some(-7));
+    assert_eq!(parse_integer("0x2A"), Some(42));
+    assert_eq!(parse_integer("-0b101"), Some(-5));
+    assert_eq!(parse_integer("0o17"), Some(15));
+    assert_eq!(parse_integer("--5"), None);
+    assert_eq!(parse_integer("21.5"), None);
+    assert_eq!(parse_integer("on"), None);
+}
+
+#[test]
+fn conversions_of_integer() {
+    let conversions = conversions("1536");
+    let get = |name| {
+        conversions
+            .iter()
+            .find(|(key, _)| *key == name)
+            .map(|(_, value)| value.as_str())
+    };
+    assert_eq!(get("Hexadecimal"), Some("0x600"));
+    assert_eq!(get("Binary"), Some("0b11000000000"));
+    assert_eq!(get("Bytes"), Some("1.5 KiB"));
+    assert_eq!(get("°C to °F"), Some("2796.8 °F"));
+    assert_eq!(get("Unix seconds"), None);
+}
+
+#[test]
+fn conversions_of_float() {
+    let conversions = conversions("21.5");
+    assert_eq!(
+        conversions,
+        [
+            ("°C to °F", "70.7 °F".to_owned()),
+            ("°F to °C", "-5.8 °C".to_owned()),
+        ]
+    );
+    assert!(self::conversions("on").is_empty());
+}
+
+#[test]
+fn epoch_works() {
+    let (name, datetime) = parse_epoch(1_700_000_000.0).unwrap();
+    assert_eq!(name, "Unix seconds");
+    assert_eq!(datetime.timestamp(), 1_700_000_000);
+    let (name, datetime) = parse_epoch(1_700_000_000_123.0).unwrap();
+    assert_eq!(name, "Unix millis");
+    assert_eq!(datetime.timestamp_millis(), 1_700_000_000_123);
+    assert!(parse_epoch(42.0).is_none());
+}
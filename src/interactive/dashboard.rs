@@ -0,0 +1,458 @@
+use std::str::FromStr;
+
+use anyhow::Context;
+use ratatui::layout::{Alignment, Constraint, Layout, Rect};
+use ratatui::style::{Color, Style};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, BorderType, Paragraph, Sparkline};
+use ratatui::Frame;
+
+use crate::interactive::details::graph::{self, Aggregate};
+use crate::interactive::mqtt_history::{MqttHistory, STYLE_DARKGRAY};
+use crate::interactive::ui::{self, focus_color, STYLE_BOLD};
+use crate::mqtt::topic_filter;
+
+/// Digits of 3×5 pixels, one row per byte with the highest of the 3 bits on the left
+const FONT: [(char, [u8; 5]); 12] = [
+    ('0', [0b111, 0b101, 0b101, 0b101, 0b111]),
+    ('1', [0b010, 0b110, 0b010, 0b010, 0b111]),
+    ('2', [0b111, 0b001, 0b111, 0b100, 0b111]),
+    ('3', [0b111, 0b001, 0b111, 0b001, 0b111]),
+    ('4', [0b101, 0b101, 0b111, 0b001, 0b001]),
+    ('5', [0b111, 0b100, 0b111, 0b001, 0b111]),
+    ('6', [0b111, 0b100, 0b111, 0b101, 0b111]),
+    ('7', [0b111, 0b001, 0b001, 0b001, 0b001]),
+    ('8', [0b111, 0b101, 0b111, 0b101, 0b111]),
+    ('9', [0b111, 0b101, 0b111, 0b001, 0b111]),
+    ('-', [0b000, 0b000, 0b111, 0b000, 0b000]),
+    ('.', [0b000, 0b000, 0b000, 0b000, 0b010]),
+];
+
+/// Terminal rows needed by the big digits as two pixels share one row
+const BIG_HEIGHT: u16 = 3;
+
+/// A value pinned to the dashboard like `home/livingroom/temp` or `home/+/humidity avg`.
+///
+/// Topic filters with wildcards aggregate the latest values of the matching topics, by default with the average.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Expression {
+    filter: String,
+    aggregate: Option<Aggregate>,
+}
+
+impl Expression {
+    pub const fn topic(topic: String) -> Self {
+        Self {
+            filter: topic,
+            aggregate: None,
+        }
+    }
+
+    pub fn children(topic: &str, aggregate: Aggregate) -> Self {
+        Self {
+            filter: format!("{topic}/+"),
+            aggregate: Some(aggregate),
+        }
+    }
+
+    /// The topic to show in the tree which is the part before the first wildcard
+    fn tree_topic(&self) -> Option<String> {
+        let levels = self
+            .filter
+            .split('/')
+            .take_while(|level| !matches!(*level, "+" | "#"))
+            .collect::<Vec<_>>();
+        (!levels.is_empty()).then(|| levels.join("/"))
+    }
+
+    fn tile(&self, history: &MqttHistory) -> Tile {
+        let Some(aggregate) = self.aggregate else {
+            let entries = history.get(&self.filter).map_or(&[][..], Vec::as_slice);
+            return Tile {
+                value: entries.last().map(|entry| entry.payload.to_string()),
+                values: entries
+                    .iter()
+                    .filter_map(graph::value)
+                    .map(|(_, value)| value)
+                    .collect(),
+                subtitle: entries.last().map(|entry| entry.time.to_string()),
+            };
+        };
+        let topics = history
+            .get_all_topics()
+            .into_iter()
+            .filter(|topic| topic_filter::matches(&self.filter, topic))
+            .filter_map(|topic| history.get(topic))
+            .map(Vec::as_slice)
+            .collect::<Vec<_>>();
+        let values = graph::aggregated(&topics, aggregate)
+            .into_iter()
+            .map(|(_, value)| value)
+            .collect::<Vec<_>>();
+        let subtitle = match topics.len() {
+            1 => "1 topic".to_owned(),
+            amount => format!("{amount} topics"),
+        };
+        Tile {
+            value: values.last().copied().map(format_number),
+            values,
+            subtitle: Some(subtitle),
+        }
+    }
+}
+
+impl FromStr for Expression {
+    type Err = anyhow::Error;
+
+    fn from_str(expression: &str) -> anyhow::Result<Self> {
+        let mut parts = expression.split_whitespace();
+        let filter = crate::cli::parse_topic_filter(parts.next().unwrap_or_default())?;
+        let aggregate = parts
+            .next()
+            .map(|name| {
+                Aggregate::from_name(name).with_context(|| {
+                    format!("Expected an aggregate like sum, avg, min or max but got {name}")
+                })
+            })
+            .transpose()?;
+        if let Some(rest) = parts.next() {
+            anyhow::bail!("Unexpected {rest} after the aggregate");
+        }
+        let aggregate =
+            aggregate.or_else(|| filter.contains(['+', '#']).then_some(Aggregate::Average));
+        Ok(Self { filter, aggregate })
+    }
+}
+
+impl std::fmt::Display for Expression {
+    fn fmt(&self, fmt: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        fmt.write_str(&self.filter)?;
+        if let Some(aggregate) = self.aggregate {
+            write!(fmt, " {}", aggregate.name().to_lowercase())?;
+        }
+        Ok(())
+    }
+}
+
+struct Tile {
+    value: Option<String>,
+    /// Numeric values over time for the sparkline, oldest first
+    values: Vec<f64>,
+    subtitle: Option<String>,
+}
+
+fn format_number(value: f64) -> String {
+    let text = format!("{value:.2}");
+    text.trim_end_matches('0').trim_end_matches('.').to_owned()
+}
+
+/// Render the text with the big digits. `None` when it contains other characters.
+fn big_lines(text: &str) -> Option<[String; BIG_HEIGHT as usize]> {
+    let glyphs = text
+        .chars()
+        .map(|char| {
+            FONT.iter()
+                .find(|(glyph, _)| *glyph == char)
+                .map(|(_, rows)| rows)
+        })
+        .collect::<Option<Vec<_>>>()?;
+    let mut lines = [String::new(), String::new(), String::new()];
+    for (index, rows) in glyphs.iter().enumerate() {
+        for (line, pair) in lines.iter_mut().zip(rows.chunks(2)) {
+            if index > 0 {
+                line.push(' ');
+            }
+            let upper = pair[0];
+            let lower = pair.get(1).copied().unwrap_or_default();
+            for bit in [0b100, 0b010, 0b001] {
+                line.push(match (upper & bit != 0, lower & bit != 0) {
+                    (true, true) => '█',
+                    (true, false) => '▀',
+                    (false, true) => '▄',
+                    (false, false) => ' ',
+                });
+            }
+        }
+    }
+    Some(lines)
+}
+
+/// Scale the values into the range the sparkline can show while keeping the lowest one visible
+#[allow(
+    clippy::cast_possible_truncation,
+    clippy::cast_precision_loss,
+    clippy::cast_sign_loss
+)]
+fn sparkline_data(values: &[f64], width: usize) -> Vec<u64> {
+    let values = &values[values.len().saturating_sub(width)..];
+    let min = values.iter().copied().fold(f64::INFINITY, f64::min);
+    let max = values.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+    let range = max - min;
+    values
+        .iter()
+        .map(|value| {
+            if range > 0.0 {
+                ((value - min) / range).mul_add(99.0, 1.0).round() as u64
+            } else {
+                1
+            }
+        })
+        .collect()
+}
+
+/// Grid of big values with sparklines of pinned topics
+pub struct Dashboard {
+    pub expressions: Vec<Expression>,
+    selected: usize,
+    /// Columns of the grid when it was drawn the last time, used to move up and down
+    columns: usize,
+}
+
+impl Dashboard {
+    pub const fn new(expressions: Vec<Expression>) -> Self {
+        Self {
+            expressions,
+            selected: 0,
+            columns: 1,
+        }
+    }
+
+    pub fn is_pinned(&self, expression: &Expression) -> bool {
+        self.expressions.contains(expression)
+    }
+
+    /// Returns `true` when the expression is pinned afterwards
+    pub fn toggle(&mut self, expression: Expression) -> bool {
+        if let Some(index) = self
+            .expressions
+            .iter()
+            .position(|pinned| *pinned == expression)
+        {
+            self.expressions.remove(index);
+            false
+        } else {
+            self.expressions.push(expression);
+            true
+        }
+    }
+
+    pub fn remove_selected(&mut self) -> bool {
+        if self.selected >= self.expressions.len() {
+            return false;
+        }
+        self.expressions.remove(self.selected);
+        self.selected = self.selected.min(self.expressions.len().saturating_sub(1));
+        true
+    }
+
+    /// Topic of the selected tile to show in the tree
+    pub fn get_selected_topic(&self) -> Option<String> {
+        self.expressions.get(self.selected)?.tree_topic()
+    }
+
+    pub fn select_next(&mut self) -> bool {
+        self.select(self.selected.saturating_add(1))
+    }
+
+    pub fn select_previous(&mut self) -> bool {
+        self.select(self.selected.saturating_sub(1))
+    }
+
+    pub fn select_below(&mut self) -> bool {
+        self.select(self.selected.saturating_add(self.columns))
+    }
+
+    pub fn select_above(&mut self) -> bool {
+        self.select(self.selected.saturating_sub(self.columns))
+    }
+
+    fn select(&mut self, index: usize) -> bool {
+        let index = index.min(self.expressions.len().saturating_sub(1));
+        let changed = index != self.selected;
+        self.selected = index;
+        changed
+    }
+
+    pub fn draw(&mut self, frame: &mut Frame, area: Rect, history: &MqttHistory) {
+        if self.expressions.is_empty() {
+            let block = Block::bordered()
+                .border_type(BorderType::Rounded)
+                .border_style(Style::new().fg(focus_color(true)))
+                .title_alignment(Alignment::Center)
+                .title("Dashboard");
+            let hint = Paragraph::new(vec![
+                Line::raw("Nothing pinned yet."),
+                Line::styled(
+                    "Pin the selected topic with w in the topic tree or start with --dashboard",
+                    STYLE_DARKGRAY,
+                ),
+            ])
+            .alignment(Alignment::Center)
+            .block(block);
+            frame.render_widget(hint, area);
+            return;
+        }
+
+        let amount = self.expressions.len();
+        // Roughly square tiles as terminal cells are about twice as high as wide
+        let columns = (1..=amount)
+            .find(|columns| columns * columns >= amount)
+            .unwrap_or(1);
+        let rows = amount.div_ceil(columns);
+        self.columns = columns;
+        self.selected = self.selected.min(amount - 1);
+
+        #[allow(clippy::cast_possible_truncation)]
+        let row_areas = Layout::vertical(vec![Constraint::Ratio(1, rows as u32); rows]).split(area);
+        #[allow(clippy::cast_possible_truncation)]
+        let column_constraints = vec![Constraint::Ratio(1, columns as u32); columns];
+        for (index, expression) in self.expressions.iter().enumerate() {
+            let row_area = row_areas[index / columns];
+            let tile_area =
+                Layout::horizontal(column_constraints.clone()).split(row_area)[index % columns];
+            draw_tile(
+                frame,
+                tile_area,
+                expression,
+                &expression.tile(history),
+                index == self.selected,
+            );
+        }
+    }
+}
+
+fn draw_tile(frame: &mut Frame, area: Rect, expression: &Expression, tile: &Tile, selected: bool) {
+    let mut block = Block::bordered()
+        .border_type(BorderType::Rounded)
+        .border_style(Style::new().fg(focus_color(selected)))
+        .title_alignment(Alignment::Center)
+        .title(expression.to_string());
+    if let Some(subtitle) = &tile.subtitle {
+        block = block.title_bottom(Line::styled(subtitle.as_str(), STYLE_DARKGRAY));
+    }
+    let inner = block.inner(area);
+    frame.render_widget(block, area);
+
+    let text = tile.value.as_deref().unwrap_or("no message yet").trim();
+    let big = big_lines(text).filter(|lines| {
+        let width = lines[0].chars().count();
+        inner.height > BIG_HEIGHT && width <= usize::from(inner.width)
+    });
+    let value_height = big.as_ref().map_or(1, |_| BIG_HEIGHT);
+    let sparkline_height = inner.height.saturating_sub(value_height) / 2;
+    let value_area = Rect {
+        y: inner.y + inner.height.saturating_sub(value_height + sparkline_height) / 2,
+        height: value_height.min(inner.height),
+        ..inner
+    };
+    let value = big.map_or_else(
+        || {
+            let style = if tile.value.is_some() {
+                STYLE_BOLD
+            } else {
+                STYLE_DARKGRAY
+            };
+            vec![Line::styled(
+                ui::ellipsize(text, usize::from(inner.width)).into_owned(),
+                style,
+            )]
+        },
+        |lines| lines.into_iter().map(Line::raw).collect(),
+    );
+    frame.render_widget(
+        Paragraph::new(value)
+            .alignment(Alignment::Center)
+            .style(STYLE_BOLD),
+        value_area,
+    );
+
+    if sparkline_height > 0 && tile.values.len() > 1 {
+        let sparkline_area = Rect {
+            y: inner.bottom().saturating_sub(sparkline_height),
+            height: sparkline_height,
+            ..inner
+        };
+        let data = sparkline_data(&tile.values, usize::from(inner.width));
+        frame.render_widget(
+            Sparkline::default()
+                .data(&data)
+                .style(Style::new().fg(Color::LightCyan)),
+            sparkline_area,
+        );
+    }
+}
+
+#[test]
+fn parse_expressions() {
+    let expression = "home/livingroom/temp".parse::<Expression>().unwrap();
+    assert_eq!(
+        expression,
+        Expression::topic("home/livingroom/temp".to_owned())
+    );
+    assert_eq!(
+        expression.tree_topic().as_deref(),
+        Some("home/livingroom/temp")
+    );
+
+    let expression = "home/+/humidity".parse::<Expression>().unwrap();
+    assert_eq!(expression.aggregate, Some(Aggregate::Average));
+    assert_eq!(expression.to_string(), "home/+/humidity average");
+    assert_eq!(expression.tree_topic().as_deref(), Some("home"));
+    assert_eq!(
+        "home/+ MAX".parse::<Expression>().unwrap(),
+        Expression::children("home", Aggregate::Max)
+    );
+
+    assert!("".parse::<Expression>().is_err());
+    assert!("home/# median".parse::<Expression>().is_err());
+    assert!("home/+ sum min".parse::<Expression>().is_err());
+    assert!("home/#/temp".parse::<Expression>().is_err());
+}
+
+#[test]
+fn tiles_work() {
+    let mut history = MqttHistory::new();
+    history.add(
+        "home/kitchen/temp".to_owned(),
+        crate::mqtt::HistoryEntry::example("20", crate::mqtt::Time::new_now(false)),
+    );
+    history.add(
+        "home/kitchen/temp".to_owned(),
+        crate::mqtt::HistoryEntry::example("21.5", crate::mqtt::Time::new_now(false)),
+    );
+    history.add(
+        "home/bath/temp".to_owned(),
+        crate::mqtt::HistoryEntry::example("23", crate::mqtt::Time::new_now(false)),
+    );
+    history.add(
+        "home/bath/state".to_owned(),
+        crate::mqtt::HistoryEntry::example("on", crate::mqtt::Time::new_now(false)),
+    );
+
+    let tile = Expression::topic("home/kitchen/temp".to_owned()).tile(&history);
+    assert_eq!(tile.value.as_deref(), Some("21.5"));
+    assert_eq!(tile.values, [20.0, 21.5]);
+
+    let tile = "home/+/temp".parse::<Expression>().unwrap().tile(&history);
+    assert_eq!(tile.value.as_deref(), Some("22.25"));
+    assert_eq!(tile.subtitle.as_deref(), Some("2 topics"));
+
+    let tile = Expression::topic("home/bath/state".to_owned()).tile(&history);
+    assert_eq!(tile.value.as_deref(), Some("on"));
+    assert!(tile.values.is_empty());
+    assert!(Expression::topic("nope".to_owned())
+        .tile(&history)
+        .value
+        .is_none());
+}
+
+#[test]
+fn big_digits_work() {
+    assert_eq!(
+        big_lines("-1.5").unwrap(),
+        ["    ▄█      █▀▀", "▀▀▀  █      ▀▀█", "    ▀▀▀  ▀  ▀▀▀"]
+    );
+    assert!(big_lines("on").is_none());
+    assert_eq!(sparkline_data(&[1.0, 3.0, 2.0], 2), [100, 1]);
+    assert_eq!(sparkline_data(&[5.0, 5.0], 10), [1, 1]);
+}
@@ -0,0 +1,130 @@
+use ratatui::layout::{Alignment, Constraint, Rect};
+use ratatui::style::{Color, Style};
+use ratatui::widgets::{Block, BorderType, Cell, Row, Table};
+use ratatui::Frame;
+
+use crate::interactive::mqtt_history::STYLE_DARKGRAY;
+use crate::interactive::ui::{focus_color, BORDERS_TOP_RIGHT, STYLE_BOLD};
+use crate::payload::{Endianness, NumberType};
+
+/// Width of the inspector next to the binary payload
+pub const WIDTH: u16 = 40;
+/// The inspector is only shown when this width remains for the bytes
+pub const MIN_DATA_WIDTH: u16 = 32;
+
+/// Types which can be chosen to interpret the bytes at the selected address
+const TYPES: [NumberType; 7] = [
+    NumberType::U8,
+    NumberType::I16,
+    NumberType::U16,
+    NumberType::I32,
+    NumberType::U32,
+    NumberType::F32,
+    NumberType::F64,
+];
+
+/// How the bytes at the selected address of a binary payload are shown in the history and graph
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Interpretation {
+    pub number: NumberType,
+    pub endianness: Endianness,
+}
+
+impl Default for Interpretation {
+    fn default() -> Self {
+        Self {
+            number: NumberType::U8,
+            endianness: Endianness::Big,
+        }
+    }
+}
+
+impl Interpretation {
+    pub fn next_type(&mut self) {
+        let index = TYPES
+            .iter()
+            .position(|number| *number == self.number)
+            .map_or(0, |index| index.saturating_add(1) % TYPES.len());
+        self.number = TYPES[index];
+    }
+
+    pub fn toggle_endianness(&mut self) {
+        self.endianness = match self.endianness {
+            Endianness::Big => Endianness::Little,
+            Endianness::Little => Endianness::Big,
+        };
+    }
+
+    pub fn read(self, data: &[u8], address: usize) -> Option<serde_json::Number> {
+        self.number.read(data.get(address..)?, self.endianness)
+    }
+}
+
+/// Show the bytes at the address interpreted as every type in both endiannesses
+pub fn draw(
+    frame: &mut Frame,
+    area: Rect,
+    data: &[u8],
+    address: usize,
+    interpretation: Interpretation,
+    has_focus: bool,
+) {
+    let chosen_style = Style::new().fg(Color::Black).bg(focus_color(has_focus));
+    let rows = TYPES.into_iter().map(|number| {
+        let cells = [Endianness::Big, Endianness::Little].map(|endianness| {
+            let current = Interpretation { number, endianness };
+            let value = current
+                .read(data, address)
+                .map(|value| value.to_string())
+                .unwrap_or_default();
+            let cell = Cell::new(value);
+            if current == interpretation {
+                cell.style(chosen_style)
+            } else {
+                cell
+            }
+        });
+        let [big, little] = cells;
+        Row::new([Cell::new(number.name()).style(STYLE_BOLD), big, little])
+    });
+    let table = Table::new(
+        rows,
+        [
+            Constraint::Length(3),
+            Constraint::Fill(1),
+            Constraint::Fill(1),
+        ],
+    )
+    .header(Row::new(["", "Big endian", "Little endian"]).style(STYLE_DARKGRAY))
+    .block(
+        Block::new()
+            .border_type(BorderType::Rounded)
+            .borders(BORDERS_TOP_RIGHT)
+            .title_alignment(Alignment::Center)
+            .border_style(Style::new().fg(focus_color(has_focus)))
+            .title(format!("Value at {address:#x}")),
+    );
+    frame.render_widget(table, area);
+}
+
+#[test]
+fn next_type_cycles() {
+    let mut interpretation = Interpretation::default();
+    for _ in 0..TYPES.len() {
+        interpretation.next_type();
+    }
+    assert_eq!(interpretation, Interpretation::default());
+}
+
+#[test]
+fn read_at_address() {
+    let mut interpretation = Interpretation::default();
+    let data = [0x00, 0x01, 0x02];
+    assert_eq!(interpretation.read(&data, 1), Some(1.into()));
+    interpretation.next_type();
+    assert_eq!(interpretation.read(&data, 1), Some(0x0102.into()));
+    interpretation.toggle_endianness();
+    assert_eq!(interpretation.read(&data, 1), Some(0x0201.into()));
+    assert_eq!(interpretation.read(&data, 2), None);
+    assert_eq!(interpretation.read(&data, 5), None);
+}
@@ -1,4 +1,4 @@
-use chrono::NaiveDateTime;
+use chrono::{NaiveDateTime, TimeDelta};
 use ratatui::layout::{Alignment, Rect};
 use ratatui::style::{Color, Style};
 use ratatui::text::Span;
@@ -6,12 +6,146 @@ use ratatui::widgets::{Axis, Block, Borders, Chart, Dataset, GraphType};
 use ratatui::{symbols, Frame};
 
 use self::point::Point;
+use super::binary_inspector::Interpretation;
+use crate::interactive::history_summary::MinuteSummary;
+use crate::interactive::marker::{self, Marker};
 use crate::mqtt::HistoryEntry;
-use crate::payload::JsonSelector;
+use crate::payload::{JsonSelector, Payload};
 
 mod point;
 
+/// Intervals to count the messages in. The first one resulting in enough points is used.
+const RATE_INTERVALS: [(i64, &str); 8] = [
+    (1, "1s"),
+    (5, "5s"),
+    (10, "10s"),
+    (30, "30s"),
+    (60, "minute"),
+    (5 * 60, "5 minutes"),
+    (15 * 60, "15 minutes"),
+    (60 * 60, "hour"),
+];
+/// Upper limit of points of the message rate graph
+const RATE_MAX_POINTS: i64 = 120;
+
+/// What the graph shows on the Y-axis
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum GraphSource {
+    /// The value of the payload (or the selected part of it)
+    #[default]
+    Value,
+    /// Amount of messages per interval
+    Rate,
+    /// Payload size in bytes
+    Size,
+}
+
+impl GraphSource {
+    pub const fn next(self) -> Self {
+        match self {
+            Self::Value => Self::Rate,
+            Self::Rate => Self::Size,
+            Self::Size => Self::Value,
+        }
+    }
+}
+
+/// How the latest values of multiple topics are combined into one graph
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Aggregate {
+    #[default]
+    Sum,
+    Average,
+    Min,
+    Max,
+}
+
+impl Aggregate {
+    pub const fn next(self) -> Self {
+        match self {
+            Self::Sum => Self::Average,
+            Self::Average => Self::Min,
+            Self::Min => Self::Max,
+            Self::Max => Self::Sum,
+        }
+    }
+
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Sum => "Sum",
+            Self::Average => "Average",
+            Self::Min => "Min",
+            Self::Max => "Max",
+        }
+    }
+
+    /// Short lowercase names like `avg` are accepted too
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "sum" => Some(Self::Sum),
+            "avg" | "average" => Some(Self::Average),
+            "min" => Some(Self::Min),
+            "max" => Some(Self::Max),
+            _ => None,
+        }
+    }
+
+    #[allow(clippy::cast_precision_loss)]
+    fn apply(self, values: &[f64]) -> Option<f64> {
+        let mut values = values.iter().copied();
+        let first = values.next()?;
+        Some(match self {
+            Self::Sum => values.sum::<f64>() + first,
+            Self::Average => {
+                let (sum, amount) =
+                    values.fold((first, 1), |(sum, amount), value| (sum + value, amount + 1));
+                sum / f64::from(amount)
+            }
+            Self::Min => values.fold(first, f64::min),
+            Self::Max => values.fold(first, f64::max),
+        })
+    }
+}
+
+/// The aggregated value of the latest value of every topic after each message, oldest first
+pub fn aggregated(topics: &[&[HistoryEntry]], aggregate: Aggregate) -> Vec<(NaiveDateTime, f64)> {
+    let mut events = topics
+        .iter()
+        .enumerate()
+        .flat_map(|(index, entries)| {
+            entries.iter().filter_map(move |entry| {
+                let point = Point::parse(entry, 0, Interpretation::default(), &[], None)?;
+                Some((index, point))
+            })
+        })
+        .collect::<Vec<_>>();
+    events.sort_by_key(|(_, point)| point.time);
+
+    let mut latest = vec![None; topics.len()];
+    let mut values = Vec::with_capacity(events.len());
+    for (index, point) in events {
+        latest[index] = Some(point.y);
+        let current = latest.iter().flatten().copied().collect::<Vec<_>>();
+        if let Some(value) = aggregate.apply(&current) {
+            values.push((point.time, value));
+        }
+    }
+    values
+}
+
+/// Time and numeric value of the whole payload.
+///
+/// Binary payloads are skipped as their interpretation is only chosen while viewing them.
+pub fn value(entry: &HistoryEntry) -> Option<(NaiveDateTime, f64)> {
+    if matches!(*entry.payload, Payload::Binary(_)) {
+        return None;
+    }
+    let point = Point::parse(entry, 0, Interpretation::default(), &[], None)?;
+    Some((point.time, point.y))
+}
+
 pub struct Graph {
+    title: String,
     data: Vec<(f64, f64)>,
     first_time: NaiveDateTime,
     last_time: NaiveDateTime,
@@ -23,16 +157,119 @@ pub struct Graph {
 
 impl Graph {
     /// Ensures to create a useful graph (has at least 2 points)
+    ///
+    /// The summaries of messages removed by the history limit are shown as their average in front.
+    /// They only contain the whole payload so they are skipped when a part of it is selected.
     pub fn parse(
         entries: &[HistoryEntry],
+        summaries: &[MinuteSummary],
         binary_address: usize,
+        binary_interpretation: Interpretation,
         json_selector: &[JsonSelector],
+        csv_column: Option<usize>,
     ) -> Option<Self> {
         let points = entries
             .iter()
-            .filter_map(|entry| Point::parse(entry, binary_address, json_selector))
+            .filter_map(|entry| {
+                Point::parse(
+                    entry,
+                    binary_address,
+                    binary_interpretation,
+                    json_selector,
+                    csv_column,
+                )
+            })
+            .collect::<Vec<_>>();
+        if summaries.is_empty() || !json_selector.is_empty() || csv_column.is_some() {
+            return Self::from_points("Graph".to_owned(), &points);
+        }
+        let points = summaries
+            .iter()
+            .map(|summary| Point {
+                time: summary.minute,
+                y: summary.average(),
+            })
+            .chain(points)
+            .collect::<Box<[_]>>();
+        Self::from_points("Graph (older as per-minute average)".to_owned(), &points)
+    }
+
+    /// Combine the values of multiple topics like the power of every device into the total power.
+    ///
+    /// Every message results in a point aggregating the latest value of every topic until then.
+    pub fn aggregate(topics: &[&[HistoryEntry]], aggregate: Aggregate) -> Option<Self> {
+        let points = aggregated(topics, aggregate)
+            .into_iter()
+            .map(|(time, y)| Point { time, y })
             .collect::<Box<[_]>>();
+        let title = match topics.len() {
+            1 => format!("{} of 1 topic", aggregate.name()),
+            amount => format!("{} of {amount} topics", aggregate.name()),
+        };
+        Self::from_points(title, &points)
+    }
 
+    /// Payload size of the received messages
+    #[allow(clippy::cast_precision_loss)]
+    pub fn size(entries: &[HistoryEntry]) -> Option<Self> {
+        let points = entries
+            .iter()
+            .filter_map(|entry| {
+                let time = *entry.time.as_optional()?;
+                Some(Point {
+                    time,
+                    y: entry.payload_size as f64,
+                })
+            })
+            .collect::<Box<[_]>>();
+        Self::from_points("Payload size in bytes".to_owned(), &points)
+    }
+
+    /// Amount of received messages per interval.
+    /// The interval depends on the time span of the messages.
+    pub fn rate(entries: &[HistoryEntry]) -> Option<Self> {
+        let times = entries
+            .iter()
+            .filter_map(|entry| entry.time.as_optional())
+            .collect::<Box<[_]>>();
+        let [first, .., last] = *times else {
+            return None;
+        };
+        let span = (*last - *first).num_seconds();
+        let (interval, interval_name) = RATE_INTERVALS
+            .into_iter()
+            .find(|(interval, _)| span / interval < RATE_MAX_POINTS)
+            .unwrap_or(RATE_INTERVALS[RATE_INTERVALS.len() - 1]);
+
+        let mut points: Vec<Point> = Vec::new();
+        for time in times.iter() {
+            let offset = (**time - *first).num_seconds() / interval;
+            let bucket = *first + TimeDelta::seconds(offset * interval);
+            match points.last_mut() {
+                Some(point) if point.time == bucket => point.y += 1.0,
+                _ => {
+                    // Intervals without messages are zero
+                    if let Some(previous) = points.last().map(|point| point.time) {
+                        let mut empty = previous + TimeDelta::seconds(interval);
+                        while empty < bucket {
+                            points.push(Point {
+                                time: empty,
+                                y: 0.0,
+                            });
+                            empty += TimeDelta::seconds(interval);
+                        }
+                    }
+                    points.push(Point {
+                        time: bucket,
+                        y: 1.0,
+                    });
+                }
+            }
+        }
+        Self::from_points(format!("Messages per {interval_name}"), &points)
+    }
+
+    fn from_points(title: String, points: &[Point]) -> Option<Self> {
         let [ref first, .., ref last] = *points else {
             return None;
         };
@@ -48,6 +285,7 @@ impl Graph {
         }
 
         Some(Self {
+            title,
             data,
             first_time: first.time,
             last_time: last.time,
@@ -58,19 +296,45 @@ impl Graph {
         })
     }
 
-    pub fn draw(&self, frame: &mut Frame, area: Rect) {
+    /// Vertical lines of the markers within the time span of the graph
+    fn marker_lines<'a>(&self, markers: &'a [Marker]) -> Vec<(&'a str, [(f64, f64); 2])> {
+        markers
+            .iter()
+            .filter(|marker| (self.first_time..=self.last_time).contains(&marker.time))
+            .map(|marker| {
+                let x = Point {
+                    time: marker.time,
+                    y: 0.0,
+                }
+                .as_graph_x();
+                (marker.name.as_str(), [(x, self.y_min), (x, self.y_max)])
+            })
+            .collect()
+    }
+
+    pub fn draw(&self, frame: &mut Frame, area: Rect, markers: &[Marker]) {
         const STYLE: Style = Style::new().fg(Color::LightGreen);
-        let dataset = Dataset::default()
+        let marker_lines = self.marker_lines(markers);
+        let mut datasets = vec![Dataset::default()
             .graph_type(GraphType::Line)
             .marker(symbols::Marker::Braille)
             .style(STYLE)
-            .data(&self.data);
-        let chart = Chart::new(vec![dataset])
+            .data(&self.data)];
+        // Named to be listed in the legend
+        datasets.extend(marker_lines.iter().map(|(name, line)| {
+            Dataset::default()
+                .name(*name)
+                .graph_type(GraphType::Line)
+                .marker(symbols::Marker::Braille)
+                .style(marker::STYLE)
+                .data(line)
+        }));
+        let chart = Chart::new(datasets)
             .block(
                 Block::new()
                     .borders(Borders::TOP)
                     .title_alignment(Alignment::Center)
-                    .title("Graph"),
+                    .title(self.title.as_str()),
             )
             .x_axis(
                 Axis::default()
@@ -105,7 +369,7 @@ mod tests {
             qos: rumqttc::QoS::AtMostOnce,
             time,
             payload_size: payload.len(),
-            payload: Payload::String(payload.into()),
+            payload: Payload::String(payload.into()).into(),
         }
     }
 
@@ -117,7 +381,7 @@ mod tests {
             // After an MQTT reconnect retained are sent again -> also filter them out
             entry(Time::Retained, "12.3"),
         ];
-        let graph = Graph::parse(&entries, 0, &[]);
+        let graph = Graph::parse(&entries, &[], 0, Interpretation::default(), &[], None);
         assert!(graph.is_none());
     }
 
@@ -133,7 +397,8 @@ mod tests {
             entry(Time::Local(second_date), "12.5"),
         ];
 
-        let graph = Graph::parse(&entries, 0, &[]).expect("Should be possible to create graph");
+        let graph = Graph::parse(&entries, &[], 0, Interpretation::default(), &[], None)
+            .expect("Should be possible to create graph");
 
         assert_eq!(graph.data.len(), 2);
         assert_eq!(graph.first_time, first_date);
@@ -141,4 +406,90 @@ mod tests {
         assert!((graph.y_min - 12.4).abs() < 0.01);
         assert!((graph.y_max - 12.5).abs() < 0.01);
     }
+
+    #[test]
+    fn aggregate_uses_latest_values() {
+        let first_date = Time::datetime_example().with_second(0).unwrap();
+        let time = |second| Time::Local(first_date + TimeDelta::seconds(second));
+        let a = vec![entry(time(0), "1"), entry(time(2), "3")];
+        let b = vec![entry(Time::Retained, "5"), entry(time(1), "10")];
+        let topics = [a.as_slice(), b.as_slice()];
+
+        let graph = Graph::aggregate(&topics, Aggregate::Sum).unwrap();
+        assert_eq!(graph.title, "Sum of 2 topics");
+        let values = graph.data.iter().map(|(_, y)| *y).collect::<Vec<_>>();
+        assert_eq!(values, [1.0, 11.0, 13.0]);
+
+        let graph = Graph::aggregate(&topics, Aggregate::Average).unwrap();
+        let values = graph.data.iter().map(|(_, y)| *y).collect::<Vec<_>>();
+        assert_eq!(values, [1.0, 5.5, 6.5]);
+
+        let graph = Graph::aggregate(&topics, Aggregate::Max).unwrap();
+        let values = graph.data.iter().map(|(_, y)| *y).collect::<Vec<_>>();
+        assert_eq!(values, [1.0, 10.0, 10.0]);
+    }
+
+    #[test]
+    fn marker_lines_within_time_span() {
+        let first_date = Time::datetime_example().with_second(0).unwrap();
+        let time = |second| first_date + TimeDelta::seconds(second);
+        let entries = [
+            entry(Time::Local(time(0)), "1"),
+            entry(Time::Local(time(10)), "3"),
+        ];
+        let graph = Graph::parse(&entries, &[], 0, Interpretation::default(), &[], None).unwrap();
+        let markers = [-1, 5, 11].map(|second| Marker {
+            time: time(second),
+            name: format!("at {second}"),
+        });
+        let lines = graph.marker_lines(&markers);
+        assert_eq!(lines.len(), 1);
+        let (name, [(x, y_min), (_, y_max)]) = lines[0];
+        assert_eq!(name, "at 5");
+        assert!(graph.x_min < x && x < graph.x_max);
+        assert!((y_min - 1.0).abs() < 0.01);
+        assert!((y_max - 3.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn size_works() {
+        let first_date = Time::datetime_example();
+        let second_date = first_date.with_second(59).unwrap();
+        let entries = vec![
+            entry(Time::Retained, "12.3"),
+            entry(Time::Local(first_date), "1"),
+            entry(Time::Local(second_date), "123"),
+        ];
+        let graph = Graph::size(&entries).expect("Should be possible to create graph");
+        assert_eq!(graph.data.len(), 2);
+        assert!((graph.y_min - 1.0).abs() < 0.01);
+        assert!((graph.y_max - 3.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn rate_counts_per_interval() {
+        let first_date = Time::datetime_example().with_second(0).unwrap();
+        let entries = [0, 0, 1, 1, 1, 4]
+            .into_iter()
+            .map(|second| {
+                let time = first_date + TimeDelta::seconds(second);
+                entry(Time::Local(time), "")
+            })
+            .collect::<Vec<_>>();
+        let graph = Graph::rate(&entries).expect("Should be possible to create graph");
+        assert_eq!(graph.title, "Messages per 1s");
+        let counts = graph.data.iter().map(|(_, y)| *y).collect::<Vec<_>>();
+        assert_eq!(counts, [2.0, 3.0, 0.0, 0.0, 1.0]);
+    }
+
+    #[test]
+    fn rate_uses_longer_interval_for_long_spans() {
+        let first_date = Time::datetime_example();
+        let entries = [
+            entry(Time::Local(first_date), ""),
+            entry(Time::Local(first_date + TimeDelta::minutes(30)), ""),
+        ];
+        let graph = Graph::rate(&entries).expect("Should be possible to create graph");
+        assert_eq!(graph.title, "Messages per 30s");
+    }
 }
@@ -1,7 +1,8 @@
 use chrono::NaiveDateTime;
 
+use crate::interactive::details::binary_inspector::Interpretation;
 use crate::mqtt::HistoryEntry;
-use crate::payload::{JsonSelector, Payload};
+use crate::payload::{Csv, JsonSelector, Payload};
 
 pub struct Point {
     pub time: NaiveDateTime,
@@ -12,18 +13,24 @@ impl Point {
     pub fn parse(
         entry: &HistoryEntry,
         binary_address: usize,
+        binary_interpretation: Interpretation,
         json_selector: &[JsonSelector],
+        csv_column: Option<usize>,
     ) -> Option<Self> {
         let time = *entry.time.as_optional()?;
-        let y = match &entry.payload {
-            Payload::Binary(data) => data.get(binary_address).copied().map(f64::from),
+        let y = match &*entry.payload {
+            Payload::Binary(data) => binary_interpretation
+                .read(data, binary_address)
+                .and_then(|number| number.as_f64()),
             Payload::Json(json) => {
                 f64_from_json(JsonSelector::get_json(json, json_selector).unwrap_or(json))
             }
             Payload::MessagePack(messagepack) => f64_from_messagepack(
                 JsonSelector::get_messagepack(messagepack, json_selector).unwrap_or(messagepack),
             ),
-            Payload::String(str) => f64_from_string(str),
+            Payload::String(str) => csv_column
+                .and_then(|column| Csv::parse(str)?.last_value(column))
+                .map_or_else(|| f64_from_string(str), f64_from_string),
         }
         .filter(|y| y.is_finite())?;
         Some(Self { time, y })
@@ -105,9 +112,9 @@ mod parse_tests {
             qos: QoS::AtMostOnce,
             time: Time::Retained,
             payload_size: 42,
-            payload: Payload::unlimited(vec![]),
+            payload: Payload::unlimited(vec![]).into(),
         };
-        let point = Point::parse(&entry, 0, &[]);
+        let point = Point::parse(&entry, 0, Interpretation::default(), &[], None);
         assert!(point.is_none());
     }
 
@@ -119,9 +126,9 @@ mod parse_tests {
             qos: QoS::AtMostOnce,
             time: Time::Local(date),
             payload_size: 42,
-            payload: Payload::Json(Value::Number(Number::from_f64(12.3).unwrap())),
+            payload: Payload::Json(Value::Number(Number::from_f64(12.3).unwrap())).into(),
         };
-        let point = Point::parse(&entry, 0, &[]).unwrap();
+        let point = Point::parse(&entry, 0, Interpretation::default(), &[], None).unwrap();
         assert_eq!(point.time, date);
         assert!((point.y - 12.3).abs() < 0.1);
     }
@@ -133,10 +140,41 @@ mod parse_tests {
             qos: QoS::AtMostOnce,
             time: Time::Local(date),
             payload_size: 42,
-            payload: Payload::MessagePack(rmpv::Value::F64(12.3)),
+            payload: Payload::MessagePack(rmpv::Value::F64(12.3)).into(),
         };
-        let point = Point::parse(&entry, 0, &[]).unwrap();
+        let point = Point::parse(&entry, 0, Interpretation::default(), &[], None).unwrap();
         assert_eq!(point.time, date);
         assert!((point.y - 12.3).abs() < 0.1);
     }
+
+    #[test]
+    fn binary_interpretation_works() {
+        let date = Time::datetime_example();
+        let entry = HistoryEntry {
+            qos: QoS::AtMostOnce,
+            time: Time::Local(date),
+            payload_size: 42,
+            payload: Payload::Binary([0xff, 0x01, 0x02].into()).into(),
+        };
+        let point = Point::parse(&entry, 1, Interpretation::default(), &[], None).unwrap();
+        assert!((point.y - 1.0).abs() < 0.1);
+        let mut interpretation = Interpretation::default();
+        interpretation.next_type();
+        let point = Point::parse(&entry, 1, interpretation, &[], None).unwrap();
+        assert!((point.y - 258.0).abs() < 0.1);
+    }
+
+    #[test]
+    fn csv_column_works() {
+        let date = Time::datetime_example();
+        let entry = HistoryEntry {
+            qos: QoS::AtMostOnce,
+            time: Time::Local(date),
+            payload_size: 42,
+            payload: Payload::String("12:00,21.5,40\n12:01,21.7,41".into()).into(),
+        };
+        let point = Point::parse(&entry, 0, Interpretation::default(), &[], Some(2)).unwrap();
+        assert!((point.y - 41.0).abs() < 0.1);
+        assert!(Point::parse(&entry, 0, Interpretation::default(), &[], None).is_none());
+    }
 }
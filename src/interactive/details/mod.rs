@@ -1,27 +1,156 @@
+use std::collections::HashMap;
+
 use ratatui::layout::{Position, Rect};
 use ratatui::widgets::TableState;
 use ratatui::Frame;
+use ratatui_binary_data_widget::BinaryDataWidgetState;
+use tui_tree_widget::TreeState;
 
+use crate::cli::HistoryColumn;
+use crate::interactive::history_summary::MinuteSummary;
+use crate::interactive::marker::Marker;
 use crate::interactive::ui::{split_area_vertically, ElementInFocus};
 use crate::mqtt::HistoryEntry;
+use crate::payload::JsonSelector;
 
-mod graph;
+mod binary_inspector;
+pub mod graph;
 mod payload_view;
 mod table;
+mod text_selection;
+
+/// How long the changes to the previous payload are highlighted after a message arrived
+const HIGHLIGHT_CHANGES_SECONDS: i64 = 5;
+
+/// The state of the details of a topic which is currently not selected
+#[derive(Default)]
+struct TopicState {
+    table: TableState,
+    binary: BinaryDataWidgetState,
+    json: TreeState<JsonSelector>,
+    history_length: usize,
+}
 
 #[derive(Default)]
 pub struct Details {
     pub table_state: TableState,
     pub last_table_area: Rect,
     pub payload: payload_view::PayloadView,
+    /// Columns of the history table besides the value
+    pub history_columns: Vec<HistoryColumn>,
+    /// Show the age of messages instead of their time
+    pub relative_time: bool,
+    /// Keep the selected history entry when new messages arrive instead of following the newest one
+    pub pinned: bool,
+    /// Collapse consecutive identical payloads into one history table row with a repeat counter
+    pub collapse_duplicates: bool,
+    pub graph_source: graph::GraphSource,
+    /// How the values of the children are combined when a topic without messages is selected
+    pub aggregate: graph::Aggregate,
+    /// Shown in the graphs and history tables of every topic, oldest first
+    pub markers: Vec<Marker>,
+    /// Amount of history entries of the topic on the last draw in order to notice new messages
+    history_length: usize,
+    /// The topic the current state belongs to
+    topic: Option<String>,
+    other_topics: HashMap<String, TopicState>,
 }
 
 impl Details {
-    pub fn selected_history_index(&self, topic_history_length: usize) -> usize {
-        self.table_state
+    pub fn new(
+        history_columns: Vec<HistoryColumn>,
+        relative_time: bool,
+        collapse_duplicates: bool,
+        pinned: bool,
+    ) -> Self {
+        Self {
+            history_columns,
+            relative_time,
+            pinned,
+            collapse_duplicates,
+            ..Self::default()
+        }
+    }
+
+    /// Show or hide the column in the history table
+    pub fn toggle_column(&mut self, column: HistoryColumn) {
+        if self.history_columns.contains(&column) {
+            self.history_columns.retain(|existing| *existing != column);
+        } else {
+            self.history_columns.push(column);
+        }
+    }
+
+    /// Cycle the time column of the history table between with milliseconds, without and hidden
+    pub fn toggle_time_column(&mut self) {
+        if self.history_columns.contains(&HistoryColumn::Time) {
+            self.toggle_column(HistoryColumn::Time);
+            self.toggle_column(HistoryColumn::ShortTime);
+        } else if self.history_columns.contains(&HistoryColumn::ShortTime) {
+            self.toggle_column(HistoryColumn::ShortTime);
+        } else {
+            self.toggle_column(HistoryColumn::Time);
+        }
+    }
+
+    /// Collapse or expand the identical payloads while keeping the selected history entry selected
+    pub fn toggle_collapse_duplicates(&mut self, topic_history: &[HistoryEntry]) {
+        let selected = self
+            .table_state
             .selected()
-            .unwrap_or(usize::MAX)
-            .min(topic_history_length.saturating_sub(1))
+            .map(|_| self.selected_history_index(topic_history));
+        self.collapse_duplicates = !self.collapse_duplicates;
+        if let Some(selected) = selected {
+            let row = table::rows(topic_history, self.collapse_duplicates, &self.markers)
+                .iter()
+                .position(|row| row.index >= selected);
+            self.table_state.select(row);
+        }
+    }
+
+    /// Store the state of the previous topic and restore the state of the given one.
+    pub fn switch_topic(&mut self, topic: Option<String>) {
+        if self.topic == topic {
+            return;
+        }
+        let restored = topic
+            .as_ref()
+            .and_then(|topic| self.other_topics.remove(topic))
+            .unwrap_or_default();
+        let previous = TopicState {
+            table: std::mem::replace(&mut self.table_state, restored.table),
+            binary: std::mem::replace(&mut self.payload.binary_state, restored.binary),
+            json: std::mem::replace(&mut self.payload.json_state, restored.json),
+            history_length: std::mem::replace(&mut self.history_length, restored.history_length),
+        };
+        self.payload.text_selection.clear();
+        self.payload.csv_column = None;
+        if let Some(previous_topic) = std::mem::replace(&mut self.topic, topic) {
+            self.other_topics.insert(previous_topic, previous);
+        }
+    }
+
+    /// Select the newest row when a new message arrived unless the selection is pinned.
+    /// When pinned without a selection the current newest row is selected.
+    fn follow_history(&mut self, topic_history_length: usize, rows: usize) {
+        if self.pinned {
+            if self.table_state.selected().is_none() {
+                self.table_state.select(Some(rows.saturating_sub(1)));
+            }
+        } else if topic_history_length > self.history_length {
+            self.table_state.select(None);
+        }
+        self.history_length = topic_history_length;
+    }
+
+    pub fn selected_history_index(&self, topic_history: &[HistoryEntry]) -> usize {
+        let selected = self.table_state.selected().unwrap_or(usize::MAX);
+        if !self.collapse_duplicates && self.markers.is_empty() {
+            return selected.min(topic_history.len().saturating_sub(1));
+        }
+        let rows = table::rows(topic_history, self.collapse_duplicates, &self.markers);
+        rows.get(selected.min(rows.len().saturating_sub(1)))
+            .map_or(0, |row| row.index)
     }
 
     const fn table_index_of_click(&self, position: Position) -> Option<usize> {
@@ -44,44 +173,151 @@ impl Details {
         true
     }
 
+    /// Graph the aggregated values of the children of a topic without own messages.
+    ///
+    /// Returns the graph to draw when there are enough values.
+    pub fn aggregate_graph(&self, children: &[&[HistoryEntry]]) -> Option<graph::Graph> {
+        graph::Graph::aggregate(children, self.aggregate)
+    }
+
     pub fn draw(
         &mut self,
         frame: &mut Frame,
         full_area: Rect,
         topic_history: &[HistoryEntry],
+        summaries: &[MinuteSummary],
         focus: &ElementInFocus,
     ) {
+        let rows = table::rows(topic_history, self.collapse_duplicates, &self.markers);
+        self.follow_history(topic_history.len(), rows.len());
+        let index = self.selected_history_index(topic_history);
         let entry = topic_history
-            .get(self.selected_history_index(topic_history.len()))
+            .get(index)
             .expect("when Details are drawn they should always have at least one HistoryEntry");
+        // Briefly show what changed when a new message arrived
+        let previous = index
+            .checked_sub(1)
+            .and_then(|index| topic_history.get(index))
+            .filter(|_| {
+                entry.time.as_optional().is_some_and(|time| {
+                    (chrono::Local::now().naive_local() - *time).num_seconds()
+                        < HIGHLIGHT_CHANGES_SECONDS
+                })
+            });
         let history_area = self.payload.draw(
             frame,
             full_area,
-            matches!(focus, ElementInFocus::Payload),
+            matches!(
+                focus,
+                ElementInFocus::Payload | ElementInFocus::PayloadSearch
+            ),
             entry,
+            previous,
         );
         let binary_address = self.payload.binary_state.selected_address();
+        let binary_interpretation = self.payload.binary_interpretation;
         let json_selector = self.payload.json_state.selected();
+        let csv_column = self.payload.csv_column;
 
-        let table_area =
-            graph::Graph::parse(topic_history, binary_address.unwrap_or(0), json_selector).map_or(
-                history_area,
-                |graph| {
-                    let (table_area, graph_area) =
-                        split_area_vertically(history_area, history_area.height / 2);
-                    graph.draw(frame, graph_area);
-                    table_area
-                },
-            );
+        let graph = match self.graph_source {
+            graph::GraphSource::Value => graph::Graph::parse(
+                topic_history,
+                summaries,
+                binary_address.unwrap_or(0),
+                binary_interpretation,
+                json_selector,
+                csv_column,
+            ),
+            graph::GraphSource::Rate => graph::Graph::rate(topic_history),
+            graph::GraphSource::Size => graph::Graph::size(topic_history),
+        };
+        let table_area = graph.map_or(history_area, |graph| {
+            let (table_area, graph_area) =
+                split_area_vertically(history_area, history_area.height / 2);
+            graph.draw(frame, graph_area, &self.markers);
+            table_area
+        });
         self.last_table_area = table_area;
         table::draw(
             frame,
             table_area,
             topic_history,
+            &rows,
+            &self.markers,
             binary_address,
+            binary_interpretation,
             json_selector,
+            csv_column,
+            &self.history_columns,
+            self.relative_time
+                .then(|| chrono::Local::now().naive_local()),
             &mut self.table_state,
             matches!(focus, ElementInFocus::HistoryTable),
         );
     }
 }
+
+#[test]
+fn switch_topic_restores_state() {
+    let mut details = Details::default();
+    details.switch_topic(Some("foo".to_owned()));
+    details.table_state.select(Some(2));
+    details
+        .payload
+        .json_state
+        .select(vec![JsonSelector::ArrayIndex(1)]);
+
+    details.switch_topic(Some("bar".to_owned()));
+    assert_eq!(details.table_state.selected(), None);
+    assert!(details.payload.json_state.selected().is_empty());
+    details.table_state.select(Some(5));
+
+    details.switch_topic(Some("foo".to_owned()));
+    assert_eq!(details.table_state.selected(), Some(2));
+    assert_eq!(
+        details.payload.json_state.selected(),
+        [JsonSelector::ArrayIndex(1)]
+    );
+
+    details.switch_topic(Some("bar".to_owned()));
+    assert_eq!(details.table_state.selected(), Some(5));
+}
+
+#[test]
+fn follow_history_works() {
+    let mut details = Details::default();
+    details.follow_history(3, 3);
+    details.table_state.select(Some(1));
+    details.follow_history(3, 3);
+    assert_eq!(details.table_state.selected(), Some(1));
+    details.follow_history(4, 4);
+    assert_eq!(details.table_state.selected(), None);
+
+    details.pinned = true;
+    details.follow_history(4, 2);
+    assert_eq!(details.table_state.selected(), Some(1));
+    details.follow_history(5, 3);
+    assert_eq!(details.table_state.selected(), Some(1));
+}
+
+#[test]
+fn toggle_time_column_cycles() {
+    let mut details = Details::new(
+        vec![HistoryColumn::Time, HistoryColumn::Qos],
+        false,
+        false,
+        false,
+    );
+    details.toggle_time_column();
+    assert_eq!(
+        details.history_columns,
+        [HistoryColumn::Qos, HistoryColumn::ShortTime]
+    );
+    details.toggle_time_column();
+    assert_eq!(details.history_columns, [HistoryColumn::Qos]);
+    details.toggle_time_column();
+    assert_eq!(
+        details.history_columns,
+        [HistoryColumn::Qos, HistoryColumn::Time]
+    );
+}
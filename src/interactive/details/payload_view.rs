@@ -1,22 +1,36 @@
 use std::cmp::min;
 
-use ratatui::layout::{Alignment, Rect};
+use ratatui::layout::{Alignment, Constraint, Rect};
 use ratatui::style::{Color, Style};
-use ratatui::text::Text;
-use ratatui::widgets::{Block, BorderType, Paragraph, Scrollbar, ScrollbarOrientation};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{
+    Block, BorderType, Cell, Paragraph, Row, Scrollbar, ScrollbarOrientation, Table,
+};
 use ratatui::Frame;
 use ratatui_binary_data_widget::{BinaryDataWidget, BinaryDataWidgetState};
 use tui_tree_widget::{Tree, TreeState};
 
-use crate::interactive::ui::{focus_color, split_area_vertically, BORDERS_TOP_RIGHT};
+use super::binary_inspector::{self, Interpretation};
+use super::text_selection::TextSelection;
+use crate::interactive::ui::{
+    self, focus_color, split_area_vertically, BORDERS_TOP_RIGHT, STYLE_BOLD, STYLE_WARNING,
+};
 use crate::mqtt::HistoryEntry;
-use crate::payload::{tree_items_from_json, tree_items_from_messagepack, JsonSelector, Payload};
+use crate::payload::{
+    tree_items_from_json, tree_items_from_messagepack, Csv, JsonSelector, Payload,
+};
 
 #[derive(Default)]
 pub struct PayloadView {
     pub binary_state: BinaryDataWidgetState,
+    pub binary_interpretation: Interpretation,
+    /// Column of a CSV payload shown in the history and graph
+    pub csv_column: Option<usize>,
     pub json_state: TreeState<JsonSelector>,
     pub last_area: Rect,
+    /// Lowercase search for keys and values of the JSON or `MessagePack` tree
+    pub search: String,
+    pub text_selection: TextSelection,
 }
 
 impl PayloadView {
@@ -26,15 +40,40 @@ impl PayloadView {
         area: Rect,
         has_focus: bool,
         entry: &HistoryEntry,
+        previous: Option<&HistoryEntry>,
     ) -> Rect {
         let size = entry.payload_size;
-        match &entry.payload {
-            Payload::Binary(data) => self.draw_binary(frame, area, has_focus, size, data),
-            Payload::Json(json) => self.draw_json(frame, area, has_focus, size, json),
+        let truncated = entry.is_truncated();
+        let previous = previous.map(|previous| &*previous.payload);
+        match &*entry.payload {
+            Payload::Binary(data) => self.draw_binary(
+                frame,
+                area,
+                has_focus,
+                title("Binary Payload", size, truncated),
+                data,
+            ),
+            Payload::Json(json) => {
+                let previous = match previous {
+                    Some(Payload::Json(previous)) => Some(previous),
+                    _ => None,
+                };
+                self.draw_json(frame, area, has_focus, size, json, previous)
+            }
             Payload::MessagePack(messagepack) => {
-                self.draw_messagepack(frame, area, has_focus, size, messagepack)
+                let previous = match previous {
+                    Some(Payload::MessagePack(previous)) => Some(previous),
+                    _ => None,
+                };
+                self.draw_messagepack(frame, area, has_focus, size, messagepack, previous)
             }
-            Payload::String(str) => self.draw_string(frame, area, has_focus, size, str),
+            Payload::String(str) => self.draw_string(
+                frame,
+                area,
+                has_focus,
+                title("Payload", size, truncated),
+                str,
+            ),
         }
     }
 
@@ -59,11 +98,9 @@ impl PayloadView {
         frame: &mut Frame,
         area: Rect,
         has_focus: bool,
-        payload_bytes: usize,
+        title: Line,
         data: &[u8],
     ) -> Rect {
-        let title = format!("Binary Payload (Bytes: {payload_bytes})");
-
         let focus_color = focus_color(has_focus);
         let widget = BinaryDataWidget::new(data)
             .highlight_style(Style::new().fg(Color::Black).bg(focus_color))
@@ -76,10 +113,44 @@ impl PayloadView {
                     .title(title),
             );
 
-        let max_lines = widget.get_max_lines_of_data_in_area(area);
-        let (payload_area, remaining_area) = self.areas(area, has_focus, max_lines);
+        // Show the value at the selected address next to the bytes when there is enough space
+        let address = self.binary_state.selected_address().filter(|_| {
+            area.width >= binary_inspector::WIDTH.saturating_add(binary_inspector::MIN_DATA_WIDTH)
+        });
+        let Some(address) = address else {
+            let max_lines = widget.get_max_lines_of_data_in_area(area);
+            let (payload_area, remaining_area) = self.areas(area, has_focus, max_lines);
+            frame.render_stateful_widget(widget, payload_area, &mut self.binary_state);
+            return remaining_area;
+        };
 
-        frame.render_stateful_widget(widget, payload_area, &mut self.binary_state);
+        let data_width = area.width.saturating_sub(binary_inspector::WIDTH);
+        let max_lines = widget.get_max_lines_of_data_in_area(Rect {
+            width: data_width,
+            ..area
+        });
+        // Header and the types
+        let inspector_lines = 8;
+        let (payload_area, remaining_area) =
+            self.areas(area, has_focus, max_lines.max(inspector_lines));
+        let data_area = Rect {
+            width: data_width,
+            ..payload_area
+        };
+        let inspector_area = Rect {
+            x: data_area.right(),
+            width: binary_inspector::WIDTH,
+            ..payload_area
+        };
+        frame.render_stateful_widget(widget, data_area, &mut self.binary_state);
+        binary_inspector::draw(
+            frame,
+            inspector_area,
+            data,
+            address,
+            self.binary_interpretation,
+            has_focus,
+        );
         remaining_area
     }
 
@@ -90,9 +161,10 @@ impl PayloadView {
         has_focus: bool,
         payload_bytes: usize,
         json: &serde_json::Value,
+        previous: Option<&serde_json::Value>,
     ) -> Rect {
         let title = format!("JSON Payload (Bytes: {payload_bytes})");
-        let items = tree_items_from_json(json);
+        let items = tree_items_from_json(json, previous);
 
         let visible = self.json_state.flatten(&items);
         let content_height = visible
@@ -130,9 +202,10 @@ impl PayloadView {
         has_focus: bool,
         payload_bytes: usize,
         messagepack: &rmpv::Value,
+        previous: Option<&rmpv::Value>,
     ) -> Rect {
         let title = format!("MessagePack Payload (Bytes: {payload_bytes})");
-        let items = tree_items_from_messagepack(messagepack);
+        let items = tree_items_from_messagepack(messagepack, previous);
 
         let visible = self.json_state.flatten(&items);
         let content_height = visible
@@ -163,25 +236,147 @@ impl PayloadView {
         remaining_area
     }
 
-    fn draw_string(
+    /// The selected number or text within the payload like the JSON leaf or the CSV column
+    pub fn selected_value(&self, payload: &Payload) -> Option<String> {
+        match payload {
+            Payload::Binary(data) => {
+                let address = self.binary_state.selected_address()?;
+                Some(self.binary_interpretation.read(data, address)?.to_string())
+            }
+            Payload::Json(json) => {
+                match JsonSelector::get_json(json, self.json_state.selected())? {
+                    serde_json::Value::String(str) => Some(str.clone()),
+                    serde_json::Value::Array(_) | serde_json::Value::Object(_) => None,
+                    value => Some(value.to_string()),
+                }
+            }
+            Payload::MessagePack(messagepack) => {
+                match JsonSelector::get_messagepack(messagepack, self.json_state.selected())? {
+                    rmpv::Value::String(str) => str.as_str().map(ToOwned::to_owned),
+                    rmpv::Value::Array(_) | rmpv::Value::Map(_) | rmpv::Value::Binary(_) => None,
+                    value => Some(value.to_string()),
+                }
+            }
+            Payload::String(str) => self.csv_column.map_or_else(
+                || Some(str.trim().to_owned()),
+                |column| Csv::parse(str)?.last_value(column).map(ToOwned::to_owned),
+            ),
+        }
+    }
+
+    /// Select the column of a CSV payload with the given amount of columns.
+    /// Returns `true` when the selection changed.
+    pub fn select_csv_column(&mut self, column: Option<usize>, columns: usize) -> bool {
+        let column = column.map(|column| column.min(columns.saturating_sub(1)));
+        let changed = self.csv_column != column;
+        self.csv_column = column;
+        changed
+    }
+
+    fn draw_csv(
         &mut self,
         frame: &mut Frame,
         area: Rect,
         has_focus: bool,
-        payload_bytes: usize,
-        payload: &str,
+        title: Line,
+        csv: &Csv,
     ) -> Rect {
-        let title = format!("Payload (Bytes: {payload_bytes})");
-        let text = Text::from(payload);
-        let (payload_area, remaining_area) = self.areas(area, has_focus, text.height());
-        let widget = Paragraph::new(text).block(
+        let content_height = csv
+            .rows
+            .len()
+            .saturating_add(usize::from(csv.header.is_some()));
+        let (payload_area, remaining_area) = self.areas(area, has_focus, content_height);
+        // The text selection is not available for tables
+        self.text_selection.last_area = Rect::default();
+
+        let focus_color = focus_color(has_focus);
+        let selected_style = Style::new().fg(Color::Black).bg(focus_color);
+        let widths = (0..csv.columns()).map(|column| {
+            let width = csv
+                .header
+                .iter()
+                .chain(&csv.rows)
+                .filter_map(|row| row.get(column))
+                .map(|cell| ui::width(cell))
+                .max()
+                .unwrap_or_default();
+            Constraint::Length(u16::try_from(width).unwrap_or(u16::MAX))
+        });
+        let mut table = Table::new(
+            csv.rows
+                .iter()
+                .map(|cells| csv_row(cells, self.csv_column, selected_style)),
+            widths,
+        )
+        .column_spacing(2)
+        .block(
             Block::new()
                 .border_type(BorderType::Rounded)
                 .borders(BORDERS_TOP_RIGHT)
                 .title_alignment(Alignment::Center)
+                .border_style(Style::new().fg(focus_color))
                 .title(title),
         );
-        frame.render_widget(widget, payload_area);
+        if let Some(header) = &csv.header {
+            table =
+                table.header(csv_row(header, self.csv_column, selected_style).style(STYLE_BOLD));
+        }
+        frame.render_widget(table, payload_area);
         remaining_area
     }
+
+    fn draw_string(
+        &mut self,
+        frame: &mut Frame,
+        area: Rect,
+        has_focus: bool,
+        title: Line,
+        payload: &str,
+    ) -> Rect {
+        if let Some(csv) = Csv::parse(payload) {
+            let mut title = title;
+            title.spans.insert(0, Span::raw("CSV "));
+            return self.draw_csv(frame, area, has_focus, title, &csv);
+        }
+        let text = self
+            .text_selection
+            .highlight(payload, Style::new().fg(Color::Black).bg(focus_color(true)));
+        let (payload_area, remaining_area) = self.areas(area, has_focus, text.height());
+        let block = Block::new()
+            .border_type(BorderType::Rounded)
+            .borders(BORDERS_TOP_RIGHT)
+            .title_alignment(Alignment::Center)
+            .title(title);
+        self.text_selection.last_area = block.inner(payload_area);
+        frame.render_widget(Paragraph::new(text).block(block), payload_area);
+        remaining_area
+    }
+}
+
+/// Payloads truncated by the payload size limit are marked in the title
+fn title(kind: &str, payload_bytes: usize, truncated: bool) -> Line<'static> {
+    let mut title = Line::raw(format!("{kind} (Bytes: {payload_bytes})"));
+    if truncated {
+        title.spans.push(Span::raw(" "));
+        title.spans.push(Span::styled(
+            " truncated — raise --payload-size-limit or press R ",
+            STYLE_WARNING,
+        ));
+    }
+    title
+}
+
+fn csv_row<'a>(
+    cells: &[&'a str],
+    selected_column: Option<usize>,
+    selected_style: Style,
+) -> Row<'a> {
+    Row::new(cells.iter().enumerate().map(|(column, cell)| {
+        let cell = Cell::new(*cell);
+        if selected_column == Some(column) {
+            cell.style(selected_style)
+        } else {
+            cell
+        }
+    }))
 }
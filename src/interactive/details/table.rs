@@ -1,24 +1,158 @@
 use std::fmt::Write;
+use std::sync::Arc;
 
+use chrono::NaiveDateTime;
 use ratatui::layout::{Alignment, Constraint, Rect};
 use ratatui::style::{Color, Style};
 use ratatui::widgets::{
-    Block, BorderType, Row, ScrollbarOrientation, ScrollbarState, Table, TableState,
+    Block, BorderType, Cell, Row, ScrollbarOrientation, ScrollbarState, Table, TableState,
 };
 use ratatui::Frame;
 
+use super::binary_inspector::Interpretation;
+use crate::cli::HistoryColumn;
 use crate::format;
-use crate::interactive::ui::{focus_color, BORDERS_TOP_RIGHT, STYLE_BOLD};
-use crate::mqtt::HistoryEntry;
-use crate::payload::{JsonSelector, Payload};
+use crate::interactive::marker::{self, Marker};
+use crate::interactive::ui::{focus_color, BORDERS_TOP_RIGHT, STYLE_BOLD, STYLE_WARNING};
+use crate::mqtt::{HistoryEntry, Time};
+use crate::payload::{Csv, JsonSelector, Payload};
 
-#[allow(clippy::cast_precision_loss, clippy::too_many_lines)]
+/// Order of the columns regardless of the order they were enabled in
+const COLUMN_ORDER: [HistoryColumn; 5] = [
+    HistoryColumn::Time,
+    HistoryColumn::ShortTime,
+    HistoryColumn::Qos,
+    HistoryColumn::Size,
+    HistoryColumn::Retain,
+];
+
+const fn column_title(column: HistoryColumn) -> &'static str {
+    match column {
+        HistoryColumn::Time | HistoryColumn::ShortTime => "Time",
+        HistoryColumn::Qos => "QoS",
+        HistoryColumn::Size => "Size",
+        HistoryColumn::Retain => "Retain",
+    }
+}
+
+const fn column_width(column: HistoryColumn) -> u16 {
+    match column {
+        HistoryColumn::Time => 12,
+        HistoryColumn::ShortTime => 8,
+        HistoryColumn::Qos => 11,
+        HistoryColumn::Size => 7,
+        HistoryColumn::Retain => 6,
+    }
+}
+
+/// A row of the history table
+#[derive(Debug, PartialEq, Eq)]
+pub struct HistoryRow {
+    /// Index of the newest history entry shown by the row
+    pub index: usize,
+    /// Amount of consecutive identical payloads collapsed into the row
+    pub repeated: usize,
+    /// Index of the marker shown by the row instead. `index` is the history entry before it then.
+    pub marker: Option<usize>,
+}
+
+/// Rows of the history table. Consecutive identical payloads are collapsed into one row when enabled.
+///
+/// The markers are placed between the messages by their time.
+/// Markers before the first message are skipped as they are not related to the topic.
+pub fn rows(
+    topic_history: &[HistoryEntry],
+    collapse_duplicates: bool,
+    markers: &[Marker],
+) -> Vec<HistoryRow> {
+    let mut rows: Vec<HistoryRow> = Vec::with_capacity(topic_history.len());
+    let mut markers = markers.iter().enumerate().peekable();
+    for (index, entry) in topic_history.iter().enumerate() {
+        if let Some(time) = entry.time.as_optional() {
+            while let Some((marker, _)) = markers.next_if(|(_, marker)| marker.time < *time) {
+                if let Some(before) = index.checked_sub(1) {
+                    rows.push(HistoryRow {
+                        index: before,
+                        repeated: 1,
+                        marker: Some(marker),
+                    });
+                }
+            }
+        }
+        if collapse_duplicates {
+            if let Some(row) = rows.last_mut().filter(|row| row.marker.is_none()) {
+                let previous = &topic_history[row.index].payload;
+                if Arc::ptr_eq(previous, &entry.payload) || previous == &entry.payload {
+                    row.index = index;
+                    row.repeated += 1;
+                    continue;
+                }
+            }
+        }
+        rows.push(HistoryRow {
+            index,
+            repeated: 1,
+            marker: None,
+        });
+    }
+    if let Some(last) = topic_history.len().checked_sub(1) {
+        rows.extend(markers.map(|(marker, _)| HistoryRow {
+            index: last,
+            repeated: 1,
+            marker: Some(marker),
+        }));
+    }
+    rows
+}
+
+/// The age is shown instead of the time when `age_at` is given
+fn time_value(column: HistoryColumn, time: &Time, age_at: Option<NaiveDateTime>) -> String {
+    match (age_at, time.as_optional()) {
+        (Some(now), Some(time)) => format::age((now - *time).num_seconds()),
+        (None, Some(time)) if column == HistoryColumn::ShortTime => {
+            time.format("%_H:%M:%S").to_string()
+        }
+        _ => time.to_string(),
+    }
+}
+
+/// The ages of the messages are shown instead of their time when `age_at` is given
+fn column_value(
+    column: HistoryColumn,
+    entry: &HistoryEntry,
+    age_at: Option<NaiveDateTime>,
+) -> String {
+    match column {
+        HistoryColumn::Time | HistoryColumn::ShortTime => time_value(column, &entry.time, age_at),
+        HistoryColumn::Qos => format::qos(entry.qos).to_owned(),
+        HistoryColumn::Size => entry.payload_size.to_string(),
+        HistoryColumn::Retain => {
+            if matches!(entry.time, Time::Retained) {
+                "yes".to_owned()
+            } else {
+                String::new()
+            }
+        }
+    }
+}
+
+#[allow(
+    clippy::cast_precision_loss,
+    clippy::too_many_arguments,
+    clippy::too_many_lines
+)]
 pub fn draw(
     frame: &mut Frame,
     area: Rect,
     topic_history: &[HistoryEntry],
+    rows: &[HistoryRow],
+    markers: &[Marker],
     binary_address: Option<usize>,
+    binary_interpretation: Interpretation,
     json_selector: &[JsonSelector],
+    csv_column: Option<usize>,
+    columns: &[HistoryColumn],
+    age_at: Option<NaiveDateTime>,
     state: &mut TableState,
     has_focus: bool,
 ) {
@@ -47,16 +181,42 @@ pub fn draw(
             .expect("write to string should never fail");
         }
     }
+    let message_rows = rows.iter().filter(|row| row.marker.is_none()).count();
+    if message_rows < topic_history.len() {
+        write!(title, ", {message_rows} rows").expect("write to string should never fail");
+    }
+    // The values are easily mistaken for the whole payloads
+    if !json_selector.is_empty() {
+        write!(title, ", value: {}", JsonSelector::jq_path(json_selector))
+            .expect("write to string should never fail");
+    }
     title += ")";
 
-    let last_index = topic_history.len().saturating_sub(1);
-    let rows = topic_history.iter().enumerate().map(|(index, entry)| {
-        let time = entry.time.to_string();
-        let qos = format::qos(entry.qos).to_owned();
-        let value = match &entry.payload {
+    let columns = COLUMN_ORDER
+        .into_iter()
+        .filter(|column| columns.contains(column))
+        .collect::<Vec<_>>();
+
+    let last_index = rows.len().saturating_sub(1);
+    let rows = rows.iter().map(|row| {
+        if let Some(marker) = row.marker.map(|marker| &markers[marker]) {
+            let mut cells = columns
+                .iter()
+                .map(|column| match column {
+                    HistoryColumn::Time | HistoryColumn::ShortTime => {
+                        Cell::new(time_value(*column, &Time::Local(marker.time), age_at))
+                    }
+                    _ => Cell::default(),
+                })
+                .collect::<Vec<_>>();
+            cells.push(Cell::new(format!("▶ {}", marker.name)));
+            return Row::new(cells).style(marker::STYLE);
+        }
+        let entry = &topic_history[row.index];
+        let mut value = match &*entry.payload {
             Payload::Binary(data) => binary_address
-                .and_then(|address| data.get(address).copied())
-                .map_or_else(|| format!("{data:?}"), |data| format!("{data}")),
+                .and_then(|address| binary_interpretation.read(data, address))
+                .map_or_else(|| format!("{data:?}"), |number| number.to_string()),
             Payload::Json(json) => JsonSelector::get_json(json, json_selector)
                 .unwrap_or(json)
                 .to_string(),
@@ -65,44 +225,62 @@ pub fn draw(
                     .unwrap_or(messagepack)
                     .to_string()
             }
-            Payload::String(str) => str.to_string(),
+            Payload::String(str) => csv_column
+                .and_then(|column| Csv::parse(str)?.last_value(column))
+                .unwrap_or(str)
+                .to_owned(),
         };
-        let row = Row::new(vec![time, qos, value]);
-        if index == last_index {
-            row.style(STYLE_BOLD)
+        if row.repeated > 1 {
+            write!(value, "  ×{}", row.repeated).expect("write to string should never fail");
+        }
+        let mut cells = columns
+            .iter()
+            .map(|column| Cell::new(column_value(*column, entry, age_at)))
+            .collect::<Vec<_>>();
+        cells.push(if entry.is_truncated() {
+            Cell::new(format!("✂ {value}")).style(STYLE_WARNING)
+        } else {
+            Cell::new(value)
+        });
+        let table_row = Row::new(cells);
+        // The newest message, markers might come after it
+        if row.index + 1 == topic_history.len() {
+            table_row.style(STYLE_BOLD)
         } else {
-            row
+            table_row
         }
     });
 
     let focus_color = focus_color(has_focus);
 
-    let mut table = Table::new(
-        rows,
-        [
-            Constraint::Length(12),
-            Constraint::Length(11),
-            Constraint::Percentage(100),
-        ],
-    )
-    .header(Row::new(["Time", "QoS", "Value"]).style(STYLE_BOLD))
-    .block(
-        Block::new()
-            .border_type(BorderType::Rounded)
-            .borders(BORDERS_TOP_RIGHT)
-            .title_alignment(Alignment::Center)
-            .border_style(Style::new().fg(focus_color))
-            .title(title),
-    );
+    let mut header = Vec::new();
+    let mut widths = Vec::new();
+    for column in &columns {
+        header.push(column_title(*column));
+        widths.push(Constraint::Length(column_width(*column)));
+    }
+    header.push("Value");
+    widths.push(Constraint::Percentage(100));
+
+    let mut table = Table::new(rows, widths)
+        .header(Row::new(header).style(STYLE_BOLD))
+        .block(
+            Block::new()
+                .border_type(BorderType::Rounded)
+                .borders(BORDERS_TOP_RIGHT)
+                .title_alignment(Alignment::Center)
+                .border_style(Style::new().fg(focus_color))
+                .title(title),
+        );
 
     // Ensure selection is possible
     if let Some(selection) = state.selected_mut() {
-        *selection = (*selection).min(topic_history.len().saturating_sub(1));
+        *selection = (*selection).min(last_index);
     }
 
     // Scroll down offset as much as possible
     let height = area.height.saturating_sub(2); // remove block and title
-    let offset_with_last_in_view = topic_history.len().saturating_sub(height as usize);
+    let offset_with_last_in_view = (last_index + 1).saturating_sub(height as usize);
     if let Some(selection) = state.selected() {
         // Only scroll when the change will include both end and selection.
         // When the user manually scrolled away from the end keep the offset.
@@ -115,7 +293,7 @@ pub fn draw(
 
     // Workaround selection, see https://github.com/ratatui-org/ratatui/issues/174
     if state.selected().is_none() {
-        let mut state = TableState::new().with_selected(Some(topic_history.len() - 1));
+        let mut state = TableState::new().with_selected(Some(last_index));
         frame.render_stateful_widget(table, area, &mut state);
     } else {
         table = table.highlight_style(Style::new().fg(Color::Black).bg(focus_color));
@@ -129,7 +307,7 @@ pub fn draw(
             .end_symbol(None);
         // Work around overscroll by removing height from total
         let mut scrollbar_state =
-            ScrollbarState::new(topic_history.len().saturating_sub(usize::from(height)))
+            ScrollbarState::new((last_index + 1).saturating_sub(usize::from(height)))
                 .position(state.offset())
                 .viewport_content_length(height as usize);
         let scrollbar_area = Rect {
@@ -140,3 +318,64 @@ pub fn draw(
         frame.render_stateful_widget(scrollbar, scrollbar_area, &mut scrollbar_state);
     }
 }
+
+#[test]
+fn rows_collapse_duplicates() {
+    let history = ["on", "on", "off", "on", "on", "on"]
+        .map(|payload| HistoryEntry::example(payload, Time::Retained));
+    assert_eq!(rows(&history, false, &[]).len(), 6);
+    assert_eq!(
+        rows(&history, true, &[]),
+        [
+            HistoryRow {
+                index: 1,
+                repeated: 2,
+                marker: None,
+            },
+            HistoryRow {
+                index: 2,
+                repeated: 1,
+                marker: None,
+            },
+            HistoryRow {
+                index: 5,
+                repeated: 3,
+                marker: None,
+            },
+        ]
+    );
+}
+
+#[test]
+fn rows_with_markers() {
+    let first = Time::datetime_example();
+    let time = |second| first + chrono::TimeDelta::seconds(second);
+    let entry = |second| HistoryEntry {
+        qos: rumqttc::QoS::AtMostOnce,
+        time: Time::Local(time(second)),
+        payload_size: 2,
+        payload: Payload::String("on".into()).into(),
+    };
+    let marker = |second| Marker {
+        time: time(second),
+        name: String::new(),
+    };
+    let history = [entry(0), entry(10), entry(20)];
+    let markers = [marker(-5), marker(15), marker(30)];
+
+    let indices = |rows: Vec<HistoryRow>| {
+        rows.into_iter()
+            .map(|row| (row.index, row.marker))
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(
+        indices(rows(&history, false, &markers)),
+        [(0, None), (1, None), (1, Some(1)), (2, None), (2, Some(2))]
+    );
+    // Markers interrupt the collapsed duplicates
+    assert_eq!(
+        indices(rows(&history, true, &markers)),
+        [(1, None), (1, Some(1)), (2, None), (2, Some(2))]
+    );
+    assert!(rows(&[], false, &markers).is_empty());
+}
@@ -0,0 +1,226 @@
+use std::time::{Duration, Instant};
+
+use ratatui::layout::{Position, Rect};
+use ratatui::style::Style;
+use ratatui::text::{Line, Span, Text};
+
+/// Two clicks on the same position within this duration are a double-click
+const DOUBLE_CLICK: Duration = Duration::from_millis(500);
+
+/// (line, char) within the text
+type TextPosition = (usize, usize);
+
+/// Mouse selection within the string payload.
+///
+/// The native selection of the terminal does not know about the panes and selects the borders and
+/// neighbouring panes too.
+#[derive(Default)]
+pub struct TextSelection {
+    /// Area the text was rendered in on the last draw
+    pub last_area: Rect,
+    /// Where the selection started
+    anchor: Option<TextPosition>,
+    cursor: TextPosition,
+    dragging: bool,
+    last_click: Option<(Position, Instant)>,
+}
+
+impl TextSelection {
+    pub fn clear(&mut self) {
+        self.anchor = None;
+        self.dragging = false;
+    }
+
+    fn text_position(&self, position: Position) -> TextPosition {
+        let area = self.last_area;
+        let row = position
+            .y
+            .clamp(area.top(), area.bottom().saturating_sub(1))
+            .saturating_sub(area.y);
+        let column = position
+            .x
+            .clamp(area.left(), area.right().saturating_sub(1))
+            .saturating_sub(area.x);
+        (usize::from(row), usize::from(column))
+    }
+
+    /// Start a selection. Returns the word to copy on a double-click.
+    pub fn mouse_down(&mut self, position: Position, text: &str) -> Option<String> {
+        if !self.last_area.contains(position) {
+            self.clear();
+            return None;
+        }
+        let is_double_click = self
+            .last_click
+            .is_some_and(|(last, instant)| last == position && instant.elapsed() < DOUBLE_CLICK);
+        let (line, column) = self.text_position(position);
+        if is_double_click {
+            self.last_click = None;
+            self.dragging = false;
+            let (start, end) = word_at(text.lines().nth(line)?, column)?;
+            self.anchor = Some((line, start));
+            self.cursor = (line, end.saturating_sub(1));
+            return self.selected(text);
+        }
+        self.last_click = Some((position, Instant::now()));
+        self.anchor = Some((line, column));
+        self.cursor = (line, column);
+        self.dragging = true;
+        None
+    }
+
+    /// Returns `true` when the selection changed
+    pub fn mouse_drag(&mut self, position: Position) -> bool {
+        if !self.dragging {
+            return false;
+        }
+        let cursor = self.text_position(position);
+        let changed = self.cursor != cursor;
+        self.cursor = cursor;
+        changed
+    }
+
+    /// Finish dragging. Returns the selected text to copy.
+    pub fn mouse_up(&mut self, text: &str) -> Option<String> {
+        if !std::mem::take(&mut self.dragging) || self.anchor == Some(self.cursor) {
+            return None;
+        }
+        self.selected(text)
+    }
+
+    /// Start and inclusive end of the selection
+    fn range(&self) -> Option<(TextPosition, TextPosition)> {
+        let anchor = self.anchor?;
+        if anchor <= self.cursor {
+            Some((anchor, self.cursor))
+        } else {
+            Some((self.cursor, anchor))
+        }
+    }
+
+    fn is_selected(&self, line: usize, column: usize) -> bool {
+        self.range()
+            .is_some_and(|(start, end)| start <= (line, column) && (line, column) <= end)
+    }
+
+    fn selected(&self, text: &str) -> Option<String> {
+        let ((start_line, _), (end_line, _)) = self.range()?;
+        let selected = text
+            .lines()
+            .enumerate()
+            .skip(start_line)
+            .take(end_line.saturating_sub(start_line).saturating_add(1))
+            .map(|(line, content)| {
+                content
+                    .chars()
+                    .enumerate()
+                    .filter(|(column, _)| self.is_selected(line, *column))
+                    .map(|(_, char)| char)
+                    .collect::<String>()
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+        Some(selected).filter(|selected| !selected.is_empty())
+    }
+
+    /// The text with the selection styled
+    pub fn highlight<'a>(&self, text: &'a str, style: Style) -> Text<'a> {
+        let Some(((start_line, _), (end_line, _))) = self.range() else {
+            return Text::from(text);
+        };
+        text.lines()
+            .enumerate()
+            .map(|(line, content)| {
+                if line < start_line || line > end_line {
+                    return Line::raw(content);
+                }
+                let mut spans = Vec::new();
+                let mut span_start = 0;
+                let mut span_selected = false;
+                for (column, (index, _)) in content.char_indices().enumerate() {
+                    let selected = self.is_selected(line, column);
+                    if selected != span_selected {
+                        spans.push(styled(&content[span_start..index], span_selected, style));
+                        span_start = index;
+                        span_selected = selected;
+                    }
+                }
+                spans.push(styled(&content[span_start..], span_selected, style));
+                Line::from(spans)
+            })
+            .collect::<Vec<_>>()
+            .into()
+    }
+}
+
+fn styled(content: &str, selected: bool, style: Style) -> Span<'_> {
+    if selected {
+        Span::styled(content, style)
+    } else {
+        Span::raw(content)
+    }
+}
+
+/// Values like `21.5`, `-3` or `living_room` are a single word
+fn is_word_char(char: char) -> bool {
+    char.is_alphanumeric() || matches!(char, '.' | '-' | '_' | '+')
+}
+
+/// Start and exclusive end char index of the word at the column
+fn word_at(line: &str, column: usize) -> Option<(usize, usize)> {
+    let chars = line.chars().collect::<Vec<_>>();
+    if !is_word_char(*chars.get(column)?) {
+        return None;
+    }
+    let start = chars[..column]
+        .iter()
+        .rposition(|char| !is_word_char(*char))
+        .map_or(0, |index| index.saturating_add(1));
+    let end = chars[column..]
+        .iter()
+        .position(|char| !is_word_char(*char))
+        .map_or(chars.len(), |index| index.saturating_add(column));
+    Some((start, end))
+}
+
+#[test]
+fn word_at_works() {
+    let line = "temperature=21.5 humidity";
+    assert_eq!(word_at(line, 0), Some((0, 11)));
+    assert_eq!(word_at(line, 5), Some((0, 11)));
+    assert_eq!(word_at(line, 11), None);
+    assert_eq!(word_at(line, 13), Some((12, 16)));
+    assert_eq!(word_at(line, 24), Some((17, 25)));
+    assert_eq!(word_at(line, 25), None);
+}
+
+#[test]
+fn drag_selects_across_lines() {
+    let text = "first line\nsecond line\nthird";
+    let mut selection = TextSelection {
+        last_area: Rect::new(10, 5, 20, 3),
+        ..TextSelection::default()
+    };
+    assert_eq!(selection.mouse_down(Position::new(16, 5), text), None);
+    assert!(selection.mouse_drag(Position::new(15, 6)));
+    assert_eq!(selection.mouse_up(text).as_deref(), Some("line\nsecond"));
+    // Dragging outside of the area stops at its border
+    selection.mouse_down(Position::new(10, 7), text);
+    selection.mouse_drag(Position::new(50, 9));
+    assert_eq!(selection.mouse_up(text).as_deref(), Some("third"));
+}
+
+#[test]
+fn double_click_selects_word() {
+    let text = "temperature=21.5";
+    let mut selection = TextSelection {
+        last_area: Rect::new(0, 0, 20, 1),
+        ..TextSelection::default()
+    };
+    assert_eq!(selection.mouse_down(Position::new(13, 0), text), None);
+    assert_eq!(selection.mouse_up(text), None);
+    assert_eq!(
+        selection.mouse_down(Position::new(13, 0), text).as_deref(),
+        Some("21.5")
+    );
+}
@@ -1,18 +1,60 @@
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
 use ratatui::layout::Rect;
 use ratatui::style::{Color, Modifier, Style};
 use ratatui::text::{Line, Span};
 use ratatui::Frame;
 
 use crate::cli::Broker;
+use crate::interactive::details::graph::GraphSource;
+use crate::interactive::ui::{self, STYLE_WARNING};
 use crate::interactive::{App, ElementInFocus};
+use crate::payload::Payload;
 
 const VERSION_TEXT: &str = concat!(" mqttui ", env!("CARGO_PKG_VERSION"), " ");
 const VERSION_STYLE: Style = Style::new().fg(Color::Black).bg(Color::Gray);
+const SEARCH_STYLE: Style = Style::new()
+    .fg(Color::Black)
+    .bg(Color::LightGreen)
+    .add_modifier(Modifier::BOLD);
+const TOAST_STYLE: Style = Style::new()
+    .fg(Color::Black)
+    .bg(Color::LightCyan)
+    .add_modifier(Modifier::BOLD);
 const KEY_STYLE: Style = Style::new()
     .fg(Color::Black)
     .bg(Color::Gray)
     .add_modifier(Modifier::BOLD);
 
+/// Key hint which can be clicked instead of pressing the key
+pub struct Button {
+    pub area: Rect,
+    pub key: KeyEvent,
+}
+
+/// The key event of a key hint like `q`, `Enter` or `C-p`.
+/// Hints describing multiple keys like `↑↓` are not clickable.
+fn key_event(label: &str) -> Option<KeyEvent> {
+    let (label, modifiers) = label
+        .strip_prefix("C-")
+        .map_or((label, KeyModifiers::NONE), |label| {
+            (label, KeyModifiers::CONTROL)
+        });
+    let code = match label {
+        "Enter" => KeyCode::Enter,
+        "Esc" => KeyCode::Esc,
+        "Del" => KeyCode::Delete,
+        "Tab" => KeyCode::Tab,
+        _ => {
+            let mut chars = label.chars();
+            match (chars.next(), chars.next()) {
+                (Some(char), None) if char.is_ascii_graphic() => KeyCode::Char(char),
+                _ => return None,
+            }
+        }
+    };
+    Some(KeyEvent::new(code, modifiers))
+}
+
 pub struct Footer {
     broker: Box<str>,
     full_info: Box<str>,
@@ -26,11 +68,15 @@ impl Footer {
         }
     }
 
-    pub fn draw(&self, frame: &mut Frame, area: Rect, app: &App) {
+    /// Returns the clickable key hints
+    #[allow(clippy::too_many_lines)]
+    pub fn draw(&self, frame: &mut Frame, area: Rect, app: &App) -> Vec<Button> {
         let mut keys = Vec::new();
+        let mut buttons = Vec::new();
 
         macro_rules! add {
             ($key:literal, $text:literal) => {
+                let start = keys.iter().map(Span::width).sum::<usize>();
                 keys.push(Span {
                     content: std::borrow::Cow::Borrowed(concat![" ", $key, " "]),
                     style: KEY_STYLE,
@@ -39,16 +85,26 @@ impl Footer {
                     content: std::borrow::Cow::Borrowed(concat![" ", $text, " "]),
                     style: Style::new(),
                 });
+                if let Some(key) = key_event($key) {
+                    let end = keys.iter().map(Span::width).sum::<usize>();
+                    #[allow(clippy::cast_possible_truncation)]
+                    buttons.push(Button {
+                        area: Rect {
+                            x: area.x.saturating_add(start as u16),
+                            width: end.saturating_sub(start) as u16,
+                            height: 1,
+                            ..area
+                        },
+                        key,
+                    });
+                }
             };
         }
 
         match app.focus {
             ElementInFocus::TopicOverview => {
+                // Only the essentials fit, everything else is in the command palette
                 add!("q", "Quit");
-                add!("/", "Search");
-                if app.topic_overview.get_selected().is_some() {
-                    add!("Del", "Clean retained");
-                }
                 if app.can_switch_to_payload() {
                     add!("Tab", "Switch to Payload");
                 } else if app.can_switch_to_history_table() {
@@ -56,24 +112,75 @@ impl Footer {
                 } else {
                     // Changing somewhere is pointless currently
                 }
+                add!("C-p", "Commands");
+                add!("/", "Search");
+                if app.topic_overview.last_search().is_some() {
+                    add!("n/N", "Next/Previous match");
+                }
+                if !app.topic_overview.marked.is_empty() {
+                    add!("Esc", "Unmark all");
+                    add!("Del", "Clean marked");
+                } else if app.topic_overview.get_selected().is_some() {
+                    add!("Del", "Clean retained");
+                }
+                if app.is_selected_truncated() {
+                    if app.is_full_payload_pending() {
+                        keys.push(Span::styled(" Waiting for full payload ", STYLE_WARNING));
+                    } else {
+                        add!("R", "Full payload");
+                    }
+                }
             }
             ElementInFocus::TopicSearch => {
-                add!("↑", "Before");
-                add!("↓", "Next");
+                add!("↑↓", "History");
                 add!("Enter", "Open All");
                 add!("Esc", "Clear");
+                if app.topic_overview.filter {
+                    add!("C-f", "Highlight");
+                } else {
+                    add!("C-f", "Filter");
+                }
                 keys.push(Span::styled(
-                    " Search: ",
-                    Style::new()
-                        .fg(Color::Black)
-                        .bg(Color::LightGreen)
-                        .add_modifier(Modifier::BOLD),
+                    if app.topic_overview.filter {
+                        " Filter: "
+                    } else {
+                        " Search: "
+                    },
+                    SEARCH_STYLE,
                 ));
                 keys.push(Span::raw(" "));
                 keys.push(Span::raw(&app.topic_overview.search));
             }
             ElementInFocus::Payload => {
                 add!("q", "Quit");
+                add!("C-p", "Commands");
+                if matches!(
+                    app.get_selected_payload().as_deref(),
+                    Some(Payload::Json(_) | Payload::MessagePack(_))
+                ) {
+                    add!("/", "Search");
+                    if !app.details.payload.search.is_empty() {
+                        add!("n/N", "Next/Previous match");
+                    }
+                    add!("y/Y", "Copy jq path/JSON Pointer");
+                }
+                if app
+                    .details
+                    .payload
+                    .binary_state
+                    .selected_address()
+                    .is_some()
+                {
+                    add!("v", "Value type");
+                    add!("e", "Endianness");
+                }
+                add!("c", "Convert");
+                if matches!(
+                    app.get_selected_payload().as_deref(),
+                    Some(Payload::String(_))
+                ) {
+                    add!("←→", "Column");
+                }
                 #[allow(clippy::branches_sharing_code)]
                 if app.can_switch_to_history_table() {
                     add!("Tab", "Switch to History");
@@ -81,40 +188,172 @@ impl Footer {
                     add!("Tab", "Switch to Topics");
                 }
             }
+            ElementInFocus::PayloadSearch => {
+                add!("↑", "Before");
+                add!("↓", "Next");
+                add!("Tab", "Keep");
+                add!("Esc", "Clear");
+                keys.push(Span::styled(" Search: ", SEARCH_STYLE));
+                keys.push(Span::raw(" "));
+                keys.push(Span::raw(&app.details.payload.search));
+            }
             ElementInFocus::HistoryTable => {
                 add!("q", "Quit");
+                add!("C-p", "Commands");
                 add!("Tab", "Switch to Topics");
+                add!("1-4", "Toggle columns");
+                if app.details.relative_time {
+                    add!("a", "Absolute time");
+                } else {
+                    add!("a", "Relative time");
+                }
+                match app.details.graph_source {
+                    GraphSource::Value => {
+                        add!("g", "Graph rate");
+                    }
+                    GraphSource::Rate => {
+                        add!("g", "Graph size");
+                    }
+                    GraphSource::Size => {
+                        add!("g", "Graph value");
+                    }
+                }
+                if !app.details.payload.json_state.selected().is_empty() {
+                    add!("x", "Clear value selection");
+                }
+                if app.details.pinned {
+                    add!("p", "Follow newest");
+                    keys.push(Span::styled(" Pinned ", STYLE_WARNING));
+                } else {
+                    add!("p", "Pin selection");
+                }
+                if app.details.collapse_duplicates {
+                    add!("d", "Show duplicates");
+                } else {
+                    add!("d", "Collapse duplicates");
+                }
+                if app.compare.is_some() {
+                    add!("P", "Unpin comparison");
+                } else {
+                    add!("P", "Compare");
+                }
+                add!("M", "Marker");
+                if app.is_selected_truncated() {
+                    if app.is_full_payload_pending() {
+                        keys.push(Span::styled(" Waiting for full payload ", STYLE_WARNING));
+                    } else {
+                        add!("R", "Full payload");
+                    }
+                }
+            }
+            ElementInFocus::Dashboard => {
+                add!("q", "Quit");
+                if !app.dashboard.expressions.is_empty() {
+                    add!("Enter", "Show topic");
+                    add!("Del", "Unpin");
+                }
+                add!("Esc", "Close");
+            }
+            ElementInFocus::Zigbee2MqttDashboard => {
+                add!("q", "Quit");
+                add!("Enter", "Show topic");
+                add!("Esc", "Close");
+            }
+            ElementInFocus::TasmotaCommandPopup(_) => {
+                add!("Enter", "Send command");
+                add!("Esc", "Abort");
+            }
+            ElementInFocus::BrokerSwitchPopup(_) => {
+                add!("Enter", "Connect");
+                add!("Tab", "Keep/Clear messages");
+                add!("↑↓", "Discovered");
+                add!("Esc", "Abort");
+            }
+            ElementInFocus::SubscribePopup(_) => {
+                add!("Enter", "Subscribe");
+                add!("Tab", "Complete");
+                add!("↑↓", "Select");
+                add!("Esc", "Abort");
+            }
+            ElementInFocus::PayloadSizesPopup => {
+                add!("q", "Quit");
+                add!("Esc", "Close");
+            }
+            ElementInFocus::SettingsPopup(_) => {
+                add!("Enter", "Apply");
+                add!("s", "Save as default");
+                add!("↑↓", "Select");
+                add!("Space", "Change");
+                add!("Esc", "Abort");
+            }
+            ElementInFocus::MarkerPopup(_) => {
+                add!("Enter", "Add marker");
+                add!("Esc", "Abort");
+            }
+            ElementInFocus::ConvertPopup(_) => {
+                add!("Enter", "Copy");
+                add!("↑↓", "Select");
+                add!("Esc", "Close");
+            }
+            ElementInFocus::CommandPalette(_) => {
+                add!("Enter", "Run");
+                add!("↑↓", "Select");
+                add!("Esc", "Abort");
             }
             ElementInFocus::CleanRetainedPopup(_) => {
                 add!("Enter", "Clean topic tree");
+                add!("↑↓", "Scroll");
                 add!("Any", "Abort");
             }
         }
+        let warning = app.mqtt_thread.get_stats().ingest_warning();
+        if let Some(warning) = warning {
+            keys.push(Span::styled(format!(" {warning} "), STYLE_WARNING));
+        }
         let keys = Line::from(keys);
 
         #[allow(clippy::cast_possible_truncation)]
-        if matches!(app.focus, ElementInFocus::TopicSearch) {
+        if matches!(
+            app.focus,
+            ElementInFocus::TopicSearch | ElementInFocus::PayloadSearch
+        ) {
             let x = area.left().saturating_add(keys.width() as u16);
             frame.set_cursor(x, area.y);
         }
 
+        // The toast replaces the version / broker and is shown even when the keys need the space
+        if let Some(toast) = app.toast.current() {
+            frame.render_widget(keys, area);
+            let toast = Span::styled(format!(" {toast} "), TOAST_STYLE);
+            #[allow(clippy::cast_possible_truncation)]
+            let width = (toast.width() as u16).min(area.width);
+            let area = Rect {
+                x: area.right().saturating_sub(width),
+                width,
+                ..area
+            };
+            frame.render_widget(toast, area);
+            return buttons;
+        }
+
         // Show version / broker when enough space
         {
             let remaining = (area.width as usize).saturating_sub(keys.width());
-            let text = if remaining > self.full_info.len() {
+            let text = if remaining > ui::width(&self.full_info) {
                 Some(&*self.full_info)
-            } else if remaining > self.broker.len() {
+            } else if remaining > ui::width(&self.broker) {
                 Some(&*self.broker)
-            } else if remaining > VERSION_TEXT.len() {
+            } else if remaining > ui::width(VERSION_TEXT) {
                 Some(VERSION_TEXT)
             } else {
                 None // Not enough space -> show nothing
             };
             if let Some(text) = text {
                 #[allow(clippy::cast_possible_truncation)]
+                let width = ui::width(text) as u16;
                 let area = Rect {
-                    x: area.width.saturating_sub(text.len() as u16),
-                    width: text.len() as u16,
+                    x: area.width.saturating_sub(width),
+                    width,
                     ..area
                 };
                 frame.render_widget(Span::styled(text, VERSION_STYLE), area);
@@ -122,5 +361,25 @@ impl Footer {
         }
 
         frame.render_widget(keys, area);
+        buttons
     }
 }
+
+#[test]
+fn key_event_works() {
+    assert_eq!(
+        key_event("q"),
+        Some(KeyEvent::new(KeyCode::Char('q'), KeyModifiers::NONE))
+    );
+    assert_eq!(
+        key_event("Del"),
+        Some(KeyEvent::new(KeyCode::Delete, KeyModifiers::NONE))
+    );
+    assert_eq!(
+        key_event("C-p"),
+        Some(KeyEvent::new(KeyCode::Char('p'), KeyModifiers::CONTROL))
+    );
+    assert_eq!(key_event("↑↓"), None);
+    assert_eq!(key_event("n/N"), None);
+    assert_eq!(key_event("Any"), None);
+}
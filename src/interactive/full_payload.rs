@@ -0,0 +1,64 @@
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+/// How long further copies of the full message are stored without the size limit.
+/// The temporary subscription overlaps with the existing one which might deliver the message twice.
+const GRACE: Duration = Duration::from_secs(2);
+
+/// Topics whose next message is stored without the payload size limit
+#[derive(Default)]
+pub struct FullPayloadRequests {
+    /// `None` while waiting for the message, the arrival time of the first full message otherwise
+    requested: HashMap<String, Option<Instant>>,
+}
+
+pub enum Arrival {
+    /// The first full message arrived. The temporary subscription can be removed.
+    First,
+    /// Another copy arrived within the grace period
+    Copy,
+}
+
+impl FullPayloadRequests {
+    pub fn request(&mut self, topic: String) {
+        self.requested.insert(topic, None);
+    }
+
+    pub fn is_pending(&self, topic: &str) -> bool {
+        matches!(self.requested.get(topic), Some(None))
+    }
+
+    /// Returns whether the message of the topic is to be stored without the size limit
+    pub fn on_message(&mut self, topic: &str, now: Instant) -> Option<Arrival> {
+        self.requested
+            .retain(|_, arrived| arrived.map_or(true, |arrived| now - arrived < GRACE));
+        let arrived = self.requested.get_mut(topic)?;
+        if arrived.is_some() {
+            Some(Arrival::Copy)
+        } else {
+            *arrived = Some(now);
+            Some(Arrival::First)
+        }
+    }
+}
+
+#[test]
+fn requests_work() {
+    let mut requests = FullPayloadRequests::default();
+    let now = Instant::now();
+    assert!(requests.on_message("foo", now).is_none());
+
+    requests.request("foo".to_owned());
+    assert!(requests.is_pending("foo"));
+    assert!(matches!(
+        requests.on_message("foo", now),
+        Some(Arrival::First)
+    ));
+    assert!(!requests.is_pending("foo"));
+    assert!(matches!(
+        requests.on_message("foo", now + Duration::from_secs(1)),
+        Some(Arrival::Copy)
+    ));
+    assert!(requests.on_message("foo", now + GRACE).is_none());
+    assert!(requests.on_message("bar", now).is_none());
+}
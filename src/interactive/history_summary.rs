@@ -0,0 +1,79 @@
+use chrono::{NaiveDateTime, Timelike};
+
+/// Minutes kept per topic. Older summaries are dropped to keep the memory bounded.
+const MAX_MINUTES: usize = 24 * 60;
+
+/// Numeric values of one minute which were removed by the history limit
+#[derive(Debug, Clone, PartialEq)]
+pub struct MinuteSummary {
+    /// Start of the minute
+    pub minute: NaiveDateTime,
+    pub min: f64,
+    pub max: f64,
+    sum: f64,
+    pub count: usize,
+}
+
+impl MinuteSummary {
+    #[allow(clippy::cast_precision_loss)]
+    pub fn average(&self) -> f64 {
+        self.sum / self.count as f64
+    }
+}
+
+/// Downsampled series of the messages removed from the history, oldest first
+#[derive(Debug, Default)]
+pub struct SummarizedSeries {
+    minutes: Vec<MinuteSummary>,
+}
+
+impl SummarizedSeries {
+    /// Values have to be added in order of their time
+    pub fn add(&mut self, time: NaiveDateTime, value: f64) {
+        let minute = time
+            .with_second(0)
+            .and_then(|time| time.with_nanosecond(0))
+            .expect("zero seconds and nanoseconds should always be valid");
+        match self.minutes.last_mut() {
+            Some(last) if last.minute == minute => {
+                last.min = last.min.min(value);
+                last.max = last.max.max(value);
+                last.sum += value;
+                last.count = last.count.saturating_add(1);
+            }
+            _ => {
+                if self.minutes.len() >= MAX_MINUTES {
+                    self.minutes.remove(0);
+                }
+                self.minutes.push(MinuteSummary {
+                    minute,
+                    min: value,
+                    max: value,
+                    sum: value,
+                    count: 1,
+                });
+            }
+        }
+    }
+
+    pub fn minutes(&self) -> &[MinuteSummary] {
+        &self.minutes
+    }
+}
+
+#[test]
+fn summarizes_per_minute() {
+    let start = crate::mqtt::Time::datetime_example();
+    let mut series = SummarizedSeries::default();
+    series.add(start.with_second(1).unwrap(), 2.0);
+    series.add(start.with_second(30).unwrap(), 4.0);
+    series.add(start.with_second(59).unwrap(), 9.0);
+    series.add(start + chrono::TimeDelta::minutes(1), 1.0);
+    let [first, second] = series.minutes() else {
+        panic!("expected two minutes {series:?}");
+    };
+    assert_eq!(first.minute, start.with_second(0).unwrap());
+    assert_eq!((first.min, first.max, first.count), (2.0, 9.0, 3));
+    assert!((first.average() - 5.0).abs() < f64::EPSILON);
+    assert_eq!(second.count, 1);
+}
@@ -0,0 +1,231 @@
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+use serde_json::json;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::runtime::Handle;
+
+use crate::interactive::mqtt_history::MqttHistory;
+use crate::mqtt::HistoryEntry;
+
+/// Maximum size of the request line and headers
+const REQUEST_HEAD_LIMIT: u64 = 8 * 1024;
+/// Time a client has to send its request and read the response
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+const ACCEPT_BACKOFF_MIN: Duration = Duration::from_millis(50);
+const ACCEPT_BACKOFF_MAX: Duration = Duration::from_secs(5);
+
+/// Error of accepting HTTP connections which is shown as long as it persists
+pub type AcceptErrorArc = Arc<RwLock<Option<String>>>;
+
+/// Serve the latest payloads read-only as JSON via HTTP
+///
+/// `/topics` returns all topics, `/topics/<topic>` the given one.
+pub fn spawn(
+    runtime: &Handle,
+    listener: TcpListener,
+    history: Arc<RwLock<MqttHistory>>,
+) -> AcceptErrorArc {
+    let accept_err: AcceptErrorArc = Arc::new(RwLock::new(None));
+    let shared_accept_err = Arc::clone(&accept_err);
+    runtime.spawn(async move {
+        let mut backoff = ACCEPT_BACKOFF_MIN;
+        loop {
+            match listener.accept().await {
+                Ok((stream, _)) => {
+                    backoff = ACCEPT_BACKOFF_MIN;
+                    if shared_accept_err.read().unwrap().is_some() {
+                        *shared_accept_err.write().unwrap() = None;
+                    }
+                    // Errors of a single request are not relevant for the terminal UI
+                    tokio::spawn(handle(Arc::clone(&history), stream));
+                }
+                // Like running out of file descriptors. Retrying right away would only spin.
+                Err(err) => {
+                    *shared_accept_err.write().unwrap() =
+                        Some(format!("Failed to accept HTTP connections: {err}"));
+                    tokio::time::sleep(backoff).await;
+                    backoff = (backoff * 2).min(ACCEPT_BACKOFF_MAX);
+                }
+            }
+        }
+    });
+    accept_err
+}
+
+async fn handle(history: Arc<RwLock<MqttHistory>>, mut stream: TcpStream) -> std::io::Result<()> {
+    let (reader, mut writer) = stream.split();
+    let request_line = tokio::time::timeout(REQUEST_TIMEOUT, read_request_line(reader))
+        .await
+        .unwrap_or(Err(("408 Request Timeout", "The request took too long")));
+    let (status, body) = match request_line {
+        Ok(request_line) => respond(
+            &history.read().expect("mqtt history thread panicked"),
+            &request_line,
+        ),
+        Err((status, message)) => (status, json!(message).to_string()),
+    };
+    let response = format!(
+        "HTTP/1.1 {status}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    );
+    tokio::time::timeout(REQUEST_TIMEOUT, writer.write_all(response.as_bytes()))
+        .await
+        .map_err(|_| std::io::ErrorKind::TimedOut)?
+}
+
+/// Returns the request line after reading the headers or the status and message to answer with
+async fn read_request_line(
+    reader: impl AsyncRead + Unpin,
+) -> Result<String, (&'static str, &'static str)> {
+    const BAD_REQUEST: (&str, &str) = ("400 Bad Request", "Invalid HTTP request");
+    let mut reader = BufReader::new(reader.take(REQUEST_HEAD_LIMIT));
+    let mut request_line = String::new();
+    let mut header = String::new();
+    loop {
+        let line = if request_line.is_empty() {
+            &mut request_line
+        } else {
+            header.clear();
+            &mut header
+        };
+        let read = reader.read_line(line).await.map_err(|_| BAD_REQUEST)?;
+        if !line.ends_with('\n') {
+            return Err(if reader.get_ref().limit() == 0 {
+                (
+                    "431 Request Header Fields Too Large",
+                    "The request headers are too large",
+                )
+            } else {
+                BAD_REQUEST
+            });
+        }
+        // The headers end with an empty line
+        if read <= 2 && !header.is_empty() {
+            return Ok(request_line);
+        }
+    }
+}
+
+fn respond(history: &MqttHistory, request_line: &str) -> (&'static str, String) {
+    let mut parts = request_line.split_whitespace();
+    if parts.next() != Some("GET") {
+        return (
+            "405 Method Not Allowed",
+            json!("Only GET is supported").to_string(),
+        );
+    }
+    let path = parts
+        .next()
+        .unwrap_or_default()
+        .split(['?', '#'])
+        .next()
+        .unwrap_or_default();
+
+    if path == "/topics" || path == "/topics/" {
+        let topics = history
+            .get_all_topics()
+            .into_iter()
+            .filter_map(|topic| {
+                let entry = history.get(topic)?.last()?;
+                Some((topic.clone(), entry_json(entry)))
+            })
+            .collect::<serde_json::Map<_, _>>();
+        return ("200 OK", serde_json::Value::Object(topics).to_string());
+    }
+    if let Some(topic) = path.strip_prefix("/topics/") {
+        let topic = percent_decode(topic);
+        return history
+            .get(&topic)
+            .and_then(|entries| entries.last())
+            .map_or_else(
+                || ("404 Not Found", json!("Unknown topic").to_string()),
+                |entry| ("200 OK", entry_json(entry).to_string()),
+            );
+    }
+    ("404 Not Found", json!("Unknown path").to_string())
+}
+
+fn entry_json(entry: &HistoryEntry) -> serde_json::Value {
+    json!({
+        "time": entry.time,
+        "qos": entry.qos as u8,
+        "size": entry.payload_size,
+        "payload": *entry.payload,
+    })
+}
+
+/// Decode `%2F` like escapes of the URL path. Invalid escapes are kept as they are.
+fn percent_decode(input: &str) -> String {
+    let bytes = input.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut index = 0;
+    while index < bytes.len() {
+        let escaped = bytes
+            .get(index + 1..index + 3)
+            .filter(|hex| bytes[index] == b'%' && hex.iter().all(u8::is_ascii_hexdigit))
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        if let Some(byte) = escaped {
+            decoded.push(byte);
+            index += 3;
+        } else {
+            decoded.push(bytes[index]);
+            index += 1;
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+#[test]
+fn percent_decode_works() {
+    assert_eq!(percent_decode("foo/bar"), "foo/bar");
+    assert_eq!(percent_decode("foo%20bar%2Fbaz"), "foo bar/baz");
+    assert_eq!(percent_decode("100%"), "100%");
+    assert_eq!(percent_decode("%zz"), "%zz");
+    assert_eq!(percent_decode("%+1"), "%+1");
+}
+
+#[test]
+fn respond_works() {
+    let history = MqttHistory::example();
+    let (status, body) = respond(&history, "GET /topics/foo/bar HTTP/1.1\r\n");
+    assert_eq!(status, "200 OK");
+    assert!(body.contains(r#""payload":"D""#));
+
+    let (status, body) = respond(&history, "GET /topics HTTP/1.1\r\n");
+    assert_eq!(status, "200 OK");
+    let topics = serde_json::from_str::<serde_json::Value>(&body).unwrap();
+    assert_eq!(topics.as_object().unwrap().len(), 4);
+
+    let (status, _) = respond(&history, "GET /topics/foo HTTP/1.1\r\n");
+    assert_eq!(status, "404 Not Found");
+
+    let (status, _) = respond(&history, "POST /topics HTTP/1.1\r\n");
+    assert_eq!(status, "405 Method Not Allowed");
+}
+
+#[tokio::test]
+async fn read_request_line_works() {
+    let request = b"GET /topics HTTP/1.1\r\nHost: localhost\r\n\r\n";
+    assert_eq!(
+        read_request_line(&request[..]).await.unwrap(),
+        "GET /topics HTTP/1.1\r\n"
+    );
+
+    let incomplete = b"GET /topics HTTP/1.1\r\nHost: local";
+    assert_eq!(
+        read_request_line(&incomplete[..]).await.unwrap_err().0,
+        "400 Bad Request"
+    );
+
+    let huge = format!(
+        "GET /topics HTTP/1.1\r\nCookie: {}\r\n\r\n",
+        "a".repeat(10_000)
+    );
+    assert_eq!(
+        read_request_line(huge.as_bytes()).await.unwrap_err().0,
+        "431 Request Header Fields Too Large"
+    );
+}
@@ -0,0 +1,106 @@
+use chrono::NaiveDateTime;
+use ratatui::layout::{Alignment, Rect};
+use ratatui::style::{Color, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Clear, Paragraph};
+use ratatui::Frame;
+
+use crate::interactive::mqtt_history::STYLE_DARKGRAY;
+use crate::interactive::ui::{self, ElementInFocus, STYLE_BOLD};
+
+const PROMPT: &str = "Name: ";
+
+pub const STYLE: Style = Style::new().fg(Color::LightMagenta);
+
+/// Named point in time like "rebooted the device" to correlate with the received messages
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Marker {
+    pub time: NaiveDateTime,
+    pub name: String,
+}
+
+/// Name a marker at the time the popup was opened
+pub struct Popup {
+    time: NaiveDateTime,
+    pub input: String,
+    /// Focus to return to when the popup is closed
+    pub previous: Box<ElementInFocus>,
+}
+
+impl Popup {
+    pub fn new(previous: ElementInFocus) -> Self {
+        Self {
+            time: chrono::Local::now().naive_local(),
+            input: String::new(),
+            previous: Box::new(previous),
+        }
+    }
+
+    /// The marker with the entered name. Without a name it is numbered after the existing ones.
+    pub fn marker(&self, existing: usize) -> Marker {
+        let name = self.input.trim();
+        let name = if name.is_empty() {
+            format!("Marker {}", existing.saturating_add(1))
+        } else {
+            name.to_owned()
+        };
+        Marker {
+            time: self.time,
+            name,
+        }
+    }
+
+    pub fn draw(&self, frame: &mut Frame) {
+        let lines = vec![
+            Line::styled(
+                format!(
+                    "Shown in the graphs and history tables at {}",
+                    self.time.format("%_H:%M:%S")
+                ),
+                STYLE_DARKGRAY,
+            ),
+            Line::raw(""),
+            Line::from(vec![
+                Span::styled(PROMPT, STYLE_BOLD),
+                Span::raw(self.input.as_str()),
+            ]),
+        ];
+
+        let frame_area = frame.size();
+        let width = frame_area.width.saturating_sub(8).min(60);
+        let height = 5;
+        let area = Rect {
+            x: frame_area.width.saturating_sub(width) / 2,
+            y: frame_area.height.saturating_sub(height) / 2,
+            width,
+            height,
+        };
+        let block = Block::bordered()
+            .border_style(STYLE)
+            .title_alignment(Alignment::Center)
+            .title("Add marker");
+        frame.render_widget(Clear, area); // clear the background of the popup
+        frame.render_widget(Paragraph::new(lines).block(block), area);
+
+        #[allow(clippy::cast_possible_truncation)]
+        let cursor_x = area
+            .x
+            .saturating_add(1)
+            .saturating_add(PROMPT.len() as u16)
+            .saturating_add(ui::width(&self.input) as u16);
+        frame.set_cursor(
+            cursor_x.min(area.right().saturating_sub(2)),
+            area.y.saturating_add(3),
+        );
+    }
+}
+
+#[test]
+fn marker_is_named() {
+    let mut popup = Popup::new(ElementInFocus::TopicOverview);
+    assert_eq!(popup.marker(2).name, "Marker 3");
+    popup.input = " rebooted ".to_owned();
+    let marker = popup.marker(2);
+    assert_eq!(marker.name, "rebooted");
+    assert_eq!(marker.time, popup.time);
+}
@@ -1,5 +1,11 @@
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
 use std::time::{Duration, Instant};
 
+use anyhow::Context;
+use chrono::Timelike;
 use crossterm::event::{
     Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers, MouseButton, MouseEventKind,
 };
@@ -8,20 +14,50 @@ use ratatui::layout::{Alignment, Position, Rect};
 use ratatui::text::Span;
 use ratatui::widgets::Paragraph;
 use ratatui::{Frame, Terminal};
-use rumqttc::{Client, Connection};
+use rumqttc::{AsyncClient, EventLoop};
+use tokio::runtime::Handle;
 
+pub use self::dashboard::Expression as DashboardExpression;
+pub use self::mqtt_history::{HistoryLimit, MqttHistory};
 use self::ui::ElementInFocus;
-use crate::cli::Broker;
-use crate::payload::Payload;
+use crate::clean_retained::Safeguards;
+use crate::cli::{BranchSummary, Broker, Cli, HistoryColumn, MqttConnection};
+use crate::mqtt::{topic_filter, HistoryEntry};
+use crate::payload::{
+    tree_texts_from_json, tree_texts_from_messagepack, Csv, Decoding, JsonSelector, Payload,
+};
 
+mod announce;
+mod bell;
+mod broker_switch;
 mod clean_retained;
+mod clipboard;
+mod command_palette;
+mod compare;
+mod connection_stats;
+mod convert;
+mod dashboard;
 mod details;
 mod footer;
+mod full_payload;
+mod history_summary;
+mod http;
+mod marker;
 mod mqtt_error_widget;
 mod mqtt_history;
 mod mqtt_thread;
+mod packet_log;
+mod payload_sizes;
+mod settings;
+mod subscribe;
+mod tasmota;
+mod terminal_title;
+mod toast;
+mod topic_input;
 mod topic_overview;
+mod topic_table;
 mod ui;
+mod zigbee2mqtt;
 
 enum Refresh {
     /// Update the TUI
@@ -32,6 +68,9 @@ enum Refresh {
     Quit,
 }
 
+/// Give up connecting to another broker after this time to keep the UI responsive
+const BROKER_SWITCH_TIMEOUT: Duration = Duration::from_secs(10);
+
 #[derive(Clone, Copy)]
 enum SearchSelection {
     Before,
@@ -39,6 +78,28 @@ enum SearchSelection {
     After,
 }
 
+impl SearchSelection {
+    /// Find the match to select from the matches with their index in the list of all items.
+    /// `begin_index` is the index of the currently selected item. Wraps around at the end.
+    fn find<T>(self, matches: &[(usize, T)], begin_index: usize) -> Option<&(usize, T)> {
+        match self {
+            Self::Before => matches
+                .iter()
+                .rev()
+                .find(|(index, _)| *index < begin_index)
+                .or_else(|| matches.last()),
+            Self::Stay => matches
+                .iter()
+                .find(|(index, _)| *index >= begin_index)
+                .or_else(|| matches.first()),
+            Self::After => matches
+                .iter()
+                .find(|(index, _)| *index > begin_index)
+                .or_else(|| matches.first()),
+        }
+    }
+}
+
 #[derive(Clone, Copy)]
 enum ScrollDirection {
     Up,
@@ -51,21 +112,104 @@ fn reset_terminal() -> anyhow::Result<()> {
         std::io::stdout(),
         crossterm::terminal::LeaveAlternateScreen,
         crossterm::event::DisableMouseCapture,
-        crossterm::cursor::Show
+        crossterm::cursor::Show,
+        terminal_title::PopTitle
     )?;
     Ok(())
 }
 
+/// Options of the interactive terminal UI given as arguments
+#[allow(clippy::struct_excessive_bools)]
+pub struct Options {
+    pub subscribe_topic: Vec<String>,
+    pub payload_size_limit: usize,
+    pub history_limit: Option<HistoryLimit>,
+    pub ignore_topic: Vec<String>,
+    pub skip_retained_duplicates: bool,
+    pub clean_safeguards: Safeguards,
+    pub http_listen: Option<SocketAddr>,
+    pub terminal_title: String,
+    pub history_columns: Vec<HistoryColumn>,
+    pub relative_time: bool,
+    pub collapse_duplicates: bool,
+    pub pin_selection: bool,
+    pub branch_summary: BranchSummary,
+    pub accessible: bool,
+    pub arrival_pulse: bool,
+    pub announce_file: Option<PathBuf>,
+    pub dashboard: Vec<DashboardExpression>,
+}
+
+impl Options {
+    pub fn from_cli(matches: &Cli) -> anyhow::Result<Self> {
+        let dashboard = matches
+            .dashboard
+            .iter()
+            .map(|expression| {
+                expression
+                    .parse()
+                    .with_context(|| format!("Invalid dashboard expression {expression}"))
+            })
+            .collect::<anyhow::Result<_>>()?;
+        Ok(Self {
+            subscribe_topic: matches.topic.clone(),
+            payload_size_limit: matches.payload_size_limit,
+            history_limit: matches.history_limit.map(|messages| HistoryLimit {
+                messages: messages.get(),
+                compaction: matches.history_compaction,
+            }),
+            ignore_topic: matches.ignore_topic.clone(),
+            skip_retained_duplicates: matches.skip_retained_duplicates,
+            clean_safeguards: Safeguards {
+                rate_limit: matches.clean_rate_limit,
+                max_topics: matches.clean_max_topics,
+            },
+            http_listen: matches.http_listen,
+            terminal_title: matches.terminal_title.clone(),
+            history_columns: matches.history_columns.clone(),
+            relative_time: matches.relative_time,
+            collapse_duplicates: matches.collapse_duplicates,
+            pin_selection: matches.pin_selection,
+            branch_summary: matches.branch_summary,
+            accessible: matches.accessible,
+            arrival_pulse: matches.arrival_pulse,
+            announce_file: matches.announce_file.clone(),
+            dashboard,
+        })
+    }
+}
+
 pub fn show(
-    client: Client,
-    connection: Connection,
-    broker: &Broker,
-    subscribe_topic: Vec<String>,
-    payload_size_limit: usize,
+    runtime: &Handle,
+    client: AsyncClient,
+    eventloop: EventLoop,
+    mqtt_connection: MqttConnection,
+    decoding: Decoding,
+    options: Options,
+    client_id_warning: Option<String>,
 ) -> anyhow::Result<()> {
+    let announcer = options
+        .announce_file
+        .as_deref()
+        .map(announce::Announcer::new)
+        .transpose()?;
+    let http_listener = options
+        .http_listen
+        .map(|address| {
+            runtime
+                .block_on(tokio::net::TcpListener::bind(address))
+                .with_context(|| format!("Failed to listen for HTTP on {address}"))
+        })
+        .transpose()?;
     let mqtt_thread =
-        mqtt_thread::MqttThread::new(client, connection, subscribe_topic, payload_size_limit)?;
-    let app = App::new(broker, mqtt_thread);
+        mqtt_thread::MqttThread::new(runtime.clone(), client, eventloop, decoding, &options);
+    let http_accept_err = http_listener
+        .map(|listener| http::spawn(mqtt_thread.runtime(), listener, mqtt_thread.share_history()));
+    let mut app = App::new(mqtt_connection, mqtt_thread, options, announcer);
+    app.http_accept_err = http_accept_err;
+    if let Some(warning) = client_id_warning {
+        app.toast.show(warning);
+    }
 
     let original_hook = std::panic::take_hook();
     std::panic::set_hook(Box::new(move |panic| {
@@ -77,6 +221,7 @@ pub fn show(
     let mut stdout = std::io::stdout();
     crossterm::execute!(
         stdout,
+        terminal_title::PushTitle,
         crossterm::terminal::EnterAlternateScreen,
         crossterm::event::EnableMouseCapture,
         crossterm::cursor::Hide
@@ -85,14 +230,22 @@ pub fn show(
 
     terminal.clear()?;
 
-    let main_loop_result = main_loop(app, terminal);
+    let terminate = Arc::new(AtomicBool::new(false));
+    signal_hook::flag::register(signal_hook::consts::SIGTERM, Arc::clone(&terminate))?;
+
+    let main_loop_result = main_loop(&mut app, terminal, &terminate);
 
     reset_terminal()?;
+    app.mqtt_thread.disconnect();
 
     main_loop_result
 }
 
-fn main_loop<B>(mut app: App, mut terminal: Terminal<B>) -> anyhow::Result<()>
+fn main_loop<B>(
+    app: &mut App,
+    mut terminal: Terminal<B>,
+    terminate: &AtomicBool,
+) -> anyhow::Result<()>
 where
     B: Backend,
 {
@@ -100,11 +253,16 @@ where
     const DEBOUNCE: Duration = Duration::from_millis(20); // 50 FPS
 
     terminal.draw(|frame| app.draw(frame))?;
+    app.update_terminal_title()?;
+    app.announce()?;
 
     let mut last_render = Instant::now();
     let mut debounce: Option<Instant> = None;
 
     loop {
+        if terminate.load(Ordering::Relaxed) {
+            return Ok(());
+        }
         let timeout = debounce.map_or(INTERVAL, |start| DEBOUNCE.saturating_sub(start.elapsed()));
         if crossterm::event::poll(timeout)? {
             let refresh = match crossterm::event::read()? {
@@ -112,8 +270,10 @@ where
                 Event::Key(key) => app.on_key(key)?,
                 Event::Mouse(mouse) => match mouse.kind {
                     MouseEventKind::Down(MouseButton::Left) => {
-                        app.on_click(mouse.column, mouse.row)
+                        app.on_click(mouse.column, mouse.row)?
                     }
+                    MouseEventKind::Drag(MouseButton::Left) => app.on_drag(mouse.column, mouse.row),
+                    MouseEventKind::Up(MouseButton::Left) => app.on_mouse_up()?,
                     MouseEventKind::ScrollDown => {
                         app.on_scroll(ScrollDirection::Down, mouse.column, mouse.row)
                     }
@@ -138,6 +298,9 @@ where
             |debounce| debounce.elapsed() > DEBOUNCE,
         ) {
             terminal.draw(|frame| app.draw(frame))?;
+            app.update_terminal_title()?;
+            app.announce()?;
+            app.ring_bell()?;
             last_render = Instant::now();
             debounce = None;
         }
@@ -145,24 +308,156 @@ where
 }
 
 pub struct App {
+    /// High contrast without relying on colors only
+    accessible: bool,
+    announcer: Option<announce::Announcer>,
+    /// Briefly highlight topics in the tree when they receive a message
+    arrival_pulse: bool,
+    /// Ring the terminal bell on messages of the topic
+    bell: Option<bell::Bell>,
+    branch_summary: BranchSummary,
+    clean_safeguards: Safeguards,
+    /// Message pinned with `P` which is compared with the latest message of the selected topic
+    compare: Option<compare::Pinned>,
+    /// Values pinned with `w` which are shown with `D`
+    dashboard: dashboard::Dashboard,
     details: details::Details,
     focus: ElementInFocus,
     footer: footer::Footer,
+    footer_buttons: Vec<footer::Button>,
+    http_accept_err: Option<http::AcceptErrorArc>,
+    last_header_area: Rect,
+    mqtt_connection: MqttConnection,
     mqtt_thread: mqtt_thread::MqttThread,
+    show_packet_log: bool,
+    terminal_title: terminal_title::TerminalTitle,
+    toast: toast::Toast,
     topic_overview: topic_overview::TopicOverview,
+    tree_items: mqtt_history::TreeItemsCache,
+    zigbee2mqtt: zigbee2mqtt::Dashboard,
+    zigbee2mqtt_base_topic: zigbee2mqtt::BaseTopicCache,
 }
 
 impl App {
-    fn new(broker: &Broker, mqtt_thread: mqtt_thread::MqttThread) -> Self {
+    fn new(
+        mqtt_connection: MqttConnection,
+        mqtt_thread: mqtt_thread::MqttThread,
+        options: Options,
+        announcer: Option<announce::Announcer>,
+    ) -> Self {
+        let Options {
+            clean_safeguards,
+            terminal_title,
+            history_columns,
+            relative_time,
+            collapse_duplicates,
+            pin_selection,
+            branch_summary,
+            accessible,
+            arrival_pulse,
+            dashboard,
+            ..
+        } = options;
+        let broker = &mqtt_connection.broker;
+        let focus = if dashboard.is_empty() {
+            ElementInFocus::TopicOverview
+        } else {
+            ElementInFocus::Dashboard
+        };
         Self {
-            details: details::Details::default(),
-            focus: ElementInFocus::TopicOverview,
+            accessible,
+            announcer,
+            arrival_pulse,
+            bell: None,
+            branch_summary,
+            clean_safeguards,
+            compare: None,
+            dashboard: dashboard::Dashboard::new(dashboard),
+            details: details::Details::new(
+                history_columns,
+                relative_time,
+                collapse_duplicates,
+                pin_selection,
+            ),
+            focus,
             footer: footer::Footer::new(broker),
+            footer_buttons: Vec::new(),
+            http_accept_err: None,
+            last_header_area: Rect::default(),
+            show_packet_log: false,
+            terminal_title: terminal_title::TerminalTitle::new(&terminal_title, broker),
+            toast: toast::Toast::default(),
+            mqtt_connection,
             mqtt_thread,
             topic_overview: topic_overview::TopicOverview::default(),
+            tree_items: mqtt_history::TreeItemsCache::default(),
+            zigbee2mqtt: zigbee2mqtt::Dashboard::default(),
+            zigbee2mqtt_base_topic: zigbee2mqtt::BaseTopicCache::default(),
         }
     }
 
+    fn update_terminal_title(&mut self) -> std::io::Result<()> {
+        let topics = self.mqtt_thread.get_history().topic_amount();
+        self.terminal_title.update(topics)
+    }
+
+    fn announce(&mut self) -> std::io::Result<()> {
+        let Some(text) = self.announcement() else {
+            return Ok(());
+        };
+        self.announcer
+            .as_mut()
+            .map_or(Ok(()), |announcer| announcer.announce(text))
+    }
+
+    /// Textual description of the focus and selection for screen readers
+    fn announcement(&self) -> Option<String> {
+        fn message(entry: &HistoryEntry) -> String {
+            let payload = entry.payload.to_string();
+            format!("{}, {}", entry.time, ui::ellipsize(&payload, 200))
+        }
+
+        self.announcer.as_ref()?;
+        let topic = self.topic_overview.get_selected();
+        let history = self.mqtt_thread.get_history();
+        let entries = topic.as_ref().and_then(|topic| history.get(topic));
+        let text = match (&self.focus, topic, entries) {
+            (ElementInFocus::TopicOverview, None, _) => "Topics, nothing selected".to_owned(),
+            (ElementInFocus::TopicOverview, Some(topic), None) => {
+                let below = history.get_topics_below(&topic).len();
+                format!("Topic {topic}, {below} topics below")
+            }
+            (ElementInFocus::TopicOverview, Some(topic), Some(entries)) => {
+                let latest = entries.last().map(message).unwrap_or_default();
+                format!(
+                    "Topic {topic}, {} messages, latest: {latest}",
+                    entries.len()
+                )
+            }
+            (ElementInFocus::TopicSearch, ..) => {
+                format!("Search topics: {}", self.topic_overview.search)
+            }
+            (
+                ElementInFocus::HistoryTable | ElementInFocus::Payload,
+                Some(topic),
+                Some(entries),
+            ) => {
+                let index = self.details.selected_history_index(entries);
+                let selected = entries.get(index).map(message).unwrap_or_default();
+                format!(
+                    "Message {} of {} on {topic}: {selected}",
+                    index.saturating_add(1),
+                    entries.len()
+                )
+            }
+            (ElementInFocus::SettingsPopup(_), ..) => "Settings".to_owned(),
+            (ElementInFocus::CommandPalette(_), ..) => "Command palette".to_owned(),
+            _ => return None,
+        };
+        drop(history);
+        Some(text)
+    }
+
     fn can_switch_to_history_table(&self) -> bool {
         let Some(topic) = self.topic_overview.get_selected() else {
             return false;
@@ -170,6 +465,11 @@ impl App {
         self.mqtt_thread.get_history().get(&topic).is_some()
     }
 
+    /// A topic without messages is selected which graphs the aggregated values of its children
+    fn is_aggregate_selected(&self) -> bool {
+        self.topic_overview.get_selected().is_some() && !self.can_switch_to_history_table()
+    }
+
     fn can_switch_to_payload(&self) -> bool {
         let Some(topic) = self.topic_overview.get_selected() else {
             return false;
@@ -178,28 +478,365 @@ impl App {
             .get_history()
             .get(&topic)
             .and_then(|entries| {
-                let index = self.details.selected_history_index(entries.len());
+                let index = self.details.selected_history_index(entries);
                 entries.get(index)
             })
-            .is_some_and(|entry| {
-                matches!(
-                    entry.payload,
-                    Payload::Binary(_) | Payload::Json(_) | Payload::MessagePack(_)
-                )
+            .is_some_and(|entry| match &*entry.payload {
+                Payload::Binary(_) | Payload::Json(_) | Payload::MessagePack(_) => true,
+                Payload::String(str) => Csv::parse(str).is_some(),
             })
     }
 
+    /// The marked topics or the selected one when none are marked
+    fn targeted_topics(&self) -> Vec<String> {
+        if self.topic_overview.marked.is_empty() {
+            self.topic_overview.get_selected().into_iter().collect()
+        } else {
+            self.topic_overview.marked.iter().cloned().collect()
+        }
+    }
+
+    /// Clean the retained topics at the rate limit and close the popup
+    fn clean(&mut self, topics: Vec<String>) -> anyhow::Result<()> {
+        let amount = topics.len();
+        self.mqtt_thread
+            .clean(topics, self.clean_safeguards.interval())?;
+        self.toast.show(format!("Cleaned {amount} retained topics"));
+        self.topic_overview.marked.clear();
+        self.focus = ElementInFocus::TopicOverview;
+        Ok(())
+    }
+
+    /// Ring the bell on messages of the selected topic or stop ringing it
+    fn toggle_bell(&mut self) -> bool {
+        let Some(topic) = self.topic_overview.get_selected() else {
+            return false;
+        };
+        if self.bell.as_ref().is_some_and(|bell| bell.topic == topic) {
+            self.bell = None;
+            self.toast
+                .show(format!("Bell on messages of {topic} stopped"));
+        } else {
+            self.toast.show(format!("Bell on messages of {topic}"));
+            self.bell = Some(bell::Bell::new(topic, &self.mqtt_thread.get_history()));
+        }
+        true
+    }
+
+    /// The selected topic or, when it has no messages, the aggregate of its children as graphed
+    fn selected_dashboard_expression(&self) -> Option<DashboardExpression> {
+        let topic = self.topic_overview.get_selected()?;
+        Some(if self.is_aggregate_selected() {
+            DashboardExpression::children(&topic, self.details.aggregate)
+        } else {
+            DashboardExpression::topic(topic)
+        })
+    }
+
+    fn is_selected_on_dashboard(&self) -> bool {
+        self.selected_dashboard_expression()
+            .is_some_and(|expression| self.dashboard.is_pinned(&expression))
+    }
+
+    fn toggle_dashboard_pin(&mut self) -> bool {
+        let Some(expression) = self.selected_dashboard_expression() else {
+            return false;
+        };
+        let text = expression.to_string();
+        if self.dashboard.toggle(expression) {
+            self.toast.show(format!("Pinned {text} to the dashboard"));
+        } else {
+            self.toast
+                .show(format!("Unpinned {text} from the dashboard"));
+        }
+        true
+    }
+
+    fn ring_bell(&mut self) -> std::io::Result<()> {
+        let Some(bell) = &mut self.bell else {
+            return Ok(());
+        };
+        if bell.check(&self.mqtt_thread.get_history()) {
+            bell::ring()?;
+        }
+        Ok(())
+    }
+
+    /// Forget and optionally ignore the targeted topics.
+    /// When the selected topic was forgotten its remaining parent gets selected.
+    fn forget_targeted_topics(&mut self, ignore: bool) -> bool {
+        let topics = self.targeted_topics();
+        if topics.is_empty() {
+            return false;
+        }
+        for topic in &topics {
+            if ignore {
+                self.mqtt_thread.ignore_below(topic);
+            } else {
+                self.mqtt_thread.forget_below(topic);
+            }
+        }
+        let what = if let [topic] = topics.as_slice() {
+            topic.clone()
+        } else {
+            format!("{} topic trees", topics.len())
+        };
+        self.toast.show(if ignore {
+            format!("Ignoring {what}")
+        } else {
+            format!("Forgot {what}")
+        });
+        if let Some(selected) = self.topic_overview.get_selected() {
+            let forgotten = topics.iter().find(|topic| {
+                selected == **topic
+                    || selected
+                        .strip_prefix(topic.as_str())
+                        .is_some_and(|rest| rest.starts_with('/'))
+            });
+            if let Some(forgotten) = forgotten {
+                let mut parent = forgotten
+                    .split('/')
+                    .map(ToOwned::to_owned)
+                    .collect::<Vec<_>>();
+                parent.pop();
+                self.topic_overview.state.select(parent);
+            }
+        }
+        self.topic_overview.marked.clear();
+        true
+    }
+
+    /// Subscribe to everything below the selected topic.
+    /// Unsubscribes again when it was subscribed at runtime.
+    fn toggle_subscribe_below(&mut self) -> anyhow::Result<bool> {
+        let Some(topic) = self.topic_overview.get_selected() else {
+            return Ok(false);
+        };
+        let filter = format!("{topic}/#");
+        if self.mqtt_thread.is_runtime_subscription(&filter) {
+            self.mqtt_thread.unsubscribe(&filter)?;
+            self.toast.show(format!("Unsubscribed from {filter}"));
+        } else if let Some(existing) = self
+            .mqtt_thread
+            .subscribe_topic()
+            .into_iter()
+            .find(|existing| topic_filter::covers_subtree(existing, &topic))
+        {
+            self.toast
+                .show(format!("Already subscribed via {existing}"));
+        } else {
+            self.toast.show(format!("Subscribed to {filter}"));
+            self.mqtt_thread.subscribe(filter)?;
+        }
+        Ok(true)
+    }
+
+    /// Pin the selected message to compare it with the latest message of the selected topic.
+    /// Unpins the message when one is already pinned.
+    fn toggle_compare(&mut self) -> Refresh {
+        if self.compare.take().is_some() {
+            self.toast.show("Unpinned comparison".to_owned());
+            return Refresh::Update;
+        }
+        let Some(topic) = self.topic_overview.get_selected() else {
+            return Refresh::Skip;
+        };
+        let history = self.mqtt_thread.get_history();
+        let Some(entry) = history.get(&topic).and_then(|entries| {
+            let index = self.details.selected_history_index(entries);
+            entries.get(index)
+        }) else {
+            return Refresh::Skip;
+        };
+        self.compare = Some(compare::Pinned::new(topic, entry));
+        drop(history);
+        self.toast
+            .show("Pinned message to compare with the latest message".to_owned());
+        Refresh::Update
+    }
+
+    /// Return to the focus the command palette was opened in
+    fn close_command_palette(&mut self) {
+        let focus = std::mem::replace(&mut self.focus, ElementInFocus::TopicOverview);
+        if let ElementInFocus::CommandPalette(palette) = focus {
+            self.focus = *palette.previous;
+        }
+    }
+
+    /// Return to the focus the marker popup was opened in
+    fn close_marker_popup(&mut self) {
+        let focus = std::mem::replace(&mut self.focus, ElementInFocus::TopicOverview);
+        if let ElementInFocus::MarkerPopup(popup) = focus {
+            self.focus = *popup.previous;
+        }
+    }
+
+    /// Connect to the broker and replace the current connection with it
+    fn switch_broker(&mut self, broker: Broker, keep_history: bool) -> anyhow::Result<()> {
+        let connection = MqttConnection {
+            broker,
+            client_id: None,
+            ..self.mqtt_connection.clone()
+        };
+        let (broker, client, eventloop) = self.mqtt_thread.runtime().block_on(async {
+            tokio::time::timeout(
+                BROKER_SWITCH_TIMEOUT,
+                crate::mqtt::connect_async(connection.clone(), None),
+            )
+            .await
+            .map_err(|_| anyhow::anyhow!("Connecting to {} timed out", connection.broker))?
+        })?;
+        self.mqtt_thread
+            .switch_connection(client, eventloop, keep_history);
+        self.footer = footer::Footer::new(&broker);
+        self.terminal_title.set_broker(&broker);
+        self.toast.show(format!("Connected to {broker}"));
+        if !keep_history {
+            self.topic_overview.marked.clear();
+        }
+        self.mqtt_connection = connection;
+        Ok(())
+    }
+
+    fn selected_tasmota_device(&self) -> Option<String> {
+        let topic = self.topic_overview.get_selected()?;
+        tasmota::device_of(&topic).map(ToOwned::to_owned)
+    }
+
+    /// Based on the history of the last drawn frame
+    fn has_zigbee2mqtt(&self) -> bool {
+        self.zigbee2mqtt_base_topic.get().is_some()
+    }
+
+    fn has_system_topics(&self) -> bool {
+        self.mqtt_thread.get_history().has_system_topics()
+    }
+
+    /// Hide or show the broker internal topic trees like `$SYS`.
+    ///
+    /// Returns `false` when there are none to hide.
+    fn toggle_system_topics(&mut self) -> bool {
+        if !self.topic_overview.hide_system_topics && !self.has_system_topics() {
+            return false;
+        }
+        let overview = &mut self.topic_overview;
+        overview.hide_system_topics = !overview.hide_system_topics;
+        if overview.hide_system_topics
+            && overview
+                .get_selected()
+                .is_some_and(|topic| topic.starts_with('$'))
+        {
+            overview.state.select(Vec::new());
+        }
+        self.toast.show(
+            if overview.hide_system_topics {
+                "Hiding $ topics"
+            } else {
+                "Showing $ topics"
+            }
+            .to_owned(),
+        );
+        true
+    }
+
+    /// Collapse or expand the identical messages of the selected topic in the history table
+    fn toggle_collapse_duplicates(&mut self) {
+        let history = self.mqtt_thread.get_history();
+        let entries = self
+            .topic_overview
+            .get_selected()
+            .and_then(|topic| history.get(&topic))
+            .map_or(&[][..], |entries| entries);
+        self.details.toggle_collapse_duplicates(entries);
+        drop(history);
+    }
+
+    /// Apply the settings of the settings popup and optionally save them as defaults
+    fn apply_settings(&mut self, save: bool) -> anyhow::Result<()> {
+        let ElementInFocus::SettingsPopup(popup) = &self.focus else {
+            return Ok(());
+        };
+        let limit = popup.payload_size_limit()?;
+        let history_limit = popup.history_limit()?;
+        let config = save
+            .then(|| popup.config(&self.details.history_columns))
+            .transpose()?;
+        let history_columns = popup.history_columns(&self.details.history_columns);
+        let relative_time = popup.relative_time;
+        let collapse_duplicates = popup.collapse_duplicates;
+        let pin_selection = popup.pin_selection;
+        let branch_summary = popup.branch_summary;
+        let accessible = popup.accessible;
+        let arrival_pulse = popup.arrival_pulse;
+        let message = if let Some(config) = config {
+            let path = crate::config::save(&config)?;
+            format!("Saved settings to {}", path.display())
+        } else {
+            "Applied settings".to_owned()
+        };
+
+        self.mqtt_thread.set_payload_size_limit(limit);
+        self.mqtt_thread.set_history_limit(history_limit);
+        self.details.history_columns = history_columns;
+        self.details.relative_time = relative_time;
+        self.details.pinned = pin_selection;
+        self.branch_summary = branch_summary;
+        self.accessible = accessible;
+        self.arrival_pulse = arrival_pulse;
+        if self.details.collapse_duplicates != collapse_duplicates {
+            self.toggle_collapse_duplicates();
+        }
+        self.toast.show(message);
+        self.focus = ElementInFocus::TopicOverview;
+        Ok(())
+    }
+
+    /// The message selected in the history table was truncated by the payload size limit
+    fn is_selected_truncated(&self) -> bool {
+        let Some(topic) = self.topic_overview.get_selected() else {
+            return false;
+        };
+        self.mqtt_thread
+            .get_history()
+            .get(&topic)
+            .and_then(|entries| {
+                let index = self.details.selected_history_index(entries);
+                entries.get(index)
+            })
+            .is_some_and(HistoryEntry::is_truncated)
+    }
+
+    fn is_full_payload_pending(&self) -> bool {
+        self.topic_overview
+            .get_selected()
+            .is_some_and(|topic| self.mqtt_thread.is_full_payload_pending(&topic))
+    }
+
+    /// Store the next message of the selected topic without the payload size limit
+    fn request_full_payload(&mut self) -> anyhow::Result<Refresh> {
+        let Some(topic) = self.topic_overview.get_selected() else {
+            return Ok(Refresh::Skip);
+        };
+        if !self.is_selected_truncated() {
+            return Ok(Refresh::Skip);
+        }
+        self.toast
+            .show(format!("Waiting for the next full message of {topic}"));
+        self.mqtt_thread.request_full_payload(topic)?;
+        Ok(Refresh::Update)
+    }
+
     /// On current topic with the current history table index
-    fn get_selected_payload(&self) -> Option<Payload> {
+    fn get_selected_payload(&self) -> Option<Arc<Payload>> {
         let topic = self.topic_overview.get_selected()?;
         self.mqtt_thread
             .get_history()
             .get(&topic)
             .and_then(|entries| {
-                let index = self.details.selected_history_index(entries.len());
+                let index = self.details.selected_history_index(entries);
                 entries.get(index)
             })
-            .map(|entry| entry.payload.clone())
+            .map(|entry| Arc::clone(&entry.payload))
     }
 
     #[allow(clippy::too_many_lines, clippy::cognitive_complexity)]
@@ -207,6 +844,235 @@ impl App {
         if key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL) {
             return Ok(Refresh::Quit);
         }
+        if key.code == KeyCode::F(12) {
+            self.show_packet_log = !self.show_packet_log;
+            return Ok(Refresh::Update);
+        }
+        if key.code == KeyCode::Char('P')
+            && matches!(
+                self.focus,
+                ElementInFocus::TopicOverview
+                    | ElementInFocus::Payload
+                    | ElementInFocus::HistoryTable
+            )
+        {
+            return Ok(self.toggle_compare());
+        }
+        if key.code == KeyCode::Char('R')
+            && matches!(
+                self.focus,
+                ElementInFocus::TopicOverview
+                    | ElementInFocus::Payload
+                    | ElementInFocus::HistoryTable
+            )
+        {
+            return self.request_full_payload();
+        }
+        if key.code == KeyCode::Char('M')
+            && matches!(
+                self.focus,
+                ElementInFocus::TopicOverview
+                    | ElementInFocus::Payload
+                    | ElementInFocus::HistoryTable
+            )
+        {
+            let previous = std::mem::replace(&mut self.focus, ElementInFocus::TopicOverview);
+            self.focus = ElementInFocus::MarkerPopup(marker::Popup::new(previous));
+            return Ok(Refresh::Update);
+        }
+        if key.code == KeyCode::Char('p')
+            && key.modifiers.contains(KeyModifiers::CONTROL)
+            && matches!(
+                self.focus,
+                ElementInFocus::TopicOverview
+                    | ElementInFocus::Payload
+                    | ElementInFocus::HistoryTable
+            )
+        {
+            let actions = command_palette::actions(self);
+            let previous = std::mem::replace(&mut self.focus, ElementInFocus::TopicOverview);
+            self.focus =
+                ElementInFocus::CommandPalette(command_palette::Palette::new(actions, previous));
+            return Ok(Refresh::Update);
+        }
+
+        if let ElementInFocus::CommandPalette(palette) = &mut self.focus {
+            match key.code {
+                KeyCode::Char(char) => palette.push(char),
+                KeyCode::Backspace => palette.pop(),
+                KeyCode::Down => palette.select_next(),
+                KeyCode::Up => palette.select_previous(),
+                KeyCode::Enter => {
+                    let action = palette.selected_action();
+                    self.close_command_palette();
+                    if let Some(action) = action {
+                        // Run the action like its key was pressed in the previous focus
+                        return self.on_key(action.key_event());
+                    }
+                }
+                KeyCode::Esc => self.close_command_palette(),
+                _ => return Ok(Refresh::Skip),
+            }
+            return Ok(Refresh::Update);
+        }
+
+        if let ElementInFocus::MarkerPopup(popup) = &mut self.focus {
+            match key.code {
+                KeyCode::Char(char) => popup.input.push(char),
+                KeyCode::Backspace => _ = popup.input.pop(),
+                KeyCode::Enter => {
+                    let marker = popup.marker(self.details.markers.len());
+                    self.toast.show(format!("Added marker {}", marker.name));
+                    self.details.markers.push(marker);
+                    self.close_marker_popup();
+                }
+                KeyCode::Esc => self.close_marker_popup(),
+                _ => return Ok(Refresh::Skip),
+            }
+            return Ok(Refresh::Update);
+        }
+
+        if let ElementInFocus::TasmotaCommandPopup(popup) = &mut self.focus {
+            match key.code {
+                KeyCode::Char(char) => popup.input.push(char),
+                KeyCode::Backspace => _ = popup.input.pop(),
+                KeyCode::Enter => {
+                    if let Some((topic, payload)) = popup.command() {
+                        self.toast.show(format!("Sent {payload:?} to {topic}"));
+                        self.mqtt_thread.publish(topic, payload)?;
+                    }
+                    self.focus = ElementInFocus::TopicOverview;
+                }
+                KeyCode::Esc => self.focus = ElementInFocus::TopicOverview,
+                _ => return Ok(Refresh::Skip),
+            }
+            return Ok(Refresh::Update);
+        }
+
+        if let ElementInFocus::ConvertPopup(popup) = &mut self.focus {
+            match key.code {
+                KeyCode::Down | KeyCode::Char('j') => popup.select_next(),
+                KeyCode::Up | KeyCode::Char('k') => popup.select_previous(),
+                KeyCode::Enter => {
+                    if let Some(converted) = popup.selected_conversion() {
+                        clipboard::copy(converted)?;
+                        self.toast.show(format!("Copied {converted}"));
+                    }
+                    self.focus = ElementInFocus::Payload;
+                }
+                KeyCode::Esc | KeyCode::Char('q' | 'c') => self.focus = ElementInFocus::Payload,
+                _ => return Ok(Refresh::Skip),
+            }
+            return Ok(Refresh::Update);
+        }
+
+        if let ElementInFocus::BrokerSwitchPopup(popup) = &mut self.focus {
+            match key.code {
+                KeyCode::Char(char) => popup.input.push(char),
+                KeyCode::Backspace => _ = popup.input.pop(),
+                KeyCode::Tab => popup.keep_history = !popup.keep_history,
+                KeyCode::Down => popup.select_next(),
+                KeyCode::Up => popup.select_previous(),
+                KeyCode::Enter => {
+                    let keep_history = popup.keep_history;
+                    let result = popup
+                        .broker()
+                        .and_then(|broker| self.switch_broker(broker, keep_history));
+                    match result {
+                        Ok(()) => self.focus = ElementInFocus::TopicOverview,
+                        Err(err) => {
+                            if let ElementInFocus::BrokerSwitchPopup(popup) = &mut self.focus {
+                                popup.error = Some(format!("{err:#}").replace('\n', " "));
+                            }
+                        }
+                    }
+                }
+                KeyCode::Esc => self.focus = ElementInFocus::TopicOverview,
+                _ => return Ok(Refresh::Skip),
+            }
+            return Ok(Refresh::Update);
+        }
+
+        if let ElementInFocus::SubscribePopup(popup) = &mut self.focus {
+            let history = self.mqtt_thread.get_history();
+            let topics = history.get_all_topics();
+            match key.code {
+                KeyCode::Char(char) => popup.topic.push(char, &topics),
+                KeyCode::Backspace => popup.topic.pop(&topics),
+                KeyCode::Down => popup.topic.select_next(),
+                KeyCode::Up => popup.topic.select_previous(),
+                KeyCode::Tab => _ = popup.topic.complete(&topics),
+                KeyCode::Enter => match popup.filter() {
+                    Ok(filter) => {
+                        drop(history);
+                        self.mqtt_thread.subscribe(filter.clone())?;
+                        self.toast.show(format!("Subscribed to {filter}"));
+                        self.focus = ElementInFocus::TopicOverview;
+                    }
+                    Err(err) => popup.error = Some(format!("{err:#}")),
+                },
+                KeyCode::Esc => self.focus = ElementInFocus::TopicOverview,
+                _ => return Ok(Refresh::Skip),
+            }
+            return Ok(Refresh::Update);
+        }
+
+        if let ElementInFocus::SettingsPopup(popup) = &mut self.focus {
+            match key.code {
+                KeyCode::Down | KeyCode::Char('j') => popup.select_next(),
+                KeyCode::Up | KeyCode::Char('k') => popup.select_previous(),
+                KeyCode::Left | KeyCode::Right | KeyCode::Char(' ') => popup.change(),
+                KeyCode::Char(char) if popup.input(char) => {}
+                KeyCode::Backspace if popup.backspace() => {}
+                KeyCode::Enter | KeyCode::Char('s') => {
+                    if let Err(err) = self.apply_settings(key.code == KeyCode::Char('s')) {
+                        if let ElementInFocus::SettingsPopup(popup) = &mut self.focus {
+                            popup.error = Some(format!("{err:#}"));
+                        }
+                    }
+                }
+                KeyCode::Esc => self.focus = ElementInFocus::TopicOverview,
+                _ => return Ok(Refresh::Skip),
+            }
+            return Ok(Refresh::Update);
+        }
+
+        if let ElementInFocus::CleanRetainedPopup(popup) = &mut self.focus {
+            match key.code {
+                KeyCode::Down | KeyCode::Char('j') => popup.scroll_down(1),
+                KeyCode::Up | KeyCode::Char('k') => {
+                    if !popup.scroll_up(1) {
+                        return Ok(Refresh::Skip);
+                    }
+                }
+                KeyCode::PageDown => popup.scroll_down(10),
+                KeyCode::PageUp => {
+                    if !popup.scroll_up(10) {
+                        return Ok(Refresh::Skip);
+                    }
+                }
+                KeyCode::Enter | KeyCode::Char(' ') if popup.extra_confirmation.is_none() => {
+                    let topics = popup
+                        .affected(&self.mqtt_thread.get_history())
+                        .into_keys()
+                        .collect::<Vec<_>>();
+                    if self.clean_safeguards.exceeds_max_topics(topics.len()) {
+                        popup.extra_confirmation = self.clean_safeguards.max_topics;
+                    } else {
+                        self.clean(topics)?;
+                    }
+                }
+                KeyCode::Char('Y') if popup.extra_confirmation.is_some() => {
+                    let topics = popup
+                        .affected(&self.mqtt_thread.get_history())
+                        .into_keys()
+                        .collect::<Vec<_>>();
+                    self.clean(topics)?;
+                }
+                _ => self.focus = ElementInFocus::TopicOverview,
+            }
+            return Ok(Refresh::Update);
+        }
 
         let update = match &self.focus {
             ElementInFocus::TopicOverview => match key.code {
@@ -223,7 +1089,65 @@ impl App {
                     self.focus = ElementInFocus::TopicSearch;
                     true
                 }
+                KeyCode::Char('a') => {
+                    self.details.relative_time = !self.details.relative_time;
+                    true
+                }
+                KeyCode::Char('g') => {
+                    if self.is_aggregate_selected() {
+                        self.details.aggregate = self.details.aggregate.next();
+                    } else {
+                        self.details.graph_source = self.details.graph_source.next();
+                    }
+                    true
+                }
+                KeyCode::Char('n') => self.repeat_last_search(SearchSelection::After),
+                KeyCode::Char('N') => self.repeat_last_search(SearchSelection::Before),
+                KeyCode::Esc if !self.topic_overview.marked.is_empty() => {
+                    self.topic_overview.marked.clear();
+                    true
+                }
                 KeyCode::Esc => self.topic_overview.state.select(vec![]),
+                KeyCode::Char('T') => {
+                    self.topic_overview.flat = !self.topic_overview.flat;
+                    true
+                }
+                KeyCode::Char('o') if self.topic_overview.flat => {
+                    let table = &mut self.topic_overview.table;
+                    table.sort = table.sort.next();
+                    true
+                }
+                KeyCode::Char('O') if self.topic_overview.flat => {
+                    let table = &mut self.topic_overview.table;
+                    table.descending = !table.descending;
+                    true
+                }
+                KeyCode::Down | KeyCode::Char('j') if self.topic_overview.flat => {
+                    self.topic_overview.select_in_table(1)
+                }
+                KeyCode::Up | KeyCode::Char('k') if self.topic_overview.flat => {
+                    self.topic_overview.select_in_table(-1)
+                }
+                KeyCode::Home if self.topic_overview.flat => {
+                    self.topic_overview.select_in_table(isize::MIN)
+                }
+                KeyCode::End if self.topic_overview.flat => {
+                    self.topic_overview.select_in_table(isize::MAX)
+                }
+                KeyCode::PageUp if self.topic_overview.flat => {
+                    self.topic_overview.page_in_table(false)
+                }
+                KeyCode::PageDown if self.topic_overview.flat => {
+                    self.topic_overview.page_in_table(true)
+                }
+                KeyCode::Enter
+                | KeyCode::Char(' ' | 'h' | 'l')
+                | KeyCode::Left
+                | KeyCode::Right
+                    if self.topic_overview.flat =>
+                {
+                    false
+                }
                 KeyCode::Enter | KeyCode::Char(' ') => self.topic_overview.state.toggle_selected(),
                 KeyCode::Down | KeyCode::Char('j') => self.topic_overview.state.key_down(),
                 KeyCode::Up | KeyCode::Char('k') => self.topic_overview.state.key_up(),
@@ -248,35 +1172,115 @@ impl App {
                     self.topic_overview.state.scroll_down(page_jump)
                 }
                 KeyCode::Backspace | KeyCode::Delete => {
-                    if let Some(topic) = self.topic_overview.get_selected() {
-                        self.focus = ElementInFocus::CleanRetainedPopup(topic);
+                    let topics = self.targeted_topics();
+                    if topics.is_empty() {
+                        false
+                    } else {
+                        self.focus =
+                            ElementInFocus::CleanRetainedPopup(clean_retained::Popup::new(topics));
+                        true
+                    }
+                }
+                KeyCode::Char('m') => self.topic_overview.toggle_mark(),
+                KeyCode::Char('$') => self.toggle_system_topics(),
+                KeyCode::Char('f') => self.forget_targeted_topics(false),
+                KeyCode::Char('t') => {
+                    if let Some(device) = self.selected_tasmota_device() {
+                        self.focus =
+                            ElementInFocus::TasmotaCommandPopup(tasmota::CommandPopup::new(device));
+                        true
+                    } else {
+                        false
+                    }
+                }
+                KeyCode::Char('z') => {
+                    let base_topic = zigbee2mqtt::find_base_topic(&self.mqtt_thread.get_history());
+                    if let Some(base_topic) = base_topic {
+                        self.zigbee2mqtt.base_topic = base_topic;
+                        self.focus = ElementInFocus::Zigbee2MqttDashboard;
                         true
                     } else {
                         false
                     }
                 }
+                KeyCode::Char('i') => self.forget_targeted_topics(true),
+                KeyCode::Char('s') => self.toggle_subscribe_below()?,
+                KeyCode::Char('+') => {
+                    self.focus = ElementInFocus::SubscribePopup(subscribe::Popup::default());
+                    true
+                }
+                KeyCode::Char('b') => {
+                    self.focus =
+                        ElementInFocus::BrokerSwitchPopup(broker_switch::Popup::with_discovery());
+                    true
+                }
+                KeyCode::Char('B') => self.toggle_bell(),
+                KeyCode::Char('w') => self.toggle_dashboard_pin(),
+                KeyCode::Char('D') => {
+                    self.focus = ElementInFocus::Dashboard;
+                    true
+                }
+                KeyCode::Char('S') => {
+                    self.focus = ElementInFocus::PayloadSizesPopup;
+                    true
+                }
+                KeyCode::Char(',') => {
+                    self.focus = ElementInFocus::SettingsPopup(settings::Popup::new(
+                        self.mqtt_thread.payload_size_limit(),
+                        self.mqtt_thread.history_limit(),
+                        &self.details.history_columns,
+                        self.details.relative_time,
+                        self.details.collapse_duplicates,
+                        self.details.pinned,
+                        self.branch_summary,
+                        self.accessible,
+                        self.arrival_pulse,
+                    ));
+                    true
+                }
                 _ => false,
             },
             ElementInFocus::TopicSearch => match key.code {
+                KeyCode::Char('f') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    self.topic_overview.filter = !self.topic_overview.filter;
+                    if self.topic_overview.filter {
+                        self.open_all_search_matches();
+                    }
+                    true
+                }
                 KeyCode::Char(char) => {
                     self.topic_overview.search += &char.to_lowercase().to_string();
+                    self.topic_overview.stop_search_history();
                     self.search_select(SearchSelection::Stay);
                     true
                 }
                 KeyCode::Backspace => {
                     self.topic_overview.search.pop();
+                    self.topic_overview.stop_search_history();
                     self.search_select(SearchSelection::Stay);
                     true
                 }
-                KeyCode::Up => self.search_select(SearchSelection::Before),
-                KeyCode::Down => self.search_select(SearchSelection::After),
+                KeyCode::Up => {
+                    if self.topic_overview.search_history_older() {
+                        self.search_select(SearchSelection::Stay);
+                    }
+                    true
+                }
+                KeyCode::Down => {
+                    if self.topic_overview.search_history_newer() {
+                        self.search_select(SearchSelection::Stay);
+                    }
+                    true
+                }
                 KeyCode::Enter => {
+                    self.topic_overview.remember_search();
                     self.search_select(SearchSelection::After);
                     self.topic_overview.state.close_all();
                     self.open_all_search_matches();
                     true
                 }
                 KeyCode::Esc => {
+                    self.topic_overview.remember_search();
                     self.topic_overview.search = String::new();
                     self.focus = ElementInFocus::TopicOverview;
                     true
@@ -290,6 +1294,7 @@ impl App {
                     self.topic_overview.state.scroll_down(page_jump)
                 }
                 KeyCode::Tab => {
+                    self.topic_overview.remember_search();
                     self.focus = ElementInFocus::TopicOverview;
                     true
                 }
@@ -307,7 +1312,18 @@ impl App {
                     self.focus = ElementInFocus::TopicOverview;
                     return Ok(Refresh::Update);
                 }
-                match self.get_selected_payload() {
+                if key.code == KeyCode::Char('c') {
+                    let popup = self
+                        .get_selected_payload()
+                        .and_then(|payload| self.details.payload.selected_value(&payload))
+                        .and_then(convert::Popup::new);
+                    match popup {
+                        Some(popup) => self.focus = ElementInFocus::ConvertPopup(popup),
+                        None => self.toast.show("Select a number to convert".to_owned()),
+                    }
+                    return Ok(Refresh::Update);
+                }
+                match self.get_selected_payload().as_deref() {
                     Some(Payload::Binary(_)) => match key.code {
                         KeyCode::Esc => self.details.payload.binary_state.select_address(None),
                         KeyCode::Down | KeyCode::Char('j') => {
@@ -340,13 +1356,50 @@ impl App {
                         KeyCode::Char('d') if key.modifiers.contains(KeyModifiers::CONTROL) => {
                             self.details.payload.binary_state.scroll_down(3)
                         }
+                        KeyCode::Char('v') => {
+                            self.details.payload.binary_interpretation.next_type();
+                            true
+                        }
+                        KeyCode::Char('e') => {
+                            self.details
+                                .payload
+                                .binary_interpretation
+                                .toggle_endianness();
+                            true
+                        }
                         _ => false,
                     },
                     Some(Payload::Json(_) | Payload::MessagePack(_)) => match key.code {
                         KeyCode::Esc => self.details.payload.json_state.select(vec![]),
+                        KeyCode::Char('/') => {
+                            self.focus = ElementInFocus::PayloadSearch;
+                            true
+                        }
+                        KeyCode::Char('n') if !self.details.payload.search.is_empty() => {
+                            self.payload_search_select(SearchSelection::After)
+                        }
+                        KeyCode::Char('N') if !self.details.payload.search.is_empty() => {
+                            self.payload_search_select(SearchSelection::Before)
+                        }
                         KeyCode::Enter | KeyCode::Char(' ') => {
                             self.details.payload.json_state.toggle_selected()
                         }
+                        KeyCode::Char('y' | 'Y') => {
+                            let selected = self.details.payload.json_state.selected();
+                            if selected.is_empty() {
+                                self.toast
+                                    .show("Select a value to copy its path".to_owned());
+                            } else {
+                                let path = if key.code == KeyCode::Char('y') {
+                                    JsonSelector::jq_path(selected)
+                                } else {
+                                    JsonSelector::json_pointer(selected)
+                                };
+                                clipboard::copy(&path)?;
+                                self.toast.show(format!("Copied {path}"));
+                            }
+                            true
+                        }
                         KeyCode::Down | KeyCode::Char('j') => {
                             self.details.payload.json_state.key_down()
                         }
@@ -371,9 +1424,52 @@ impl App {
                         }
                         _ => false,
                     },
-                    Some(Payload::String(_)) | None => false,
+                    Some(Payload::String(str)) => {
+                        let columns = Csv::parse(str).map_or(0, |csv| csv.columns());
+                        let column = self.details.payload.csv_column;
+                        let column = match key.code {
+                            KeyCode::Esc => None,
+                            KeyCode::Left | KeyCode::Char('h') => {
+                                Some(column.map_or(0, |column| column.saturating_sub(1)))
+                            }
+                            KeyCode::Right | KeyCode::Char('l') => {
+                                Some(column.map_or(0, |column| column.saturating_add(1)))
+                            }
+                            KeyCode::Home => Some(0),
+                            KeyCode::End => Some(usize::MAX),
+                            _ => return Ok(Refresh::Skip),
+                        };
+                        self.details.payload.select_csv_column(column, columns)
+                    }
+                    None => false,
                 }
             }
+            ElementInFocus::PayloadSearch => match key.code {
+                KeyCode::Char(char) => {
+                    self.details.payload.search += &char.to_lowercase().to_string();
+                    self.payload_search_select(SearchSelection::Stay);
+                    true
+                }
+                KeyCode::Backspace => {
+                    self.details.payload.search.pop();
+                    self.payload_search_select(SearchSelection::Stay);
+                    true
+                }
+                KeyCode::Up => self.payload_search_select(SearchSelection::Before),
+                KeyCode::Down | KeyCode::Enter => {
+                    self.payload_search_select(SearchSelection::After)
+                }
+                KeyCode::Esc => {
+                    self.details.payload.search = String::new();
+                    self.focus = ElementInFocus::Payload;
+                    true
+                }
+                KeyCode::Tab => {
+                    self.focus = ElementInFocus::Payload;
+                    true
+                }
+                _ => false,
+            },
             ElementInFocus::HistoryTable => match key.code {
                 KeyCode::Char('q') => return Ok(Refresh::Quit),
                 KeyCode::BackTab if self.can_switch_to_payload() => {
@@ -390,6 +1486,43 @@ impl App {
                     *selection = None;
                     before != *selection
                 }
+                KeyCode::Char('p') => {
+                    self.details.pinned = !self.details.pinned;
+                    true
+                }
+                KeyCode::Char('x') => self.details.payload.json_state.select(vec![]),
+                KeyCode::Char('d') if !key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    self.toggle_collapse_duplicates();
+                    true
+                }
+                KeyCode::Char('a') => {
+                    self.details.relative_time = !self.details.relative_time;
+                    true
+                }
+                KeyCode::Char('g') => {
+                    if self.is_aggregate_selected() {
+                        self.details.aggregate = self.details.aggregate.next();
+                    } else {
+                        self.details.graph_source = self.details.graph_source.next();
+                    }
+                    true
+                }
+                KeyCode::Char('1') => {
+                    self.details.toggle_time_column();
+                    true
+                }
+                KeyCode::Char('2') => {
+                    self.details.toggle_column(HistoryColumn::Qos);
+                    true
+                }
+                KeyCode::Char('3') => {
+                    self.details.toggle_column(HistoryColumn::Size);
+                    true
+                }
+                KeyCode::Char('4') => {
+                    self.details.toggle_column(HistoryColumn::Retain);
+                    true
+                }
                 KeyCode::Down | KeyCode::Char('j') => {
                     let selection = self.details.table_state.selected_mut();
                     let before = *selection;
@@ -439,9 +1572,86 @@ impl App {
                 }
                 _ => false,
             },
-            ElementInFocus::CleanRetainedPopup(topic) => {
-                if matches!(key.code, KeyCode::Enter | KeyCode::Char(' ')) {
-                    self.mqtt_thread.clean_below(topic)?;
+            ElementInFocus::Dashboard => match key.code {
+                KeyCode::Char('q') => return Ok(Refresh::Quit),
+                KeyCode::Esc | KeyCode::Char('D') => {
+                    self.focus = ElementInFocus::TopicOverview;
+                    true
+                }
+                KeyCode::Enter => {
+                    if let Some(topic) = self.dashboard.get_selected_topic() {
+                        self.topic_overview
+                            .select_topic(topic.split('/').map(ToOwned::to_owned).collect());
+                        self.focus = ElementInFocus::TopicOverview;
+                        true
+                    } else {
+                        false
+                    }
+                }
+                KeyCode::Backspace | KeyCode::Delete => self.dashboard.remove_selected(),
+                KeyCode::Right | KeyCode::Char('l') => self.dashboard.select_next(),
+                KeyCode::Left | KeyCode::Char('h') => self.dashboard.select_previous(),
+                KeyCode::Down | KeyCode::Char('j') => self.dashboard.select_below(),
+                KeyCode::Up | KeyCode::Char('k') => self.dashboard.select_above(),
+                _ => false,
+            },
+            ElementInFocus::Zigbee2MqttDashboard => match key.code {
+                KeyCode::Char('q') => return Ok(Refresh::Quit),
+                KeyCode::Esc | KeyCode::Char('z') => {
+                    self.focus = ElementInFocus::TopicOverview;
+                    true
+                }
+                KeyCode::Enter => {
+                    let topic = self
+                        .zigbee2mqtt
+                        .get_selected(&self.mqtt_thread.get_history());
+                    if let Some(topic) = topic {
+                        self.topic_overview
+                            .select_topic(topic.split('/').map(ToOwned::to_owned).collect());
+                        self.focus = ElementInFocus::TopicOverview;
+                        true
+                    } else {
+                        false
+                    }
+                }
+                KeyCode::Down | KeyCode::Char('j') => {
+                    let selection = self.zigbee2mqtt.state.selected_mut();
+                    let before = *selection;
+                    *selection = Some(selection.map_or(0, |selection| selection.saturating_add(1)));
+                    before != *selection
+                }
+                KeyCode::Up | KeyCode::Char('k') => {
+                    let selection = self.zigbee2mqtt.state.selected_mut();
+                    let before = *selection;
+                    *selection = Some(selection.map_or(0, |selection| selection.saturating_sub(1)));
+                    before != *selection
+                }
+                KeyCode::Home => {
+                    let selection = self.zigbee2mqtt.state.selected_mut();
+                    let before = *selection;
+                    *selection = Some(0);
+                    before != *selection
+                }
+                KeyCode::End => {
+                    let selection = self.zigbee2mqtt.state.selected_mut();
+                    let before = *selection;
+                    *selection = Some(usize::MAX);
+                    before != *selection
+                }
+                _ => false,
+            },
+            // Handled above as they need mutable access
+            ElementInFocus::BrokerSwitchPopup(_)
+            | ElementInFocus::CommandPalette(_)
+            | ElementInFocus::ConvertPopup(_)
+            | ElementInFocus::MarkerPopup(_)
+            | ElementInFocus::SettingsPopup(_)
+            | ElementInFocus::SubscribePopup(_)
+            | ElementInFocus::TasmotaCommandPopup(_)
+            | ElementInFocus::CleanRetainedPopup(_) => false,
+            ElementInFocus::PayloadSizesPopup => {
+                if key.code == KeyCode::Char('q') {
+                    return Ok(Refresh::Quit);
                 }
                 self.focus = ElementInFocus::TopicOverview;
                 true
@@ -455,42 +1665,54 @@ impl App {
     }
 
     fn on_scroll(&mut self, direction: ScrollDirection, column: u16, row: u16) -> Refresh {
+        if matches!(
+            self.focus,
+            ElementInFocus::Dashboard | ElementInFocus::Zigbee2MqttDashboard
+        ) {
+            return Refresh::Skip;
+        }
         let position = Position { x: column, y: row };
 
-        let changed = if self.topic_overview.last_area.contains(position) {
-            match direction {
-                ScrollDirection::Up => self.topic_overview.state.scroll_up(1),
-                ScrollDirection::Down => self.topic_overview.state.scroll_down(1),
-            }
-        } else if self.details.payload.last_area.contains(position) {
-            match self.get_selected_payload() {
-                Some(Payload::Binary(_)) => {
-                    let state = &mut self.details.payload.binary_state;
-                    match direction {
-                        ScrollDirection::Up => state.scroll_up(1),
-                        ScrollDirection::Down => state.scroll_down(1),
-                    }
+        let changed =
+            if self.topic_overview.flat && self.topic_overview.last_area.contains(position) {
+                match direction {
+                    ScrollDirection::Up => self.topic_overview.select_in_table(-1),
+                    ScrollDirection::Down => self.topic_overview.select_in_table(1),
                 }
-                Some(Payload::Json(_) | Payload::MessagePack(_)) => {
-                    let state = &mut self.details.payload.json_state;
-                    match direction {
-                        ScrollDirection::Up => state.scroll_up(1),
-                        ScrollDirection::Down => state.scroll_down(1),
+            } else if self.topic_overview.last_area.contains(position) {
+                match direction {
+                    ScrollDirection::Up => self.topic_overview.state.scroll_up(1),
+                    ScrollDirection::Down => self.topic_overview.state.scroll_down(1),
+                }
+            } else if self.details.payload.last_area.contains(position) {
+                match self.get_selected_payload().as_deref() {
+                    Some(Payload::Binary(_)) => {
+                        let state = &mut self.details.payload.binary_state;
+                        match direction {
+                            ScrollDirection::Up => state.scroll_up(1),
+                            ScrollDirection::Down => state.scroll_down(1),
+                        }
+                    }
+                    Some(Payload::Json(_) | Payload::MessagePack(_)) => {
+                        let state = &mut self.details.payload.json_state;
+                        match direction {
+                            ScrollDirection::Up => state.scroll_up(1),
+                            ScrollDirection::Down => state.scroll_down(1),
+                        }
                     }
+                    Some(Payload::String(_)) | None => return Refresh::Skip,
                 }
-                Some(Payload::String(_)) | None => return Refresh::Skip,
-            }
-        } else if self.details.last_table_area.contains(position) {
-            let offset = self.details.table_state.offset_mut();
-            let before = *offset;
-            match direction {
-                ScrollDirection::Down => *offset = offset.saturating_add(1),
-                ScrollDirection::Up => *offset = offset.saturating_sub(1),
-            }
-            *offset != before
-        } else {
-            false
-        };
+            } else if self.details.last_table_area.contains(position) {
+                let offset = self.details.table_state.offset_mut();
+                let before = *offset;
+                match direction {
+                    ScrollDirection::Down => *offset = offset.saturating_add(1),
+                    ScrollDirection::Up => *offset = offset.saturating_sub(1),
+                }
+                *offset != before
+            } else {
+                false
+            };
         if changed {
             Refresh::Update
         } else {
@@ -498,10 +1720,47 @@ impl App {
         }
     }
 
-    fn on_click(&mut self, column: u16, row: u16) -> Refresh {
+    fn on_click(&mut self, column: u16, row: u16) -> anyhow::Result<Refresh> {
         let position = Position::new(column, row);
 
-        if let Some(identifier) = self.topic_overview.state.rendered_at(position) {
+        let button = self
+            .footer_buttons
+            .iter()
+            .find(|button| button.area.contains(position));
+        if let Some(button) = button {
+            return self.on_key(button.key);
+        }
+
+        if matches!(
+            self.focus,
+            ElementInFocus::Dashboard | ElementInFocus::Zigbee2MqttDashboard
+        ) {
+            return Ok(Refresh::Skip);
+        }
+
+        self.details.payload.text_selection.clear();
+
+        if self.last_header_area.contains(position) {
+            if let Some(topic) = self.topic_overview.get_selected() {
+                clipboard::copy(&topic)?;
+                self.toast.show(format!("Copied {topic}"));
+                return Ok(Refresh::Update);
+            }
+        }
+
+        if self.topic_overview.flat {
+            let topic = self
+                .topic_overview
+                .table
+                .topic_at(self.topic_overview.last_area, position)
+                .cloned();
+            if let Some(topic) = topic {
+                self.focus = ElementInFocus::TopicOverview;
+                self.topic_overview
+                    .select_topic(topic.split('/').map(ToOwned::to_owned).collect());
+                return Ok(Refresh::Update);
+            }
+        } else if let Some(identifier) = self.topic_overview.state.rendered_at(position) {
             let is_already_selected = identifier == self.topic_overview.state.selected();
             if is_already_selected {
                 // change focus or toggle, don't do both
@@ -515,40 +1774,88 @@ impl App {
                 self.topic_overview.state.select(identifier.to_vec());
             }
 
-            return Refresh::Update;
+            return Ok(Refresh::Update);
         }
-        if self.topic_overview.state.click_at(position) {
+        if !self.topic_overview.flat && self.topic_overview.state.click_at(position) {
             self.focus = ElementInFocus::TopicOverview;
-            return Refresh::Update;
+            return Ok(Refresh::Update);
         }
 
         if self.details.payload.last_area.contains(position) {
-            match self.get_selected_payload() {
-                None => return Refresh::Update, // No payload but click into payload area -> redraw
+            match self.get_selected_payload().as_deref() {
+                None => return Ok(Refresh::Update), // No payload but click into payload area -> redraw
                 Some(Payload::Binary(_)) => {
                     self.details.payload.binary_state.select_at(column, row);
                     self.focus = ElementInFocus::Payload;
-                    return Refresh::Update;
+                    return Ok(Refresh::Update);
                 }
                 Some(Payload::Json(_) | Payload::MessagePack(_)) => {
                     self.details.payload.json_state.click_at(position);
                     self.focus = ElementInFocus::Payload;
-                    return Refresh::Update;
+                    return Ok(Refresh::Update);
+                }
+                Some(Payload::String(text)) => {
+                    let word = self
+                        .details
+                        .payload
+                        .text_selection
+                        .mouse_down(position, text);
+                    if let Some(word) = word {
+                        clipboard::copy(&word)?;
+                        self.toast.show(format!("Copied {word}"));
+                    }
+                    return Ok(Refresh::Update);
                 }
-                Some(Payload::String(_)) => return Refresh::Skip,
             }
         }
 
         if self.details.table_click(position) {
             self.focus = ElementInFocus::HistoryTable;
-            return Refresh::Update;
+            return Ok(Refresh::Update);
         }
 
-        Refresh::Skip
+        Ok(Refresh::Skip)
+    }
+
+    fn on_drag(&mut self, column: u16, row: u16) -> Refresh {
+        let position = Position::new(column, row);
+        if self.details.payload.text_selection.mouse_drag(position) {
+            Refresh::Update
+        } else {
+            Refresh::Skip
+        }
+    }
+
+    fn on_mouse_up(&mut self) -> anyhow::Result<Refresh> {
+        let payload = self.get_selected_payload();
+        let Some(Payload::String(text)) = payload.as_deref() else {
+            return Ok(Refresh::Skip);
+        };
+        let Some(selected) = self.details.payload.text_selection.mouse_up(text) else {
+            return Ok(Refresh::Skip);
+        };
+        clipboard::copy(&selected)?;
+        self.toast
+            .show(format!("Copied {} characters", selected.chars().count()));
+        Ok(Refresh::Update)
     }
 
     // Returns `true` when selection changed
     fn search_select(&mut self, advance: SearchSelection) -> bool {
+        let search = self.topic_overview.search.clone();
+        self.select_matching(&search, advance)
+    }
+
+    /// Jump to the next or previous match of the last search without opening the search bar.
+    fn repeat_last_search(&mut self, advance: SearchSelection) -> bool {
+        let Some(search) = self.topic_overview.last_search().map(ToOwned::to_owned) else {
+            return false;
+        };
+        self.select_matching(&search, advance)
+    }
+
+    // Returns `true` when selection changed
+    fn select_matching(&mut self, search: &str, advance: SearchSelection) -> bool {
         let selection = self.topic_overview.get_selected();
         let history = self.mqtt_thread.get_history();
         let mut topics = history
@@ -567,33 +1874,45 @@ impl App {
             .unwrap_or(0);
 
         // Filter out topics not matching the search
-        topics.retain(|(_, topic)| topic.to_lowercase().contains(&self.topic_overview.search));
+        topics.retain(|(_, topic)| topic.to_lowercase().contains(search));
 
-        let select = match advance {
-            SearchSelection::Before => topics
-                .iter()
-                .rev()
-                .find(|(index, _)| *index < begin_index)
-                .or_else(|| topics.last()),
-            SearchSelection::Stay => topics
-                .iter()
-                .find(|(index, _)| *index >= begin_index)
-                .or_else(|| topics.first()),
-            SearchSelection::After => topics
-                .iter()
-                .find(|(index, _)| *index > begin_index)
-                .or_else(|| topics.first()),
-        };
-        let select = select.map_or(Vec::new(), |(_, topic)| {
-            topic.split('/').map(ToOwned::to_owned).collect()
-        });
+        let select = advance
+            .find(&topics, begin_index)
+            .map_or(Vec::new(), |(_, topic)| {
+                topic.split('/').map(ToOwned::to_owned).collect()
+            });
         drop(history);
 
-        for i in 0..select.len() {
-            self.topic_overview.state.open(select[0..i].to_vec());
-        }
+        self.topic_overview.select_topic(select)
+    }
 
-        self.topic_overview.state.select(select)
+    /// Select the entry of the JSON or `MessagePack` payload matching the payload search.
+    ///
+    /// Returns `true` when selection changed
+    fn payload_search_select(&mut self, advance: SearchSelection) -> bool {
+        let texts = match self.get_selected_payload().as_deref() {
+            Some(Payload::Json(json)) => tree_texts_from_json(json),
+            Some(Payload::MessagePack(messagepack)) => tree_texts_from_messagepack(messagepack),
+            Some(Payload::Binary(_) | Payload::String(_)) | None => return false,
+        };
+        let payload = &mut self.details.payload;
+        let selected = payload.json_state.selected();
+        let begin_index = texts
+            .iter()
+            .position(|(selector, _)| *selector == selected)
+            .unwrap_or(0);
+        let matches = texts
+            .into_iter()
+            .enumerate()
+            .filter(|(_, (_, text))| text.to_lowercase().contains(&payload.search))
+            .collect::<Vec<_>>();
+        let Some((_, (selector, _))) = advance.find(&matches, begin_index) else {
+            return false;
+        };
+        for i in 1..selector.len() {
+            payload.json_state.open(selector[0..i].to_vec());
+        }
+        payload.json_state.select(selector.clone())
     }
 
     fn open_all_search_matches(&mut self) {
@@ -612,11 +1931,65 @@ impl App {
         }
     }
 
+    /// Returns the area of the selected topic
+    fn draw_header(&self, frame: &mut Frame, area: Rect) -> Rect {
+        let stats = self.mqtt_thread.get_stats().to_string();
+        #[allow(clippy::cast_possible_truncation)]
+        let stats_width = stats.len() as u16;
+        let stats_area = Rect {
+            x: area.width.saturating_sub(stats_width),
+            width: stats_width.min(area.width),
+            ..area
+        };
+        frame.render_widget(
+            Span::styled(stats, mqtt_history::STYLE_DARKGRAY),
+            stats_area,
+        );
+
+        let topic_area = Rect {
+            width: area.width.saturating_sub(stats_width.saturating_add(1)),
+            ..area
+        };
+        if let Some(topic) = self.topic_overview.get_selected() {
+            let paragraph = Paragraph::new(Span::styled(topic, ui::STYLE_BOLD));
+            frame.render_widget(paragraph.alignment(Alignment::Center), topic_area);
+        }
+        topic_area
+    }
+
+    #[allow(clippy::too_many_lines)]
     fn draw(&mut self, frame: &mut Frame) {
         const HEADER_HEIGHT: u16 = 1;
         const FOOTER_HEIGHT: u16 = 1;
 
-        let connection_error = self.mqtt_thread.has_connection_err();
+        self.details
+            .switch_topic(self.topic_overview.get_selected());
+
+        let error = self
+            .mqtt_thread
+            .has_takeover_err()
+            .map(|error| ("MQTT Session Takeover", error))
+            .or_else(|| {
+                self.mqtt_thread
+                    .has_connection_err()
+                    .map(|error| ("MQTT Connection Error", error))
+            })
+            .or_else(|| {
+                self.mqtt_thread
+                    .has_subscription_err()
+                    .map(|error| ("MQTT Subscription Error", error))
+            })
+            .or_else(|| {
+                self.mqtt_thread
+                    .has_decode_err()
+                    .map(|error| ("Payload Decoding Error", error))
+            })
+            .or_else(|| {
+                self.http_accept_err
+                    .as_ref()
+                    .and_then(|error| error.read().unwrap().clone())
+                    .map(|error| ("HTTP Error", error))
+            });
 
         let area = frame.size();
         let Rect { width, height, .. } = area;
@@ -633,7 +2006,7 @@ impl App {
             y: height - 1,
             ..area
         };
-        let error_height = if connection_error.is_some() { 4 } else { 0 };
+        let error_height = if error.is_some() { 4 } else { 0 };
         let error_area = Rect {
             height: error_height,
             y: height
@@ -648,57 +2021,151 @@ impl App {
             y: HEADER_HEIGHT,
             ..area
         };
+        let main_area = if self.show_packet_log {
+            let (main_area, packet_log_area) =
+                ui::split_area_vertically(main_area, main_area.height.saturating_mul(2) / 3);
+            self.mqtt_thread
+                .get_packet_log()
+                .draw(frame, packet_log_area);
+            main_area
+        } else {
+            main_area
+        };
 
-        if let Some(topic) = self.topic_overview.get_selected() {
-            let paragraph = Paragraph::new(Span::styled(topic, ui::STYLE_BOLD));
-            frame.render_widget(paragraph.alignment(Alignment::Center), header_area);
-        }
+        self.last_header_area = self.draw_header(frame, header_area);
 
-        self.footer.draw(frame, footer_area, self);
-        if let Some(connection_error) = connection_error {
-            mqtt_error_widget::draw(
-                frame,
-                error_area,
-                "MQTT Connection Error",
-                &connection_error,
-            );
+        self.zigbee2mqtt_base_topic
+            .update(&self.mqtt_thread.get_history());
+        self.footer_buttons = self.footer.draw(frame, footer_area, self);
+        if let Some((title, error)) = error {
+            mqtt_error_widget::draw(frame, error_area, title, &error);
         }
 
         let history = self.mqtt_thread.get_history();
+        if matches!(self.focus, ElementInFocus::Dashboard) {
+            self.dashboard.draw(frame, main_area, &history);
+            return;
+        }
+        if matches!(self.focus, ElementInFocus::Zigbee2MqttDashboard) {
+            self.zigbee2mqtt.draw(frame, main_area, &history);
+            return;
+        }
+        let (topic_amount, message_amount, tree_items) = self.tree_items.get(
+            &history,
+            mqtt_history::TreeItemsOptions {
+                marked: self.topic_overview.marked.clone(),
+                opened: Some(self.topic_overview.state.opened().clone()),
+                search: self.topic_overview.search.clone(),
+                filter: self.topic_overview.filter,
+                hide_system_topics: self.topic_overview.hide_system_topics,
+                branch_summary: self.branch_summary,
+                age_at: self.details.relative_time.then(|| {
+                    // Only rebuild the tree items once per second
+                    chrono::Local::now()
+                        .naive_local()
+                        .with_nanosecond(0)
+                        .expect("zero nanoseconds should always be valid")
+                }),
+                // Only rebuild the tree items on every frame while a topic pulses
+                pulse_at: self
+                    .arrival_pulse
+                    .then(|| chrono::Local::now().naive_local())
+                    .filter(|now| {
+                        history
+                            .latest()
+                            .is_some_and(|latest| (*now - latest).num_seconds() < 1)
+                    }),
+                subscriptions: match self.mqtt_thread.subscribe_topic() {
+                    subscriptions if subscriptions.len() == 1 => Vec::new(),
+                    subscriptions => subscriptions,
+                },
+            },
+        );
 
-        let overview_area = self
-            .topic_overview
-            .get_selected()
+        let selected_topic = self.topic_overview.get_selected();
+        let topic_history = selected_topic
             .as_ref()
-            .and_then(|selected_topic| history.get(selected_topic))
-            .map_or(main_area, |topic_history| {
-                let x = width / 3;
-                let details_area = Rect {
-                    width: width - x,
-                    x,
-                    ..main_area
+            .and_then(|selected_topic| history.get(selected_topic));
+        let aggregate_graph = selected_topic
+            .as_ref()
+            .filter(|_| topic_history.is_none())
+            .and_then(|selected_topic| {
+                self.details
+                    .aggregate_graph(&history.get_children(selected_topic))
+            });
+        let overview_area = if topic_history.is_some() || aggregate_graph.is_some() {
+            let x = self.topic_overview.width(tree_items, width);
+            let details_area = Rect {
+                width: width - x,
+                x,
+                ..main_area
+            };
+            if let Some(topic_history) = topic_history {
+                let details_area = match (&self.compare, &selected_topic, topic_history.last()) {
+                    (Some(pinned), Some(topic), Some(latest)) => {
+                        let (details_area, compare_area) =
+                            ui::split_area_vertically(details_area, details_area.height / 2);
+                        pinned.draw(frame, compare_area, topic, latest);
+                        details_area
+                    }
+                    _ => details_area,
                 };
-
+                let summaries = selected_topic
+                    .as_ref()
+                    .map_or(&[][..], |topic| history.get_summaries(topic));
                 self.details
-                    .draw(frame, details_area, topic_history, &self.focus);
+                    .draw(frame, details_area, topic_history, summaries, &self.focus);
+            } else if let Some(graph) = aggregate_graph {
+                graph.draw(frame, details_area, &self.details.markers);
+            }
+            Rect {
+                width: x,
+                x: 0,
+                ..main_area
+            }
+        } else {
+            main_area
+        };
 
-                Rect {
-                    width: x,
-                    x: 0,
-                    ..main_area
-                }
-            });
+        let overview_focus = matches!(self.focus, ElementInFocus::TopicOverview);
+        if self.topic_overview.flat {
+            self.topic_overview
+                .draw_table(frame, overview_area, &history, overview_focus);
+            drop(history);
+        } else {
+            drop(history);
+            self.topic_overview.draw(
+                frame,
+                overview_area,
+                topic_amount,
+                message_amount,
+                tree_items,
+                &self.mqtt_thread.subscribe_topic(),
+                overview_focus,
+            );
+        }
 
-        self.topic_overview.draw(
-            frame,
-            overview_area,
-            &history,
-            matches!(self.focus, ElementInFocus::TopicOverview),
-        );
-        drop(history);
+        match &mut self.focus {
+            ElementInFocus::CleanRetainedPopup(popup) => {
+                popup.draw(frame, &self.mqtt_thread.get_history());
+            }
+            ElementInFocus::TasmotaCommandPopup(popup) => {
+                popup.draw(frame, &self.mqtt_thread.get_history());
+            }
+            ElementInFocus::BrokerSwitchPopup(popup) => popup.draw(frame),
+            ElementInFocus::ConvertPopup(popup) => popup.draw(frame),
+            ElementInFocus::MarkerPopup(popup) => popup.draw(frame),
+            ElementInFocus::SettingsPopup(popup) => popup.draw(frame),
+            ElementInFocus::PayloadSizesPopup => {
+                self.mqtt_thread.get_history().payload_sizes.draw(frame);
+            }
+            ElementInFocus::SubscribePopup(popup) => popup.draw(frame),
+            ElementInFocus::CommandPalette(palette) => palette.draw(frame),
+            _ => {}
+        }
 
-        if let ElementInFocus::CleanRetainedPopup(topic) = &self.focus {
-            clean_retained::draw_popup(frame, topic);
+        if self.accessible {
+            ui::high_contrast(frame.buffer_mut());
         }
     }
 }
@@ -1,26 +1,61 @@
-use std::collections::HashMap;
+use std::collections::{BTreeSet, HashMap, HashSet};
 
 use ego_tree::{NodeId, NodeRef, Tree};
-use ratatui::style::{Color, Style};
+use ratatui::style::{Color, Modifier, Style};
 use ratatui::text::{Line, Span};
 use tui_tree_widget::TreeItem;
 
-use crate::interactive::ui::STYLE_BOLD;
-use crate::mqtt::HistoryEntry;
+use crate::cli::BranchSummary;
+use crate::interactive::details::graph;
+use crate::interactive::history_summary::{MinuteSummary, SummarizedSeries};
+use crate::interactive::payload_sizes::PayloadSizes;
+use crate::interactive::ui::{ellipsize, STYLE_BOLD};
+use crate::mqtt::{topic_filter, HistoryEntry, Time};
 
 pub const STYLE_DARKGRAY: Style = Style::new().fg(Color::DarkGray);
+pub const STYLE_RETAINED: Style = Style::new().fg(Color::Yellow);
+pub const STYLE_MARKED: Style = Style::new()
+    .fg(Color::LightMagenta)
+    .add_modifier(Modifier::BOLD);
+const STYLE_SEARCH_MATCH: Style = Style::new()
+    .fg(Color::Black)
+    .bg(Color::LightGreen)
+    .add_modifier(Modifier::BOLD);
+
+/// Colors of the subscriptions in the order of their topic filters. Repeats with more filters.
+const SUBSCRIPTION_COLORS: [Color; 6] = [
+    Color::Cyan,
+    Color::LightMagenta,
+    Color::LightBlue,
+    Color::LightGreen,
+    Color::LightRed,
+    Color::LightYellow,
+];
+
+/// Only the first subscriptions get a marker in the topic tree
+const MAX_SUBSCRIPTION_MARKERS: usize = 64;
+
+pub const fn subscription_color(index: usize) -> Color {
+    SUBSCRIPTION_COLORS[index % SUBSCRIPTION_COLORS.len()]
+}
+
+/// Longer topic segments are shortened in the tree. The full topic is shown in the header.
+pub const MAX_LEAF_WIDTH: usize = 30;
 
 struct Topic {
     /// Topic `foo/bar` would have the leaf `bar`
     leaf: Box<str>,
     history: Vec<HistoryEntry>,
+    /// Numeric values of the messages removed by the history limit
+    summaries: SummarizedSeries,
 }
 
 impl Topic {
-    const fn new(leaf: Box<str>) -> Self {
+    fn new(leaf: Box<str>) -> Self {
         Self {
             leaf,
             history: Vec::new(),
+            summaries: SummarizedSeries::default(),
         }
     }
 }
@@ -29,19 +64,104 @@ struct RecursiveTreeItemGenerator {
     messages_below: usize,
     messages: usize,
     topics_below: usize,
-    tree_item: TreeItem<'static, String>,
+    /// Payload bytes of the topic and below. Only counted for [`BranchSummary::Bytes`].
+    bytes: usize,
+    /// Time of the latest not retained message of the topic or below
+    latest: Option<chrono::NaiveDateTime>,
+    /// The topic itself or one below matches the search
+    search_match: bool,
+    /// Bit per subscription matching the topic or one below
+    subscriptions: u64,
+    /// Only built when the node is visible
+    tree_item: Option<TreeItem<'static, String>>,
+}
+
+/// Everything besides the history the tree items depend on
+#[derive(Default, Clone, PartialEq, Eq)]
+pub struct TreeItemsOptions {
+    /// Highlighted topics
+    pub marked: BTreeSet<String>,
+    /// Only the opened topics are built with their children. `None` builds everything.
+    pub opened: Option<HashSet<Vec<String>>>,
+    /// Lowercase search which is highlighted. Topics not matching are dimmed.
+    pub search: String,
+    /// Hide the topics not matching the search instead of dimming them
+    pub filter: bool,
+    /// Hide the broker internal topic trees like `$SYS`
+    pub hide_system_topics: bool,
+    /// Shown next to topics without messages
+    pub branch_summary: BranchSummary,
+    /// Show the age of the last message at the given time
+    pub age_at: Option<chrono::NaiveDateTime>,
+    /// Highlight the topics which received a message shortly before the given time
+    pub pulse_at: Option<chrono::NaiveDateTime>,
+    /// Topic filters of the subscriptions. The topic roots are marked in the color of the matching ones.
+    pub subscriptions: Vec<String>,
+}
+
+/// Bound the memory of long running sessions
+#[derive(Debug, Clone, Copy)]
+pub struct HistoryLimit {
+    /// Messages kept per topic
+    pub messages: usize,
+    /// Keep per-minute summaries of the numeric messages which are removed
+    pub compaction: bool,
 }
 
 pub struct MqttHistory {
     tree: Tree<Topic>,
+    limit: Option<HistoryLimit>,
+    /// Time of the latest not retained message
+    latest: Option<chrono::NaiveDateTime>,
     ids: HashMap<String, NodeId>,
+    /// Increased on every change to know when derived data like the tree items is outdated
+    generation: u64,
+    pub payload_sizes: PayloadSizes,
+}
+
+/// The tree items are only rebuilt when the history or the options changed
+#[derive(Default)]
+pub struct TreeItemsCache {
+    generation: Option<u64>,
+    options: TreeItemsOptions,
+    topic_amount: usize,
+    message_amount: usize,
+    items: Vec<TreeItem<'static, String>>,
+}
+
+impl TreeItemsCache {
+    /// Returns (`topic_amount`, `message_amount`, `TreeItem`s) like [`MqttHistory::to_tree_items`]
+    pub fn get(
+        &mut self,
+        history: &MqttHistory,
+        options: TreeItemsOptions,
+    ) -> (usize, usize, &[TreeItem<'static, String>]) {
+        if self.generation != Some(history.generation) || self.options != options {
+            let (topic_amount, message_amount, items) = history.to_tree_items(&options);
+            self.generation = Some(history.generation);
+            self.options = options;
+            self.topic_amount = topic_amount;
+            self.message_amount = message_amount;
+            self.items = items;
+        }
+        (self.topic_amount, self.message_amount, &self.items)
+    }
 }
 
 impl MqttHistory {
+    #[cfg(test)]
     pub fn new() -> Self {
+        Self::with_limit(None)
+    }
+
+    pub fn with_limit(limit: Option<HistoryLimit>) -> Self {
         Self {
             tree: Tree::new(Topic::new("".into())),
+            limit,
+            latest: None,
             ids: HashMap::new(),
+            generation: 0,
+            payload_sizes: PayloadSizes::default(),
         }
     }
 
@@ -72,13 +192,109 @@ impl MqttHistory {
     }
 
     pub fn add(&mut self, topic: String, history_entry: HistoryEntry) {
+        self.generation = self.generation.wrapping_add(1);
+        self.latest = self.latest.max(history_entry.time.as_optional().copied());
+        self.payload_sizes
+            .add(&topic, history_entry.payload_size, history_entry.time);
         let id = self.entry(topic);
-        self.tree
-            .get_mut(id)
+        let mut node = self.tree.get_mut(id).unwrap();
+        let topic = node.value();
+        topic.history.push(history_entry);
+
+        let Some(limit) = self.limit else {
+            return;
+        };
+        let excess = topic.history.len().saturating_sub(limit.messages);
+        for removed in topic.history.drain(..excess) {
+            if limit.compaction {
+                if let Some((time, value)) = graph::value(&removed) {
+                    topic.summaries.add(time, value);
+                }
+            }
+        }
+    }
+
+    /// The entry is retained and byte-identical to the latest retained entry of the topic.
+    /// Truncated payloads are never considered identical as their end is unknown.
+    pub fn is_retained_duplicate(&self, topic: &str, entry: &HistoryEntry) -> bool {
+        if entry.time.as_optional().is_some() || entry.is_truncated() {
+            return false;
+        }
+        self.get(topic)
+            .and_then(|entries| {
+                entries
+                    .iter()
+                    .rev()
+                    .find(|existing| existing.time.as_optional().is_none())
+            })
+            .is_some_and(|latest| {
+                latest.payload_size == entry.payload_size
+                    && !latest.is_truncated()
+                    && latest.payload == entry.payload
+            })
+    }
+
+    /// Remove all topics from the local history
+    pub fn clear(&mut self) {
+        self.generation = self.generation.wrapping_add(1);
+        self.tree = Tree::new(Topic::new("".into()));
+        self.ids.clear();
+        self.payload_sizes.clear();
+    }
+
+    /// Remove the topic and all topics below from the local history.
+    ///
+    /// Nothing is sent to the broker, new messages will show up again.
+    pub fn remove_below(&mut self, base: &str) {
+        let Some(id) = self.find_node(base) else {
+            return;
+        };
+        self.generation = self.generation.wrapping_add(1);
+
+        let below = self
+            .tree
+            .get(id)
             .unwrap()
-            .value()
-            .history
-            .push(history_entry);
+            .descendants()
+            .map(|node| node.id())
+            .collect::<Vec<_>>();
+        for id in below {
+            // Detached nodes stay allocated in the tree so at least free their history
+            let mut node = self.tree.get_mut(id).unwrap();
+            let topic = node.value();
+            topic.history = Vec::new();
+            topic.summaries = SummarizedSeries::default();
+        }
+        self.ids.retain(|topic, _| !is_topic_below(base, topic));
+        self.payload_sizes.remove_below(base);
+
+        // Also remove parents which are empty now
+        let root = self.tree.root().id();
+        let mut id = id;
+        loop {
+            let mut node = self.tree.get_mut(id).unwrap();
+            let parent = node.parent().map(|parent| parent.id());
+            node.detach();
+            let Some(parent) = parent.filter(|parent| *parent != root) else {
+                break;
+            };
+            let parent_node = self.tree.get(parent).unwrap();
+            if parent_node.has_children() || !parent_node.value().history.is_empty() {
+                break;
+            }
+            id = parent;
+        }
+    }
+
+    /// Find the node of a topic even when it has no messages itself
+    fn find_node(&self, topic: &str) -> Option<NodeId> {
+        let mut node = self.tree.root();
+        for part in topic.split('/') {
+            node = node
+                .children()
+                .find(|node| node.value().leaf.as_ref() == part)?;
+        }
+        Some(node.id())
     }
 
     pub fn get(&self, topic: &str) -> Option<&Vec<HistoryEntry>> {
@@ -86,6 +302,62 @@ impl MqttHistory {
         self.tree.get(*id).map(|node| &node.value().history)
     }
 
+    /// Per-minute summaries of the messages removed by the history limit, oldest first
+    pub fn get_summaries(&self, topic: &str) -> &[MinuteSummary] {
+        self.ids
+            .get(topic)
+            .and_then(|id| self.tree.get(*id))
+            .map_or(&[], |node| node.value().summaries.minutes())
+    }
+
+    /// Histories of the direct children of the topic which have messages
+    pub fn get_children(&self, topic: &str) -> Vec<&[HistoryEntry]> {
+        let Some(id) = self.find_node(topic) else {
+            return Vec::new();
+        };
+        self.tree
+            .get(id)
+            .unwrap()
+            .children()
+            .map(|node| node.value().history.as_slice())
+            .filter(|history| !history.is_empty())
+            .collect()
+    }
+
+    /// Time of the latest not retained message of any topic
+    pub const fn latest(&self) -> Option<chrono::NaiveDateTime> {
+        self.latest
+    }
+
+    /// Topic trees starting with `$` like `$SYS` exist
+    pub fn has_system_topics(&self) -> bool {
+        self.tree.root().children().any(is_system_topic)
+    }
+
+    /// Amount of topics with messages
+    pub fn topic_amount(&self) -> usize {
+        self.ids.len()
+    }
+
+    pub const fn limit(&self) -> Option<HistoryLimit> {
+        self.limit
+    }
+
+    /// Applies to each topic when it receives its next message
+    pub fn set_limit(&mut self, limit: Option<HistoryLimit>) {
+        self.limit = limit;
+    }
+
+    /// Increased on every change of the history
+    pub const fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    /// Topics with messages in no particular order
+    pub fn topics(&self) -> impl Iterator<Item = &String> {
+        self.ids.keys()
+    }
+
     pub fn get_all_topics(&self) -> Vec<&String> {
         let mut topics = self.ids.keys().collect::<Vec<_>>();
         topics.sort();
@@ -101,49 +373,25 @@ impl MqttHistory {
     }
 
     /// Returns (`topic_amount`, `message_amount`, `TreeItem`s)
-    pub fn to_tree_items(&self) -> (usize, usize, Vec<TreeItem<'static, String>>) {
-        fn build_recursive(prefix: &[&str], node: NodeRef<Topic>) -> RecursiveTreeItemGenerator {
-            let Topic { leaf, history } = node.value();
-            let mut topic = prefix.to_vec();
-            topic.push(leaf);
-
-            let entries_below = node.children().map(|node| build_recursive(&topic, node));
-            let mut messages_below: usize = 0;
-            let mut topics_below: usize = 0;
-            let mut children = Vec::new();
-            for below in entries_below {
-                messages_below = messages_below
-                    .saturating_add(below.messages)
-                    .saturating_add(below.messages_below);
-                topics_below = topics_below
-                    .saturating_add(usize::from(below.messages > 0))
-                    .saturating_add(below.topics_below);
-                children.push(below.tree_item);
-            }
-
-            let meta = history.last().map(|entry| &entry.payload).map_or_else(
-                || format!("({topics_below} topics, {messages_below} messages)"),
-                |payload| format!("= {payload}"),
-            );
-            let text = Line::from(vec![
-                Span::styled(leaf.to_string(), STYLE_BOLD),
-                Span::raw(" "),
-                Span::styled(meta, STYLE_DARKGRAY),
-            ]);
-
-            RecursiveTreeItemGenerator {
-                messages_below,
-                messages: history.len(),
-                topics_below,
-                tree_item: TreeItem::new(leaf.to_string(), text, children).unwrap(),
-            }
-        }
-
-        let children = self
-            .tree
-            .root()
+    ///
+    /// Marked topics and search matches are highlighted.
+    /// When filtering only the search matches and their parents are included, also in the amounts.
+    /// When the opened topics are given only the visible items are built with their children.
+    /// Closed items get a placeholder child in order to be shown as openable.
+    /// The amounts are always calculated for the whole tree.
+    /// Broker internal topic trees like `$SYS` are sorted to the end or hidden including their amounts.
+    pub fn to_tree_items(
+        &self,
+        options: &TreeItemsOptions,
+    ) -> (usize, usize, Vec<TreeItem<'static, String>>) {
+        let root = self.tree.root();
+        let regular = root.children().filter(|node| !is_system_topic(*node));
+        let system = root
             .children()
-            .map(|node| build_recursive(&[], node));
+            .filter(|node| !options.hide_system_topics && is_system_topic(*node));
+        let children = regular
+            .chain(system)
+            .map(|node| build_recursive(&[], node, options, true));
         let mut topics: usize = 0;
         let mut messages: usize = 0;
         let mut items = Vec::new();
@@ -154,33 +402,282 @@ impl MqttHistory {
             messages = messages
                 .saturating_add(child.messages)
                 .saturating_add(child.messages_below);
-            items.push(child.tree_item);
+            items.extend(child.tree_item);
         }
         (topics, messages, items)
     }
 
     #[cfg(test)]
     pub fn example() -> Self {
-        fn entry(payload: &str) -> HistoryEntry {
-            HistoryEntry {
-                qos: rumqttc::QoS::AtLeastOnce,
-                time: crate::mqtt::Time::new_now(false),
-                payload_size: payload.len(),
-                payload: crate::payload::Payload::unlimited(payload.into()),
+        let mut history = Self::new();
+        history.add(
+            "test".to_owned(),
+            HistoryEntry::example("A", Time::new_now(false)),
+        );
+        history.add(
+            "foo/test".to_owned(),
+            HistoryEntry::example("B", Time::new_now(false)),
+        );
+        history.add(
+            "test".to_owned(),
+            HistoryEntry::example("C", Time::new_now(false)),
+        );
+        history.add(
+            "foo/bar".to_owned(),
+            HistoryEntry::example("D", Time::new_now(false)),
+        );
+        history.add(
+            "testing/stuff".to_owned(),
+            HistoryEntry::example("E", Time::new_now(false)),
+        );
+        history
+    }
+}
+
+/// Count the messages and topics below and build the tree item when visible
+#[allow(clippy::too_many_lines)]
+fn build_recursive(
+    prefix: &[&str],
+    node: NodeRef<Topic>,
+    options: &TreeItemsOptions,
+    visible: bool,
+) -> RecursiveTreeItemGenerator {
+    let Topic { leaf, history, .. } = node.value();
+    let mut topic = prefix.to_vec();
+    topic.push(leaf);
+
+    let is_open = visible
+        && node.has_children()
+        && options.opened.as_ref().map_or(true, |opened| {
+            opened.contains(
+                &topic
+                    .iter()
+                    .map(|part| (*part).to_owned())
+                    .collect::<Vec<_>>(),
+            )
+        });
+    let entries_below = node
+        .children()
+        .map(|node| build_recursive(&topic, node, options, is_open));
+    let mut messages_below: usize = 0;
+    let mut topics_below: usize = 0;
+    let mut bytes: usize = if options.branch_summary == BranchSummary::Bytes {
+        history
+            .iter()
+            .fold(0, |sum, entry| sum.saturating_add(entry.payload_size))
+    } else {
+        0
+    };
+    let mut latest = history
+        .last()
+        .and_then(|entry| entry.time.as_optional())
+        .copied();
+    let mut search_match = !options.search.is_empty()
+        && !history.is_empty()
+        && topic.join("/").to_lowercase().contains(&options.search);
+    let mut subscriptions = if history.is_empty() || options.subscriptions.is_empty() {
+        0
+    } else {
+        matching_subscriptions(&options.subscriptions, &topic.join("/"))
+    };
+    let mut children = Vec::new();
+    for below in entries_below {
+        search_match |= below.search_match;
+        subscriptions |= below.subscriptions;
+        messages_below = messages_below
+            .saturating_add(below.messages)
+            .saturating_add(below.messages_below);
+        topics_below = topics_below
+            .saturating_add(usize::from(below.messages > 0))
+            .saturating_add(below.topics_below);
+        bytes = bytes.saturating_add(below.bytes);
+        latest = latest.max(below.latest);
+        children.extend(below.tree_item);
+    }
+
+    if options.filter && !options.search.is_empty() && !search_match {
+        return RecursiveTreeItemGenerator {
+            messages_below: 0,
+            messages: 0,
+            topics_below: 0,
+            bytes: 0,
+            latest: None,
+            search_match,
+            subscriptions: 0,
+            tree_item: None,
+        };
+    }
+    if !visible {
+        return RecursiveTreeItemGenerator {
+            messages_below,
+            messages: history.len(),
+            topics_below,
+            bytes,
+            latest,
+            search_match,
+            subscriptions,
+            tree_item: None,
+        };
+    }
+    if !is_open && node.has_children() {
+        children.push(TreeItem::new_leaf(String::new(), ""));
+    }
+
+    let mut text = Vec::new();
+    if prefix.is_empty() && subscriptions != 0 {
+        text.extend(subscription_markers(subscriptions));
+    }
+    if !options.marked.is_empty() && options.marked.contains(&topic.join("/")) {
+        text.push(Span::styled("* ", STYLE_MARKED));
+    }
+    let leaf_style = if options.search.is_empty() || search_match {
+        STYLE_BOLD
+    } else {
+        STYLE_DARKGRAY
+    };
+    text.extend(highlight_search(
+        &ellipsize(leaf, MAX_LEAF_WIDTH),
+        &options.search,
+        leaf_style,
+    ));
+    text.push(Span::raw(" "));
+    if let Some(entry) = history.last() {
+        text.push(Span::styled(format!("= {}", entry.payload), STYLE_DARKGRAY));
+        let style = if matches!(entry.time, Time::Retained) {
+            STYLE_RETAINED
+        } else {
+            STYLE_DARKGRAY
+        };
+        text.push(Span::styled(flags(entry), style));
+        if let Some((now, time)) = options.age_at.zip(entry.time.as_optional()) {
+            text.push(Span::styled(
+                format!(" {}", crate::format::age((now - *time).num_seconds())),
+                STYLE_DARKGRAY,
+            ));
+        }
+    } else {
+        let summary = match options.branch_summary {
+            BranchSummary::Counts => {
+                Some(format!("{topics_below} topics, {messages_below} messages"))
             }
+            BranchSummary::LatestUpdate => Some(latest.map_or_else(
+                || "only retained".to_owned(),
+                |latest| {
+                    options.age_at.map_or_else(
+                        || format!("updated {}", latest.format("%_H:%M:%S")),
+                        |now| {
+                            format!(
+                                "updated {}",
+                                crate::format::age((now - latest).num_seconds())
+                            )
+                        },
+                    )
+                },
+            )),
+            BranchSummary::Bytes => Some(crate::format::bytes(bytes as u64)),
+            BranchSummary::Hidden => None,
+        };
+        if let Some(summary) = summary {
+            text.push(Span::styled(format!("({summary})"), STYLE_DARKGRAY));
         }
-
-        let mut history = Self::new();
-        history.add("test".to_owned(), entry("A"));
-        history.add("foo/test".to_owned(), entry("B"));
-        history.add("test".to_owned(), entry("C"));
-        history.add("foo/bar".to_owned(), entry("D"));
-        history.add("testing/stuff".to_owned(), entry("E"));
+    }
+    // Collapsed topics also pulse for the topics below them
+    let pulse_time = if is_open {
         history
+            .last()
+            .and_then(|entry| entry.time.as_optional())
+            .copied()
+    } else {
+        latest
+    };
+    let pulse = options
+        .pulse_at
+        .zip(pulse_time)
+        .and_then(|(now, time)| pulse_style(now - time));
+    let text = Line::from(text).style(pulse.unwrap_or_default());
+
+    RecursiveTreeItemGenerator {
+        messages_below,
+        messages: history.len(),
+        topics_below,
+        bytes,
+        latest,
+        search_match,
+        subscriptions,
+        tree_item: Some(TreeItem::new(leaf.to_string(), text, children).unwrap()),
     }
 }
 
-fn is_topic_below(base: &str, child: &str) -> bool {
+/// Fades out within a second after a message arrived
+const fn pulse_style(age: chrono::TimeDelta) -> Option<Style> {
+    match age.num_milliseconds() {
+        0..=499 => Some(Style::new().add_modifier(Modifier::REVERSED)),
+        500..=999 => Some(Style::new().add_modifier(Modifier::UNDERLINED)),
+        _ => None,
+    }
+}
+
+/// Colored dot for every matching subscription
+fn subscription_markers(subscriptions: u64) -> Vec<Span<'static>> {
+    let mut markers = (0..MAX_SUBSCRIPTION_MARKERS)
+        .filter(|index| subscriptions & (1 << index) != 0)
+        .map(|index| Span::styled("●", Style::new().fg(subscription_color(index))))
+        .collect::<Vec<_>>();
+    markers.push(Span::raw(" "));
+    markers
+}
+
+/// Bit per topic filter matching the topic
+fn matching_subscriptions(filters: &[String], topic: &str) -> u64 {
+    filters
+        .iter()
+        .take(MAX_SUBSCRIPTION_MARKERS)
+        .enumerate()
+        .filter(|(_, filter)| topic_filter::matches(filter, topic))
+        .fold(0, |bits, (index, _)| bits | (1 << index))
+}
+
+/// Highlight the first occurrence of the lowercase search in the text
+fn highlight_search(text: &str, search: &str, style: Style) -> Vec<Span<'static>> {
+    let lowercase = text.to_lowercase();
+    // Lowercase might change the byte length of some characters. Don't highlight them.
+    let found = (!search.is_empty() && lowercase.len() == text.len())
+        .then(|| lowercase.find(search))
+        .flatten();
+    let Some(start) = found else {
+        return vec![Span::styled(text.to_owned(), style)];
+    };
+    let end = start + search.len();
+    let mut spans = Vec::new();
+    if start > 0 {
+        spans.push(Span::styled(text[..start].to_owned(), style));
+    }
+    spans.push(Span::styled(
+        text[start..end].to_owned(),
+        STYLE_SEARCH_MATCH,
+    ));
+    if end < text.len() {
+        spans.push(Span::styled(text[end..].to_owned(), style));
+    }
+    spans
+}
+
+/// Retain flag and `QoS` of the message like ` [R,QoS1]`
+fn flags(entry: &HistoryEntry) -> String {
+    let retained = if matches!(entry.time, Time::Retained) {
+        "R,"
+    } else {
+        ""
+    };
+    format!(" [{retained}QoS{}]", entry.qos as u8)
+}
+
+/// Topics starting with `$` are broker internals and not matched by `#`, see MQTT spec 4.7.2
+fn is_system_topic(node: NodeRef<Topic>) -> bool {
+    node.value().leaf.starts_with('$')
+}
+
+pub fn is_topic_below(base: &str, child: &str) -> bool {
     if base == child {
         return true;
     }
@@ -205,10 +702,41 @@ fn topics_below_finds_itself_works() {
     assert_eq!(actual, ["test"]);
 }
 
+#[test]
+fn get_children_works() {
+    let history = MqttHistory::example();
+    let children = history.get_children("foo");
+    assert_eq!(children.len(), 2);
+    assert_eq!(children[0][0].payload.to_string(), "D");
+    assert_eq!(history.get_children("testing").len(), 1);
+    assert!(history.get_children("foo/bar").is_empty());
+    assert!(history.get_children("unknown").is_empty());
+}
+
+#[test]
+fn remove_below_works() {
+    let mut history = MqttHistory::example();
+    history.remove_below("foo");
+    assert_eq!(history.get_all_topics(), ["test", "testing/stuff"]);
+    let (topics, messages, items) = history.to_tree_items(&TreeItemsOptions::default());
+    assert_eq!(topics, 2);
+    assert_eq!(messages, 3);
+    assert_eq!(items.len(), 2);
+}
+
+#[test]
+fn remove_below_removes_empty_parents() {
+    let mut history = MqttHistory::example();
+    history.remove_below("testing/stuff");
+    assert_eq!(history.get_all_topics(), ["foo/bar", "foo/test", "test"]);
+    let (_, _, items) = history.to_tree_items(&TreeItemsOptions::default());
+    assert_eq!(items.len(), 2);
+}
+
 #[test]
 fn tree_items_works() {
     let example = MqttHistory::example();
-    let (topics, messages, items) = example.to_tree_items();
+    let (topics, messages, items) = example.to_tree_items(&TreeItemsOptions::default());
     assert_eq!(topics, 4);
     assert_eq!(messages, 5);
     dbg!(&items);
@@ -217,3 +745,261 @@ fn tree_items_works() {
     assert_eq!(items[1].children().len(), 0);
     assert_eq!(items[2].children().len(), 1);
 }
+
+#[test]
+fn tree_items_system_topics_last() {
+    let mut history = MqttHistory::example();
+    history.add(
+        "$SYS/broker/uptime".to_owned(),
+        HistoryEntry::example("1", Time::Retained),
+    );
+    history.add("aaa".to_owned(), HistoryEntry::example("1", Time::Retained));
+    assert!(history.has_system_topics());
+    let (topics, _, items) = history.to_tree_items(&TreeItemsOptions::default());
+    assert_eq!(topics, 6);
+    let roots = items.iter().map(TreeItem::identifier).collect::<Vec<_>>();
+    assert_eq!(roots, ["aaa", "foo", "test", "testing", "$SYS"]);
+
+    let options = TreeItemsOptions {
+        hide_system_topics: true,
+        ..TreeItemsOptions::default()
+    };
+    let (topics, _, items) = history.to_tree_items(&options);
+    assert_eq!(topics, 5);
+    assert_eq!(items.len(), 4);
+}
+
+#[test]
+fn pulse_fades_out() {
+    assert!(pulse_style(chrono::TimeDelta::milliseconds(100))
+        .is_some_and(|style| style.add_modifier.contains(Modifier::REVERSED)));
+    assert!(pulse_style(chrono::TimeDelta::milliseconds(700))
+        .is_some_and(|style| style.add_modifier.contains(Modifier::UNDERLINED)));
+    assert!(pulse_style(chrono::TimeDelta::seconds(1)).is_none());
+    assert!(pulse_style(chrono::TimeDelta::milliseconds(-5)).is_none());
+}
+
+#[test]
+fn limit_compacts_removed_messages() {
+    let start = Time::datetime_example();
+    let entry = |seconds, payload: &str| HistoryEntry {
+        qos: rumqttc::QoS::AtMostOnce,
+        time: Time::Local(start + chrono::TimeDelta::seconds(seconds)),
+        payload_size: payload.len(),
+        payload: crate::payload::Payload::unlimited(payload.into()).into(),
+    };
+
+    let mut history = MqttHistory::with_limit(Some(HistoryLimit {
+        messages: 2,
+        compaction: true,
+    }));
+    history.add("foo".to_owned(), entry(0, "1"));
+    history.add("foo".to_owned(), entry(1, "3"));
+    history.add("foo".to_owned(), entry(2, "text"));
+    history.add("foo".to_owned(), entry(3, "5"));
+    history.add("foo".to_owned(), entry(4, "6"));
+    let payloads = history
+        .get("foo")
+        .unwrap()
+        .iter()
+        .map(|entry| entry.payload.to_string())
+        .collect::<Vec<_>>();
+    assert_eq!(payloads, ["5", "6"]);
+    let [summary] = history.get_summaries("foo") else {
+        panic!("expected one minute");
+    };
+    assert_eq!((summary.min, summary.max, summary.count), (1.0, 3.0, 2));
+    assert!(history.get_summaries("bar").is_empty());
+}
+
+#[test]
+fn tree_items_cache_rebuilds_on_change() {
+    let mut history = MqttHistory::example();
+    let mut cache = TreeItemsCache::default();
+    let mut options = TreeItemsOptions {
+        opened: Some(HashSet::new()),
+        ..TreeItemsOptions::default()
+    };
+    assert_eq!(cache.get(&history, options.clone()).0, 4);
+
+    history.remove_below("foo");
+    assert_eq!(cache.get(&history, options.clone()).0, 2);
+
+    let before = cache.get(&history, options.clone()).2.as_ptr();
+    assert_eq!(cache.get(&history, options.clone()).2.as_ptr(), before);
+
+    options.marked.insert("test".to_owned());
+    let (_, _, items) = cache.get(&history, options);
+    assert_eq!(items.len(), 2);
+}
+
+#[test]
+fn tree_items_only_builds_opened() {
+    let example = MqttHistory::example();
+    let mut options = TreeItemsOptions {
+        opened: Some(HashSet::new()),
+        ..TreeItemsOptions::default()
+    };
+    let (topics, messages, items) = example.to_tree_items(&options);
+    assert_eq!(topics, 4);
+    assert_eq!(messages, 5);
+    assert_eq!(items.len(), 3);
+    // Placeholder to be openable
+    assert_eq!(items[0].children().len(), 1);
+    assert_eq!(items[1].children().len(), 0);
+    assert_eq!(items[2].children().len(), 1);
+
+    options
+        .opened
+        .as_mut()
+        .unwrap()
+        .insert(vec!["foo".to_owned()]);
+    let (_, _, items) = example.to_tree_items(&options);
+    assert_eq!(items[0].children().len(), 2);
+    assert_eq!(items[2].children().len(), 1);
+}
+
+#[test]
+fn highlight_search_works() {
+    let spans = highlight_search("Temperature", "per", STYLE_BOLD);
+    let texts = spans.iter().map(|span| &span.content).collect::<Vec<_>>();
+    assert_eq!(texts, ["Tem", "per", "ature"]);
+    assert_eq!(spans[1].style, STYLE_SEARCH_MATCH);
+
+    let spans = highlight_search("Temperature", "temp", STYLE_BOLD);
+    assert_eq!(spans.len(), 2);
+    assert_eq!(spans[0].style, STYLE_SEARCH_MATCH);
+
+    let spans = highlight_search("Temperature", "humid", STYLE_BOLD);
+    assert_eq!(spans.len(), 1);
+    assert_eq!(spans[0].style, STYLE_BOLD);
+}
+
+#[test]
+fn tree_items_search_match_below() {
+    let example = MqttHistory::example();
+    let options = TreeItemsOptions {
+        search: "bar".to_owned(),
+        ..TreeItemsOptions::default()
+    };
+    let (_, _, items) = example.to_tree_items(&options);
+    // foo is not dimmed as foo/bar matches
+    let foo = format!("{:?}", items[0]);
+    assert!(foo.contains("BOLD"));
+    let test = format!("{:?}", items[1]);
+    assert!(test.contains("DarkGray"));
+}
+
+#[test]
+fn tree_items_filter_works() {
+    let example = MqttHistory::example();
+    let options = TreeItemsOptions {
+        search: "bar".to_owned(),
+        filter: true,
+        ..TreeItemsOptions::default()
+    };
+    let (topics, messages, items) = example.to_tree_items(&options);
+    assert_eq!(topics, 1);
+    assert_eq!(messages, 1);
+    assert_eq!(items.len(), 1);
+    assert_eq!(items[0].identifier(), "foo");
+    assert_eq!(items[0].children().len(), 1);
+    assert_eq!(items[0].children()[0].identifier(), "bar");
+}
+
+#[test]
+fn tree_items_show_age() {
+    let example = MqttHistory::example();
+    let (_, _, items) = example.to_tree_items(&TreeItemsOptions::default());
+    assert!(!format!("{:?}", items[1]).contains(" ago"));
+
+    let options = TreeItemsOptions {
+        age_at: Some(chrono::Local::now().naive_local()),
+        ..TreeItemsOptions::default()
+    };
+    let (_, _, items) = example.to_tree_items(&options);
+    assert!(format!("{:?}", items[1]).contains("0s ago"));
+}
+
+#[test]
+fn tree_items_branch_summary() {
+    let example = MqttHistory::example();
+    let summary_of_foo = |branch_summary| {
+        let options = TreeItemsOptions {
+            branch_summary,
+            ..TreeItemsOptions::default()
+        };
+        let (_, _, items) = example.to_tree_items(&options);
+        let foo = format!("{:?}", items[0]);
+        assert!(foo.contains("\"foo\""), "{foo}");
+        foo
+    };
+    assert!(summary_of_foo(BranchSummary::Counts).contains("(2 topics, 2 messages)"));
+    assert!(summary_of_foo(BranchSummary::Bytes).contains("(2 B)"));
+    assert!(summary_of_foo(BranchSummary::LatestUpdate).contains("(updated "));
+    assert!(!summary_of_foo(BranchSummary::Hidden).contains("topics"));
+}
+
+#[test]
+fn matching_subscriptions_works() {
+    let filters = ["foo/#".to_owned(), "+/test".to_owned(), "test".to_owned()];
+    assert_eq!(matching_subscriptions(&filters, "foo/test"), 0b011);
+    assert_eq!(matching_subscriptions(&filters, "test"), 0b100);
+    assert_eq!(matching_subscriptions(&filters, "bar"), 0);
+}
+
+#[test]
+fn tree_items_mark_subscriptions() {
+    let example = MqttHistory::example();
+    let (_, _, items) = example.to_tree_items(&TreeItemsOptions::default());
+    assert!(!format!("{items:?}").contains("●"));
+
+    let options = TreeItemsOptions {
+        subscriptions: vec!["foo/bar".to_owned(), "test".to_owned()],
+        ..TreeItemsOptions::default()
+    };
+    let (_, _, items) = example.to_tree_items(&options);
+    let markers = |item: &TreeItem<'_, String>| format!("{item:?}").matches('●').count();
+    assert_eq!(items[0].identifier(), "foo");
+    assert_eq!(markers(&items[0]), 1);
+    assert!(format!("{:?}", items[0]).contains("Cyan"));
+    assert_eq!(items[1].identifier(), "test");
+    assert!(format!("{:?}", items[1]).contains("LightMagenta"));
+    assert_eq!(markers(&items[2]), 0);
+}
+
+#[test]
+fn flags_works() {
+    let mut entry = HistoryEntry {
+        qos: rumqttc::QoS::AtLeastOnce,
+        time: Time::Retained,
+        payload_size: 0,
+        payload: crate::payload::Payload::unlimited(Vec::new()).into(),
+    };
+    assert_eq!(flags(&entry), " [R,QoS1]");
+    entry.qos = rumqttc::QoS::AtMostOnce;
+    entry.time = Time::Local(Time::datetime_example());
+    assert_eq!(flags(&entry), " [QoS0]");
+}
+
+#[test]
+fn is_retained_duplicate_works() {
+    let mut history = MqttHistory::new();
+    assert!(!history.is_retained_duplicate("foo", &HistoryEntry::example("1", Time::Retained)));
+    history.add("foo".to_owned(), HistoryEntry::example("1", Time::Retained));
+    assert!(history.is_retained_duplicate("foo", &HistoryEntry::example("1", Time::Retained)));
+    assert!(!history.is_retained_duplicate("foo", &HistoryEntry::example("2", Time::Retained)));
+    assert!(
+        !history.is_retained_duplicate("foo", &HistoryEntry::example("1", Time::new_now(false)))
+    );
+    assert!(!history.is_retained_duplicate("bar", &HistoryEntry::example("1", Time::Retained)));
+
+    // Live messages in between do not matter, the latest retained one is compared
+    history.add(
+        "foo".to_owned(),
+        HistoryEntry::example("2", Time::new_now(false)),
+    );
+    assert!(history.is_retained_duplicate("foo", &HistoryEntry::example("1", Time::Retained)));
+    history.add("foo".to_owned(), HistoryEntry::example("3", Time::Retained));
+    assert!(!history.is_retained_duplicate("foo", &HistoryEntry::example("1", Time::Retained)));
+}
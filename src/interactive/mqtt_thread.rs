@@ -1,120 +1,612 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::{Arc, RwLock, RwLockReadGuard};
-use std::thread::{self, sleep};
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
-use rumqttc::{Client, Connection, ConnectionError, QoS};
+use rumqttc::{AsyncClient, ConnectionError, EventLoop, QoS};
+use tokio::runtime::Handle;
+use tokio::sync::mpsc::{
+    self, error::TrySendError, Receiver, Sender, UnboundedReceiver, UnboundedSender,
+};
+use tokio::sync::oneshot;
+use tokio::task::JoinHandle;
 
-use crate::interactive::mqtt_history::MqttHistory;
-use crate::mqtt::{HistoryEntry, Time};
-use crate::payload::Payload;
+use crate::interactive::connection_stats::ConnectionStats;
+use crate::interactive::full_payload::{Arrival, FullPayloadRequests};
+use crate::interactive::mqtt_history::{HistoryLimit, MqttHistory};
+use crate::interactive::packet_log::PacketLog;
+use crate::interactive::Options;
+use crate::mqtt::{topic_filter, HistoryEntry, Subscriptions, Time};
+use crate::payload::{Decoding, Interner};
 
 type ConnectionErrorArc = Arc<RwLock<Option<ConnectionError>>>;
+/// Topic and error of the last payload which failed to decode
+type DecodeErrorArc = Arc<RwLock<Option<(String, anyhow::Error)>>>;
+type FullPayloadArc = Arc<RwLock<FullPayloadRequests>>;
 type HistoryArc = Arc<RwLock<MqttHistory>>;
+type IgnoredArc = Arc<RwLock<Vec<String>>>;
+type PacketLogArc = Arc<RwLock<PacketLog>>;
+type StatsArc = Arc<RwLock<ConnectionStats>>;
+type SubscribeTopicArc = Arc<RwLock<Vec<String>>>;
+type SubscriptionErrorsArc = Arc<RwLock<Vec<String>>>;
 
-pub struct MqttThread {
-    client: Client,
+/// Received messages are inserted into the history in batches to not block the UI on every single message
+const BATCH_INTERVAL: Duration = Duration::from_millis(20);
+/// Messages inserted at most while holding the write lock of the history so the UI can keep drawing while catching up
+const MAX_BATCH_SIZE: usize = 1000;
+/// Messages received while the queue is full are dropped
+const QUEUE_CAPACITY: usize = 100_000;
+/// Time to wait for outstanding acknowledgements and the disconnect on quit
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(2);
+/// Subscriptions without a `SubAck` after this time since connecting are shown as failed
+const SUBACK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// A received message queued to be decoded
+struct Received {
+    topic: String,
+    payload: Vec<u8>,
+    qos: QoS,
+    time: Time,
+    /// Its full payload was requested so it is stored without the payload size limit
+    full_payload: bool,
+}
+
+/// Everything the connection task shares with the UI. Kept when switching to another broker.
+#[derive(Clone)]
+struct Shared {
+    /// Subscribed on every connect. Includes the subscriptions added at runtime.
+    subscribe_topic: SubscribeTopicArc,
+    /// Can be changed at runtime and applies to future messages
+    payload_size_limit: Arc<AtomicUsize>,
+    sender: Sender<Received>,
+    decode_err: DecodeErrorArc,
     connection_err: ConnectionErrorArc,
+    full_payload: FullPayloadArc,
+    ignored: IgnoredArc,
+    packet_log: PacketLogArc,
+    stats: StatsArc,
+    subscription_errors: SubscriptionErrorsArc,
+}
+
+/// The task handling the connection to a broker
+struct Connection {
+    client: AsyncClient,
+    /// Subscribe at runtime via the connection task which tracks the `SubAck`s
+    subscribe_requests: UnboundedSender<String>,
+    task: JoinHandle<()>,
+    shutdown: oneshot::Sender<()>,
+}
+
+impl Connection {
+    fn spawn(runtime: &Handle, client: AsyncClient, eventloop: EventLoop, shared: Shared) -> Self {
+        let (shutdown, shutdown_receiver) = oneshot::channel();
+        let (subscribe_requests, subscribe_receiver) = mpsc::unbounded_channel();
+        let task = runtime.spawn(connection_logic(
+            client.clone(),
+            eventloop,
+            shutdown_receiver,
+            subscribe_receiver,
+            shared,
+        ));
+        Self {
+            client,
+            subscribe_requests,
+            task,
+            shutdown,
+        }
+    }
+
+    /// Wait for outstanding acknowledgements of the broker and disconnect afterwards
+    fn disconnect(self, runtime: &Handle) {
+        _ = self.shutdown.send(());
+        let task = self.task;
+        _ = runtime.block_on(async move {
+            // The timeout has to be created within the runtime
+            tokio::time::timeout(SHUTDOWN_TIMEOUT, task).await
+        });
+    }
+}
+
+pub struct MqttThread {
+    runtime: Handle,
+    connection: Connection,
     history: HistoryArc,
+    /// Kept for when the history limit is changed at runtime
+    history_compaction: bool,
+    /// Topic filters given on startup which are never unsubscribed
+    initial_subscribe_topic: Vec<String>,
+    shared: Shared,
 }
 
 impl MqttThread {
+    /// Handles the connection on the given runtime
     pub fn new(
-        client: Client,
-        connection: Connection,
-        subscribe_topic: Vec<String>,
-        payload_size_limit: usize,
-    ) -> anyhow::Result<Self> {
-        for topic in &subscribe_topic {
-            client.subscribe(topic, QoS::ExactlyOnce)?;
-        }
-
-        let connection_err = Arc::new(RwLock::new(None));
-        let history = Arc::new(RwLock::new(MqttHistory::new()));
+        runtime: Handle,
+        client: AsyncClient,
+        eventloop: EventLoop,
+        decoding: Decoding,
+        options: &Options,
+    ) -> Self {
+        let history = Arc::new(RwLock::new(MqttHistory::with_limit(options.history_limit)));
+        let stats = Arc::new(RwLock::new(ConnectionStats::default()));
 
+        let (decoded_sender, decoded_receiver) = mpsc::channel(QUEUE_CAPACITY);
+        runtime.spawn(ingest_logic(
+            decoded_receiver,
+            Arc::clone(&history),
+            Arc::clone(&stats),
+            options.skip_retained_duplicates,
+        ));
+        let (sender, receiver) = mpsc::channel(QUEUE_CAPACITY);
+        let payload_size_limit = Arc::new(AtomicUsize::new(options.payload_size_limit));
+        let decode_err = Arc::new(RwLock::new(None));
         {
-            let client = client.clone();
-            let connection_err = Arc::clone(&connection_err);
-            let history = Arc::clone(&history);
-            thread::Builder::new()
-                .name("mqtt connection".to_owned())
+            let payload_size_limit = Arc::clone(&payload_size_limit);
+            let decode_err = Arc::clone(&decode_err);
+            let stats = Arc::clone(&stats);
+            // Transforms and scripts might be slow and must not keep the event loop from being polled
+            std::thread::Builder::new()
+                .name("decode".to_owned())
                 .spawn(move || {
-                    thread_logic(
-                        client,
-                        connection,
-                        &subscribe_topic,
-                        payload_size_limit,
-                        &connection_err,
-                        &history,
+                    decode_logic(
+                        receiver,
+                        &decoded_sender,
+                        &decoding,
+                        &payload_size_limit,
+                        &stats,
+                        &decode_err,
                     );
                 })
-                .expect("should be able to spawn a thread");
+                .expect("should be able to spawn the decode thread");
         }
+        let shared = Shared {
+            subscribe_topic: Arc::new(RwLock::new(options.subscribe_topic.clone())),
+            payload_size_limit,
+            sender,
+            decode_err,
+            connection_err: Arc::new(RwLock::new(None)),
+            full_payload: Arc::new(RwLock::new(FullPayloadRequests::default())),
+            ignored: Arc::new(RwLock::new(options.ignore_topic.clone())),
+            packet_log: Arc::new(RwLock::new(PacketLog::default())),
+            stats,
+            subscription_errors: Arc::new(RwLock::new(Vec::new())),
+        };
+        let connection = Connection::spawn(&runtime, client, eventloop, shared.clone());
 
-        Ok(Self {
-            client,
-            connection_err,
+        Self {
+            runtime,
+            connection,
             history,
-        })
+            history_compaction: options.history_limit.is_some_and(|limit| limit.compaction),
+            initial_subscribe_topic: options.subscribe_topic.clone(),
+            shared,
+        }
+    }
+
+    /// Topic filters subscribed to including the ones subscribed at runtime
+    pub fn subscribe_topic(&self) -> Vec<String> {
+        self.shared
+            .subscribe_topic
+            .read()
+            .expect("mqtt history thread panicked")
+            .clone()
+    }
+
+    /// The topic filter was subscribed to at runtime and can be unsubscribed again
+    pub fn is_runtime_subscription(&self, filter: &str) -> bool {
+        !self
+            .initial_subscribe_topic
+            .iter()
+            .any(|initial| initial == filter)
+            && self
+                .shared
+                .subscribe_topic
+                .read()
+                .expect("mqtt history thread panicked")
+                .iter()
+                .any(|existing| existing == filter)
+    }
+
+    /// Subscribe to the topic filter now and on every reconnect
+    pub fn subscribe(&self, filter: String) -> anyhow::Result<()> {
+        self.connection
+            .subscribe_requests
+            .send(filter.clone())
+            .map_err(|_| anyhow::anyhow!("The connection to the broker ended"))?;
+        self.shared
+            .subscribe_topic
+            .write()
+            .expect("mqtt history thread panicked")
+            .push(filter);
+        Ok(())
+    }
+
+    pub fn payload_size_limit(&self) -> usize {
+        self.shared.payload_size_limit.load(Ordering::Relaxed)
+    }
+
+    /// Truncate the payloads of future messages to the given size
+    pub fn set_payload_size_limit(&self, limit: usize) {
+        self.shared
+            .payload_size_limit
+            .store(limit, Ordering::Relaxed);
+    }
+
+    /// Messages kept per topic
+    pub fn history_limit(&self) -> Option<usize> {
+        self.get_history().limit().map(|limit| limit.messages)
+    }
+
+    pub fn set_history_limit(&self, messages: Option<usize>) {
+        let limit = messages.map(|messages| HistoryLimit {
+            messages,
+            compaction: self.history_compaction,
+        });
+        self.history.write().unwrap().set_limit(limit);
+    }
+
+    /// Store the next message of the topic without the payload size limit.
+    ///
+    /// The topic is subscribed to temporarily in order to get its retained message again.
+    pub fn request_full_payload(&self, topic: String) -> anyhow::Result<()> {
+        self.shared
+            .full_payload
+            .write()
+            .expect("mqtt history thread panicked")
+            .request(topic.clone());
+        self.connection
+            .subscribe_requests
+            .send(topic)
+            .map_err(|_| anyhow::anyhow!("The connection to the broker ended"))?;
+        Ok(())
+    }
+
+    /// Waiting for the next message of the topic to store it without the payload size limit
+    pub fn is_full_payload_pending(&self, topic: &str) -> bool {
+        self.shared
+            .full_payload
+            .read()
+            .expect("mqtt history thread panicked")
+            .is_pending(topic)
+    }
+
+    /// Unsubscribe from a topic filter subscribed to at runtime. Already received messages are kept.
+    pub fn unsubscribe(&self, filter: &str) -> anyhow::Result<()> {
+        self.shared
+            .subscribe_topic
+            .write()
+            .expect("mqtt history thread panicked")
+            .retain(|existing| existing != filter);
+        self.shared
+            .subscription_errors
+            .write()
+            .expect("mqtt history thread panicked")
+            .retain(|existing| existing != filter);
+        self.runtime
+            .block_on(self.connection.client.unsubscribe(filter))?;
+        Ok(())
+    }
+
+    /// Wait for outstanding acknowledgements of the broker and disconnect afterwards
+    pub fn disconnect(self) {
+        self.connection.disconnect(&self.runtime);
+    }
+
+    /// Disconnect from the current broker and continue with the given connection.
+    ///
+    /// The subscriptions and ignored topics stay the same.
+    /// The received messages are kept when `keep_history` is set.
+    pub fn switch_connection(
+        &mut self,
+        client: AsyncClient,
+        eventloop: EventLoop,
+        keep_history: bool,
+    ) {
+        let connection = Connection::spawn(&self.runtime, client, eventloop, self.shared.clone());
+        let previous = std::mem::replace(&mut self.connection, connection);
+        previous.disconnect(&self.runtime);
+
+        if !keep_history {
+            self.history.write().unwrap().clear();
+        }
+        *self.shared.connection_err.write().unwrap() = None;
+        *self.shared.packet_log.write().unwrap() = PacketLog::default();
+        *self.shared.stats.write().unwrap() = ConnectionStats::default();
+        self.shared.subscription_errors.write().unwrap().clear();
     }
 
     pub fn has_connection_err(&self) -> Option<String> {
-        self.connection_err
+        self.shared
+            .connection_err
             .read()
             .expect("mqtt history thread panicked")
             .as_ref()
             .map(ToString::to_string)
     }
 
-    pub fn get_history(&self) -> RwLockReadGuard<MqttHistory> {
-        self.history.read().expect("mqtt history thread panicked")
+    /// Hint about another client with the same client id when the connection keeps dropping right after connecting
+    pub fn has_takeover_err(&self) -> Option<String> {
+        if !self.get_stats().takeover_suspected() {
+            return None;
+        }
+        let hint = "The connection keeps dropping right after connecting. Probably another client with the same client id connected. Use a different --client-id (MQTTUI_CLIENTID) or none at all.";
+        Some(
+            self.has_connection_err()
+                .map_or_else(|| hint.to_owned(), |err| format!("{hint} {err}")),
+        )
+    }
+
+    pub fn has_decode_err(&self) -> Option<String> {
+        self.shared
+            .decode_err
+            .read()
+            .expect("mqtt history thread panicked")
+            .as_ref()
+            .map(|(topic, err)| format!("Failed to decode the payload of {topic}: {err:#}"))
     }
 
-    pub fn clean_below(&self, topic: &str) -> anyhow::Result<()> {
-        let topics = self.get_history().get_topics_below(topic);
-        for topic in topics {
-            self.client.publish(topic, QoS::ExactlyOnce, true, [])?;
+    pub fn has_subscription_err(&self) -> Option<String> {
+        let errors = self
+            .shared
+            .subscription_errors
+            .read()
+            .expect("mqtt history thread panicked")
+            .join(", ");
+        if errors.is_empty() {
+            return None;
         }
+        Some(format!(
+            "The broker rejected or did not acknowledge the subscription to {errors}"
+        ))
+    }
+
+    pub fn get_packet_log(&self) -> RwLockReadGuard<'_, PacketLog> {
+        self.shared
+            .packet_log
+            .read()
+            .expect("mqtt history thread panicked")
+    }
+
+    pub fn get_stats(&self) -> RwLockReadGuard<'_, ConnectionStats> {
+        self.shared
+            .stats
+            .read()
+            .expect("mqtt history thread panicked")
+    }
+
+    /// Runtime of the connection which can be shared with other tasks like the HTTP server
+    pub const fn runtime(&self) -> &Handle {
+        &self.runtime
+    }
+
+    /// Access to the history for other tasks like the HTTP server
+    pub fn share_history(&self) -> HistoryArc {
+        Arc::clone(&self.history)
+    }
+
+    pub fn get_history(&self) -> RwLockReadGuard<'_, MqttHistory> {
+        self.history.read().expect("mqtt history thread panicked")
+    }
+
+    /// Remove the topic and all below from the local history without touching the broker
+    pub fn forget_below(&self, topic: &str) {
+        self.history
+            .write()
+            .expect("mqtt history thread panicked")
+            .remove_below(topic);
+    }
+
+    /// Ignore all future messages of the topic and below and forget the already received ones
+    pub fn ignore_below(&self, topic: &str) {
+        self.shared
+            .ignored
+            .write()
+            .expect("mqtt history thread panicked")
+            .push(format!("{topic}/#"));
+        self.forget_below(topic);
+    }
+
+    pub fn publish(&self, topic: String, payload: String) -> anyhow::Result<()> {
+        self.on_publish_requested(topic.clone(), QoS::AtLeastOnce);
+        self.runtime.block_on(self.connection.client.publish(
+            topic,
+            QoS::AtLeastOnce,
+            false,
+            payload,
+        ))?;
+        Ok(())
+    }
+
+    /// Has to be called before the publish is requested in order to show it as in flight
+    fn on_publish_requested(&self, topic: String, qos: QoS) {
+        self.shared
+            .packet_log
+            .write()
+            .expect("mqtt history thread panicked")
+            .inflight
+            .on_requested(topic, qos);
+    }
+
+    /// Clean the retained topics.
+    ///
+    /// With an interval between them they are cleaned in the background to keep the UI responsive.
+    pub fn clean(&self, topics: Vec<String>, interval: Option<Duration>) -> anyhow::Result<()> {
+        let Some(interval) = interval else {
+            return self.runtime.block_on(async {
+                for topic in topics {
+                    self.on_publish_requested(topic.clone(), QoS::ExactlyOnce);
+                    self.connection
+                        .client
+                        .publish(topic, QoS::ExactlyOnce, true, [])
+                        .await?;
+                }
+                Ok(())
+            });
+        };
+        let client = self.connection.client.clone();
+        let packet_log = Arc::clone(&self.shared.packet_log);
+        self.runtime.spawn(async move {
+            let mut ticks = tokio::time::interval(interval);
+            for topic in topics {
+                ticks.tick().await;
+                packet_log
+                    .write()
+                    .expect("mqtt history thread panicked")
+                    .inflight
+                    .on_requested(topic.clone(), QoS::ExactlyOnce);
+                if client
+                    .publish(topic, QoS::ExactlyOnce, true, [])
+                    .await
+                    .is_err()
+                {
+                    // The event loop ended
+                    break;
+                }
+            }
+        });
         Ok(())
     }
 }
 
-#[allow(clippy::needless_pass_by_value)]
-fn thread_logic(
-    client: Client,
-    mut connection: Connection,
-    subscribe_topic: &[String],
-    payload_size_limit: usize,
-    connection_err: &ConnectionErrorArc,
-    history: &HistoryArc,
+#[allow(clippy::too_many_lines)]
+async fn connection_logic(
+    client: AsyncClient,
+    mut eventloop: EventLoop,
+    mut shutdown: oneshot::Receiver<()>,
+    mut subscribe_requests: UnboundedReceiver<String>,
+    Shared {
+        subscribe_topic,
+        sender,
+        payload_size_limit: _,
+        decode_err: _,
+        connection_err,
+        full_payload,
+        ignored,
+        packet_log,
+        stats,
+        subscription_errors,
+    }: Shared,
 ) {
-    for notification in connection.iter() {
+    // Subscribed to on every ConnAck
+    let mut subscriptions = Subscriptions::default();
+    // Subscribe requests are only sent while connected to keep their order consistent across reconnects
+    let mut connected = false;
+    let mut shutdown_requested = false;
+    let mut disconnect_requested = false;
+    // Verify the subscriptions of the initial connection and of every reconnect
+    let mut verify_subscriptions = Some(tokio::time::Instant::now() + SUBACK_TIMEOUT);
+    loop {
+        if shutdown_requested && !disconnect_requested && eventloop.state.inflight() == 0 {
+            disconnect_requested = client.try_disconnect().is_ok();
+        }
+        if connected {
+            subscriptions.send_queued(&client);
+        }
+        let notification = tokio::select! {
+            notification = eventloop.poll() => notification,
+            _ = &mut shutdown, if !shutdown_requested => {
+                shutdown_requested = true;
+                continue;
+            }
+            Some(topic) = subscribe_requests.recv() => {
+                subscriptions.queue(&topic, QoS::ExactlyOnce);
+                continue;
+            }
+            () = tokio::time::sleep_until(verify_subscriptions.unwrap_or_else(tokio::time::Instant::now)),
+                if verify_subscriptions.is_some() =>
+            {
+                verify_subscriptions = None;
+                let topics = subscribe_topic.read().unwrap().clone();
+                let unconfirmed = subscriptions
+                    .unconfirmed()
+                    .filter(|topic| topics.contains(topic))
+                    .cloned()
+                    .collect::<Vec<_>>();
+                let mut errors = subscription_errors.write().unwrap();
+                for topic in unconfirmed {
+                    if !errors.contains(&topic) {
+                        errors.push(topic);
+                    }
+                }
+                drop(errors);
+                continue;
+            }
+        };
         match notification {
             Ok(event) => {
                 *connection_err.write().unwrap() = None;
+                match &event {
+                    rumqttc::Event::Incoming(packet) => {
+                        packet_log.write().unwrap().add_incoming(packet);
+                    }
+                    rumqttc::Event::Outgoing(outgoing) => {
+                        packet_log.write().unwrap().add_outgoing(outgoing);
+                    }
+                }
                 match event {
                     rumqttc::Event::Incoming(rumqttc::Packet::ConnAck(_)) => {
-                        for topic in subscribe_topic {
-                            client
-                                .subscribe(topic, QoS::ExactlyOnce)
-                                .expect("should be able to subscribe");
+                        stats.write().unwrap().on_connack();
+                        // Even with a present session as only subscribing makes the broker send
+                        // the retained messages again which might have changed while disconnected
+                        subscriptions.on_reconnect_async(&mut eventloop.pending);
+                        for topic in subscribe_topic.read().unwrap().iter() {
+                            subscriptions.queue(topic, QoS::ExactlyOnce);
+                        }
+                        connected = true;
+                        verify_subscriptions = Some(tokio::time::Instant::now() + SUBACK_TIMEOUT);
+                    }
+                    rumqttc::Event::Outgoing(rumqttc::Outgoing::Subscribe(pkid)) => {
+                        subscriptions.on_outgoing(pkid);
+                    }
+                    rumqttc::Event::Incoming(rumqttc::Packet::SubAck(suback)) => {
+                        if let Some((topic, accepted)) = subscriptions.on_suback(&suback) {
+                            let mut errors = subscription_errors.write().unwrap();
+                            errors.retain(|existing| existing != &topic);
+                            if !accepted {
+                                errors.push(topic);
+                            }
                         }
                     }
                     rumqttc::Event::Incoming(rumqttc::Packet::Publish(publish)) => {
                         if publish.dup {
                             continue;
                         }
-                        history.write().unwrap().add(
-                            publish.topic,
-                            HistoryEntry {
-                                qos: publish.qos,
-                                time: Time::new_now(publish.retain),
-                                payload_size: publish.payload.len(),
-                                payload: Payload::truncated(
-                                    publish.payload.into(),
-                                    payload_size_limit,
-                                ),
-                            },
-                        );
+                        stats.write().unwrap().on_publish(publish.payload.len());
+                        if ignored
+                            .read()
+                            .unwrap()
+                            .iter()
+                            .any(|filter| topic_filter::matches(filter, &publish.topic))
+                        {
+                            continue;
+                        }
+                        let arrival = full_payload
+                            .write()
+                            .unwrap()
+                            .on_message(&publish.topic, Instant::now());
+                        if matches!(arrival, Some(Arrival::First))
+                            && !subscribe_topic.read().unwrap().contains(&publish.topic)
+                        {
+                            // Remove the temporary subscription of the full payload request
+                            _ = client.try_unsubscribe(publish.topic.clone());
+                        }
+                        let received = Received {
+                            topic: publish.topic,
+                            payload: publish.payload.into(),
+                            qos: publish.qos,
+                            time: Time::new_now(publish.retain),
+                            full_payload: arrival.is_some(),
+                        };
+                        match sender.try_send(received) {
+                            Ok(()) => stats.write().unwrap().on_queued(),
+                            Err(TrySendError::Full(_)) => stats.write().unwrap().on_dropped(),
+                            Err(TrySendError::Closed(_)) => break,
+                        }
+                    }
+                    rumqttc::Event::Incoming(rumqttc::Packet::PingResp) => {
+                        stats.write().unwrap().on_ping_response();
+                    }
+                    rumqttc::Event::Outgoing(rumqttc::Outgoing::PingReq) => {
+                        stats.write().unwrap().on_ping_request();
                     }
                     rumqttc::Event::Outgoing(rumqttc::Outgoing::Disconnect) => {
                         break;
@@ -122,10 +614,137 @@ fn thread_logic(
                     _ => {}
                 }
             }
+            Err(_) if shutdown_requested => break,
             Err(err) => {
+                connected = false;
+                stats.write().unwrap().on_connection_err();
                 *connection_err.write().unwrap() = Some(err);
-                sleep(Duration::from_millis(25));
+                tokio::time::sleep(Duration::from_millis(25)).await;
+            }
+        }
+    }
+}
+
+/// Decode the received messages in order and pass them on to be inserted into the history.
+///
+/// Runs on its own thread as transforms and scripts block while decoding.
+fn decode_logic(
+    mut receiver: Receiver<Received>,
+    sender: &Sender<(String, HistoryEntry)>,
+    decoding: &Decoding,
+    payload_size_limit: &AtomicUsize,
+    stats: &RwLock<ConnectionStats>,
+    decode_err: &RwLock<Option<(String, anyhow::Error)>>,
+) {
+    let mut interner = Interner::default();
+    let mut interned_size_limit = payload_size_limit.load(Ordering::Relaxed);
+    while let Some(received) = receiver.blocking_recv() {
+        let Received {
+            topic,
+            payload,
+            qos,
+            time,
+            full_payload,
+        } = received;
+        let mut error = None;
+        // Failing decoders fall back to the regular payload
+        let (payload_size, payload, shared) = if full_payload {
+            // The interner might know the truncated payload already
+            let (payload_size, payload) =
+                decoding.decode(&topic, payload, usize::MAX, |err| error = Some(err));
+            (payload_size, Arc::new(payload), false)
+        } else {
+            let size_limit = payload_size_limit.load(Ordering::Relaxed);
+            if size_limit != interned_size_limit {
+                // The interned payloads were truncated with the previous limit
+                interner = Interner::default();
+                interned_size_limit = size_limit;
+            }
+            decoding.decode_interned(
+                &topic,
+                payload,
+                size_limit,
+                |err| error = Some(err),
+                &mut interner,
+            )
+        };
+        if shared {
+            stats.write().unwrap().on_deduplicated(payload_size);
+        }
+        {
+            let mut decode_err = decode_err.write().unwrap();
+            if let Some(err) = error {
+                *decode_err = Some((topic.clone(), err));
+            } else if decode_err
+                .as_ref()
+                .is_some_and(|(failed, _)| failed == &topic)
+            {
+                // The topic decodes fine again
+                *decode_err = None;
             }
+        }
+        let entry = HistoryEntry {
+            qos,
+            time,
+            payload_size,
+            payload,
         };
+        if sender.blocking_send((topic, entry)).is_err() {
+            break;
+        }
+    }
+}
+
+/// Insert the queued messages into the history in batches
+async fn ingest_logic(
+    mut receiver: Receiver<(String, HistoryEntry)>,
+    history: HistoryArc,
+    stats: StatsArc,
+    skip_retained_duplicates: bool,
+) {
+    let mut batch = Vec::with_capacity(MAX_BATCH_SIZE);
+    while let Some(first) = receiver.recv().await {
+        // Wait for more messages to arrive in order to take the write lock less often
+        tokio::time::sleep(BATCH_INTERVAL).await;
+        batch.push(first);
+        loop {
+            while batch.len() < MAX_BATCH_SIZE {
+                let Ok(message) = receiver.try_recv() else {
+                    break;
+                };
+                batch.push(message);
+            }
+            let full = batch.len() >= MAX_BATCH_SIZE;
+            insert_batch(&mut batch, &history, &stats, skip_retained_duplicates);
+            if !full {
+                break;
+            }
+            // Release the lock between full batches so the UI does not wait for the whole backlog
+            tokio::task::yield_now().await;
+        }
+    }
+}
+
+/// Insert the messages into the history while holding its write lock once
+fn insert_batch(
+    batch: &mut Vec<(String, HistoryEntry)>,
+    history: &RwLock<MqttHistory>,
+    stats: &RwLock<ConnectionStats>,
+    skip_retained_duplicates: bool,
+) {
+    let mut inserted: usize = 0;
+    let mut skipped: usize = 0;
+    let mut history = history.write().unwrap();
+    for (topic, entry) in batch.drain(..) {
+        if skip_retained_duplicates && history.is_retained_duplicate(&topic, &entry) {
+            skipped = skipped.saturating_add(1);
+        } else {
+            history.add(topic, entry);
+            inserted = inserted.saturating_add(1);
+        }
     }
+    drop(history);
+    let mut stats = stats.write().unwrap();
+    stats.on_inserted(inserted);
+    stats.on_skipped_retained(skipped);
 }
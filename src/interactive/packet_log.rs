@@ -0,0 +1,106 @@
+use std::collections::VecDeque;
+
+use chrono::Local;
+use ratatui::layout::{Alignment, Rect};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, Borders, Paragraph};
+use ratatui::Frame;
+use rumqttc::{Outgoing, Packet};
+
+use crate::format;
+use crate::interactive::ui::STYLE_WARNING;
+use crate::mqtt::Inflight;
+
+const MAX_ENTRIES: usize = 1000;
+
+/// Keeps the latest MQTT packets in a human readable form like `log --verbose`
+#[derive(Default)]
+pub struct PacketLog {
+    entries: VecDeque<String>,
+    /// Publishes of the UI waiting for their acknowledgement
+    pub inflight: Inflight,
+}
+
+impl PacketLog {
+    pub fn add_incoming(&mut self, packet: &Packet) {
+        self.inflight.on_incoming(packet);
+        let text = if let Packet::Publish(publish) = packet {
+            // Only the header as payloads can be huge
+            format!(
+                "Publish {} QoS:{} retain:{} dup:{} pkid:{} Payload({})",
+                publish.topic,
+                format::qos(publish.qos),
+                publish.retain,
+                publish.dup,
+                publish.pkid,
+                publish.payload.len(),
+            )
+        } else {
+            format!("{packet:?}")
+        };
+        self.push("incoming", &text);
+    }
+
+    pub fn add_outgoing(&mut self, outgoing: &Outgoing) {
+        self.inflight.on_outgoing(outgoing);
+        self.push("outgoing", &format!("{outgoing:?}"));
+    }
+
+    fn push(&mut self, direction: &str, text: &str) {
+        if self.entries.len() >= MAX_ENTRIES {
+            self.entries.pop_front();
+        }
+        let time = Local::now().format("%_H:%M:%S.%3f");
+        self.entries.push_back(format!("{time} {direction} {text}"));
+    }
+
+    pub fn draw(&self, frame: &mut Frame, area: Rect) {
+        let visible = area.height.saturating_sub(1) as usize; // remove block
+                                                              // The in-flight publishes stay on top while the packets scroll below them
+        let mut lines = self
+            .inflight
+            .lines()
+            .take(visible / 2)
+            .map(|line| Line::styled(format!("in flight {line}"), STYLE_WARNING))
+            .collect::<Vec<_>>();
+        let visible = visible.saturating_sub(lines.len());
+        lines.extend(
+            self.entries
+                .iter()
+                .skip(self.entries.len().saturating_sub(visible))
+                .map(|entry| Line::raw(entry.as_str())),
+        );
+        let title = if self.inflight.is_empty() {
+            "MQTT Packets".to_owned()
+        } else {
+            format!("MQTT Packets, {} publishes in flight", self.inflight.len())
+        };
+        let paragraph = Paragraph::new(lines).block(
+            Block::new()
+                .borders(Borders::TOP)
+                .title_alignment(Alignment::Center)
+                .title(title),
+        );
+        frame.render_widget(paragraph, area);
+    }
+}
+
+#[test]
+fn publish_without_payload() {
+    let mut log = PacketLog::default();
+    let publish = rumqttc::Publish::new("foo/bar", rumqttc::QoS::AtLeastOnce, "secret");
+    log.add_incoming(&Packet::Publish(publish));
+    let entry = log.entries.back().unwrap();
+    assert!(entry.ends_with(
+        "incoming Publish foo/bar QoS:AtLeastOnce retain:false dup:false pkid:0 Payload(6)"
+    ));
+}
+
+#[test]
+fn limits_entries() {
+    let mut log = PacketLog::default();
+    for _ in 0..MAX_ENTRIES + 10 {
+        log.add_outgoing(&Outgoing::PingReq);
+    }
+    assert_eq!(log.entries.len(), MAX_ENTRIES);
+}
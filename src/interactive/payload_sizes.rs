@@ -0,0 +1,168 @@
+use std::collections::HashMap;
+
+use ratatui::layout::{Alignment, Rect};
+use ratatui::style::{Color, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Clear, Paragraph};
+use ratatui::Frame;
+
+use crate::format;
+use crate::interactive::mqtt_history::{is_topic_below, STYLE_DARKGRAY};
+use crate::interactive::ui::{self, STYLE_BOLD};
+use crate::mqtt::Time;
+
+/// Upper bounds of the histogram buckets in bytes. Bigger payloads are counted in an additional bucket.
+const BUCKETS: [u64; 8] = [
+    64,
+    256,
+    1024,
+    4 * 1024,
+    16 * 1024,
+    64 * 1024,
+    256 * 1024,
+    1024 * 1024,
+];
+
+/// Width of the bars of the histogram
+const BAR_WIDTH: usize = 30;
+
+/// Sizes of every payload received during the session, also of the ones removed by the history limit
+#[derive(Default)]
+pub struct PayloadSizes {
+    /// Messages per bucket, the last one for payloads bigger than all buckets
+    counts: [usize; BUCKETS.len() + 1],
+    /// Size and time of the biggest payload per topic
+    largest: HashMap<String, (usize, Time)>,
+}
+
+impl PayloadSizes {
+    pub fn add(&mut self, topic: &str, size: usize, time: Time) {
+        let bucket = BUCKETS
+            .iter()
+            .position(|bound| size as u64 <= *bound)
+            .unwrap_or(BUCKETS.len());
+        self.counts[bucket] = self.counts[bucket].saturating_add(1);
+        match self.largest.get_mut(topic) {
+            Some(largest) if largest.0 >= size => {}
+            Some(largest) => *largest = (size, time),
+            None => {
+                self.largest.insert(topic.to_owned(), (size, time));
+            }
+        }
+    }
+
+    /// Forget the biggest payloads of the topic and the topics below.
+    /// The histogram still contains them as they were received in this session.
+    pub fn remove_below(&mut self, base: &str) {
+        self.largest.retain(|topic, _| !is_topic_below(base, topic));
+    }
+
+    pub fn clear(&mut self) {
+        *self = Self::default();
+    }
+
+    fn messages(&self) -> usize {
+        self.counts.iter().sum()
+    }
+
+    /// Topics with their biggest payload, the biggest first
+    fn largest(&self) -> Vec<(&str, usize, Time)> {
+        let mut largest = self
+            .largest
+            .iter()
+            .map(|(topic, (size, time))| (topic.as_str(), *size, *time))
+            .collect::<Vec<_>>();
+        largest.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+        largest
+    }
+
+    fn histogram(&self) -> Vec<Line<'static>> {
+        let max = self.counts.iter().copied().max().unwrap_or_default().max(1);
+        self.counts
+            .iter()
+            .enumerate()
+            .map(|(index, count)| {
+                let label = BUCKETS.get(index).map_or_else(
+                    || format!("> {}", format::bytes(BUCKETS[BUCKETS.len() - 1])),
+                    |bound| format!("≤ {}", format::bytes(*bound)),
+                );
+                // Round up to show a little bar for every bucket with messages
+                let width = count.saturating_mul(BAR_WIDTH).div_ceil(max);
+                Line::from(vec![
+                    Span::styled(format!("{label:>11} "), STYLE_BOLD),
+                    Span::styled("█".repeat(width), Style::new().fg(Color::LightCyan)),
+                    Span::raw(format!(" {count}")),
+                ])
+            })
+            .collect()
+    }
+
+    pub fn draw(&self, frame: &mut Frame) {
+        let frame_area = frame.size();
+        let width = frame_area.width.saturating_sub(8).min(90);
+        let height = frame_area.height.saturating_sub(4);
+        let area = Rect {
+            x: frame_area.width.saturating_sub(width) / 2,
+            y: frame_area.height.saturating_sub(height) / 2,
+            width,
+            height,
+        };
+
+        let mut lines = self.histogram();
+        lines.push(Line::raw(""));
+        lines.push(Line::styled("Biggest payload per topic", STYLE_BOLD));
+        let largest = self.largest();
+        if largest.is_empty() {
+            lines.push(Line::styled("No messages yet", STYLE_DARKGRAY));
+        }
+        // Size, time and spacing
+        let topic_width = usize::from(width).saturating_sub(2 + 11 + 13 + 2);
+        for (topic, size, time) in largest {
+            lines.push(Line::from(vec![
+                Span::raw(format!("{:>11} ", format::bytes(size as u64))),
+                Span::raw(format!(
+                    "{:topic_width$} ",
+                    ui::ellipsize(topic, topic_width)
+                )),
+                Span::styled(time.to_string(), STYLE_DARKGRAY),
+            ]));
+        }
+
+        let title = format!("Payload sizes of {} messages", self.messages());
+        let block = Block::bordered()
+            .border_style(Style::new().fg(Color::LightGreen))
+            .title_alignment(Alignment::Center)
+            .title(title);
+        frame.render_widget(Clear, area); // clear the background of the popup
+        frame.render_widget(Paragraph::new(lines).block(block), area);
+    }
+}
+
+#[test]
+fn payload_sizes_works() {
+    let time = Time::Retained;
+    let mut sizes = PayloadSizes::default();
+    sizes.add("foo/small", 10, time);
+    sizes.add("foo/small", 64, time);
+    sizes.add("foo/big", 2000, time);
+    sizes.add("huge", 5 * 1024 * 1024, time);
+    assert_eq!(sizes.counts, [2, 0, 0, 1, 0, 0, 0, 0, 1]);
+    assert_eq!(sizes.messages(), 4);
+    let topics = sizes
+        .largest()
+        .into_iter()
+        .map(|(topic, size, _)| (topic, size))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        topics,
+        [
+            ("huge", 5 * 1024 * 1024),
+            ("foo/big", 2000),
+            ("foo/small", 64)
+        ]
+    );
+
+    sizes.remove_below("foo");
+    assert_eq!(sizes.largest().len(), 1);
+    assert_eq!(sizes.messages(), 4);
+}
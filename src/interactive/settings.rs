@@ -0,0 +1,423 @@
+use anyhow::Context;
+use clap::ValueEnum;
+use ratatui::layout::{Alignment, Rect};
+use ratatui::style::{Color, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Clear, Paragraph, Wrap};
+use ratatui::Frame;
+
+use crate::cli::{BranchSummary, HistoryColumn};
+use crate::config::Config;
+use crate::interactive::mqtt_history::STYLE_DARKGRAY;
+use crate::interactive::ui::STYLE_BOLD;
+
+/// Width of the setting names in front of their values
+const NAME_WIDTH: usize = 22;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Setting {
+    PayloadSizeLimit,
+    HistoryLimit,
+    TimeColumn,
+    RelativeTime,
+    CollapseDuplicates,
+    PinSelection,
+    BranchSummary,
+    Accessible,
+    ArrivalPulse,
+}
+
+impl Setting {
+    const ALL: [Self; 9] = [
+        Self::PayloadSizeLimit,
+        Self::HistoryLimit,
+        Self::TimeColumn,
+        Self::RelativeTime,
+        Self::CollapseDuplicates,
+        Self::PinSelection,
+        Self::BranchSummary,
+        Self::Accessible,
+        Self::ArrivalPulse,
+    ];
+
+    const fn name(self) -> &'static str {
+        match self {
+            Self::PayloadSizeLimit => "Payload size limit",
+            Self::HistoryLimit => "History limit",
+            Self::TimeColumn => "Time column",
+            Self::RelativeTime => "Relative time",
+            Self::CollapseDuplicates => "Collapse duplicates",
+            Self::PinSelection => "Pin selection",
+            Self::BranchSummary => "Branch summary",
+            Self::Accessible => "Accessibility mode",
+            Self::ArrivalPulse => "Arrival pulse",
+        }
+    }
+}
+
+/// How the time of messages is shown in the history table
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimeColumn {
+    WithMilliseconds,
+    WithoutMilliseconds,
+    Hidden,
+}
+
+impl TimeColumn {
+    fn new(history_columns: &[HistoryColumn]) -> Self {
+        if history_columns.contains(&HistoryColumn::Time) {
+            Self::WithMilliseconds
+        } else if history_columns.contains(&HistoryColumn::ShortTime) {
+            Self::WithoutMilliseconds
+        } else {
+            Self::Hidden
+        }
+    }
+
+    const fn next(self) -> Self {
+        match self {
+            Self::WithMilliseconds => Self::WithoutMilliseconds,
+            Self::WithoutMilliseconds => Self::Hidden,
+            Self::Hidden => Self::WithMilliseconds,
+        }
+    }
+
+    const fn column(self) -> Option<HistoryColumn> {
+        match self {
+            Self::WithMilliseconds => Some(HistoryColumn::Time),
+            Self::WithoutMilliseconds => Some(HistoryColumn::ShortTime),
+            Self::Hidden => None,
+        }
+    }
+
+    const fn text(self) -> &'static str {
+        match self {
+            Self::WithMilliseconds => "with milliseconds",
+            Self::WithoutMilliseconds => "without milliseconds",
+            Self::Hidden => "hidden",
+        }
+    }
+}
+
+/// Change options at runtime which otherwise require a restart and save them as defaults
+#[allow(clippy::struct_excessive_bools)]
+pub struct Popup {
+    /// Bytes of a payload which are stored. Applies to future messages.
+    pub payload_size_limit: String,
+    /// Messages kept per topic, empty for no limit. Applies when a topic receives its next message.
+    pub history_limit: String,
+    pub time_column: TimeColumn,
+    pub relative_time: bool,
+    pub collapse_duplicates: bool,
+    /// Keep the selected history entry instead of following the newest one
+    pub pin_selection: bool,
+    pub branch_summary: BranchSummary,
+    /// High contrast without relying on colors only
+    pub accessible: bool,
+    /// Highlight topics receiving messages
+    pub arrival_pulse: bool,
+    selected: usize,
+    /// Why the last input was not applied
+    pub error: Option<String>,
+}
+
+impl Popup {
+    #[allow(clippy::fn_params_excessive_bools, clippy::too_many_arguments)]
+    pub fn new(
+        payload_size_limit: usize,
+        history_limit: Option<usize>,
+        history_columns: &[HistoryColumn],
+        relative_time: bool,
+        collapse_duplicates: bool,
+        pin_selection: bool,
+        branch_summary: BranchSummary,
+        accessible: bool,
+        arrival_pulse: bool,
+    ) -> Self {
+        Self {
+            payload_size_limit: payload_size_limit.to_string(),
+            history_limit: history_limit
+                .map(|limit| limit.to_string())
+                .unwrap_or_default(),
+            time_column: TimeColumn::new(history_columns),
+            relative_time,
+            collapse_duplicates,
+            pin_selection,
+            branch_summary,
+            accessible,
+            arrival_pulse,
+            selected: 0,
+            error: None,
+        }
+    }
+
+    const fn selected(&self) -> Setting {
+        Setting::ALL[self.selected]
+    }
+
+    pub fn select_next(&mut self) {
+        self.selected = self.selected.saturating_add(1).min(Setting::ALL.len() - 1);
+    }
+
+    pub fn select_previous(&mut self) {
+        self.selected = self.selected.saturating_sub(1);
+    }
+
+    /// Toggle or cycle the value of the selected setting
+    pub fn change(&mut self) {
+        match self.selected() {
+            Setting::PayloadSizeLimit | Setting::HistoryLimit => {}
+            Setting::TimeColumn => self.time_column = self.time_column.next(),
+            Setting::RelativeTime => self.relative_time = !self.relative_time,
+            Setting::CollapseDuplicates => self.collapse_duplicates = !self.collapse_duplicates,
+            Setting::PinSelection => self.pin_selection = !self.pin_selection,
+            Setting::BranchSummary => {
+                let all = BranchSummary::value_variants();
+                let index = all
+                    .iter()
+                    .position(|summary| *summary == self.branch_summary)
+                    .unwrap_or_default();
+                self.branch_summary = all[(index + 1) % all.len()];
+            }
+            Setting::Accessible => self.accessible = !self.accessible,
+            Setting::ArrivalPulse => self.arrival_pulse = !self.arrival_pulse,
+        }
+    }
+
+    /// The text of the selected setting when it is typed in
+    fn selected_text(&mut self) -> Option<&mut String> {
+        match self.selected() {
+            Setting::PayloadSizeLimit => Some(&mut self.payload_size_limit),
+            Setting::HistoryLimit => Some(&mut self.history_limit),
+            _ => None,
+        }
+    }
+
+    /// Type into the selected limit.
+    /// Returns `false` when the input is not used.
+    pub fn input(&mut self, char: char) -> bool {
+        if !char.is_ascii_digit() {
+            return false;
+        }
+        self.selected_text().map(|text| text.push(char)).is_some()
+    }
+
+    pub fn backspace(&mut self) -> bool {
+        self.selected_text().and_then(String::pop).is_some()
+    }
+
+    pub fn payload_size_limit(&self) -> anyhow::Result<usize> {
+        let limit = self
+            .payload_size_limit
+            .trim()
+            .parse::<usize>()
+            .context("The payload size limit has to be a positive number of bytes")?;
+        anyhow::ensure!(
+            limit > 0,
+            "The payload size limit has to be at least 1 byte"
+        );
+        Ok(limit)
+    }
+
+    /// `None` when the history is not limited
+    pub fn history_limit(&self) -> anyhow::Result<Option<usize>> {
+        let limit = self.history_limit.trim();
+        if limit.is_empty() {
+            return Ok(None);
+        }
+        let limit = limit
+            .parse::<usize>()
+            .context("The history limit has to be a positive number of messages")?;
+        Ok((limit > 0).then_some(limit))
+    }
+
+    /// The history columns with the time column replaced by the chosen one
+    pub fn history_columns(&self, current: &[HistoryColumn]) -> Vec<HistoryColumn> {
+        current
+            .iter()
+            .copied()
+            .filter(|column| !matches!(column, HistoryColumn::Time | HistoryColumn::ShortTime))
+            .chain(self.time_column.column())
+            .collect()
+    }
+
+    pub fn config(&self, history_columns: &[HistoryColumn]) -> anyhow::Result<Config> {
+        let history_columns = self
+            .history_columns(history_columns)
+            .iter()
+            .filter_map(ValueEnum::to_possible_value)
+            .map(|value| value.get_name().to_owned())
+            .collect();
+        let branch_summary = self
+            .branch_summary
+            .to_possible_value()
+            .map(|value| value.get_name().to_owned());
+        Ok(Config {
+            payload_size_limit: Some(self.payload_size_limit()?),
+            history_limit: Some(self.history_limit()?.unwrap_or_default()),
+            history_columns: Some(history_columns),
+            relative_time: Some(self.relative_time),
+            collapse_duplicates: Some(self.collapse_duplicates),
+            pin_selection: Some(self.pin_selection),
+            branch_summary,
+            accessible: Some(self.accessible),
+            announce_file: None,
+            arrival_pulse: Some(self.arrival_pulse),
+        })
+    }
+
+    fn value(&self, setting: Setting) -> String {
+        let on_off = |value| if value { "on" } else { "off" }.to_owned();
+        match setting {
+            Setting::PayloadSizeLimit => format!("{} bytes", self.payload_size_limit),
+            Setting::HistoryLimit if self.history_limit.is_empty() => "none".to_owned(),
+            Setting::HistoryLimit => format!("{} messages per topic", self.history_limit),
+            Setting::TimeColumn => self.time_column.text().to_owned(),
+            Setting::RelativeTime => on_off(self.relative_time),
+            Setting::CollapseDuplicates => on_off(self.collapse_duplicates),
+            Setting::PinSelection => on_off(self.pin_selection),
+            Setting::BranchSummary => self
+                .branch_summary
+                .to_possible_value()
+                .map(|value| value.get_name().to_owned())
+                .unwrap_or_default(),
+            Setting::Accessible => on_off(self.accessible),
+            Setting::ArrivalPulse => on_off(self.arrival_pulse),
+        }
+    }
+
+    pub fn draw(&self, frame: &mut Frame) {
+        let selected_style = Style::new().fg(Color::Black).bg(Color::LightGreen);
+        let mut lines = Setting::ALL
+            .into_iter()
+            .map(|setting| {
+                let line = Line::from(vec![
+                    Span::styled(format!("{:NAME_WIDTH$}", setting.name()), STYLE_BOLD),
+                    Span::raw(self.value(setting)),
+                ]);
+                if setting == self.selected() {
+                    line.style(selected_style)
+                } else {
+                    line
+                }
+            })
+            .collect::<Vec<_>>();
+        lines.push(Line::raw(""));
+        lines.push(Line::styled(
+            match self.selected() {
+                Setting::PayloadSizeLimit => "Type the limit in bytes. Applies to future messages.",
+                Setting::HistoryLimit => {
+                    "Type the messages kept per topic, empty for no limit. Applies to topics on their next message."
+                }
+                _ => "Space to change",
+            },
+            STYLE_DARKGRAY,
+        ));
+        if let Some(error) = &self.error {
+            lines.push(Line::raw(""));
+            lines.push(Line::styled(error.as_str(), Style::new().fg(Color::Red)));
+        }
+
+        let frame_area = frame.size();
+        // Some space for a wrapped error message
+        #[allow(clippy::cast_possible_truncation)]
+        let height = (lines.len() as u16)
+            .saturating_add(4)
+            .min(frame_area.height);
+        let width = frame_area.width.saturating_sub(8).min(60);
+        let area = Rect {
+            x: frame_area.width.saturating_sub(width) / 2,
+            y: frame_area.height.saturating_sub(height) / 2,
+            width,
+            height,
+        };
+        let block = Block::bordered()
+            .border_style(Style::new().fg(Color::LightGreen))
+            .title_alignment(Alignment::Center)
+            .title("Settings")
+            .title_bottom(Line::styled(
+                "Enter to apply, s to save as default",
+                STYLE_DARKGRAY,
+            ));
+        frame.render_widget(Clear, area); // clear the background of the popup
+        frame.render_widget(
+            Paragraph::new(lines)
+                .block(block)
+                .wrap(Wrap { trim: false }),
+            area,
+        );
+    }
+}
+
+#[cfg(test)]
+fn popup(history_columns: &[HistoryColumn]) -> Popup {
+    Popup::new(
+        8000,
+        None,
+        history_columns,
+        false,
+        false,
+        false,
+        BranchSummary::Counts,
+        false,
+        false,
+    )
+}
+
+#[test]
+fn payload_size_limit_works() {
+    let mut popup = popup(&[]);
+    assert_eq!(popup.payload_size_limit().unwrap(), 8000);
+    assert!(popup.input('0'));
+    assert_eq!(popup.payload_size_limit().unwrap(), 80_000);
+    assert!(!popup.input('x'));
+    popup.payload_size_limit = "0".to_owned();
+    assert!(popup.payload_size_limit().is_err());
+    popup.select_next();
+    popup.select_next();
+    assert!(!popup.input('1'));
+    assert!(!popup.backspace());
+}
+
+#[test]
+fn history_limit_works() {
+    let mut popup = popup(&[]);
+    assert_eq!(popup.history_limit().unwrap(), None);
+    popup.select_next();
+    assert!(popup.input('5'));
+    assert!(popup.input('0'));
+    assert_eq!(popup.history_limit().unwrap(), Some(50));
+    assert!(popup.backspace());
+    assert!(popup.backspace());
+    assert!(!popup.backspace());
+    assert_eq!(popup.history_limit().unwrap(), None);
+    assert_eq!(popup.config(&[]).unwrap().history_limit, Some(0));
+}
+
+#[test]
+fn time_column_replaces_existing() {
+    let current = [HistoryColumn::Qos, HistoryColumn::Time, HistoryColumn::Size];
+    let mut popup = popup(&current);
+    assert_eq!(popup.time_column, TimeColumn::WithMilliseconds);
+    popup.select_next();
+    popup.select_next();
+    popup.change();
+    assert_eq!(
+        popup.history_columns(&current),
+        [
+            HistoryColumn::Qos,
+            HistoryColumn::Size,
+            HistoryColumn::ShortTime
+        ]
+    );
+    popup.change();
+    assert_eq!(
+        popup.history_columns(&current),
+        [HistoryColumn::Qos, HistoryColumn::Size]
+    );
+    let config = popup.config(&current).unwrap();
+    assert_eq!(
+        config.history_columns,
+        Some(vec!["qos".to_owned(), "size".to_owned()])
+    );
+}
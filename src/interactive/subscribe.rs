@@ -0,0 +1,84 @@
+use ratatui::layout::{Alignment, Rect};
+use ratatui::style::{Color, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Clear, Paragraph, Wrap};
+use ratatui::Frame;
+
+use crate::interactive::mqtt_history::STYLE_DARKGRAY;
+use crate::interactive::topic_input::TopicInput;
+use crate::interactive::ui::{self, STYLE_BOLD};
+
+const PROMPT: &str = "Topic filter: ";
+
+/// Subscribe to another topic filter while running
+#[derive(Default)]
+pub struct Popup {
+    pub topic: TopicInput,
+    /// Why the last attempt to subscribe failed
+    pub error: Option<String>,
+}
+
+impl Popup {
+    /// The validated topic filter to subscribe to
+    pub fn filter(&self) -> anyhow::Result<String> {
+        crate::cli::parse_topic_filter(self.topic.value().trim())
+    }
+
+    pub fn draw(&self, frame: &mut Frame) {
+        let mut lines = vec![
+            Line::styled(
+                "Known topics are suggested. Wildcards like foo/+/bar/# work too.",
+                STYLE_DARKGRAY,
+            ),
+            Line::raw(""),
+            Line::from(vec![
+                Span::styled(PROMPT, STYLE_BOLD),
+                Span::raw(self.topic.input.as_str()),
+            ]),
+        ];
+        let suggestions = self.topic.suggestion_lines();
+        if !suggestions.is_empty() {
+            lines.push(Line::raw(""));
+            lines.extend(suggestions);
+        }
+        if let Some(error) = &self.error {
+            lines.push(Line::raw(""));
+            lines.push(Line::styled(error.as_str(), Style::new().fg(Color::Red)));
+        }
+
+        let frame_area = frame.size();
+        #[allow(clippy::cast_possible_truncation)]
+        let height = (lines.len() as u16)
+            .saturating_add(4)
+            .min(frame_area.height.saturating_sub(4));
+        let width = frame_area.width.saturating_sub(8).min(80);
+        let area = Rect {
+            x: frame_area.width.saturating_sub(width) / 2,
+            y: frame_area.height.saturating_sub(height) / 2,
+            width,
+            height,
+        };
+        let block = Block::bordered()
+            .border_style(Style::new().fg(Color::LightGreen))
+            .title_alignment(Alignment::Center)
+            .title("Subscribe");
+        frame.render_widget(Clear, area); // clear the background of the popup
+        frame.render_widget(
+            Paragraph::new(lines)
+                .block(block)
+                .wrap(Wrap { trim: false }),
+            area,
+        );
+
+        #[allow(clippy::cast_possible_truncation)]
+        let cursor_x = area
+            .x
+            .saturating_add(1)
+            .saturating_add(PROMPT.len() as u16)
+            .saturating_add(ui::width(&self.topic.input) as u16);
+        frame.set_cursor(
+            cursor_x.min(area.right().saturating_sub(2)),
+            area.y.saturating_add(3),
+        );
+    }
+}
@@ -0,0 +1,130 @@
+use ratatui::layout::{Alignment, Rect};
+use ratatui::style::{Color, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Clear, Paragraph};
+use ratatui::Frame;
+
+use crate::interactive::mqtt_history::{MqttHistory, STYLE_DARKGRAY};
+use crate::interactive::ui::{self, STYLE_BOLD};
+
+/// Topic prefixes of Tasmota with the default full topic `%prefix%/%topic%/`
+const PREFIXES: [&str; 3] = ["cmnd", "stat", "tele"];
+
+/// Tasmota device name of a topic like `stat/kitchen/POWER`
+pub fn device_of(topic: &str) -> Option<&str> {
+    let mut parts = topic.splitn(3, '/');
+    let prefix = parts.next()?;
+    let device = parts.next()?;
+    (PREFIXES.contains(&prefix) && !device.is_empty()).then_some(device)
+}
+
+/// Send a command to the `cmnd` topic of a Tasmota device while seeing its latest `tele` and `stat` data
+pub struct CommandPopup {
+    pub device: String,
+    pub input: String,
+}
+
+impl CommandPopup {
+    pub const fn new(device: String) -> Self {
+        Self {
+            device,
+            input: String::new(),
+        }
+    }
+
+    /// Topic and payload of the entered command like `Power toggle`
+    pub fn command(&self) -> Option<(String, String)> {
+        let input = self.input.trim();
+        let (command, payload) = input.split_once(' ').unwrap_or((input, ""));
+        if command.is_empty() {
+            return None;
+        }
+        Some((
+            format!("cmnd/{}/{command}", self.device),
+            payload.trim().to_owned(),
+        ))
+    }
+
+    pub fn draw(&self, frame: &mut Frame, history: &MqttHistory) {
+        let mut lines = Vec::new();
+        for prefix in ["tele", "stat"] {
+            let base = format!("{prefix}/{}", self.device);
+            let mut topics = history.get_topics_below(&base);
+            topics.sort();
+            for topic in topics {
+                let Some(entry) = history.get(&topic).and_then(|entries| entries.last()) else {
+                    continue;
+                };
+                lines.push(Line::from(vec![
+                    Span::styled(topic, STYLE_BOLD),
+                    Span::raw(" "),
+                    Span::styled(format!("= {}", entry.payload), STYLE_DARKGRAY),
+                ]));
+            }
+        }
+        lines.push(Line::raw(""));
+        let prompt = format!("cmnd/{}/", self.device);
+        let cursor_offset = ui::width(&prompt).saturating_add(ui::width(&self.input));
+        lines.push(Line::from(vec![
+            Span::styled(prompt, STYLE_BOLD),
+            Span::raw(self.input.as_str()),
+        ]));
+
+        let area = popup_area(frame.size(), lines.len());
+        let block = Block::bordered()
+            .border_style(Style::new().fg(Color::LightGreen))
+            .title_alignment(Alignment::Center)
+            .title(format!("Tasmota {}", self.device));
+        let input_y = area.bottom().saturating_sub(2);
+        frame.render_widget(Clear, area); // clear the background of the popup
+        frame.render_widget(Paragraph::new(lines).block(block), area);
+
+        #[allow(clippy::cast_possible_truncation)]
+        let cursor_x = area
+            .x
+            .saturating_add(1)
+            .saturating_add(cursor_offset as u16);
+        frame.set_cursor(cursor_x.min(area.right().saturating_sub(2)), input_y);
+    }
+}
+
+/// Centered area which keeps the input line at the bottom visible
+fn popup_area(area: Rect, lines: usize) -> Rect {
+    #[allow(clippy::cast_possible_truncation)]
+    let height = (lines as u16)
+        .saturating_add(2)
+        .min(area.height.saturating_sub(4));
+    let width = area.width.saturating_sub(8).min(120);
+    Rect {
+        x: area.width.saturating_sub(width) / 2,
+        y: area.height.saturating_sub(height) / 2,
+        width,
+        height,
+    }
+}
+
+#[test]
+fn device_of_works() {
+    assert_eq!(device_of("stat/kitchen/POWER"), Some("kitchen"));
+    assert_eq!(device_of("tele/kitchen/SENSOR"), Some("kitchen"));
+    assert_eq!(device_of("cmnd/kitchen"), Some("kitchen"));
+    assert_eq!(device_of("stat"), None);
+    assert_eq!(device_of("stat//POWER"), None);
+    assert_eq!(device_of("zigbee2mqtt/kitchen"), None);
+}
+
+#[test]
+fn command_works() {
+    let mut popup = CommandPopup::new("kitchen".to_owned());
+    assert_eq!(popup.command(), None);
+    popup.input = "Power toggle".to_owned();
+    assert_eq!(
+        popup.command(),
+        Some(("cmnd/kitchen/Power".to_owned(), "toggle".to_owned()))
+    );
+    popup.input = " Status ".to_owned();
+    assert_eq!(
+        popup.command(),
+        Some(("cmnd/kitchen/Status".to_owned(), String::new()))
+    );
+}
@@ -0,0 +1,83 @@
+use crate::cli::Broker;
+
+/// Keeps the terminal title like `mqttui mqtt://localhost (42 topics)` up to date.
+///
+/// Terminal multiplexers like tmux take it over as the pane title which can be shown in their status line.
+pub struct TerminalTitle {
+    /// Empty disables setting the title
+    format: Box<str>,
+    broker: Box<str>,
+    current: String,
+}
+
+impl TerminalTitle {
+    pub fn new(format: &str, broker: &Broker) -> Self {
+        Self {
+            format: format.into(),
+            broker: broker.to_string().into(),
+            current: String::new(),
+        }
+    }
+
+    pub fn set_broker(&mut self, broker: &Broker) {
+        self.broker = broker.to_string().into();
+    }
+
+    #[allow(clippy::literal_string_with_formatting_args)]
+    fn text(&self, topics: usize) -> String {
+        self.format
+            .replace("{broker}", &self.broker)
+            .replace("{topics}", &topics.to_string())
+    }
+
+    pub fn update(&mut self, topics: usize) -> std::io::Result<()> {
+        if self.format.is_empty() {
+            return Ok(());
+        }
+        let text = self.text(topics);
+        if text != self.current {
+            crossterm::execute!(std::io::stdout(), crossterm::terminal::SetTitle(&text))?;
+            self.current = text;
+        }
+        Ok(())
+    }
+}
+
+/// Save the current title of the terminal on its title stack (XTWINOPS 22)
+pub struct PushTitle;
+
+impl crossterm::Command for PushTitle {
+    fn write_ansi(&self, f: &mut impl std::fmt::Write) -> std::fmt::Result {
+        f.write_str("\x1b[22;0t")
+    }
+
+    #[cfg(windows)]
+    fn execute_winapi(&self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Restore the title saved with [`PushTitle`] (XTWINOPS 23)
+pub struct PopTitle;
+
+impl crossterm::Command for PopTitle {
+    fn write_ansi(&self, f: &mut impl std::fmt::Write) -> std::fmt::Result {
+        f.write_str("\x1b[23;0t")
+    }
+
+    #[cfg(windows)]
+    fn execute_winapi(&self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+#[allow(clippy::literal_string_with_formatting_args)]
+fn text_works() {
+    let broker = Broker::Tcp {
+        host: "localhost".to_owned(),
+        port: 1883,
+    };
+    let title = TerminalTitle::new("mqttui {broker} ({topics} topics)", &broker);
+    assert_eq!(title.text(42), "mqttui mqtt://localhost (42 topics)");
+}
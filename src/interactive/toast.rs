@@ -0,0 +1,40 @@
+use std::time::{Duration, Instant};
+
+/// How long a message stays visible
+const DURATION: Duration = Duration::from_secs(4);
+
+/// Short feedback of a finished action like `Cleaned 14 topics` which disappears on its own
+#[derive(Default)]
+pub struct Toast {
+    message: Option<(String, Instant)>,
+}
+
+impl Toast {
+    pub fn show(&mut self, message: String) {
+        self.message = Some((message, Instant::now()));
+    }
+
+    pub fn current(&self) -> Option<&str> {
+        self.message
+            .as_ref()
+            .filter(|(_, shown)| shown.elapsed() < DURATION)
+            .map(|(message, _)| message.as_str())
+    }
+}
+
+#[test]
+fn current_works() {
+    let mut toast = Toast::default();
+    assert_eq!(toast.current(), None);
+    toast.show("Cleaned 14 topics".to_owned());
+    assert_eq!(toast.current(), Some("Cleaned 14 topics"));
+}
+
+#[test]
+fn current_expires() {
+    let mut toast = Toast::default();
+    if let Some(shown) = Instant::now().checked_sub(DURATION) {
+        toast.message = Some(("Cleaned 14 topics".to_owned(), shown));
+        assert_eq!(toast.current(), None);
+    }
+}
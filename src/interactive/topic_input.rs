@@ -0,0 +1,161 @@
+use std::cmp::Reverse;
+
+use ratatui::style::{Color, Style};
+use ratatui::text::{Line, Span};
+
+use crate::interactive::mqtt_history::STYLE_DARKGRAY;
+
+/// Amount of suggestions shown below the input
+const MAX_SUGGESTIONS: usize = 8;
+
+/// Text input for a topic which suggests the known topics matching the input fuzzy
+#[derive(Default)]
+pub struct TopicInput {
+    pub input: String,
+    suggestions: Vec<String>,
+    /// Suggestion selected with the arrow keys
+    selected: Option<usize>,
+}
+
+impl TopicInput {
+    pub fn push(&mut self, char: char, topics: &[&String]) {
+        self.input.push(char);
+        self.update_suggestions(topics);
+    }
+
+    pub fn pop(&mut self, topics: &[&String]) {
+        self.input.pop();
+        self.update_suggestions(topics);
+    }
+
+    fn update_suggestions(&mut self, topics: &[&String]) {
+        self.selected = None;
+        if self.input.is_empty() {
+            self.suggestions.clear();
+            return;
+        }
+        let mut scored = topics
+            .iter()
+            .filter_map(|topic| fuzzy_score(&self.input, topic).map(|score| (score, *topic)))
+            .collect::<Vec<_>>();
+        scored.sort_by_key(|(score, topic)| (Reverse(*score), topic.len(), *topic));
+        self.suggestions = scored
+            .into_iter()
+            .take(MAX_SUGGESTIONS)
+            .map(|(_, topic)| topic.clone())
+            .collect();
+    }
+
+    pub fn select_next(&mut self) {
+        if self.suggestions.is_empty() {
+            return;
+        }
+        let last = self.suggestions.len() - 1;
+        self.selected = Some(self.selected.map_or(0, |index| (index + 1).min(last)));
+    }
+
+    pub fn select_previous(&mut self) {
+        self.selected = self.selected.and_then(|index| index.checked_sub(1));
+    }
+
+    /// Replace the input with the selected or the best suggestion.
+    ///
+    /// Returns `false` when there is no suggestion.
+    pub fn complete(&mut self, topics: &[&String]) -> bool {
+        let Some(suggestion) = self
+            .suggestions
+            .get(self.selected.unwrap_or_default())
+            .cloned()
+        else {
+            return false;
+        };
+        self.input = suggestion;
+        self.update_suggestions(topics);
+        true
+    }
+
+    /// The selected suggestion or the input as typed
+    pub fn value(&self) -> &str {
+        self.selected
+            .and_then(|index| self.suggestions.get(index))
+            .unwrap_or(&self.input)
+    }
+
+    pub fn suggestion_lines(&self) -> Vec<Line<'_>> {
+        let selected_style = Style::new().fg(Color::Black).bg(Color::LightGreen);
+        self.suggestions
+            .iter()
+            .enumerate()
+            .map(|(index, topic)| {
+                if self.selected == Some(index) {
+                    Line::styled(topic.as_str(), selected_style)
+                } else {
+                    Line::from(Span::styled(topic.as_str(), STYLE_DARKGRAY))
+                }
+            })
+            .collect()
+    }
+}
+
+/// Score how well the topic matches the query when all its chars appear in order ignoring the case.
+///
+/// Consecutive chars and chars at the start of a topic level score higher.
+fn fuzzy_score(query: &str, topic: &str) -> Option<usize> {
+    let mut score: usize = 0;
+    let mut topic_chars = topic.chars().flat_map(char::to_lowercase).enumerate();
+    let mut last_match: Option<usize> = None;
+    let mut previous_char = '/';
+    for query_char in query.chars().flat_map(char::to_lowercase) {
+        loop {
+            let (index, char) = topic_chars.next()?;
+            let level_start = previous_char == '/';
+            previous_char = char;
+            if char != query_char {
+                continue;
+            }
+            score += 1;
+            if last_match.is_some_and(|last| last + 1 == index) {
+                score += 3;
+            }
+            if level_start {
+                score += 2;
+            }
+            last_match = Some(index);
+            break;
+        }
+    }
+    Some(score)
+}
+
+#[test]
+fn fuzzy_score_works() {
+    assert_eq!(fuzzy_score("xyz", "zigbee2mqtt/living"), None);
+    assert_eq!(fuzzy_score("ZL", "zigbee2mqtt/living"), Some(6));
+    assert!(fuzzy_score("liv", "zigbee2mqtt/living") > fuzzy_score("liv", "zigbee2mqtt/lamp/iv"));
+}
+
+#[test]
+fn suggestions_work() {
+    let topics = [
+        "zigbee2mqtt/living/lamp",
+        "zigbee2mqtt/kitchen",
+        "tasmota/living",
+    ]
+    .map(ToOwned::to_owned);
+    let topics = topics.iter().collect::<Vec<_>>();
+    let mut input = TopicInput::default();
+    for char in "zliv".chars() {
+        input.push(char, &topics);
+    }
+    assert_eq!(input.suggestions, ["zigbee2mqtt/living/lamp"]);
+    input.pop(&topics);
+    input.pop(&topics);
+    input.pop(&topics);
+    assert_eq!(input.suggestions.len(), 2);
+    assert_eq!(input.suggestions[0], "zigbee2mqtt/kitchen");
+    input.select_next();
+    input.select_next();
+    assert_eq!(input.value(), "zigbee2mqtt/living/lamp");
+    assert!(input.complete(&topics));
+    assert_eq!(input.input, "zigbee2mqtt/living/lamp");
+}
@@ -1,20 +1,134 @@
+use std::collections::BTreeSet;
+
 use ratatui::layout::{Alignment, Rect};
 use ratatui::style::{Color, Style};
+use ratatui::text::{Line, Span};
 use ratatui::widgets::{Block, BorderType, Scrollbar, ScrollbarOrientation};
 use ratatui::Frame;
-use tui_tree_widget::{Tree, TreeState};
+use tui_tree_widget::{Tree, TreeItem, TreeState};
 
-use super::mqtt_history::MqttHistory;
-use super::ui::{focus_color, BORDERS_TOP_RIGHT};
+use super::mqtt_history::{subscription_color, MqttHistory, MAX_LEAF_WIDTH};
+use super::topic_table::TopicTable;
+use super::ui::{self, focus_color, BORDERS_TOP_RIGHT};
 
 #[derive(Default)]
 pub struct TopicOverview {
     pub last_area: Rect,
+    /// Topics marked for bulk operations
+    pub marked: BTreeSet<String>,
     pub search: String,
+    /// Only show the topics matching the search instead of only highlighting them
+    pub filter: bool,
+    /// Hide the broker internal topic trees like `$SYS`
+    pub hide_system_topics: bool,
+    /// Previous searches of this session, the most recent one last
+    search_history: Vec<String>,
+    /// Position in the `search_history` while cycling through it
+    search_history_index: Option<usize>,
     pub state: TreeState<String>,
+    /// Show the flat table of all topics instead of the tree
+    pub flat: bool,
+    pub table: TopicTable,
 }
 
 impl TopicOverview {
+    /// Select the topic and open its parents to have it visible.
+    ///
+    /// Returns `true` when the selection changed.
+    pub fn select_topic(&mut self, topic: Vec<String>) -> bool {
+        for i in 0..topic.len() {
+            self.state.open(topic[0..i].to_vec());
+        }
+        self.state.select(topic)
+    }
+
+    /// Toggle the mark of the selected topic.
+    ///
+    /// Returns `true` when there was a topic selected.
+    pub fn toggle_mark(&mut self) -> bool {
+        let Some(topic) = self.get_selected() else {
+            return false;
+        };
+        if !self.marked.remove(&topic) {
+            self.marked.insert(topic);
+        }
+        true
+    }
+
+    /// Remember the current search for the search history.
+    pub fn remember_search(&mut self) {
+        self.search_history_index = None;
+        if self.search.is_empty() {
+            return;
+        }
+        self.search_history.retain(|search| search != &self.search);
+        self.search_history.push(self.search.clone());
+    }
+
+    /// The most recent search which is still used after the search bar was closed.
+    pub fn last_search(&self) -> Option<&str> {
+        self.search_history.last().map(String::as_str)
+    }
+
+    /// Replace the search with an older one of the search history.
+    ///
+    /// Returns `true` when the search changed.
+    pub fn search_history_older(&mut self) -> bool {
+        let index = self
+            .search_history_index
+            .unwrap_or(self.search_history.len());
+        let Some(index) = index.checked_sub(1) else {
+            return false;
+        };
+        self.search_history_index = Some(index);
+        self.search.clone_from(&self.search_history[index]);
+        true
+    }
+
+    /// Replace the search with a more recent one of the search history.
+    /// Going past the most recent one clears the search.
+    ///
+    /// Returns `true` when the search changed.
+    pub fn search_history_newer(&mut self) -> bool {
+        let Some(index) = self.search_history_index else {
+            return false;
+        };
+        let index = index + 1;
+        if let Some(search) = self.search_history.get(index) {
+            self.search_history_index = Some(index);
+            self.search.clone_from(search);
+        } else {
+            self.search_history_index = None;
+            self.search = String::new();
+        }
+        true
+    }
+
+    /// Typing in the search stops cycling through the search history.
+    pub fn stop_search_history(&mut self) {
+        self.search_history_index = None;
+    }
+
+    /// Move the selection in the flat table by the amount of rows.
+    ///
+    /// Returns `true` when the selection changed.
+    pub fn select_in_table(&mut self, rows: isize) -> bool {
+        let selected = self.get_selected();
+        let Some(topic) = self.table.topic_away(selected.as_deref(), rows) else {
+            return false;
+        };
+        let topic = topic.split('/').map(ToOwned::to_owned).collect();
+        self.select_topic(topic)
+    }
+
+    /// Move the selection in the flat table by a third of its height.
+    ///
+    /// Returns `true` when the selection changed.
+    pub fn page_in_table(&mut self, down: bool) -> bool {
+        let page_jump = isize::try_from(self.last_area.height / 3).unwrap_or(1);
+        self.select_in_table(if down { page_jump } else { -page_jump })
+    }
+
     pub fn get_selected(&self) -> Option<String> {
         let selected = self.state.selected();
         if selected.is_empty() {
@@ -23,11 +137,73 @@ impl TopicOverview {
         Some(selected.join("/"))
     }
 
-    pub fn draw(&mut self, frame: &mut Frame, area: Rect, history: &MqttHistory, has_focus: bool) {
-        let (topic_amount, message_amount, tree_items) = history.to_tree_items();
-        let title = format!("Topics ({topic_amount}, {message_amount} messages)");
+    /// Width of the overview next to the details.
+    ///
+    /// Uses at least a third of the available width and grows up to half of it in order to fit the topics of the deepest visible entry.
+    /// The flat table always uses half of it.
+    pub fn width(&self, tree_items: &[TreeItem<'_, String>], available: u16) -> u16 {
+        if self.flat {
+            return available / 2;
+        }
+        let required = self
+            .state
+            .flatten(tree_items)
+            .iter()
+            .map(|flattened| {
+                let leaf = flattened
+                    .identifier
+                    .last()
+                    .map_or(0, |leaf| ui::width(leaf).min(MAX_LEAF_WIDTH));
+                // Indentation of two per depth, the node symbol and the leaf
+                flattened.depth() * 2 + 2 + leaf
+            })
+            .max()
+            .unwrap_or(0)
+            // Border and scrollbar
+            .saturating_add(2);
+        let required = u16::try_from(required).unwrap_or(u16::MAX);
+        required.clamp(available / 3, available / 2)
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn draw(
+        &mut self,
+        frame: &mut Frame,
+        area: Rect,
+        topic_amount: usize,
+        message_amount: usize,
+        tree_items: &[TreeItem<'_, String>],
+        subscriptions: &[String],
+        has_focus: bool,
+    ) {
+        let title = if self.marked.is_empty() {
+            format!("Topics ({topic_amount}, {message_amount} messages)")
+        } else {
+            let marked = self.marked.len();
+            format!("Topics ({topic_amount}, {message_amount} messages, {marked} marked)")
+        };
         let focus_color = focus_color(has_focus);
-        let widget = Tree::new(&tree_items)
+        let mut block = Block::new()
+            .border_type(BorderType::Rounded)
+            .borders(BORDERS_TOP_RIGHT)
+            .border_style(Style::new().fg(focus_color))
+            .title_alignment(Alignment::Center)
+            .title(title);
+        // Legend of the subscription markers of the topic roots
+        if subscriptions.len() > 1 {
+            let legend = subscriptions
+                .iter()
+                .enumerate()
+                .flat_map(|(index, filter)| {
+                    [
+                        Span::styled(" ● ", Style::new().fg(subscription_color(index))),
+                        Span::raw(filter.as_str()),
+                    ]
+                })
+                .collect::<Vec<_>>();
+            block = block.title_bottom(Line::from(legend));
+        }
+        let widget = Tree::new(tree_items)
             .unwrap()
             .experimental_scrollbar(Some(
                 Scrollbar::new(ScrollbarOrientation::VerticalRight)
@@ -36,15 +212,57 @@ impl TopicOverview {
                     .track_symbol(None),
             ))
             .highlight_style(Style::new().fg(Color::Black).bg(focus_color))
-            .block(
-                Block::new()
-                    .border_type(BorderType::Rounded)
-                    .borders(BORDERS_TOP_RIGHT)
-                    .border_style(Style::new().fg(focus_color))
-                    .title_alignment(Alignment::Center)
-                    .title(title),
-            );
+            .block(block);
         frame.render_stateful_widget(widget, area, &mut self.state);
         self.last_area = area;
     }
+
+    pub fn draw_table(
+        &mut self,
+        frame: &mut Frame,
+        area: Rect,
+        history: &MqttHistory,
+        has_focus: bool,
+    ) {
+        let selected = self.get_selected();
+        self.table.draw(
+            frame,
+            area,
+            history,
+            &self.search,
+            self.filter,
+            self.hide_system_topics,
+            &self.marked,
+            selected.as_deref(),
+            has_focus,
+        );
+        self.last_area = area;
+    }
+}
+
+#[test]
+fn search_history_works() {
+    let mut overview = TopicOverview::default();
+    assert!(!overview.search_history_older());
+    assert_eq!(overview.last_search(), None);
+
+    for search in ["foo", "bar", "foo"] {
+        overview.search = search.to_owned();
+        overview.remember_search();
+    }
+    assert_eq!(overview.last_search(), Some("foo"));
+
+    overview.search = String::new();
+    assert!(overview.search_history_older());
+    assert_eq!(overview.search, "foo");
+    assert!(overview.search_history_older());
+    assert_eq!(overview.search, "bar");
+    assert!(!overview.search_history_older());
+    assert_eq!(overview.search, "bar");
+
+    assert!(overview.search_history_newer());
+    assert_eq!(overview.search, "foo");
+    assert!(overview.search_history_newer());
+    assert_eq!(overview.search, "");
+    assert!(!overview.search_history_newer());
 }
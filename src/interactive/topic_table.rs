@@ -0,0 +1,301 @@
+use std::cmp::Ordering;
+use std::collections::BTreeSet;
+
+use chrono::NaiveDateTime;
+use ratatui::layout::{Alignment, Constraint, Position, Rect};
+use ratatui::style::{Color, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, BorderType, Row, Table, TableState};
+use ratatui::Frame;
+
+use super::mqtt_history::{MqttHistory, STYLE_DARKGRAY, STYLE_MARKED, STYLE_RETAINED};
+use super::ui::{self, focus_color, BORDERS_TOP_RIGHT, STYLE_BOLD};
+use crate::format;
+
+/// Border and header above the first topic
+const ROWS_ABOVE_TOPICS: u16 = 2;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum SortColumn {
+    #[default]
+    Topic,
+    Value,
+    Age,
+    Messages,
+    Rate,
+}
+
+impl SortColumn {
+    const ALL: [Self; 5] = [
+        Self::Topic,
+        Self::Value,
+        Self::Age,
+        Self::Messages,
+        Self::Rate,
+    ];
+
+    pub const fn next(self) -> Self {
+        match self {
+            Self::Topic => Self::Value,
+            Self::Value => Self::Age,
+            Self::Age => Self::Messages,
+            Self::Messages => Self::Rate,
+            Self::Rate => Self::Topic,
+        }
+    }
+
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Topic => "Topic",
+            Self::Value => "Value",
+            Self::Age => "Age",
+            Self::Messages => "Messages",
+            Self::Rate => "Rate",
+        }
+    }
+}
+
+struct TopicRow {
+    topic: String,
+    value: String,
+    /// `None` for retained messages as their age is unknown
+    time: Option<NaiveDateTime>,
+    messages: usize,
+    /// Messages within the last minute
+    rate: usize,
+}
+
+impl TopicRow {
+    fn compare(&self, other: &Self, column: SortColumn) -> Ordering {
+        match column {
+            SortColumn::Topic => self.topic.cmp(&other.topic),
+            SortColumn::Value => {
+                match (
+                    self.value.trim().parse::<f64>(),
+                    other.value.trim().parse::<f64>(),
+                ) {
+                    (Ok(a), Ok(b)) => a.total_cmp(&b),
+                    _ => self.value.cmp(&other.value),
+                }
+            }
+            // The most recent is the youngest
+            SortColumn::Age => other.time.cmp(&self.time),
+            SortColumn::Messages => self.messages.cmp(&other.messages),
+            SortColumn::Rate => self.rate.cmp(&other.rate),
+        }
+        .then_with(|| self.topic.cmp(&other.topic))
+    }
+}
+
+fn topic_rows(
+    history: &MqttHistory,
+    search: &str,
+    filter: bool,
+    hide_system_topics: bool,
+    now: NaiveDateTime,
+) -> Vec<TopicRow> {
+    history
+        .get_all_topics()
+        .into_iter()
+        .filter(|topic| !(hide_system_topics && topic.starts_with('$')))
+        .filter(|topic| !filter || topic.to_lowercase().contains(search))
+        .filter_map(|topic| {
+            let entries = history.get(topic)?;
+            let last = entries.last()?;
+            let rate = entries
+                .iter()
+                .rev()
+                .map_while(|entry| entry.time.as_optional())
+                .take_while(|time| (now - **time).num_seconds() < 60)
+                .count();
+            Some(TopicRow {
+                topic: topic.clone(),
+                value: last.payload.to_string(),
+                time: last.time.as_optional().copied(),
+                messages: entries.len(),
+                rate,
+            })
+        })
+        .collect()
+}
+
+/// Flat list of every topic with messages as an alternative to the topic tree
+#[derive(Default)]
+pub struct TopicTable {
+    pub sort: SortColumn,
+    pub descending: bool,
+    state: TableState,
+    /// Topics in the order they were drawn the last time
+    topics: Vec<String>,
+}
+
+impl TopicTable {
+    /// The topic the given amount of rows away from the selected one.
+    /// Without a selection the first topic is the start.
+    pub fn topic_away(&self, selected: Option<&str>, rows: isize) -> Option<&String> {
+        let index = selected
+            .and_then(|selected| self.topics.iter().position(|topic| topic == selected))
+            .map_or(0, |index| index.saturating_add_signed(rows));
+        self.topics
+            .get(index.min(self.topics.len().saturating_sub(1)))
+    }
+
+    /// The topic drawn at the position within the area
+    pub fn topic_at(&self, area: Rect, position: Position) -> Option<&String> {
+        if !area.contains(position) {
+            return None;
+        }
+        let row = position
+            .y
+            .saturating_sub(area.y)
+            .checked_sub(ROWS_ABOVE_TOPICS)?;
+        self.topics
+            .get(self.state.offset().saturating_add(usize::from(row)))
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn draw(
+        &mut self,
+        frame: &mut Frame,
+        area: Rect,
+        history: &MqttHistory,
+        search: &str,
+        filter: bool,
+        hide_system_topics: bool,
+        marked: &BTreeSet<String>,
+        selected: Option<&str>,
+        has_focus: bool,
+    ) {
+        let now = chrono::Local::now().naive_local();
+        let mut rows = topic_rows(history, search, filter, hide_system_topics, now);
+        rows.sort_by(|a, b| {
+            let ordering = a.compare(b, self.sort);
+            if self.descending {
+                ordering.reverse()
+            } else {
+                ordering
+            }
+        });
+        self.topics = rows.iter().map(|row| row.topic.clone()).collect();
+        self.state
+            .select(selected.and_then(|selected| self.topics.iter().position(|t| t == selected)));
+
+        let title = if marked.is_empty() {
+            format!("Topics ({})", rows.len())
+        } else {
+            format!("Topics ({}, {} marked)", rows.len(), marked.len())
+        };
+        let header = SortColumn::ALL.into_iter().map(|column| {
+            if column != self.sort {
+                return column.name().to_owned();
+            }
+            let arrow = if self.descending { '▼' } else { '▲' };
+            format!("{} {arrow}", column.name())
+        });
+        // A third of what is left by the fixed columns, their spacing and the border
+        let value_width = usize::from(area.width.saturating_sub(31) / 3);
+        let table_rows = rows.into_iter().map(|row| {
+            let style = if search.is_empty() || row.topic.to_lowercase().contains(search) {
+                Style::new()
+            } else {
+                STYLE_DARKGRAY
+            };
+            let topic = if marked.contains(&row.topic) {
+                Line::from(vec![Span::styled("* ", STYLE_MARKED), Span::raw(row.topic)])
+            } else {
+                Line::raw(row.topic)
+            };
+            let age = row.time.map_or_else(
+                || Span::styled("retained", STYLE_RETAINED),
+                |time| Span::raw(format::age((now - time).num_seconds())),
+            );
+            Row::new(vec![
+                topic,
+                Line::raw(ui::ellipsize(&row.value, value_width).into_owned()),
+                Line::from(age),
+                Line::raw(row.messages.to_string()),
+                Line::raw(format!("{}/min", row.rate)),
+            ])
+            .style(style)
+        });
+
+        let focus_color = focus_color(has_focus);
+        let table = Table::new(
+            table_rows,
+            [
+                Constraint::Fill(2),
+                Constraint::Fill(1),
+                Constraint::Length(8),
+                Constraint::Length(10),
+                Constraint::Length(8),
+            ],
+        )
+        .header(Row::new(header).style(STYLE_BOLD))
+        .highlight_style(Style::new().fg(Color::Black).bg(focus_color))
+        .block(
+            Block::new()
+                .border_type(BorderType::Rounded)
+                .borders(BORDERS_TOP_RIGHT)
+                .border_style(Style::new().fg(focus_color))
+                .title_alignment(Alignment::Center)
+                .title(title),
+        );
+        frame.render_stateful_widget(table, area, &mut self.state);
+    }
+}
+
+#[test]
+fn rows_are_sorted_and_filtered() {
+    let mut history = MqttHistory::new();
+    history.add(
+        "b/temp".to_owned(),
+        crate::mqtt::HistoryEntry::example("9", crate::mqtt::Time::Retained),
+    );
+    history.add(
+        "a/temp".to_owned(),
+        crate::mqtt::HistoryEntry::example("10", crate::mqtt::Time::new_now(false)),
+    );
+    history.add(
+        "a/temp".to_owned(),
+        crate::mqtt::HistoryEntry::example("12", crate::mqtt::Time::new_now(false)),
+    );
+    history.add(
+        "$SYS/uptime".to_owned(),
+        crate::mqtt::HistoryEntry::example("42", crate::mqtt::Time::new_now(false)),
+    );
+
+    let now = chrono::Local::now().naive_local();
+    let mut rows = topic_rows(&history, "", false, true, now);
+    assert_eq!(rows.len(), 2);
+    rows.sort_by(|a, b| a.compare(b, SortColumn::Value));
+    assert_eq!(rows[0].topic, "b/temp");
+    assert_eq!(rows[1].value, "12");
+    assert_eq!(rows[1].messages, 2);
+    assert_eq!(rows[1].rate, 2);
+    assert_eq!(rows[0].rate, 0);
+    rows.sort_by(|a, b| a.compare(b, SortColumn::Age));
+    assert_eq!(rows[0].topic, "a/temp");
+
+    let rows = topic_rows(&history, "sys", true, false, now);
+    assert_eq!(rows.len(), 1);
+    assert_eq!(rows[0].topic, "$SYS/uptime");
+}
+
+#[test]
+fn topic_away_works() {
+    let table = TopicTable {
+        topics: vec!["a".to_owned(), "b".to_owned(), "c".to_owned()],
+        ..TopicTable::default()
+    };
+    assert_eq!(table.topic_away(None, 1).unwrap(), "a");
+    assert_eq!(table.topic_away(Some("a"), 1).unwrap(), "b");
+    assert_eq!(table.topic_away(Some("b"), isize::MAX).unwrap(), "c");
+    assert_eq!(table.topic_away(Some("b"), isize::MIN).unwrap(), "a");
+    assert!(TopicTable::default().topic_away(None, 1).is_none());
+
+    let area = Rect::new(0, 5, 40, 10);
+    assert_eq!(table.topic_at(area, Position::new(3, 7)).unwrap(), "a");
+    assert_eq!(table.topic_at(area, Position::new(3, 9)).unwrap(), "c");
+    assert!(table.topic_at(area, Position::new(3, 6)).is_none());
+    assert!(table.topic_at(area, Position::new(3, 10)).is_none());
+}
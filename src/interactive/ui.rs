@@ -1,16 +1,36 @@
+use std::borrow::Cow;
+
+use ratatui::buffer::Buffer;
 use ratatui::layout::Rect;
 use ratatui::style::{Color, Modifier, Style};
 use ratatui::widgets::Borders;
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
 
 pub const BORDERS_TOP_RIGHT: Borders = Borders::TOP.union(Borders::RIGHT);
 pub const STYLE_BOLD: Style = Style::new().add_modifier(Modifier::BOLD);
+pub const STYLE_WARNING: Style = Style::new()
+    .fg(Color::Black)
+    .bg(Color::Yellow)
+    .add_modifier(Modifier::BOLD);
 
 pub enum ElementInFocus {
     TopicOverview,
     TopicSearch,
     Payload,
+    PayloadSearch,
     HistoryTable,
-    CleanRetainedPopup(String),
+    BrokerSwitchPopup(super::broker_switch::Popup),
+    CleanRetainedPopup(super::clean_retained::Popup),
+    CommandPalette(super::command_palette::Palette),
+    ConvertPopup(super::convert::Popup),
+    MarkerPopup(super::marker::Popup),
+    PayloadSizesPopup,
+    SettingsPopup(super::settings::Popup),
+    SubscribePopup(super::subscribe::Popup),
+    TasmotaCommandPopup(super::tasmota::CommandPopup),
+    Dashboard,
+    Zigbee2MqttDashboard,
 }
 
 pub const fn focus_color(has_focus: bool) -> Color {
@@ -21,6 +41,22 @@ pub const fn focus_color(has_focus: bool) -> Color {
     }
 }
 
+/// Accessibility mode: the focus color gets bold and underlined to not only rely on the color,
+/// dark gray text gets more contrast and nothing blinks or is dimmed.
+pub fn high_contrast(buffer: &mut Buffer) {
+    let focus = focus_color(true);
+    for cell in &mut buffer.content {
+        cell.modifier
+            .remove(Modifier::SLOW_BLINK | Modifier::RAPID_BLINK | Modifier::DIM);
+        if cell.fg == Color::DarkGray {
+            cell.fg = Color::Gray;
+        }
+        if cell.bg == focus {
+            cell.modifier.insert(Modifier::BOLD | Modifier::UNDERLINED);
+        }
+    }
+}
+
 pub const fn split_area_vertically(area: Rect, height_first: u16) -> (Rect, Rect) {
     let first = Rect {
         height: height_first,
@@ -34,6 +70,34 @@ pub const fn split_area_vertically(area: Rect, height_first: u16) -> (Rect, Rect
     (first, second)
 }
 
+/// Columns the text takes in the terminal. Emoji and CJK take two, combining marks none.
+pub fn width(text: &str) -> usize {
+    UnicodeWidthStr::width(text)
+}
+
+/// Shorten the text to the given width by replacing the end with an ellipsis.
+///
+/// Grapheme clusters like emoji with modifiers or letters with combining marks are never split.
+pub fn ellipsize(text: &str, max_width: usize) -> Cow<'_, str> {
+    if width(text) <= max_width {
+        return Cow::Borrowed(text);
+    }
+    // The ellipsis takes one column
+    let available = max_width.saturating_sub(1);
+    let mut used: usize = 0;
+    let mut end = 0;
+    for (index, grapheme) in text.grapheme_indices(true) {
+        used = used.saturating_add(width(grapheme));
+        if used > available {
+            break;
+        }
+        end = index + grapheme.len();
+    }
+    let mut shortened = text[..end].to_owned();
+    shortened.push('…');
+    Cow::Owned(shortened)
+}
+
 #[test]
 pub fn split_vertically_example() {
     let area = Rect::new(5, 10, 10, 14);
@@ -41,3 +105,49 @@ pub fn split_vertically_example() {
     assert_eq!(first, Rect::new(5, 10, 10, 7));
     assert_eq!(second, Rect::new(5, 17, 10, 7));
 }
+
+#[test]
+fn high_contrast_works() {
+    let mut buffer = Buffer::empty(Rect::new(0, 0, 3, 1));
+    buffer.set_string(0, 0, "a", Style::new().bg(focus_color(true)));
+    buffer.set_string(
+        1,
+        0,
+        "b",
+        Style::new()
+            .fg(Color::DarkGray)
+            .add_modifier(Modifier::SLOW_BLINK),
+    );
+    buffer.set_string(2, 0, "c", Style::new().bg(focus_color(false)));
+    high_contrast(&mut buffer);
+    assert_eq!(
+        buffer.content[0].modifier,
+        Modifier::BOLD | Modifier::UNDERLINED
+    );
+    assert_eq!(buffer.content[1].fg, Color::Gray);
+    assert_eq!(buffer.content[1].modifier, Modifier::empty());
+    assert_eq!(buffer.content[2].modifier, Modifier::empty());
+}
+
+#[test]
+fn ellipsize_short_text_unchanged() {
+    assert_eq!(ellipsize("foo", 3), "foo");
+    assert_eq!(ellipsize("", 0), "");
+}
+
+#[test]
+fn ellipsize_long_text() {
+    assert_eq!(ellipsize("foobar", 4), "foo…");
+    assert_eq!(ellipsize("äöüß", 2), "ä…");
+}
+
+#[test]
+fn ellipsize_wide_and_combined() {
+    assert_eq!(width("温度"), 4);
+    assert_eq!(ellipsize("温度センサー", 5), "温度…");
+    assert_eq!(ellipsize("温度センサー", 4), "温…");
+    // The skin tone modifier is not split from the emoji
+    assert_eq!(ellipsize("👍🏽👍🏽", 6), "👍🏽…");
+    assert_eq!(ellipsize("👍🏽👍🏽", 4), "…");
+    assert_eq!(ellipsize("e\u{301}e\u{301}e\u{301}", 2), "e\u{301}…");
+}
@@ -0,0 +1,266 @@
+use ratatui::layout::{Alignment, Constraint, Rect};
+use ratatui::style::{Color, Style};
+use ratatui::widgets::{Block, BorderType, Row, Table, TableState};
+use ratatui::Frame;
+
+use crate::interactive::mqtt_history::MqttHistory;
+use crate::interactive::ui::{focus_color, BORDERS_TOP_RIGHT, STYLE_BOLD};
+use crate::payload::Payload;
+
+const DEVICES_TOPIC_SUFFIX: &str = "/bridge/devices";
+
+struct Device {
+    friendly_name: String,
+    kind: String,
+    model: String,
+    link_quality: Option<u64>,
+    battery: Option<f64>,
+    last_seen: Option<String>,
+}
+
+impl Device {
+    fn from_bridge(device: &serde_json::Value) -> Option<Self> {
+        let kind = device.get("type")?.as_str()?;
+        if kind == "Coordinator" {
+            return None;
+        }
+        let model = device
+            .pointer("/definition/model")
+            .or_else(|| device.get("model_id"))
+            .and_then(serde_json::Value::as_str)
+            .unwrap_or_default();
+        Some(Self {
+            friendly_name: device.get("friendly_name")?.as_str()?.to_owned(),
+            kind: kind.to_owned(),
+            model: model.to_owned(),
+            link_quality: None,
+            battery: None,
+            last_seen: None,
+        })
+    }
+
+    fn update_state(&mut self, history: &MqttHistory, base_topic: &str) {
+        let Some(entry) = history
+            .get(&format!("{base_topic}/{}", self.friendly_name))
+            .and_then(|entries| entries.last())
+        else {
+            return;
+        };
+        self.last_seen = Some(entry.time.to_string());
+        let Payload::Json(state) = &*entry.payload else {
+            return;
+        };
+        self.link_quality = state.get("linkquality").and_then(serde_json::Value::as_u64);
+        self.battery = state.get("battery").and_then(serde_json::Value::as_f64);
+        // Depending on the zigbee2mqtt advanced.last_seen setting it's an ISO string or epoch milliseconds
+        match state.get("last_seen") {
+            Some(serde_json::Value::String(last_seen)) => self.last_seen = Some(last_seen.clone()),
+            Some(serde_json::Value::Number(millis)) => {
+                if let Some(last_seen) = millis
+                    .as_i64()
+                    .and_then(chrono::DateTime::from_timestamp_millis)
+                {
+                    let last_seen = last_seen.with_timezone(&chrono::Local);
+                    self.last_seen = Some(last_seen.format("%F %T").to_string());
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Find the zigbee2mqtt base topic by its `bridge/devices` topic
+pub fn find_base_topic(history: &MqttHistory) -> Option<String> {
+    history
+        .topics()
+        .filter_map(|topic| topic.strip_suffix(DEVICES_TOPIC_SUFFIX))
+        .filter(|base| is_base_topic(history, base))
+        .min()
+        .map(ToOwned::to_owned)
+}
+
+/// The latest `bridge/devices` message is a device list
+fn is_base_topic(history: &MqttHistory, base_topic: &str) -> bool {
+    history
+        .get(&format!("{base_topic}{DEVICES_TOPIC_SUFFIX}"))
+        .and_then(|entries| entries.last())
+        .is_some_and(|entry| matches!(&*entry.payload, Payload::Json(serde_json::Value::Array(_))))
+}
+
+/// The base topic is only searched again when the history changed
+#[derive(Default)]
+pub struct BaseTopicCache {
+    generation: Option<u64>,
+    base_topic: Option<String>,
+}
+
+impl BaseTopicCache {
+    pub fn update(&mut self, history: &MqttHistory) {
+        if self.generation != Some(history.generation()) {
+            self.generation = Some(history.generation());
+            self.base_topic = find_base_topic(history);
+        }
+    }
+
+    pub fn get(&self) -> Option<&str> {
+        self.base_topic.as_deref()
+    }
+}
+
+fn devices(history: &MqttHistory, base_topic: &str) -> Option<Vec<Device>> {
+    let entry = history
+        .get(&format!("{base_topic}{DEVICES_TOPIC_SUFFIX}"))?
+        .last()?;
+    let Payload::Json(serde_json::Value::Array(bridge_devices)) = &*entry.payload else {
+        return None;
+    };
+    let mut devices = bridge_devices
+        .iter()
+        .filter_map(Device::from_bridge)
+        .collect::<Vec<_>>();
+    devices.sort_by(|a, b| a.friendly_name.cmp(&b.friendly_name));
+    for device in &mut devices {
+        device.update_state(history, base_topic);
+    }
+    Some(devices)
+}
+
+/// Device list of zigbee2mqtt based on its `bridge/devices` topic and the state topics of the devices
+#[derive(Default)]
+pub struct Dashboard {
+    pub base_topic: String,
+    pub state: TableState,
+}
+
+impl Dashboard {
+    /// Topic of the selected device
+    pub fn get_selected(&self, history: &MqttHistory) -> Option<String> {
+        let devices = devices(history, &self.base_topic)?;
+        let device = devices.get(self.state.selected()?)?;
+        Some(format!("{}/{}", self.base_topic, device.friendly_name))
+    }
+
+    pub fn draw(&mut self, frame: &mut Frame, area: Rect, history: &MqttHistory) {
+        let devices = devices(history, &self.base_topic).unwrap_or_default();
+        let title = format!(
+            "Zigbee2MQTT {} ({} devices)",
+            self.base_topic,
+            devices.len()
+        );
+
+        if let Some(selection) = self.state.selected_mut() {
+            *selection = (*selection).min(devices.len().saturating_sub(1));
+        }
+
+        let rows = devices.into_iter().map(|device| {
+            Row::new(vec![
+                device.friendly_name,
+                device.kind,
+                device.model,
+                device
+                    .link_quality
+                    .map_or_else(String::new, |link_quality| link_quality.to_string()),
+                device
+                    .battery
+                    .map_or_else(String::new, |battery| format!("{battery}%")),
+                device.last_seen.unwrap_or_default(),
+            ])
+        });
+
+        let focus_color = focus_color(true);
+        let table = Table::new(
+            rows,
+            [
+                Constraint::Fill(3),
+                Constraint::Length(10),
+                Constraint::Fill(2),
+                Constraint::Length(12),
+                Constraint::Length(8),
+                Constraint::Length(25),
+            ],
+        )
+        .header(
+            Row::new([
+                "Device",
+                "Type",
+                "Model",
+                "Link quality",
+                "Battery",
+                "Last seen",
+            ])
+            .style(STYLE_BOLD),
+        )
+        .highlight_style(Style::new().fg(Color::Black).bg(focus_color))
+        .block(
+            Block::new()
+                .border_type(BorderType::Rounded)
+                .borders(BORDERS_TOP_RIGHT)
+                .border_style(Style::new().fg(focus_color))
+                .title_alignment(Alignment::Center)
+                .title(title),
+        );
+        frame.render_stateful_widget(table, area, &mut self.state);
+    }
+}
+
+#[cfg(test)]
+fn example() -> MqttHistory {
+    let mut history = MqttHistory::new();
+    history.add(
+        "z2m/bridge/devices".to_owned(),
+        crate::mqtt::HistoryEntry::example(
+            r#"[
+                {"friendly_name": "Coordinator", "type": "Coordinator"},
+                {"friendly_name": "kitchen/plug", "type": "Router", "definition": {"model": "E1603"}},
+                {"friendly_name": "door", "type": "EndDevice", "model_id": "lumi.sensor"}
+            ]"#,
+            crate::mqtt::Time::Retained,
+        ),
+    );
+    history.add(
+        "z2m/door".to_owned(),
+        crate::mqtt::HistoryEntry::example(r#"{"contact": true, "battery": 91, "linkquality": 72, "last_seen": "2024-08-01T12:00:00Z"}"#,
+            crate::mqtt::Time::Retained,
+        ),
+    );
+    history
+}
+
+#[test]
+fn finds_base_topic() {
+    let history = example();
+    assert_eq!(find_base_topic(&history).as_deref(), Some("z2m"));
+    assert!(find_base_topic(&MqttHistory::new()).is_none());
+}
+
+#[test]
+fn base_topic_cache_follows_history() {
+    let mut history = example();
+    let mut cache = BaseTopicCache::default();
+    cache.update(&history);
+    assert_eq!(cache.get(), Some("z2m"));
+    history.clear();
+    cache.update(&history);
+    assert_eq!(cache.get(), None);
+}
+
+#[test]
+fn devices_works() {
+    let history = example();
+    let devices = devices(&history, "z2m").unwrap();
+    assert_eq!(devices.len(), 2);
+
+    let door = &devices[0];
+    assert_eq!(door.friendly_name, "door");
+    assert_eq!(door.kind, "EndDevice");
+    assert_eq!(door.model, "lumi.sensor");
+    assert_eq!(door.link_quality, Some(72));
+    assert_eq!(door.battery, Some(91.0));
+    assert_eq!(door.last_seen.as_deref(), Some("2024-08-01T12:00:00Z"));
+
+    let plug = &devices[1];
+    assert_eq!(plug.friendly_name, "kitchen/plug");
+    assert_eq!(plug.model, "E1603");
+    assert_eq!(plug.link_quality, None);
+    assert_eq!(plug.last_seen, None);
+}
@@ -1,46 +1,134 @@
 use std::thread::sleep;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
-use chrono::Local;
-use rumqttc::Connection;
+use chrono::{DateTime, Local, SecondsFormat, Utc};
+use rumqttc::{Client, Connection, RecvTimeoutError};
 use serde::Serialize;
 
+use crate::cli::Timestamp;
+use crate::exit::{self, chatter};
 use crate::format;
-use crate::mqtt::Time;
-use crate::payload::Payload;
+use crate::mqtt::{Subscriptions, Time};
+use crate::payload::{Decoding, Payload};
+
+/// Receive time of a message in the format chosen with `--timestamp`
+#[derive(Serialize)]
+#[serde(untagged)]
+enum LogTime {
+    Time(Time),
+    Text(String),
+    Number(serde_json::Number),
+}
+
+impl LogTime {
+    fn new(format: Timestamp, retain: bool, now: DateTime<Local>, elapsed: Duration) -> Self {
+        if retain {
+            return Self::Time(Time::Retained);
+        }
+        match format {
+            Timestamp::Local => Self::Time(Time::Local(now.naive_local())),
+            Timestamp::Utc => {
+                Self::Text(now.with_timezone(&Utc).format("%_H:%M:%S.%3f").to_string())
+            }
+            Timestamp::Iso => Self::Text(
+                now.with_timezone(&Utc)
+                    .to_rfc3339_opts(SecondsFormat::Millis, true),
+            ),
+            Timestamp::EpochMs => Self::Number(now.timestamp_millis().into()),
+            Timestamp::Relative => {
+                #[allow(clippy::cast_precision_loss)]
+                let seconds = elapsed.as_millis() as f64 / 1000.0;
+                Self::Number(
+                    serde_json::Number::from_f64(seconds)
+                        .expect("elapsed seconds should be finite"),
+                )
+            }
+        }
+    }
+}
+
+impl std::fmt::Display for LogTime {
+    fn fmt(&self, fmt: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Time(time) => time.fmt(fmt),
+            Self::Text(text) => fmt.pad(text),
+            Self::Number(number) => fmt.pad(&number.to_string()),
+        }
+    }
+}
 
 #[derive(Serialize)]
 struct JsonLog {
-    time: Time,
+    time: LogTime,
     qos: u8,
     topic: String,
     size: usize,
     payload: Payload,
 }
 
-pub fn show(mut connection: Connection, json: bool, verbose: bool) {
-    for notification in connection.iter() {
+/// When the log ends on its own. Without any it runs until the connection is closed.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Limit {
+    /// Amount of messages to print
+    pub count: Option<usize>,
+    pub duration: Option<Duration>,
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn show(
+    client: &Client,
+    mut connection: Connection,
+    mut subscriptions: Subscriptions,
+    json: bool,
+    timestamp: Timestamp,
+    decoding: &Decoding,
+    limit: Limit,
+    verbose: bool,
+) -> anyhow::Result<()> {
+    let started = Instant::now();
+    let deadline = limit.duration.map(|duration| started + duration);
+    let mut printed: usize = 0;
+    // Messages arriving after the limit was reached and before the disconnect went out are not printed
+    let mut done = false;
+    loop {
+        let notification = match deadline.filter(|_| !done) {
+            Some(deadline) => {
+                match connection.recv_timeout(deadline.saturating_duration_since(Instant::now())) {
+                    Ok(notification) => notification,
+                    Err(RecvTimeoutError::Timeout) => {
+                        done = true;
+                        _ = client.disconnect();
+                        continue;
+                    }
+                    Err(RecvTimeoutError::Disconnected) => break,
+                }
+            }
+            None => match connection.recv() {
+                Ok(notification) => notification,
+                Err(_) => break,
+            },
+        };
         match notification {
             Ok(rumqttc::Event::Outgoing(outgoing)) => {
                 if verbose {
                     eprintln!("outgoing {outgoing:?}");
                 }
-                if outgoing == rumqttc::Outgoing::Disconnect {
-                    break;
+                match outgoing {
+                    rumqttc::Outgoing::Subscribe(pkid) => subscriptions.on_outgoing(pkid),
+                    rumqttc::Outgoing::Disconnect => break,
+                    _ => {}
                 }
             }
             Ok(rumqttc::Event::Incoming(rumqttc::Packet::Publish(publish))) => {
-                if publish.dup {
+                if publish.dup || done {
                     continue;
                 }
-                let time = if publish.retain {
-                    Time::Retained
-                } else {
-                    Time::Local(Local::now().naive_local())
-                };
+                let time = LogTime::new(timestamp, publish.retain, Local::now(), started.elapsed());
                 let topic = publish.topic;
-                let size = publish.payload.len();
-                let payload = Payload::unlimited(publish.payload.into());
+                let (size, payload) =
+                    decoding.decode(&topic, publish.payload.into(), usize::MAX, |err| {
+                        chatter!("{err:#}");
+                    });
                 if json {
                     let json = serde_json::to_string(&JsonLog {
                         time,
@@ -54,17 +142,84 @@ pub fn show(mut connection: Connection, json: bool, verbose: bool) {
                 } else {
                     let qos = format::qos(publish.qos);
                     println!("{time:12} QoS:{qos:11} {topic:50} Payload({size:>3}): {payload}");
-                };
+                }
+                printed = printed.saturating_add(1);
+                if limit.count.is_some_and(|count| printed >= count) {
+                    done = true;
+                    _ = client.disconnect();
+                }
             }
             Ok(rumqttc::Event::Incoming(packet)) => {
                 if verbose {
                     eprintln!("incoming {packet:?}");
                 }
+                match packet {
+                    // The first ConnAck was handled when connecting
+                    rumqttc::Packet::ConnAck(connack) if !connack.session_present => {
+                        chatter!("Reconnected, subscribing again");
+                        subscriptions.resubscribe(client)?;
+                    }
+                    rumqttc::Packet::SubAck(suback) => {
+                        if let Some((topic, false)) = subscriptions.on_suback(&suback) {
+                            chatter!("The broker rejected the subscription to {topic}");
+                        }
+                    }
+                    _ => {}
+                }
             }
             Err(err) => {
-                eprintln!("Connection Error: {err}");
+                chatter!("Connection Error: {err}");
                 sleep(Duration::from_millis(25));
             }
         }
     }
+
+    // Ended by the duration or a signal
+    if limit.count.is_none() && limit.duration.is_none() {
+        Ok(())
+    } else if printed == 0 {
+        Err(exit::fail(exit::Code::NoMatch, "No message was received"))
+    } else if limit.count.is_some_and(|count| printed < count) {
+        Err(exit::fail(
+            exit::Code::Partial,
+            format!("Received only {printed} messages"),
+        ))
+    } else {
+        Ok(())
+    }
+}
+
+#[test]
+fn log_time_formats() {
+    let now = DateTime::parse_from_rfc3339("2024-05-01T12:00:00.123+02:00")
+        .unwrap()
+        .with_timezone(&Local);
+    let elapsed = Duration::from_millis(12_345);
+    let format = |format, retain| {
+        let time = LogTime::new(format, retain, now, elapsed);
+        (time.to_string(), serde_json::to_string(&time).unwrap())
+    };
+    assert_eq!(
+        format(Timestamp::Utc, false),
+        ("10:00:00.123".to_owned(), "\"10:00:00.123\"".to_owned())
+    );
+    assert_eq!(
+        format(Timestamp::Iso, false),
+        (
+            "2024-05-01T10:00:00.123Z".to_owned(),
+            "\"2024-05-01T10:00:00.123Z\"".to_owned()
+        )
+    );
+    assert_eq!(
+        format(Timestamp::EpochMs, false),
+        ("1714557600123".to_owned(), "1714557600123".to_owned())
+    );
+    assert_eq!(
+        format(Timestamp::Relative, false),
+        ("12.345".to_owned(), "12.345".to_owned())
+    );
+    assert_eq!(
+        format(Timestamp::Iso, true),
+        ("RETAINED".to_owned(), "null".to_owned())
+    );
 }
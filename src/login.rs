@@ -0,0 +1,130 @@
+use std::io::{IsTerminal, Write};
+
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+
+/// Service name of the keychain entries
+const SERVICE: &str = "mqttui";
+
+/// Stored as JSON in the password of the keychain entry
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
+struct Credentials {
+    username: String,
+    password: String,
+}
+
+fn entry(profile: &str) -> anyhow::Result<keyring::Entry> {
+    keyring::Entry::new(SERVICE, profile)
+        .with_context(|| format!("Failed to access the keychain entry of the profile {profile}"))
+}
+
+/// Store the credentials of the profile in the keychain of the operating system.
+///
+/// Username and password are asked for when not given.
+pub fn login(
+    profile: &str,
+    username: Option<String>,
+    password: Option<String>,
+) -> anyhow::Result<()> {
+    let username = match username {
+        Some(username) => username,
+        None => ask("Username")?,
+    };
+    let password = match password {
+        Some(password) => password,
+        None if std::io::stdin().is_terminal() => ask_hidden("Password")?,
+        None => ask("Password")?,
+    };
+    anyhow::ensure!(!username.is_empty(), "The username can not be empty");
+
+    let secret = serde_json::to_string(&Credentials {
+        username: username.clone(),
+        password,
+    })?;
+    entry(profile)?
+        .set_password(&secret)
+        .context("Failed to store the credentials in the keychain")?;
+    eprintln!("Stored the credentials of {username} as profile {profile} in the keychain");
+    Ok(())
+}
+
+/// Read one line from stdin. The question is only shown when stdin is a terminal.
+fn ask(question: &str) -> anyhow::Result<String> {
+    if std::io::stdin().is_terminal() {
+        eprint!("{question}: ");
+        std::io::stderr().flush()?;
+    }
+    let mut line = String::new();
+    std::io::stdin().read_line(&mut line)?;
+    Ok(line.trim_end_matches(['\r', '\n']).to_owned())
+}
+
+/// Read a line from the terminal without showing the typed characters
+fn ask_hidden(question: &str) -> anyhow::Result<String> {
+    use crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+
+    eprint!("{question}: ");
+    std::io::stderr().flush()?;
+    crossterm::terminal::enable_raw_mode()?;
+    let mut line = String::new();
+    let result = loop {
+        let key = match event::read() {
+            Ok(Event::Key(key)) if key.kind != KeyEventKind::Release => key,
+            Ok(_) => continue,
+            Err(err) => break Err(err.into()),
+        };
+        match key.code {
+            KeyCode::Enter => break Ok(()),
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                break Err(anyhow::anyhow!("Aborted"));
+            }
+            KeyCode::Esc => break Err(anyhow::anyhow!("Aborted")),
+            KeyCode::Backspace => {
+                line.pop();
+            }
+            KeyCode::Char(char) => line.push(char),
+            _ => {}
+        }
+    };
+    crossterm::terminal::disable_raw_mode()?;
+    eprintln!();
+    result.map(|()| line)
+}
+
+/// Returns the (username, password) stored with `mqttui login <profile>`
+pub fn lookup(profile: &str) -> anyhow::Result<(String, String)> {
+    let secret = match entry(profile)?.get_password() {
+        Ok(secret) => secret,
+        Err(keyring::Error::NoEntry) => anyhow::bail!(
+            "No credentials are stored for the profile {profile}. Store them with `mqttui login {profile}`"
+        ),
+        Err(err) => {
+            return Err(err).context("Failed to read the credentials from the keychain");
+        }
+    };
+    parse(&secret)
+        .with_context(|| format!("The keychain entry of the profile {profile} is invalid"))
+}
+
+fn parse(secret: &str) -> anyhow::Result<(String, String)> {
+    let Credentials { username, password } = serde_json::from_str(secret)?;
+    Ok((username, password))
+}
+
+#[test]
+fn stored_credentials_can_be_parsed() {
+    let secret = serde_json::to_string(&Credentials {
+        username: "me".to_owned(),
+        password: "s3cr\"et".to_owned(),
+    })
+    .unwrap();
+    assert_eq!(
+        parse(&secret).unwrap(),
+        ("me".to_owned(), "s3cr\"et".to_owned())
+    );
+}
+
+#[test]
+fn invalid_secret_is_an_error() {
+    assert!(parse("plain password").is_err());
+}
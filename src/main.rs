@@ -1,83 +1,479 @@
+use std::io::IsTerminal;
+use std::process::ExitCode;
 use std::time::Duration;
 
+use anyhow::Context;
 use clap::Parser;
 use cli::Subcommands;
 use rumqttc::QoS;
 
+mod acl_check;
+mod analyze_retained;
+mod assert;
+mod bridge;
 mod clean_retained;
 mod cli;
+mod complete_topics;
+mod config;
+mod diff;
+mod discover;
+mod exit;
 mod format;
 mod interactive;
 mod log;
+mod login;
+mod move_retained;
 mod mqtt;
 mod payload;
+mod ping;
 mod publish;
 mod read_one;
+mod request;
+#[cfg(unix)]
+mod serve;
+mod signal;
+mod simulate;
+mod snapshot;
+mod transform;
 
-fn main() -> anyhow::Result<()> {
+fn main() -> ExitCode {
+    match run() {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("Error: {err:?}");
+            ExitCode::from(exit::code(&err))
+        }
+    }
+}
+
+#[allow(clippy::too_many_lines)]
+fn run() -> anyhow::Result<()> {
+    // Before parsing as the saved settings are provided as environment variables.
+    // A broken config file is only reported to not prevent using any subcommand.
+    let config_err = config::load_into_env().err();
     let matches = cli::Cli::parse();
+    exit::set_quiet(matches.quiet);
+    if let Some(err) = config_err {
+        exit::chatter!("Ignoring the saved settings: {err:#}");
+    }
+
+    if let Some(Subcommands::Login { name }) = &matches.subcommands {
+        return login::login(
+            name,
+            matches.mqtt_connection.username,
+            matches.mqtt_connection.password,
+        );
+    }
+
+    if let Some(Subcommands::Discover { timeout }) = matches.subcommands {
+        return discover::show(Duration::from_secs_f32(timeout));
+    }
 
-    let keep_alive = if let Some(Subcommands::CleanRetained { timeout, .. }) = matches.subcommands {
-        Some(Duration::from_secs_f32(timeout))
+    // Held until the process ends.
+    // Completing topics is short-lived and should not warn a running instance about its own client id.
+    let client_id_lock = if matches!(
+        matches.subcommands,
+        Some(Subcommands::CompleteTopics { .. })
+    ) {
+        mqtt::ClientIdLock::default()
     } else {
-        None
+        mqtt::ClientIdLock::acquire(&matches.mqtt_connection)
     };
-    let (broker, client, connection) = mqtt::connect(matches.mqtt_connection, keep_alive)?;
+    if let Some(warning) = &client_id_lock.warning {
+        exit::chatter!("{warning}");
+    }
+
+    if matches.subcommands.is_none() && !matches.no_interactive && std::io::stdout().is_terminal() {
+        let options = interactive::Options::from_cli(&matches)?;
+        let decoding = payload::Decoding::new(matches.decoding)?;
+        let runtime = tokio::runtime::Runtime::new()?;
+        let (_, client, eventloop) =
+            runtime.block_on(mqtt::connect_async(matches.mqtt_connection.clone(), None))?;
+        interactive::show(
+            runtime.handle(),
+            client,
+            eventloop,
+            matches.mqtt_connection,
+            decoding,
+            options,
+            client_id_lock.warning,
+        )?;
+        return Ok(());
+    }
+
+    if let Some(Subcommands::Request {
+        topic,
+        payload,
+        response_topic,
+        qos,
+        timeout,
+        pretty,
+    }) = matches.subcommands
+    {
+        let qos = rumqttc::v5::mqttbytes::qos(qos).context("QoS has to be 0, 1 or 2")?;
+        let payload =
+            publish::Message::from_args(topic.clone(), payload, QoS::AtMostOnce, false).payload;
+        let runtime = tokio::runtime::Runtime::new()?;
+        return runtime.block_on(async {
+            let (_, client, eventloop) = mqtt::connect_v5(matches.mqtt_connection).await?;
+            request::show(
+                client,
+                eventloop,
+                request::Request {
+                    topic,
+                    payload,
+                    response_topic,
+                    qos,
+                },
+                Duration::from_secs_f32(timeout),
+                pretty,
+            )
+            .await
+        });
+    }
+
+    if let Some(Subcommands::AclCheck { topics, timeout }) = matches.subcommands {
+        let topics = acl_check::read_topics(&topics)?;
+        let runtime = tokio::runtime::Runtime::new()?;
+        return runtime.block_on(async {
+            let (_, client, eventloop) = mqtt::connect_v5(matches.mqtt_connection).await?;
+            acl_check::acl_check(client, eventloop, topics, Duration::from_secs_f32(timeout)).await
+        });
+    }
+
+    let keep_alive = match matches.subcommands {
+        Some(
+            Subcommands::CleanRetained { timeout, .. }
+            | Subcommands::Move { timeout, .. }
+            | Subcommands::CompleteTopics { timeout, .. },
+        ) => Some(Duration::from_secs_f32(timeout)),
+        Some(Subcommands::Ping { .. }) => Some(Duration::from_secs(1)),
+        _ => None,
+    };
+    let (broker, client, connection) = mqtt::connect(&matches.mqtt_connection, keep_alive)?;
+    signal::disconnect_on_signal(client.clone())?;
 
     match matches.subcommands {
-        Some(Subcommands::CleanRetained { topic, dry_run, .. }) => {
+        Some(Subcommands::Bridge {
+            topic,
+            to,
+            prefix,
+            to_username,
+            to_password,
+            verbose,
+        }) => {
+            let mut destination = cli::MqttConnection {
+                broker: to,
+                client_id: None,
+                ..matches.mqtt_connection
+            };
+            if to_username.is_some() {
+                destination.username = to_username;
+                destination.password = to_password;
+                destination.password_command = None;
+                destination.profile = None;
+            }
+            let (_, destination, destination_connection) = mqtt::connect(&destination, None)?;
+            for topic in topic {
+                client.subscribe(topic, QoS::AtLeastOnce)?;
+            }
+            bridge::bridge(
+                connection,
+                &destination,
+                destination_connection,
+                &prefix,
+                verbose,
+            );
+        }
+        Some(Subcommands::CleanRetained {
+            topic,
+            dry_run,
+            rate_limit,
+            max_topics,
+            ..
+        }) => {
             client.subscribe(topic, QoS::AtLeastOnce)?;
-            clean_retained::clean_retained(&client, connection, dry_run);
+            let safeguards = clean_retained::Safeguards {
+                rate_limit,
+                max_topics,
+            };
+            clean_retained::clean_retained(&client, connection, dry_run, safeguards)?;
+        }
+        Some(Subcommands::AnalyzeRetained {
+            topic,
+            settle,
+            depth,
+            top,
+        }) => {
+            for topic in topic {
+                client.subscribe(topic, QoS::AtLeastOnce)?;
+            }
+            analyze_retained::analyze_retained(
+                &client,
+                connection,
+                Duration::from_secs_f32(settle),
+                depth.get(),
+                top,
+            )?;
+        }
+        Some(Subcommands::Diff {
+            topic,
+            other,
+            other_username,
+            other_password,
+            timeout,
+        }) => {
+            let mut other = cli::MqttConnection {
+                broker: other,
+                client_id: None,
+                ..matches.mqtt_connection
+            };
+            if other_username.is_some() {
+                other.username = other_username;
+                other.password = other_password;
+                other.password_command = None;
+                other.profile = None;
+            }
+            let (other_broker, other_client, other_connection) = mqtt::connect(&other, None)?;
+            for topic in topic {
+                client.subscribe(&topic, QoS::AtLeastOnce)?;
+                other_client.subscribe(topic, QoS::AtLeastOnce)?;
+            }
+            diff::diff(
+                &broker,
+                &client,
+                connection,
+                &other_broker,
+                other_client,
+                other_connection,
+                Duration::from_secs_f32(timeout),
+            )?;
+        }
+        Some(Subcommands::Snapshot { topic, settle, out }) => {
+            for topic in topic {
+                client.subscribe(topic, QoS::AtLeastOnce)?;
+            }
+            snapshot::snapshot(
+                &client,
+                connection,
+                Duration::from_secs_f32(settle),
+                out.as_deref(),
+            )?;
+        }
+        Some(Subcommands::Restore {
+            file,
+            prefix,
+            timeout,
+            dry_run,
+        }) => {
+            let messages = snapshot::restore_messages(snapshot::read(&file)?, prefix.as_deref())?;
+            if dry_run {
+                for message in &messages {
+                    let topic = &message.topic;
+                    let size = message.payload.len();
+                    let payload = payload::Payload::unlimited(message.payload.clone());
+                    println!("{topic:50} Payload({size:>3}): {payload}");
+                }
+                println!("Dry run: would have restored {} topics", messages.len());
+            } else {
+                publish::publish(
+                    &client,
+                    connection,
+                    messages,
+                    Duration::from_secs_f32(timeout),
+                    false,
+                )?;
+            }
+        }
+        Some(Subcommands::Move {
+            from,
+            to,
+            delete_source,
+            dry_run,
+            ..
+        }) => {
+            let from = from.trim_end_matches('/');
+            let to = to.trim_end_matches('/');
+            anyhow::ensure!(
+                !from.contains(['+', '#']) && !to.contains(['+', '#']),
+                "move requires topic prefixes without wildcards"
+            );
+            anyhow::ensure!(
+                !mqtt::topic_filter::matches(&format!("{from}/#"), to),
+                "The destination can not be below the source as the moved messages would be received again"
+            );
+            client.subscribe(format!("{from}/#"), QoS::AtLeastOnce)?;
+            move_retained::move_retained(&client, connection, from, to, delete_source, dry_run);
         }
         Some(Subcommands::Log {
             topic,
             json,
+            timestamp,
+            decoding,
+            count,
+            duration,
             verbose,
         }) => {
+            let mut subscriptions = mqtt::Subscriptions::default();
             for topic in topic {
-                client.subscribe(topic, QoS::AtLeastOnce)?;
+                subscriptions.subscribe(&client, &topic, QoS::AtLeastOnce)?;
             }
-            log::show(connection, json, verbose);
+            let decoding = payload::Decoding::new(decoding)?;
+            log::show(
+                &client,
+                connection,
+                subscriptions,
+                json,
+                timestamp,
+                &decoding,
+                log::Limit {
+                    count: count.map(std::num::NonZeroUsize::get),
+                    duration: duration.map(Duration::from_secs_f32),
+                },
+                verbose,
+            )?;
         }
         Some(Subcommands::ReadOne {
             topic,
             ignore_retained,
             pretty,
+            count,
+            per_topic,
         }) => {
+            let amount = if per_topic {
+                anyhow::ensure!(
+                    !topic.iter().any(|topic| topic.contains(['+', '#'])),
+                    "--per-topic requires topics without wildcards"
+                );
+                read_one::Amount::PerTopic(topic.iter().cloned().collect())
+            } else {
+                read_one::Amount::Count(count)
+            };
             for topic in topic {
                 client.subscribe(topic, QoS::AtLeastOnce)?;
             }
-            read_one::show(&client, connection, ignore_retained, pretty);
+            read_one::show(&client, connection, ignore_retained, pretty, amount)?;
+        }
+        Some(Subcommands::Assert {
+            topic,
+            expect_json,
+            file,
+            timeout,
+        }) => {
+            let assertions = if let Some(file) = file {
+                assert::read_file(&file)?
+            } else {
+                let topic = topic.expect("clap requires either topic or file");
+                vec![assert::Assertion::new(topic, expect_json)?]
+            };
+            let topics = assertions
+                .iter()
+                .map(|assertion| assertion.topic.clone())
+                .collect::<std::collections::BTreeSet<_>>();
+            for topic in topics {
+                client.subscribe(topic, QoS::AtLeastOnce)?;
+            }
+            assert::assert(
+                &client,
+                connection,
+                assertions,
+                Duration::from_secs_f32(timeout),
+            )?;
+        }
+        Some(Subcommands::Simulate { file, verbose }) => {
+            let devices = simulate::read(&file)?;
+            simulate::simulate(&client, connection, devices, verbose);
         }
         Some(Subcommands::Publish {
             topic,
             payload,
+            file,
+            payload_file,
+            payload_base64,
             retain,
+            qos,
+            timeout,
             verbose,
         }) => {
-            let payload = payload.map_or_else(
-                || {
-                    use std::io::Read;
-                    let mut buffer = Vec::new();
-                    std::io::stdin()
-                        .read_to_end(&mut buffer)
-                        .expect("Should be able to read the payload from stdin");
-                    buffer
-                },
-                String::into_bytes,
-            );
-            client.publish(topic, QoS::AtLeastOnce, retain, payload)?;
-            publish::eventloop(&client, connection, verbose);
+            let qos = rumqttc::qos(qos)?;
+            let messages = if let Some(file) = file {
+                publish::read_file(&file, qos, retain)?
+            } else {
+                let topic = topic.expect("topic is required without --file which clap ensures");
+                let mut message = if let Some(path) = payload_file {
+                    publish::Message::from_file(topic, &path, qos, retain)?
+                } else {
+                    publish::Message::from_args(topic, payload, qos, retain)
+                };
+                if payload_base64 {
+                    message.decode_base64()?;
+                }
+                vec![message]
+            };
+            publish::publish(
+                &client,
+                connection,
+                messages,
+                Duration::from_secs_f32(timeout),
+                verbose,
+            )?;
+        }
+        Some(Subcommands::CompleteTopics { prefix, .. }) => {
+            client.subscribe(complete_topics::topic_filter(&prefix), QoS::AtLeastOnce)?;
+            complete_topics::complete_topics(&client, connection, &prefix)?;
         }
+        Some(Subcommands::Ping { count, tls_info }) => {
+            ping::show(&client, connection, &broker, count, tls_info)?;
+        }
+        #[cfg(unix)]
+        Some(Subcommands::Serve {
+            topic,
+            socket,
+            history_limit,
+        }) => {
+            let mut subscriptions = mqtt::Subscriptions::default();
+            for topic in topic {
+                subscriptions.subscribe(&client, &topic, QoS::AtLeastOnce)?;
+            }
+            let decoding = payload::Decoding::new(matches.decoding)?;
+            let history_limit = interactive::HistoryLimit {
+                messages: history_limit.get(),
+                compaction: false,
+            };
+            serve::serve(
+                &client,
+                connection,
+                subscriptions,
+                decoding,
+                &socket,
+                history_limit,
+            )?;
+        }
+        #[cfg(not(unix))]
+        Some(Subcommands::Serve { .. }) => {
+            anyhow::bail!("serve is only supported on unix");
+        }
+        Some(Subcommands::Request { .. }) => unreachable!("request was handled before"),
+        Some(Subcommands::AclCheck { .. }) => unreachable!("acl-check was handled before"),
+        Some(Subcommands::Discover { .. }) => unreachable!("discover was handled before"),
+        Some(Subcommands::Login { .. }) => unreachable!("login was handled before"),
+        // Not interactive as that was handled before
         None => {
-            interactive::show(
-                client.clone(),
+            let mut subscriptions = mqtt::Subscriptions::default();
+            for topic in matches.topic {
+                subscriptions.subscribe(&client, &topic, QoS::AtLeastOnce)?;
+            }
+            let decoding = payload::Decoding::new(matches.decoding)?;
+            log::show(
+                &client,
                 connection,
-                &broker,
-                matches.topic,
-                matches.payload_size_limit,
+                subscriptions,
+                false,
+                cli::Timestamp::Local,
+                &decoding,
+                log::Limit::default(),
+                false,
             )?;
-            client.disconnect()?;
         }
     }
 
@@ -0,0 +1,93 @@
+use std::thread::sleep;
+use std::time::Duration;
+
+use rumqttc::{Client, Connection, QoS};
+
+use crate::format;
+use crate::payload::Payload;
+
+/// Replace the `from` prefix of the topic with `to`
+fn destination(topic: &str, from: &str, to: &str) -> Option<String> {
+    let rest = topic.strip_prefix(from)?;
+    if rest.is_empty() || rest.starts_with('/') {
+        Some(format!("{to}{rest}"))
+    } else {
+        None
+    }
+}
+
+pub fn move_retained(
+    client: &Client,
+    mut connection: Connection,
+    from: &str,
+    to: &str,
+    delete_source: bool,
+    dry_run: bool,
+) {
+    let mut amount: usize = 0;
+    for notification in connection.iter() {
+        match notification {
+            Ok(rumqttc::Event::Outgoing(rumqttc::Outgoing::Disconnect)) => break,
+            Ok(rumqttc::Event::Outgoing(rumqttc::Outgoing::PingReq)) => {
+                client.disconnect().unwrap();
+            }
+            Ok(rumqttc::Event::Incoming(rumqttc::Packet::Publish(publish))) => {
+                if publish.payload.is_empty() {
+                    // That's probably myself cleaning up
+                    continue;
+                }
+                if !publish.retain {
+                    client.disconnect().unwrap();
+                    continue;
+                }
+                let topic = &publish.topic;
+                let Some(destination) = destination(topic, from, to) else {
+                    continue;
+                };
+                {
+                    let qos = format::qos(publish.qos);
+                    let size = publish.payload.len();
+                    let payload = Payload::unlimited(publish.payload.to_vec());
+                    println!(
+                        "QoS:{qos:11} {topic:50} -> {destination:50} Payload({size:>3}): {payload}"
+                    );
+                }
+                amount += 1;
+                if !dry_run {
+                    client
+                        .publish(destination, QoS::ExactlyOnce, true, publish.payload)
+                        .unwrap();
+                    if delete_source {
+                        client.publish(topic, QoS::ExactlyOnce, true, []).unwrap();
+                    }
+                }
+            }
+            Ok(_) => {}
+            Err(err) => {
+                eprintln!("Connection Error: {err}");
+                sleep(Duration::from_millis(25));
+            }
+        }
+    }
+    if dry_run {
+        println!("Dry run: would have moved {amount} topics");
+    } else if delete_source {
+        println!("Moved {amount} topics");
+    } else {
+        println!("Copied {amount} topics");
+    }
+}
+
+#[test]
+fn destination_works() {
+    assert_eq!(
+        destination("foo/bar", "foo", "baz").as_deref(),
+        Some("baz/bar")
+    );
+    assert_eq!(destination("foo", "foo", "baz").as_deref(), Some("baz"));
+    assert_eq!(
+        destination("foo/bar/test", "foo/bar", "a/b/c").as_deref(),
+        Some("a/b/c/test")
+    );
+    assert_eq!(destination("foobar", "foo", "baz"), None);
+}
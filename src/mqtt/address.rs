@@ -0,0 +1,74 @@
+use std::net::{IpAddr, SocketAddr, ToSocketAddrs};
+
+/// Alternate between the address families starting with the family of the first address like RFC 8305 recommends
+fn interleave(addresses: Vec<SocketAddr>) -> Vec<SocketAddr> {
+    let Some(first) = addresses.first() else {
+        return addresses;
+    };
+    let first_is_ipv6 = first.is_ipv6();
+    let (mut preferred, mut other): (Vec<_>, Vec<_>) = addresses
+        .into_iter()
+        .partition(|address| address.is_ipv6() == first_is_ipv6);
+    let mut result = Vec::with_capacity(preferred.len() + other.len());
+    preferred.reverse();
+    other.reverse();
+    loop {
+        match (preferred.pop(), other.pop()) {
+            (None, None) => return result,
+            (a, b) => result.extend(a.into_iter().chain(b)),
+        }
+    }
+}
+
+/// Addresses to try one by one after the connection to the host timed out, the next one last to be popped first.
+///
+/// The first address is skipped as the connection got stuck on it.
+fn fallbacks(addresses: Vec<SocketAddr>) -> Vec<IpAddr> {
+    let mut fallbacks = interleave(addresses)
+        .into_iter()
+        .skip(1)
+        .map(|address| address.ip())
+        .collect::<Vec<_>>();
+    fallbacks.reverse();
+    fallbacks
+}
+
+/// Resolve the other addresses of the host. A failed resolution results in no fallback.
+pub fn resolve_fallbacks(host: &str, port: u16) -> Vec<IpAddr> {
+    (host, port)
+        .to_socket_addrs()
+        .map_or_else(|_| Vec::new(), |addresses| fallbacks(addresses.collect()))
+}
+
+/// Like [`resolve_fallbacks`] but without blocking the async runtime
+pub async fn resolve_fallbacks_async(host: &str, port: u16) -> Vec<IpAddr> {
+    tokio::net::lookup_host((host, port))
+        .await
+        .map_or_else(|_| Vec::new(), |addresses| fallbacks(addresses.collect()))
+}
+
+#[test]
+fn interleave_works() {
+    let v4 = |last| SocketAddr::from(([192, 0, 2, last], 1883));
+    let v6 = |last| SocketAddr::from(([0x2001, 0xdb8, 0, 0, 0, 0, 0, last], 1883));
+    assert_eq!(
+        interleave(vec![v6(1), v6(2), v6(3), v4(1)]),
+        [v6(1), v4(1), v6(2), v6(3)]
+    );
+    assert_eq!(
+        interleave(vec![v4(1), v4(2), v6(1), v6(2)]),
+        [v4(1), v6(1), v4(2), v6(2)]
+    );
+    assert!(interleave(Vec::new()).is_empty());
+}
+
+#[test]
+fn fallbacks_skip_first() {
+    let v4 = |last| SocketAddr::from(([192, 0, 2, last], 1883));
+    let v6 = |last| SocketAddr::from(([0x2001, 0xdb8, 0, 0, 0, 0, 0, last], 1883));
+    let mut fallbacks = fallbacks(vec![v6(1), v6(2), v4(1)]);
+    assert_eq!(fallbacks.pop(), Some(v4(1).ip()));
+    assert_eq!(fallbacks.pop(), Some(v6(2).ip()));
+    assert_eq!(fallbacks.pop(), None);
+    assert!(resolve_fallbacks("127.0.0.1", 1883).is_empty());
+}
@@ -0,0 +1,202 @@
+use std::net::IpAddr;
+
+use chrono::NaiveDateTime;
+
+/// The parts of an X.509 certificate relevant to debug TLS connections
+#[derive(Debug, PartialEq, Eq)]
+pub struct Certificate {
+    pub subject: String,
+    pub issuer: String,
+    /// UTC
+    pub not_before: NaiveDateTime,
+    /// UTC
+    pub not_after: NaiveDateTime,
+    /// DNS names and IP addresses of the subject alternative name extension
+    pub names: Vec<String>,
+}
+
+/// Returns (tag, content, rest) of the first DER element
+fn element(data: &[u8]) -> Option<(u8, &[u8], &[u8])> {
+    let (&tag, data) = data.split_first()?;
+    let (&length, mut data) = data.split_first()?;
+    let length = if length < 0x80 {
+        usize::from(length)
+    } else {
+        let bytes = usize::from(length & 0x7f);
+        if bytes > 4 || data.len() < bytes {
+            return None;
+        }
+        let (length, rest) = data.split_at(bytes);
+        data = rest;
+        length
+            .iter()
+            .fold(0, |sum, byte| (sum << 8) | usize::from(*byte))
+    };
+    if data.len() < length {
+        return None;
+    }
+    let (content, rest) = data.split_at(length);
+    Some((tag, content, rest))
+}
+
+/// All DER elements directly within the content
+fn elements(mut data: &[u8]) -> impl Iterator<Item = (u8, &[u8])> {
+    core::iter::from_fn(move || {
+        let (tag, content, rest) = element(data)?;
+        data = rest;
+        Some((tag, content))
+    })
+}
+
+/// Formats a distinguished name like `C=DE, O=mqttui, CN=broker.test`
+fn name(data: &[u8]) -> String {
+    let mut parts = Vec::new();
+    for (_, set) in elements(data) {
+        for (_, attribute) in elements(set) {
+            let mut attribute = elements(attribute);
+            let (Some((0x06, oid)), Some((_, value))) = (attribute.next(), attribute.next()) else {
+                continue;
+            };
+            let key = match oid {
+                [0x55, 0x04, 0x03] => "CN",
+                [0x55, 0x04, 0x06] => "C",
+                [0x55, 0x04, 0x07] => "L",
+                [0x55, 0x04, 0x08] => "ST",
+                [0x55, 0x04, 0x0a] => "O",
+                [0x55, 0x04, 0x0b] => "OU",
+                _ => continue,
+            };
+            parts.push(format!("{key}={}", String::from_utf8_lossy(value)));
+        }
+    }
+    parts.join(", ")
+}
+
+fn time(tag: u8, data: &[u8]) -> Option<NaiveDateTime> {
+    let text = core::str::from_utf8(data).ok()?;
+    let text = match tag {
+        // UTCTime has two digit years which are 1950 to 2049
+        0x17 => {
+            let century = if text.get(..2)? < "50" { "20" } else { "19" };
+            format!("{century}{text}")
+        }
+        0x18 => text.to_owned(),
+        _ => return None,
+    };
+    NaiveDateTime::parse_from_str(&text, "%Y%m%d%H%M%SZ").ok()
+}
+
+/// Subject alternative names within the content of the extension
+fn alternative_names(data: &[u8]) -> Vec<String> {
+    let Some((0x30, names, _)) = element(data) else {
+        return Vec::new();
+    };
+    elements(names)
+        .filter_map(|(tag, value)| match tag {
+            // dNSName
+            0x82 => Some(String::from_utf8_lossy(value).into_owned()),
+            // iPAddress
+            0x87 => {
+                let ip = <[u8; 4]>::try_from(value)
+                    .map(IpAddr::from)
+                    .or_else(|_| <[u8; 16]>::try_from(value).map(IpAddr::from))
+                    .ok()?;
+                Some(ip.to_string())
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+pub fn parse(der: &[u8]) -> Option<Certificate> {
+    let (0x30, certificate, _) = element(der)? else {
+        return None;
+    };
+    let (0x30, tbs_certificate, _) = element(certificate)? else {
+        return None;
+    };
+    let mut fields = elements(tbs_certificate).peekable();
+    // Optional explicit version
+    fields.next_if(|(tag, _)| *tag == 0xa0);
+    let _serial = fields.next()?;
+    let _signature = fields.next()?;
+    let (_, issuer) = fields.next()?;
+    let (_, validity) = fields.next()?;
+    let (_, subject) = fields.next()?;
+
+    let mut validity = elements(validity);
+    let (tag, not_before) = validity.next()?;
+    let not_before = time(tag, not_before)?;
+    let (tag, not_after) = validity.next()?;
+    let not_after = time(tag, not_after)?;
+
+    let mut names = Vec::new();
+    for (_, extensions) in fields.filter(|(tag, _)| *tag == 0xa3) {
+        let Some((0x30, extensions, _)) = element(extensions) else {
+            continue;
+        };
+        for (_, extension) in elements(extensions) {
+            let mut extension = elements(extension);
+            // Subject alternative name
+            if extension.next() != Some((0x06, &[0x55, 0x1d, 0x11])) {
+                continue;
+            }
+            // The value is the last element after the optional critical flag
+            if let Some((0x04, value)) = extension.last() {
+                names = alternative_names(value);
+            }
+        }
+    }
+
+    Some(Certificate {
+        subject: name(subject),
+        issuer: name(issuer),
+        not_before,
+        not_after,
+        names,
+    })
+}
+
+#[test]
+fn parse_works() {
+    const PEM: &str = "-----BEGIN CERTIFICATE-----
+MIIB/jCCAaSgAwIBAgIUO/9dQGGYZ5VKiSaUZmqpjn1v7GkwCgYIKoZIzj0EAwIw
+NDELMAkGA1UEBhMCREUxDzANBgNVBAoMBm1xdHR1aTEUMBIGA1UEAwwLYnJva2Vy
+LnRlc3QwHhcNMjYxMDE2MDkxMTI2WhcNMzYxMDEzMDkxMTI2WjA0MQswCQYDVQQG
+EwJERTEPMA0GA1UECgwGbXF0dHVpMRQwEgYDVQQDDAticm9rZXIudGVzdDBZMBMG
+ByqGSM49AgEGCCqGSM49AwEHA0IABDWuSLVI1uhlFBMswFy7bFKL1mA6MRRwqm8P
+BU4DHfd7JcvOAe+XT3LjNuMdb5IruVEoX2Mi1TWDXpU065R4GPOjgZMwgZAwHQYD
+VR0OBBYEFAZsmCBEo0zkUzZx3ribuB1eBKcCMB8GA1UdIwQYMBaAFAZsmCBEo0zk
+UzZx3ribuB1eBKcCMA8GA1UdEwEB/wQFMAMBAf8wPQYDVR0RBDYwNIILYnJva2Vy
+LnRlc3SCDSouYnJva2VyLnRlc3SHBH8AAAGHEAAAAAAAAAAAAAAAAAAAAAEwCgYI
+KoZIzj0EAwIDSAAwRQIgRM/p8K6Nw/qVxUi0yRk8f3DT4B216Fe9l+PM86jZRewC
+IQCCO1ehUi3SZ0pypiiYAnb79MK6HrZnJNyjLSlcICIKsw==
+-----END CERTIFICATE-----";
+    let der = rustls_pemfile::certs(&mut PEM.as_bytes())
+        .next()
+        .unwrap()
+        .unwrap();
+    let datetime = |text| NaiveDateTime::parse_from_str(text, "%Y-%m-%d %H:%M:%S").unwrap();
+    assert_eq!(
+        parse(&der),
+        Some(Certificate {
+            subject: "C=DE, O=mqttui, CN=broker.test".to_owned(),
+            issuer: "C=DE, O=mqttui, CN=broker.test".to_owned(),
+            not_before: datetime("2026-10-16 09:11:26"),
+            not_after: datetime("2036-10-13 09:11:26"),
+            names: vec![
+                "broker.test".to_owned(),
+                "*.broker.test".to_owned(),
+                "127.0.0.1".to_owned(),
+                "::1".to_owned(),
+            ],
+        })
+    );
+}
+
+#[test]
+fn parse_invalid() {
+    assert_eq!(parse(&[]), None);
+    assert_eq!(parse(&[0x30, 0x82, 0xff]), None);
+    assert_eq!(parse(&[0x30, 0x00]), None);
+}
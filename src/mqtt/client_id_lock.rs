@@ -0,0 +1,206 @@
+use std::fs::File;
+use std::io::{Seek, Write};
+use std::path::{Path, PathBuf};
+
+use crate::cli::MqttConnection;
+
+/// Advisory lock of an explicit client id on a broker which is held until the process ends.
+///
+/// Brokers disconnect the existing connection when the same client id connects again.
+/// Two instances with the same client id kick each other out which only looks like random disconnects.
+#[derive(Default)]
+pub struct ClientIdLock {
+    /// The operating system releases the lock when the file is closed
+    _file: Option<File>,
+    /// Set when another instance holds the lock already
+    pub warning: Option<String>,
+}
+
+impl ClientIdLock {
+    /// Random client ids are unique and not locked
+    pub fn acquire(connection: &MqttConnection) -> Self {
+        let Some(client_id) = &connection.client_id else {
+            return Self::default();
+        };
+        // The lock is only advisory so not being able to create it is not a reason to stop
+        let Some(directory) = directory() else {
+            return Self::default();
+        };
+        let broker = &connection.broker;
+        match acquire_in(&directory, &file_name(&broker.to_string(), client_id)) {
+            Err(_) => Self::default(),
+            Ok(Ok(file)) => Self {
+                _file: Some(file),
+                warning: None,
+            },
+            Ok(Err(holder)) => Self {
+                _file: None,
+                warning: Some(format!(
+                    "Another mqttui instance{} is connected to {broker} with the client id {client_id}. The broker will disconnect one of them whenever the other connects. Use a different --client-id.",
+                    holder.map(|pid| format!(" (process {pid})")).unwrap_or_default()
+                )),
+            },
+        }
+    }
+}
+
+/// Per user directory as other users could place files with the predictable lock names in a shared one
+fn directory() -> Option<PathBuf> {
+    let var = |key| std::env::var_os(key).filter(|value| !value.is_empty());
+    let base = var("XDG_RUNTIME_DIR")
+        .or_else(|| var("XDG_CACHE_HOME"))
+        .map(PathBuf::from)
+        .or_else(|| var("HOME").map(|home| PathBuf::from(home).join(".cache")))
+        .or_else(|| var("LOCALAPPDATA").map(PathBuf::from))?;
+    Some(base.join("mqttui"))
+}
+
+/// Only accessible by the current user
+#[cfg(unix)]
+fn create_private_dir(directory: &Path) -> std::io::Result<()> {
+    use std::os::unix::fs::DirBuilderExt;
+    std::fs::DirBuilder::new()
+        .recursive(true)
+        .mode(0o700)
+        .create(directory)?;
+    let metadata = std::fs::symlink_metadata(directory)?;
+    if metadata.is_dir() && is_owned_by_current_user(&metadata) {
+        Ok(())
+    } else {
+        Err(std::io::Error::new(
+            std::io::ErrorKind::PermissionDenied,
+            format!(
+                "{} is not a directory of the current user",
+                directory.display()
+            ),
+        ))
+    }
+}
+
+#[cfg(not(unix))]
+fn create_private_dir(directory: &Path) -> std::io::Result<()> {
+    std::fs::create_dir_all(directory)
+}
+
+#[cfg(unix)]
+fn is_owned_by_current_user(metadata: &std::fs::Metadata) -> bool {
+    use std::os::unix::fs::MetadataExt;
+    metadata.uid() == rustix::process::getuid().as_raw()
+}
+
+/// Readable and safe to use as a file name on every platform
+fn file_name(broker: &str, client_id: &str) -> String {
+    let name = format!("{broker} {client_id}")
+        .chars()
+        .map(|char| {
+            if char.is_ascii_alphanumeric() || char == '-' || char == '.' {
+                char
+            } else {
+                '_'
+            }
+        })
+        .collect::<String>();
+    format!("{name}.lock")
+}
+
+/// Returns the locked file or the process id of the holder when it is already locked
+fn acquire_in(directory: &Path, file_name: &str) -> std::io::Result<Result<File, Option<u32>>> {
+    create_private_dir(directory)?;
+    let path = directory.join(file_name);
+    // Only files of the current user are opened so truncating it can not destroy anything else
+    let Some(mut file) = try_lock(&path)? else {
+        let holder = std::fs::read_to_string(&path)
+            .ok()
+            .and_then(|content| content.trim().parse().ok());
+        return Ok(Err(holder));
+    };
+    file.set_len(0)?;
+    file.rewind()?;
+    write!(file, "{}", std::process::id())?;
+    file.flush()?;
+    Ok(Ok(file))
+}
+
+/// Returns `None` when another process holds the lock
+#[cfg(unix)]
+fn try_lock(path: &Path) -> std::io::Result<Option<File>> {
+    use rustix::fs::{flock, FlockOperation, Mode, OFlags};
+    // O_NOFOLLOW as a symlink would redirect the write of the process id to another file
+    let file = File::from(rustix::fs::open(
+        path,
+        OFlags::RDWR | OFlags::CREATE | OFlags::NOFOLLOW | OFlags::CLOEXEC,
+        Mode::RUSR | Mode::WUSR,
+    )?);
+    let metadata = file.metadata()?;
+    if !metadata.is_file() || !is_owned_by_current_user(&metadata) {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::PermissionDenied,
+            format!("{} is not a file of the current user", path.display()),
+        ));
+    }
+    match flock(&file, FlockOperation::NonBlockingLockExclusive) {
+        Ok(()) => Ok(Some(file)),
+        Err(rustix::io::Errno::WOULDBLOCK) => Ok(None),
+        Err(err) => Err(err.into()),
+    }
+}
+
+/// Returns `None` when another process holds the lock
+#[cfg(windows)]
+fn try_lock(path: &Path) -> std::io::Result<Option<File>> {
+    use std::os::windows::fs::OpenOptionsExt;
+    /// The file is opened by another process without sharing access
+    const ERROR_SHARING_VIOLATION: i32 = 32;
+    let result = File::options()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .share_mode(0)
+        .open(path);
+    match result {
+        Ok(file) => Ok(Some(file)),
+        Err(err) if err.raw_os_error() == Some(ERROR_SHARING_VIOLATION) => Ok(None),
+        Err(err) => Err(err),
+    }
+}
+
+#[cfg(not(any(unix, windows)))]
+fn try_lock(path: &Path) -> std::io::Result<Option<File>> {
+    File::create(path).map(Some)
+}
+
+#[test]
+fn file_name_works() {
+    assert_eq!(
+        file_name("mqtt://pi-home.local@1884", "my client/1"),
+        "mqtt___pi-home.local_1884_my_client_1.lock"
+    );
+}
+
+#[cfg(any(unix, windows))]
+#[test]
+fn second_lock_is_refused() {
+    let directory = std::env::temp_dir().join(format!("mqttui-test-{}", std::process::id()));
+    let first = acquire_in(&directory, "test.lock").unwrap();
+    assert!(first.is_ok());
+    let second = acquire_in(&directory, "test.lock").unwrap();
+    assert_eq!(second.unwrap_err(), Some(std::process::id()));
+    drop(first);
+    assert!(acquire_in(&directory, "test.lock").unwrap().is_ok());
+    _ = std::fs::remove_dir_all(directory);
+}
+
+#[cfg(unix)]
+#[test]
+fn symlink_is_not_followed() {
+    let directory =
+        std::env::temp_dir().join(format!("mqttui-test-symlink-{}", std::process::id()));
+    std::fs::create_dir_all(&directory).unwrap();
+    let target = directory.join("target");
+    std::fs::write(&target, "important").unwrap();
+    std::os::unix::fs::symlink(&target, directory.join("test.lock")).unwrap();
+    assert!(acquire_in(&directory, "test.lock").is_err());
+    assert_eq!(std::fs::read_to_string(&target).unwrap(), "important");
+    _ = std::fs::remove_dir_all(directory);
+}
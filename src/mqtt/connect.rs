@@ -1,74 +1,383 @@
+use std::future::Future;
+use std::net::IpAddr;
+use std::sync::Arc;
+use std::task::{Context, Poll, Wake, Waker};
 use std::time::Duration;
 
-use anyhow::Context;
-use rumqttc::{Client, Connection, Event, MqttOptions, Packet, Transport};
+use rumqttc::{
+    v5, AsyncClient, Client, Connection, ConnectionError, Event, EventLoop, MqttOptions,
+    NetworkOptions, Packet, Transport,
+};
 
 use crate::cli::{Broker, MqttConnection};
+use crate::exit::chatter;
 
-pub fn connect(
+/// Returns (`Transport`, host, port)
+///
+/// The address is connected to instead of the host when given.
+/// TLS still verifies the certificate for the host then.
+fn transport(
     MqttConnection {
         broker,
-        username,
-        password,
-        client_id,
         client_cert,
         client_key,
+        ca_cert,
+        tls_server_name,
         insecure,
-    }: MqttConnection,
-    keep_alive: Option<Duration>,
-) -> anyhow::Result<(Broker, Client, Connection)> {
-    let (transport, host, port) = match &broker {
-        Broker::Tcp { host, port } => (Transport::Tcp, host.clone(), *port),
-        Broker::Ssl { host, port } => (
-            Transport::Tls(super::encryption::create_tls_configuration(
-                insecure,
-                client_cert.as_deref(),
-                client_key.as_deref(),
-            )?),
-            host.clone(),
-            *port,
-        ),
+        ..
+    }: &MqttConnection,
+    address: Option<IpAddr>,
+) -> anyhow::Result<(Transport, String, u16)> {
+    let insecure = *insecure;
+    let host_or_address =
+        |host: &String| address.map_or_else(|| host.clone(), |address| address.to_string());
+    Ok(match broker {
+        Broker::Tcp { host, port } => (Transport::Tcp, host_or_address(host), *port),
+        Broker::Ssl { host, port } => {
+            let server_name = tls_server_name
+                .as_deref()
+                .or_else(|| address.is_some().then_some(host.as_str()));
+            (
+                Transport::Tls(super::encryption::create_tls_configuration(
+                    insecure,
+                    ca_cert.as_deref(),
+                    server_name,
+                    client_cert.as_deref(),
+                    client_key.as_deref(),
+                )?),
+                host_or_address(host),
+                *port,
+            )
+        }
         // On WebSockets the port is ignored. See https://github.com/bytebeamio/rumqtt/issues/270
         Broker::WebSocket(url) => (Transport::Ws, url.to_string(), 666),
         Broker::WebSocketSsl(url) => (
             Transport::Wss(super::encryption::create_tls_configuration(
                 insecure,
+                ca_cert.as_deref(),
+                tls_server_name.as_deref(),
                 client_cert.as_deref(),
                 client_key.as_deref(),
             )?),
             url.to_string(),
             666,
         ),
-    };
+        // The host is used as the path of the socket, the port is ignored
+        #[cfg(unix)]
+        Broker::Unix(path) => (Transport::Unix, path.to_string_lossy().into_owned(), 0),
+        #[cfg(not(unix))]
+        Broker::Unix(_) => anyhow::bail!("Unix domain sockets are only supported on Unix"),
+    })
+}
+
+/// The keep alive of --keep-alive or the one required by the subcommand, whatever is shorter
+fn keep_alive(connection: &MqttConnection, required: Option<Duration>) -> Option<Duration> {
+    connection
+        .keep_alive
+        .map(|seconds| Duration::from_secs(seconds.get().into()))
+        .into_iter()
+        .chain(required)
+        .min()
+}
+
+fn network_options(connection: &MqttConnection) -> NetworkOptions {
+    let mut network_options = NetworkOptions::new();
+    network_options.set_connection_timeout(connection.connection_timeout.get());
+    network_options
+}
 
-    let client_id = client_id.unwrap_or_else(|| format!("mqttui-{:x}", rand::random::<u32>()));
+fn options(
+    connection: &MqttConnection,
+    keep_alive: Option<Duration>,
+    address: Option<IpAddr>,
+) -> anyhow::Result<(MqttOptions, NetworkOptions)> {
+    let (transport, host, port) = transport(connection, address)?;
+    let credentials = super::credentials::credentials(connection)?;
+    let keep_alive = self::keep_alive(connection, keep_alive);
+    let network_options = network_options(connection);
 
-    let mut mqttoptions = MqttOptions::new(client_id, host, port);
-    mqttoptions.set_max_packet_size(usize::MAX, usize::MAX);
+    let mut mqttoptions = MqttOptions::new(client_id(connection), host, port);
+    let max_packet_size = connection
+        .max_packet_size
+        .map_or(usize::MAX, |size| size.get() as usize);
+    mqttoptions.set_max_packet_size(max_packet_size, max_packet_size);
     mqttoptions.set_transport(transport);
 
-    if let (Some(username), Some(password)) = (username, password) {
+    if let Some((username, password)) = credentials {
         mqttoptions.set_credentials(username, password);
     }
     if let Some(keep_alive) = keep_alive {
         mqttoptions.set_keep_alive(keep_alive);
     }
 
-    let (client, mut connection) = Client::new(mqttoptions, 10);
-
-    for event in connection.iter() {
-        let event = event.with_context(|| format!(
-            "Failed to connect to the MQTT broker {broker}.\nAre your MQTT connection options correct? For more information on them see --help"
-        ))?;
-        match event {
-            Event::Incoming(Packet::ConnAck(_)) => return Ok((broker, client, connection)),
-            Event::Incoming(packet) => eprintln!(
-                "Received an MQTT packet before the ConnAck. This is suspicious behaviour of the broker {broker}. The packet: {packet:?}"
-            ),
-            Event::Outgoing(_) => {} // Sending stuff is fine
-        }
+    Ok((mqttoptions, network_options))
+}
+
+fn client_id(connection: &MqttConnection) -> String {
+    connection
+        .client_id
+        .clone()
+        .unwrap_or_else(|| format!("mqttui-{:x}", rand::random::<u32>()))
+}
+
+/// Host and port of brokers connected to via TCP whose other addresses can be tried when connecting timed out
+fn tcp_host(broker: &Broker) -> Option<(&str, u16)> {
+    match broker {
+        Broker::Tcp { host, port } | Broker::Ssl { host, port } => Some((host, *port)),
+        _ => None,
     }
-    Err(anyhow::anyhow!(
-        "The MQTT connection to {broker} ended unexpectedly before it was acknowledged."
+}
+
+fn connection_failed<E>(broker: &Broker, via: &str, err: E) -> anyhow::Error
+where
+    E: std::error::Error + Send + Sync + 'static,
+{
+    let hint = super::encryption::certificate_error_hint(&err).unwrap_or(
+        "Are your MQTT connection options correct? For more information on them see --help",
+    );
+    anyhow::Error::new(err).context(format!(
+        "Failed to connect to the MQTT broker {broker}{via}.\n{hint}"
     ))
 }
+
+/// Returns true on the `ConnAck`
+fn is_connected(broker: &Broker, event: Event) -> bool {
+    match event {
+        Event::Incoming(Packet::ConnAck(_)) => return true,
+        Event::Incoming(packet) => chatter!(
+            "Received an MQTT packet before the ConnAck. This is suspicious behaviour of the broker {broker}. The packet: {packet:?}"
+        ),
+        Event::Outgoing(_) => {} // Sending stuff is fine
+    }
+    false
+}
+
+/// Try the next address of the host when connecting timed out as the connection might be stuck on an unreachable address.
+/// Each address gets the whole connection timeout.
+fn next_fallback(broker: &Broker, fallbacks: &mut Vec<IpAddr>) -> Option<IpAddr> {
+    let next = fallbacks.pop()?;
+    chatter!("Connecting to the MQTT broker {broker} timed out. Trying its address {next}");
+    Some(next)
+}
+
+/// Run the connection attempt with the broker and with the other addresses of its host while the attempts time out.
+///
+/// The addresses are only resolved after the first timeout.
+/// Failing to set up an attempt is returned right away, the error of the connection attempt with context about the broker.
+async fn with_fallbacks<T, E, Resolved, Attempted>(
+    broker: &Broker,
+    via: &str,
+    resolve: impl FnOnce(String, u16) -> Resolved,
+    mut attempt: impl FnMut(Option<IpAddr>) -> Attempted,
+    is_timeout: impl Fn(&E) -> bool,
+) -> anyhow::Result<T>
+where
+    E: std::error::Error + Send + Sync + 'static,
+    Resolved: Future<Output = Vec<IpAddr>>,
+    Attempted: Future<Output = anyhow::Result<Result<T, E>>>,
+{
+    let mut resolve = Some(resolve);
+    let mut fallbacks = Vec::new();
+    let mut address = None;
+    loop {
+        let err = match attempt(address).await? {
+            Ok(connected) => return Ok(connected),
+            Err(err) => err,
+        };
+        if !is_timeout(&err) {
+            return Err(connection_failed(broker, via, err));
+        }
+        if let (Some(resolve), Some((host, port))) = (resolve.take(), tcp_host(broker)) {
+            fallbacks = resolve(host.to_owned(), port).await;
+        }
+        address = next_fallback(broker, &mut fallbacks);
+        if address.is_none() {
+            return Err(connection_failed(broker, via, err));
+        }
+    }
+}
+
+/// Poll a future which is never pending like [`with_fallbacks`] with already finished attempts
+fn now<F: Future>(future: F) -> F::Output {
+    struct Noop;
+    impl Wake for Noop {
+        fn wake(self: Arc<Self>) {}
+    }
+    let waker = Waker::from(Arc::new(Noop));
+    match std::pin::pin!(future).poll(&mut Context::from_waker(&waker)) {
+        Poll::Ready(output) => output,
+        Poll::Pending => unreachable!("the future should not wait on anything"),
+    }
+}
+
+fn attempt(
+    connection: &MqttConnection,
+    keep_alive: Option<Duration>,
+    address: Option<IpAddr>,
+) -> anyhow::Result<Result<(Client, Connection), ConnectionError>> {
+    let (mqttoptions, network_options) = options(connection, keep_alive, address)?;
+    let (client, mut connection_events) = Client::new(mqttoptions, 10);
+    connection_events
+        .eventloop
+        .set_network_options(network_options);
+
+    let result = connection_events.iter().find_map(|event| match event {
+        Ok(event) => is_connected(&connection.broker, event).then_some(Ok(())),
+        Err(err) => Some(Err(err)),
+    });
+    match result {
+        Some(Ok(())) => Ok(Ok((client, connection_events))),
+        Some(Err(err)) => Ok(Err(err)),
+        None => anyhow::bail!(
+            "The MQTT connection to {} ended unexpectedly before it was acknowledged.",
+            connection.broker
+        ),
+    }
+}
+
+pub fn connect(
+    connection: &MqttConnection,
+    keep_alive: Option<Duration>,
+) -> anyhow::Result<(Broker, Client, Connection)> {
+    let broker = &connection.broker;
+    let (client, connection_events) = now(with_fallbacks(
+        broker,
+        "",
+        |host, port| std::future::ready(super::address::resolve_fallbacks(&host, port)),
+        |address| std::future::ready(attempt(connection, keep_alive, address)),
+        |err| matches!(err, ConnectionError::NetworkTimeout),
+    ))?;
+    Ok((broker.clone(), client, connection_events))
+}
+
+async fn attempt_async(
+    connection: &MqttConnection,
+    keep_alive: Option<Duration>,
+    address: Option<IpAddr>,
+) -> anyhow::Result<Result<(AsyncClient, EventLoop), ConnectionError>> {
+    let (mqttoptions, network_options) = options(connection, keep_alive, address)?;
+    let (client, mut eventloop) = AsyncClient::new(mqttoptions, 10);
+    eventloop.set_network_options(network_options);
+    loop {
+        match eventloop.poll().await {
+            Ok(event) => {
+                if is_connected(&connection.broker, event) {
+                    return Ok(Ok((client, eventloop)));
+                }
+            }
+            Err(err) => return Ok(Err(err)),
+        }
+    }
+}
+
+/// Like [`connect`] but for usage within an async runtime
+pub async fn connect_async(
+    connection: MqttConnection,
+    keep_alive: Option<Duration>,
+) -> anyhow::Result<(Broker, AsyncClient, EventLoop)> {
+    let (client, eventloop) = with_fallbacks(
+        &connection.broker,
+        "",
+        |host, port| async move { super::address::resolve_fallbacks_async(&host, port).await },
+        |address| attempt_async(&connection, keep_alive, address),
+        |err| matches!(err, ConnectionError::NetworkTimeout),
+    )
+    .await?;
+    Ok((connection.broker, client, eventloop))
+}
+
+fn options_v5(
+    connection: &MqttConnection,
+    address: Option<IpAddr>,
+) -> anyhow::Result<v5::MqttOptions> {
+    let (transport, host, port) = transport(connection, address)?;
+    let credentials = super::credentials::credentials(connection)?;
+    let keep_alive = keep_alive(connection, None);
+
+    let mut mqttoptions = v5::MqttOptions::new(client_id(connection), host, port);
+    mqttoptions.set_max_packet_size(connection.max_packet_size.map(std::num::NonZeroU32::get));
+    mqttoptions.set_connection_timeout(connection.connection_timeout.get());
+    mqttoptions.set_transport(transport);
+    if let Some((username, password)) = credentials {
+        mqttoptions.set_credentials(username, password);
+    }
+    if let Some(keep_alive) = keep_alive {
+        anyhow::ensure!(
+            keep_alive >= Duration::from_secs(5),
+            "MQTT 5 connections require a --keep-alive of at least 5 seconds"
+        );
+        mqttoptions.set_keep_alive(keep_alive);
+    }
+    Ok(mqttoptions)
+}
+
+async fn attempt_v5(
+    connection: &MqttConnection,
+    address: Option<IpAddr>,
+) -> anyhow::Result<Result<(v5::AsyncClient, v5::EventLoop), v5::ConnectionError>> {
+    let (client, mut eventloop) = v5::AsyncClient::new(options_v5(connection, address)?, 10);
+    loop {
+        match eventloop.poll().await {
+            Ok(v5::Event::Incoming(v5::Incoming::ConnAck(_))) => {
+                return Ok(Ok((client, eventloop)));
+            }
+            Ok(_) => {}
+            Err(err) => return Ok(Err(err)),
+        }
+    }
+}
+
+/// Like [`connect_async`] but with MQTT 5 for the features only available there
+pub async fn connect_v5(
+    connection: MqttConnection,
+) -> anyhow::Result<(Broker, v5::AsyncClient, v5::EventLoop)> {
+    let (client, eventloop) = with_fallbacks(
+        &connection.broker,
+        " via MQTT 5",
+        |host, port| async move { super::address::resolve_fallbacks_async(&host, port).await },
+        |address| attempt_v5(&connection, address),
+        |err| matches!(err, v5::ConnectionError::Timeout(_)),
+    )
+    .await?;
+    Ok((connection.broker, client, eventloop))
+}
+
+#[test]
+fn with_fallbacks_tries_the_other_addresses_on_timeouts() {
+    let broker = Broker::Tcp {
+        host: "broker.example".to_owned(),
+        port: 1883,
+    };
+    let first = IpAddr::from([192, 0, 2, 1]);
+    let second = IpAddr::from([192, 0, 2, 2]);
+    let mut tried = Vec::new();
+    let connected = now(with_fallbacks(
+        &broker,
+        "",
+        |_, _| std::future::ready(vec![second, first]),
+        |address| {
+            tried.push(address);
+            std::future::ready(Ok(if address == Some(second) {
+                Ok(())
+            } else {
+                Err(std::io::Error::from(std::io::ErrorKind::TimedOut))
+            }))
+        },
+        |err| err.kind() == std::io::ErrorKind::TimedOut,
+    ));
+    assert!(connected.is_ok());
+    assert_eq!(tried, [None, Some(first), Some(second)]);
+
+    let failed = now(with_fallbacks(
+        &broker,
+        "",
+        |_, _| std::future::ready(vec![first]),
+        |_| {
+            std::future::ready(Ok(Err::<(), _>(std::io::Error::from(
+                std::io::ErrorKind::TimedOut,
+            ))))
+        },
+        |err| err.kind() == std::io::ErrorKind::TimedOut,
+    ));
+    assert!(failed.is_err());
+}
@@ -0,0 +1,175 @@
+use std::path::PathBuf;
+
+use anyhow::Context;
+
+use crate::cli::{Broker, MqttConnection};
+
+/// Returns the (username, password) to connect with or `None` for anonymous access
+pub fn credentials(connection: &MqttConnection) -> anyhow::Result<Option<(String, String)>> {
+    let mut username = connection.username.clone();
+    let mut password = if let Some(command) = &connection.password_command {
+        Some(password_from_command(command)?)
+    } else {
+        connection.password.clone()
+    };
+
+    if let Some(profile) = connection.profile.as_deref().filter(|_| password.is_none()) {
+        let (login, stored_password) = crate::login::lookup(profile)?;
+        username = username.or(Some(login));
+        password = Some(stored_password);
+    }
+
+    if connection.netrc && password.is_none() {
+        let path = netrc_path().context("Failed to find the netrc file")?;
+        let content = std::fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read the netrc file {}", path.display()))?;
+        if let Some((login, netrc_password)) =
+            netrc_lookup(&content, host(&connection.broker), username.as_deref())
+        {
+            username = username.or(login);
+            password = Some(netrc_password);
+        }
+    }
+
+    match (username, password) {
+        (Some(username), Some(password)) => Ok(Some((username, password))),
+        (Some(username), None) => Err(anyhow::anyhow!(
+            "No password for the username {username} on {} was found",
+            connection.broker
+        )),
+        (None, _) => Ok(None),
+    }
+}
+
+fn host(broker: &Broker) -> &str {
+    match broker {
+        Broker::Tcp { host, .. } | Broker::Ssl { host, .. } => host,
+        Broker::WebSocket(url) | Broker::WebSocketSsl(url) => url.host_str().unwrap_or_default(),
+        // A socket is always on the local machine
+        Broker::Unix(_) => "localhost",
+    }
+}
+
+fn password_from_command(command: &str) -> anyhow::Result<String> {
+    #[cfg(windows)]
+    let output = std::process::Command::new("cmd")
+        .arg("/C")
+        .arg(command)
+        .output();
+    #[cfg(not(windows))]
+    let output = std::process::Command::new("sh")
+        .arg("-c")
+        .arg(command)
+        .output();
+    let output = output.with_context(|| format!("Failed to run the password command {command}"))?;
+    anyhow::ensure!(
+        output.status.success(),
+        "The password command {command} failed with {}: {}",
+        output.status,
+        String::from_utf8_lossy(&output.stderr).trim(),
+    );
+    let stdout =
+        String::from_utf8(output.stdout).context("The password command returned invalid UTF-8")?;
+    Ok(stdout.lines().next().unwrap_or_default().to_owned())
+}
+
+fn netrc_path() -> Option<PathBuf> {
+    if let Some(path) = std::env::var_os("NETRC") {
+        return Some(PathBuf::from(path));
+    }
+    let home = std::env::var_os("HOME").or_else(|| std::env::var_os("USERPROFILE"))?;
+    Some(PathBuf::from(home).join(".netrc"))
+}
+
+/// Returns (login, password) of the first entry matching the host (and login when given).
+///
+/// The `default` entry is used when no machine entry matches.
+fn netrc_lookup(
+    content: &str,
+    host: &str,
+    login: Option<&str>,
+) -> Option<(Option<String>, String)> {
+    #[derive(Default)]
+    struct Entry<'a> {
+        machine: Option<&'a str>,
+        login: Option<&'a str>,
+        password: Option<&'a str>,
+    }
+
+    let mut entries = Vec::new();
+    let mut tokens = content.split_whitespace();
+    while let Some(token) = tokens.next() {
+        match token {
+            "machine" => entries.push(Entry {
+                machine: tokens.next(),
+                ..Entry::default()
+            }),
+            "default" => entries.push(Entry::default()),
+            "login" => {
+                if let Some(entry) = entries.last_mut() {
+                    entry.login = tokens.next();
+                }
+            }
+            "password" => {
+                if let Some(entry) = entries.last_mut() {
+                    entry.password = tokens.next();
+                }
+            }
+            // Macros end with an empty line and are not relevant here
+            "macdef" => break,
+            _ => {}
+        }
+    }
+
+    let login_matches = |entry: &&Entry| login.map_or(true, |login| entry.login == Some(login));
+    entries
+        .iter()
+        .filter(login_matches)
+        .find(|entry| entry.machine == Some(host))
+        .or_else(|| {
+            entries
+                .iter()
+                .filter(login_matches)
+                .find(|entry| entry.machine.is_none())
+        })
+        .and_then(|entry| {
+            Some((
+                entry.login.map(ToOwned::to_owned),
+                entry.password?.to_owned(),
+            ))
+        })
+}
+
+#[test]
+fn netrc_lookup_machine() {
+    let content = "machine other login foo password bar\nmachine localhost\n  login user\n  password secret\n";
+    assert_eq!(
+        netrc_lookup(content, "localhost", None),
+        Some((Some("user".to_owned()), "secret".to_owned()))
+    );
+    assert_eq!(netrc_lookup(content, "unknown", None), None);
+}
+
+#[test]
+fn netrc_lookup_login() {
+    let content =
+        "machine localhost login a password first machine localhost login b password second";
+    assert_eq!(
+        netrc_lookup(content, "localhost", Some("b")),
+        Some((Some("b".to_owned()), "second".to_owned()))
+    );
+    assert_eq!(netrc_lookup(content, "localhost", Some("c")), None);
+}
+
+#[test]
+fn netrc_lookup_default() {
+    let content = "machine other login foo password bar default login user password secret";
+    assert_eq!(
+        netrc_lookup(content, "localhost", None),
+        Some((Some("user".to_owned()), "secret".to_owned()))
+    );
+    assert_eq!(
+        netrc_lookup(content, "other", None),
+        Some((Some("foo".to_owned()), "bar".to_owned()))
+    );
+}
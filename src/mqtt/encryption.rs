@@ -1,11 +1,13 @@
 use std::fs::File;
 use std::io::BufReader;
 use std::path::Path;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 
+use anyhow::Context;
 use rumqttc::TlsConfiguration;
-use rustls::client::danger::{HandshakeSignatureValid, ServerCertVerified};
-use rustls::{ClientConfig, DigitallySignedStruct, SignatureScheme};
+use rustls::client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier};
+use rustls::client::WebPkiServerVerifier;
+use rustls::{CertificateError, ClientConfig, DigitallySignedStruct, SignatureScheme};
 use rustls_pki_types::{CertificateDer, PrivateKeyDer, ServerName, UnixTime};
 
 #[derive(Debug)]
@@ -59,8 +61,113 @@ impl rustls::client::danger::ServerCertVerifier for NoVerifier {
     }
 }
 
+/// Certificate chain presented by the broker on the latest TLS handshake
+static PEER_CERTIFICATES: Mutex<Vec<CertificateDer<'static>>> = Mutex::new(Vec::new());
+
+/// Returns the certificate chain presented by the broker on the latest TLS handshake
+pub fn peer_certificates() -> Vec<CertificateDer<'static>> {
+    PEER_CERTIFICATES
+        .lock()
+        .map(|certificates| certificates.clone())
+        .unwrap_or_default()
+}
+
+/// Remembers the certificate chain before handing the verification to the inner verifier
+#[derive(Debug)]
+struct RecordingVerifier(Arc<dyn ServerCertVerifier>);
+impl ServerCertVerifier for RecordingVerifier {
+    fn verify_server_cert(
+        &self,
+        end_entity: &CertificateDer<'_>,
+        intermediates: &[CertificateDer<'_>],
+        server_name: &ServerName<'_>,
+        ocsp_response: &[u8],
+        now: UnixTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        if let Ok(mut certificates) = PEER_CERTIFICATES.lock() {
+            *certificates = core::iter::once(end_entity)
+                .chain(intermediates)
+                .map(|certificate| certificate.clone().into_owned())
+                .collect();
+        }
+        self.0
+            .verify_server_cert(end_entity, intermediates, server_name, ocsp_response, now)
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        self.0.verify_tls12_signature(message, cert, dss)
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        self.0.verify_tls13_signature(message, cert, dss)
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.0.supported_verify_schemes()
+    }
+}
+
+/// Verifies the certificate against the given name instead of the host connected to
+#[derive(Debug)]
+struct ServerNameVerifier {
+    inner: Arc<WebPkiServerVerifier>,
+    server_name: ServerName<'static>,
+}
+impl ServerCertVerifier for ServerNameVerifier {
+    fn verify_server_cert(
+        &self,
+        end_entity: &CertificateDer<'_>,
+        intermediates: &[CertificateDer<'_>],
+        _server_name: &ServerName<'_>,
+        ocsp_response: &[u8],
+        now: UnixTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        self.inner.verify_server_cert(
+            end_entity,
+            intermediates,
+            &self.server_name,
+            ocsp_response,
+            now,
+        )
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        self.inner.verify_tls12_signature(message, cert, dss)
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        self.inner.verify_tls13_signature(message, cert, dss)
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.inner.supported_verify_schemes()
+    }
+}
+
 pub fn create_tls_configuration(
     insecure: bool,
+    ca_certificate_path: Option<&Path>,
+    server_name: Option<&str>,
     client_certificate_path: Option<&Path>,
     client_private_key_path: Option<&Path>,
 ) -> anyhow::Result<TlsConfiguration> {
@@ -69,10 +176,39 @@ pub fn create_tls_configuration(
     for cert in certs {
         _ = roots.add(cert);
     }
+    if let Some(path) = ca_certificate_path {
+        let certs = read_certificate_file(path).with_context(|| {
+            format!("Failed to read the CA certificate file {}", path.display())
+        })?;
+        anyhow::ensure!(
+            !certs.is_empty(),
+            "no certificates found in {}",
+            path.display()
+        );
+        for cert in certs {
+            roots
+                .add(cert)
+                .with_context(|| format!("Invalid CA certificate in {}", path.display()))?;
+        }
+    }
+    let roots = Arc::new(roots);
 
-    let conf = ClientConfig::builder().with_root_certificates(roots);
+    let verifier: Arc<dyn ServerCertVerifier> = if insecure {
+        Arc::new(NoVerifier {})
+    } else if let Some(server_name) = server_name {
+        Arc::new(ServerNameVerifier {
+            inner: WebPkiServerVerifier::builder(roots).build()?,
+            server_name: ServerName::try_from(server_name.to_owned())
+                .with_context(|| format!("Invalid TLS server name {server_name}"))?,
+        })
+    } else {
+        WebPkiServerVerifier::builder(roots).build()?
+    };
+    let conf = ClientConfig::builder()
+        .dangerous()
+        .with_custom_certificate_verifier(Arc::new(RecordingVerifier(verifier)));
 
-    let mut conf = match (client_certificate_path, client_private_key_path) {
+    let conf = match (client_certificate_path, client_private_key_path) {
         (Some(certificate_path), Some(private_key_path)) => conf.with_client_auth_cert(
             read_certificate_file(certificate_path)?,
             read_private_key_file(private_key_path)?,
@@ -81,11 +217,6 @@ pub fn create_tls_configuration(
         _ => unreachable!("requires both cert and key which should be ensured by clap"),
     };
 
-    if insecure {
-        let mut danger = conf.dangerous();
-        danger.set_certificate_verifier(Arc::new(NoVerifier {}));
-    }
-
     Ok(TlsConfiguration::Rustls(Arc::new(conf)))
 }
 
@@ -116,3 +247,44 @@ fn read_private_key_file(file: &Path) -> anyhow::Result<PrivateKeyDer<'static>>
         "no keys found in {file:?} (encrypted keys not supported)"
     ))
 }
+
+/// Explains why the certificate of the broker was rejected when the error was caused by its verification
+pub fn certificate_error_hint(error: &(dyn std::error::Error + 'static)) -> Option<&'static str> {
+    let mut source = Some(error);
+    while let Some(error) = source {
+        // io::Error::source skips the wrapped error so look into it directly
+        let rustls_error = error.downcast_ref::<rustls::Error>().or_else(|| {
+            error
+                .downcast_ref::<std::io::Error>()
+                .and_then(std::io::Error::get_ref)
+                .and_then(|inner| inner.downcast_ref::<rustls::Error>())
+        });
+        if let Some(rustls::Error::InvalidCertificate(certificate_error)) = rustls_error {
+            return Some(match certificate_error {
+                CertificateError::UnknownIssuer => "The certificate of the broker is not signed by a trusted certificate authority. Trust its CA with --ca-cert.",
+                CertificateError::NotValidForName => "The certificate of the broker is not valid for its host. Use --tls-server-name with the name the certificate was issued for.",
+                CertificateError::Expired => "The certificate of the broker has expired.",
+                CertificateError::NotValidYet => "The certificate of the broker is not valid yet. Is the clock of this system correct?",
+                CertificateError::Revoked => "The certificate of the broker was revoked.",
+                CertificateError::BadSignature => "The signature of the certificate of the broker is invalid.",
+                CertificateError::InvalidPurpose => "The certificate of the broker is not meant to be used by a server.",
+                _ => "The certificate of the broker could not be verified.",
+            });
+        }
+        source = error.source();
+    }
+    None
+}
+
+#[test]
+fn certificate_error_hint_finds_wrapped_error() {
+    let error = std::io::Error::new(
+        std::io::ErrorKind::InvalidData,
+        rustls::Error::InvalidCertificate(CertificateError::UnknownIssuer),
+    );
+    let hint = certificate_error_hint(&error).unwrap();
+    assert!(hint.contains("--ca-cert"));
+
+    let error = std::io::Error::other("something else");
+    assert_eq!(certificate_error_hint(&error), None);
+}
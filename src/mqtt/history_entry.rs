@@ -1,8 +1,48 @@
+use std::sync::Arc;
+
 use rumqttc::QoS;
 
+use crate::payload::Payload;
+
 pub struct HistoryEntry {
     pub qos: QoS,
     pub time: crate::mqtt::Time,
     pub payload_size: usize,
-    pub payload: crate::payload::Payload,
+    /// Identical payloads share their memory
+    pub payload: Arc<Payload>,
+}
+
+impl HistoryEntry {
+    #[cfg(test)]
+    pub fn example(payload: &str, time: crate::mqtt::Time) -> Self {
+        Self {
+            qos: QoS::AtMostOnce,
+            time,
+            payload_size: payload.len(),
+            payload: Arc::new(Payload::unlimited(payload.into())),
+        }
+    }
+
+    /// Only the beginning of the payload was stored because of the payload size limit
+    pub fn is_truncated(&self) -> bool {
+        match &*self.payload {
+            Payload::Binary(data) => data.len() < self.payload_size,
+            Payload::String(str) => str.len() < self.payload_size,
+            Payload::Json(_) | Payload::MessagePack(_) => false,
+        }
+    }
+}
+
+#[test]
+fn truncated() {
+    let entry = |payload: &[u8], size_limit| HistoryEntry {
+        qos: QoS::AtMostOnce,
+        time: crate::mqtt::Time::Retained,
+        payload_size: payload.len(),
+        payload: Arc::new(Payload::truncated(payload.to_vec(), size_limit)),
+    };
+    assert!(!entry(br#"{"a": 42}"#, 100).is_truncated());
+    assert!(entry(br#"{"a": 42}"#, 5).is_truncated());
+    assert!(entry("ä".as_bytes(), 1).is_truncated());
+    assert!(!entry(b"hello", 5).is_truncated());
 }
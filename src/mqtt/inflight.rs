@@ -0,0 +1,272 @@
+use std::collections::{BTreeMap, VecDeque};
+use std::fmt::Write;
+use std::time::Instant;
+
+use rumqttc::{Outgoing, Packet, QoS};
+
+use crate::format;
+
+/// The acknowledgement a publish is waiting for
+#[allow(clippy::enum_variant_names)] // named like the packets
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Awaiting {
+    PubAck,
+    PubRec,
+    PubComp,
+}
+
+impl Awaiting {
+    const fn initial(qos: QoS) -> Self {
+        match qos {
+            QoS::AtMostOnce | QoS::AtLeastOnce => Self::PubAck,
+            QoS::ExactlyOnce => Self::PubRec,
+        }
+    }
+
+    const fn name(self) -> &'static str {
+        match self {
+            Self::PubAck => "PubAck",
+            Self::PubRec => "PubRec",
+            Self::PubComp => "PubComp",
+        }
+    }
+}
+
+/// How a packet changed the publishes
+#[derive(Debug, PartialEq, Eq)]
+pub enum Change {
+    /// The publish is in flight now or awaits the next acknowledgement
+    Progressed,
+    /// The publish was sent with `QoS` 0 or completely acknowledged
+    Finished { topic: String, qos: QoS },
+}
+
+pub struct Publish {
+    pub topic: String,
+    pub qos: QoS,
+    pub awaiting: Awaiting,
+    /// When the publish or the release was sent the last time
+    pub since: Instant,
+    /// Amount of times the publish or release was sent again, for example after a reconnect
+    pub retries: usize,
+}
+
+/// Outgoing `QoS` 1 and 2 publishes which are not yet completely acknowledged by the broker.
+///
+/// The packet events only contain the packet id so the publishes have to be announced in the order they are requested.
+/// Requested `QoS` 0 publishes are finished once they are sent.
+#[derive(Default)]
+pub struct Inflight {
+    requested: VecDeque<(String, QoS)>,
+    publishes: BTreeMap<u16, Publish>,
+}
+
+impl Inflight {
+    pub fn on_requested(&mut self, topic: String, qos: QoS) {
+        self.requested.push_back((topic, qos));
+    }
+
+    /// Returns `None` when the publishes did not change
+    pub fn on_outgoing(&mut self, outgoing: &Outgoing) -> Option<Change> {
+        self.on_outgoing_at(outgoing, Instant::now())
+    }
+
+    fn on_outgoing_at(&mut self, outgoing: &Outgoing, now: Instant) -> Option<Change> {
+        match *outgoing {
+            Outgoing::Publish(pkid) => {
+                if let Some(publish) = self.publishes.get_mut(&pkid) {
+                    // Resent as it was not acknowledged
+                    publish.awaiting = Awaiting::initial(publish.qos);
+                    publish.since = now;
+                    publish.retries = publish.retries.saturating_add(1);
+                    return Some(Change::Progressed);
+                }
+                let (topic, qos) = self.requested.pop_front()?;
+                if qos == QoS::AtMostOnce {
+                    return Some(Change::Finished { topic, qos });
+                }
+                self.publishes.insert(
+                    pkid,
+                    Publish {
+                        topic,
+                        qos,
+                        awaiting: Awaiting::initial(qos),
+                        since: now,
+                        retries: 0,
+                    },
+                );
+                Some(Change::Progressed)
+            }
+            Outgoing::PubRel(pkid) => {
+                let publish = self.publishes.get_mut(&pkid)?;
+                if publish.awaiting == Awaiting::PubComp {
+                    publish.retries = publish.retries.saturating_add(1);
+                }
+                publish.awaiting = Awaiting::PubComp;
+                publish.since = now;
+                Some(Change::Progressed)
+            }
+            _ => None,
+        }
+    }
+
+    /// Returns `None` when the publishes did not change
+    pub fn on_incoming(&mut self, packet: &Packet) -> Option<Change> {
+        let (pkid, acknowledgement) = match packet {
+            Packet::PubAck(ack) => (ack.pkid, Awaiting::PubAck),
+            Packet::PubRec(rec) => (rec.pkid, Awaiting::PubRec),
+            Packet::PubComp(comp) => (comp.pkid, Awaiting::PubComp),
+            _ => return None,
+        };
+        // Brokers might send unexpected acknowledgements which do not complete the publish
+        if self
+            .publishes
+            .get(&pkid)
+            .map_or(true, |publish| publish.awaiting != acknowledgement)
+        {
+            return None;
+        }
+        // The release is sent right after the PubRec
+        if acknowledgement == Awaiting::PubRec {
+            return Some(Change::Progressed);
+        }
+        let Publish { topic, qos, .. } = self.publishes.remove(&pkid)?;
+        Some(Change::Finished { topic, qos })
+    }
+
+    /// Topic and `QoS` of the publishes in flight followed by the requested ones which were not sent yet
+    pub fn pending(&self) -> impl Iterator<Item = (&str, QoS)> {
+        self.publishes
+            .values()
+            .map(|publish| (publish.topic.as_str(), publish.qos))
+            .chain(
+                self.requested
+                    .iter()
+                    .map(|(topic, qos)| (topic.as_str(), *qos)),
+            )
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.publishes.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.publishes.len()
+    }
+
+    /// One human readable line per in-flight publish ordered by packet id
+    pub fn lines(&self) -> impl Iterator<Item = String> + '_ {
+        self.lines_at(Instant::now())
+    }
+
+    fn lines_at(&self, now: Instant) -> impl Iterator<Item = String> + '_ {
+        self.publishes.iter().map(move |(pkid, publish)| {
+            let waiting = now.saturating_duration_since(publish.since);
+            let mut line = format!(
+                "pkid:{pkid} {} QoS:{} awaiting {} for {:.1}s",
+                publish.topic,
+                format::qos(publish.qos),
+                publish.awaiting.name(),
+                waiting.as_secs_f32(),
+            );
+            if publish.retries > 0 {
+                _ = write!(line, " retries:{}", publish.retries);
+            }
+            line
+        })
+    }
+}
+
+#[test]
+fn qos_flows() {
+    let mut inflight = Inflight::default();
+    let now = Instant::now();
+    inflight.on_requested("zero".to_owned(), QoS::AtMostOnce);
+    inflight.on_requested("one".to_owned(), QoS::AtLeastOnce);
+    inflight.on_requested("two".to_owned(), QoS::ExactlyOnce);
+    assert_eq!(
+        inflight.on_outgoing_at(&Outgoing::Publish(0), now),
+        Some(Change::Finished {
+            topic: "zero".to_owned(),
+            qos: QoS::AtMostOnce
+        })
+    );
+    assert_eq!(
+        inflight.on_outgoing_at(&Outgoing::Publish(1), now),
+        Some(Change::Progressed)
+    );
+    assert_eq!(
+        inflight.on_outgoing_at(&Outgoing::Publish(2), now),
+        Some(Change::Progressed)
+    );
+    assert_eq!(inflight.len(), 2);
+
+    // Resent after a reconnect
+    assert_eq!(
+        inflight.on_outgoing_at(&Outgoing::Publish(1), now),
+        Some(Change::Progressed)
+    );
+    assert_eq!(inflight.len(), 2);
+    assert!(inflight
+        .on_incoming(&Packet::PubAck(rumqttc::PubAck::new(2)))
+        .is_none());
+    assert_eq!(
+        inflight.on_incoming(&Packet::PubRec(rumqttc::PubRec::new(2))),
+        Some(Change::Progressed)
+    );
+    assert_eq!(
+        inflight.on_outgoing_at(&Outgoing::PubRel(2), now),
+        Some(Change::Progressed)
+    );
+    assert_eq!(
+        inflight
+            .lines_at(now + std::time::Duration::from_millis(1500))
+            .collect::<Vec<_>>(),
+        [
+            "pkid:1 one QoS:AtLeastOnce awaiting PubAck for 1.5s retries:1",
+            "pkid:2 two QoS:ExactlyOnce awaiting PubComp for 1.5s",
+        ]
+    );
+
+    assert_eq!(
+        inflight.on_incoming(&Packet::PubAck(rumqttc::PubAck::new(1))),
+        Some(Change::Finished {
+            topic: "one".to_owned(),
+            qos: QoS::AtLeastOnce
+        })
+    );
+    assert_eq!(
+        inflight.on_incoming(&Packet::PubComp(rumqttc::PubComp::new(2))),
+        Some(Change::Finished {
+            topic: "two".to_owned(),
+            qos: QoS::ExactlyOnce
+        })
+    );
+    assert!(inflight.is_empty());
+    assert!(inflight
+        .on_incoming(&Packet::PubAck(rumqttc::PubAck::new(1)))
+        .is_none());
+}
+
+#[test]
+fn resent_publish_is_not_requested_again() {
+    let mut inflight = Inflight::default();
+    inflight.on_requested("foo".to_owned(), QoS::AtLeastOnce);
+    inflight.on_requested("bar".to_owned(), QoS::AtMostOnce);
+    inflight.on_outgoing(&Outgoing::Publish(1));
+    // Reconnected and sent again
+    inflight.on_outgoing(&Outgoing::Publish(1));
+    assert_eq!(
+        inflight.pending().collect::<Vec<_>>(),
+        [("foo", QoS::AtLeastOnce), ("bar", QoS::AtMostOnce)]
+    );
+    assert_eq!(
+        inflight.on_outgoing(&Outgoing::Publish(0)),
+        Some(Change::Finished {
+            topic: "bar".to_owned(),
+            qos: QoS::AtMostOnce
+        })
+    );
+    inflight.on_incoming(&Packet::PubAck(rumqttc::PubAck::new(1)));
+    assert_eq!(inflight.pending().next(), None);
+}
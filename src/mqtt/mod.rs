@@ -1,8 +1,18 @@
-pub use self::connect::connect;
+pub use self::client_id_lock::ClientIdLock;
+pub use self::connect::{connect, connect_async, connect_v5};
 pub use self::history_entry::HistoryEntry;
+pub use self::inflight::{Change as InflightChange, Inflight};
+pub use self::subscriptions::Subscriptions;
 pub use self::time::Time;
 
+mod address;
+pub mod certificate;
+mod client_id_lock;
 mod connect;
+mod credentials;
 pub mod encryption;
 mod history_entry;
+mod inflight;
+mod subscriptions;
 mod time;
+pub mod topic_filter;
@@ -0,0 +1,215 @@
+use std::collections::{HashMap, VecDeque};
+
+use rumqttc::{AsyncClient, Client, ClientError, QoS, Request, SubAck, SubscribeReasonCode};
+
+/// Matches the `SubAck`s of the broker to the topics subscribed to.
+///
+/// This allows to tell which subscriptions were rejected by the broker.
+#[derive(Default)]
+pub struct Subscriptions {
+    /// Subscribe requests in order which are not yet handed to the [`AsyncClient`]
+    queued: VecDeque<(String, QoS)>,
+    /// Subscribe requests in order which are not yet sent to the broker
+    requested: VecDeque<String>,
+    /// Subscribe requests sent to the broker awaiting their `SubAck`
+    sent: HashMap<u16, String>,
+    /// Subscribed via [`Self::subscribe`] to subscribe again on reconnect
+    topics: Vec<(String, QoS)>,
+}
+
+impl Subscriptions {
+    pub fn subscribe(&mut self, client: &Client, topic: &str, qos: QoS) -> Result<(), ClientError> {
+        self.topics.push((topic.to_owned(), qos));
+        self.requested.push_back(topic.to_owned());
+        client.subscribe(topic, qos)
+    }
+
+    /// Subscribe again to everything subscribed to via [`Self::subscribe`].
+    ///
+    /// Call on every `ConnAck` without a present session as the broker forgot the subscriptions then.
+    /// The broker sends the retained messages again which might have changed while disconnected.
+    pub fn resubscribe(&mut self, client: &Client) -> Result<(), ClientError> {
+        self.on_reconnect();
+        for (topic, qos) in &self.topics {
+            self.requested.push_back(topic.clone());
+            client.subscribe(topic, *qos)?;
+        }
+        Ok(())
+    }
+
+    /// Call on every `ConnAck`.
+    /// The `SubAck`s of requests sent on the previous connection never arrive and the requests are not sent again.
+    pub fn on_reconnect(&mut self) {
+        self.sent.clear();
+    }
+
+    /// Like [`Self::on_reconnect`] for the [`AsyncClient`].
+    ///
+    /// The subscribe requests not yet sent are taken from the pending requests of the event loop and queued again in front.
+    /// This keeps them in the same order as they are tracked.
+    pub fn on_reconnect_async(&mut self, pending: &mut VecDeque<Request>) {
+        self.on_reconnect();
+        self.requested.clear();
+        let mut queued = VecDeque::new();
+        pending.retain(|request| {
+            if let Request::Subscribe(subscribe) = request {
+                queued.extend(
+                    subscribe
+                        .filters
+                        .iter()
+                        .map(|filter| (filter.path.clone(), filter.qos)),
+                );
+                false
+            } else {
+                true
+            }
+        });
+        queued.append(&mut self.queued);
+        self.queued = queued;
+    }
+
+    /// Topics subscribed to without a `SubAck` yet
+    pub fn unconfirmed(&self) -> impl Iterator<Item = &String> {
+        self.queued
+            .iter()
+            .map(|(topic, _)| topic)
+            .chain(&self.requested)
+            .chain(self.sent.values())
+    }
+
+    /// Like [`Self::subscribe`] for the [`AsyncClient`].
+    ///
+    /// The request is sent in order with the other queued ones by [`Self::send_queued`].
+    /// Topics already queued are skipped.
+    pub fn queue(&mut self, topic: &str, qos: QoS) {
+        if !self.queued.iter().any(|(queued, _)| queued == topic) {
+            self.queued.push_back((topic.to_owned(), qos));
+        }
+    }
+
+    /// Hand the queued subscribe requests in order to the client until its request channel is full.
+    ///
+    /// Call repeatedly while connected as the event loop has to be polled in order to accept more requests.
+    pub fn send_queued(&mut self, client: &AsyncClient) {
+        while let Some((topic, qos)) = self.queued.front() {
+            if client.try_subscribe(topic.clone(), *qos).is_err() {
+                return;
+            }
+            if let Some((topic, _)) = self.queued.pop_front() {
+                self.requested.push_back(topic);
+            }
+        }
+    }
+
+    /// Call on every `Outgoing::Subscribe`
+    pub fn on_outgoing(&mut self, pkid: u16) {
+        if let Some(topic) = self.requested.pop_front() {
+            self.sent.insert(pkid, topic);
+        }
+    }
+
+    /// Call on every `SubAck`.
+    /// Returns the topic and whether the broker accepted the subscription.
+    pub fn on_suback(&mut self, suback: &SubAck) -> Option<(String, bool)> {
+        let topic = self.sent.remove(&suback.pkid)?;
+        let accepted = suback
+            .return_codes
+            .iter()
+            .all(|code| matches!(code, SubscribeReasonCode::Success(_)));
+        Some((topic, accepted))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn subscriptions(topics: &[&str]) -> Subscriptions {
+        Subscriptions {
+            queued: VecDeque::new(),
+            requested: topics.iter().map(|topic| (*topic).to_owned()).collect(),
+            sent: HashMap::new(),
+            topics: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn accepted() {
+        let mut subscriptions = subscriptions(&["foo"]);
+        subscriptions.on_outgoing(42);
+        let suback = SubAck::new(42, vec![SubscribeReasonCode::Success(QoS::AtLeastOnce)]);
+        assert_eq!(
+            subscriptions.on_suback(&suback),
+            Some(("foo".to_owned(), true))
+        );
+    }
+
+    #[test]
+    fn rejected_in_order() {
+        let mut subscriptions = subscriptions(&["foo", "#"]);
+        subscriptions.on_outgoing(1);
+        subscriptions.on_outgoing(2);
+        let suback = SubAck::new(2, vec![SubscribeReasonCode::Failure]);
+        assert_eq!(
+            subscriptions.on_suback(&suback),
+            Some(("#".to_owned(), false))
+        );
+    }
+
+    #[test]
+    fn unconfirmed_after_reconnect() {
+        let mut subscriptions = subscriptions(&["foo", "bar"]);
+        subscriptions.on_outgoing(1);
+        subscriptions.on_outgoing(2);
+        let suback = SubAck::new(1, vec![SubscribeReasonCode::Success(QoS::AtLeastOnce)]);
+        subscriptions.on_suback(&suback);
+        assert_eq!(subscriptions.unconfirmed().collect::<Vec<_>>(), ["bar"]);
+        subscriptions.on_reconnect();
+        assert_eq!(subscriptions.unconfirmed().count(), 0);
+    }
+
+    #[test]
+    fn async_in_order() {
+        let (client, mut eventloop) =
+            AsyncClient::new(rumqttc::MqttOptions::new("test", "localhost", 1883), 1);
+        let mut subscriptions = Subscriptions::default();
+        subscriptions.queue("foo", QoS::AtLeastOnce);
+        subscriptions.queue("bar", QoS::AtLeastOnce);
+        subscriptions.queue("foo", QoS::AtLeastOnce);
+        subscriptions.send_queued(&client);
+        // The channel is full after the first request
+        assert_eq!(
+            subscriptions.unconfirmed().collect::<Vec<_>>(),
+            ["bar", "foo"]
+        );
+        assert_eq!(subscriptions.requested, ["foo"]);
+
+        // The event loop takes the unsent requests into its pending ones when the connection fails
+        eventloop
+            .pending
+            .push_back(Request::PingReq(rumqttc::PingReq));
+        eventloop.clean();
+        subscriptions.on_reconnect_async(&mut eventloop.pending);
+        assert!(subscriptions.requested.is_empty());
+        assert_eq!(eventloop.pending.len(), 1);
+        assert_eq!(
+            subscriptions.unconfirmed().collect::<Vec<_>>(),
+            ["foo", "bar"]
+        );
+
+        subscriptions.send_queued(&client);
+        subscriptions.on_outgoing(1);
+        let suback = SubAck::new(1, vec![SubscribeReasonCode::Failure]);
+        assert_eq!(
+            subscriptions.on_suback(&suback),
+            Some(("foo".to_owned(), false))
+        );
+    }
+
+    #[test]
+    fn unknown_suback() {
+        let mut subscriptions = subscriptions(&[]);
+        let suback = SubAck::new(2, vec![SubscribeReasonCode::Failure]);
+        assert_eq!(subscriptions.on_suback(&suback), None);
+    }
+}
@@ -0,0 +1,76 @@
+/// Check if the topic is matched by the topic filter which might contain wildcards like `+` or `#`.
+///
+/// Topics beginning with `$` are not matched by a wildcard on the first level as described by the MQTT specification.
+pub fn matches(filter: &str, topic: &str) -> bool {
+    if topic.starts_with('$') && filter.starts_with(['+', '#']) {
+        return false;
+    }
+
+    let mut filter = filter.split('/');
+    let mut topic = topic.split('/');
+    loop {
+        match (filter.next(), topic.next()) {
+            (Some("#"), _) | (None, None) => return true,
+            (Some("+"), Some(_)) => {}
+            (Some(filter), Some(topic)) if filter == topic => {}
+            _ => return false,
+        }
+    }
+}
+
+/// Check if the topic filter matches the topic and every topic below it like `foo/#` does for `foo`.
+pub fn covers_subtree(filter: &str, topic: &str) -> bool {
+    if filter == "#" {
+        return !topic.starts_with('$');
+    }
+    let Some(prefix) = filter.strip_suffix("/#") else {
+        return false;
+    };
+    topic
+        .match_indices('/')
+        .map(|(index, _)| &topic[..index])
+        .chain([topic])
+        .any(|ancestor| matches(prefix, ancestor))
+}
+
+#[test]
+fn exact() {
+    assert!(matches("foo/bar", "foo/bar"));
+    assert!(!matches("foo/bar", "foo/baz"));
+    assert!(!matches("foo/bar", "foo"));
+    assert!(!matches("foo", "foo/bar"));
+}
+
+#[test]
+fn single_level() {
+    assert!(matches("foo/+/baz", "foo/bar/baz"));
+    assert!(matches("foo/+", "foo/"));
+    assert!(!matches("foo/+", "foo"));
+    assert!(!matches("foo/+", "foo/bar/baz"));
+}
+
+#[test]
+fn multi_level() {
+    assert!(matches("#", "foo/bar"));
+    assert!(matches("foo/#", "foo/bar/baz"));
+    assert!(matches("foo/#", "foo"));
+    assert!(!matches("foo/#", "bar/foo"));
+}
+
+#[test]
+fn dollar_topics() {
+    assert!(!matches("#", "$SYS/uptime"));
+    assert!(!matches("+/uptime", "$SYS/uptime"));
+    assert!(matches("$SYS/#", "$SYS/uptime"));
+}
+
+#[test]
+fn subtree() {
+    assert!(covers_subtree("#", "foo/bar"));
+    assert!(covers_subtree("foo/#", "foo"));
+    assert!(covers_subtree("foo/#", "foo/bar"));
+    assert!(covers_subtree("+/bar/#", "foo/bar/baz"));
+    assert!(!covers_subtree("foo/+", "foo"));
+    assert!(!covers_subtree("foo/bar/#", "foo"));
+    assert!(!covers_subtree("#", "$SYS"));
+}
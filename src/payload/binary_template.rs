@@ -0,0 +1,287 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::Context;
+
+use crate::mqtt::topic_filter;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Endianness {
+    Little,
+    Big,
+}
+
+/// Fixed size number within binary data
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NumberType {
+    U8,
+    I8,
+    U16,
+    I16,
+    U32,
+    I32,
+    U64,
+    I64,
+    F32,
+    F64,
+}
+
+impl NumberType {
+    pub const fn size(self) -> usize {
+        match self {
+            Self::U8 | Self::I8 => 1,
+            Self::U16 | Self::I16 => 2,
+            Self::U32 | Self::I32 | Self::F32 => 4,
+            Self::U64 | Self::I64 | Self::F64 => 8,
+        }
+    }
+
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::U8 => "u8",
+            Self::I8 => "i8",
+            Self::U16 => "u16",
+            Self::I16 => "i16",
+            Self::U32 => "u32",
+            Self::I32 => "i32",
+            Self::U64 => "u64",
+            Self::I64 => "i64",
+            Self::F32 => "f32",
+            Self::F64 => "f64",
+        }
+    }
+
+    /// Interpret the bytes at the start of the data.
+    ///
+    /// Returns `None` when the data is too short or the float is not finite.
+    pub fn read(self, data: &[u8], endianness: Endianness) -> Option<serde_json::Number> {
+        macro_rules! read {
+            ($type:ty) => {{
+                let bytes = data.get(..self.size())?.try_into().ok()?;
+                match endianness {
+                    Endianness::Little => <$type>::from_le_bytes(bytes),
+                    Endianness::Big => <$type>::from_be_bytes(bytes),
+                }
+            }};
+        }
+        let number = match self {
+            Self::U8 => read!(u8).into(),
+            Self::I8 => read!(i8).into(),
+            Self::U16 => read!(u16).into(),
+            Self::I16 => read!(i16).into(),
+            Self::U32 => read!(u32).into(),
+            Self::I32 => read!(i32).into(),
+            Self::U64 => read!(u64).into(),
+            Self::I64 => read!(i64).into(),
+            // Via the shortest text representation to get 3.3 instead of 3.299999952316284
+            Self::F32 => serde_json::Number::from_f64(read!(f32).to_string().parse().ok()?)?,
+            Self::F64 => serde_json::Number::from_f64(read!(f64))?,
+        };
+        Some(number)
+    }
+}
+
+/// Parse types like `u8`, `i16le` or `f32be`. Multi-byte types need their endianness.
+fn parse_type(text: &str) -> anyhow::Result<(NumberType, Endianness)> {
+    let (name, endianness) = text
+        .strip_suffix("le")
+        .map(|name| (name, Some(Endianness::Little)))
+        .or_else(|| {
+            text.strip_suffix("be")
+                .map(|name| (name, Some(Endianness::Big)))
+        })
+        .unwrap_or((text, None));
+    let number = match name {
+        "u8" => NumberType::U8,
+        "i8" => NumberType::I8,
+        "u16" => NumberType::U16,
+        "i16" => NumberType::I16,
+        "u32" => NumberType::U32,
+        "i32" => NumberType::I32,
+        "u64" => NumberType::U64,
+        "i64" => NumberType::I64,
+        "f32" => NumberType::F32,
+        "f64" => NumberType::F64,
+        _ => anyhow::bail!("Unknown type {text}. Known are u8, i8, u16, i16, u32, i32, u64, i64, f32 and f64 with the endianness like u16le or u16be"),
+    };
+    match endianness {
+        Some(endianness) => Ok((number, endianness)),
+        None if number.size() == 1 => Ok((number, Endianness::Big)),
+        None => anyhow::bail!("The type {text} needs the endianness like {text}le or {text}be"),
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+struct Field {
+    offset: usize,
+    number: NumberType,
+    endianness: Endianness,
+    name: String,
+}
+
+/// Parse one field per line in the form `OFFSET TYPE NAME`. Lines starting with `#` are comments.
+fn parse_template(content: &str) -> anyhow::Result<Vec<Field>> {
+    let mut fields = Vec::new();
+    for (index, line) in content.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let line_number = index.saturating_add(1);
+        let mut parts = line.split_whitespace();
+        let (Some(offset), Some(number), Some(name), None) =
+            (parts.next(), parts.next(), parts.next(), parts.next())
+        else {
+            anyhow::bail!("Line {line_number} should be in the form OFFSET TYPE NAME like '0 u16le temperature'");
+        };
+        let offset = offset
+            .strip_prefix("0x")
+            .map_or_else(|| offset.parse(), |hex| usize::from_str_radix(hex, 16))
+            .with_context(|| format!("Line {line_number} has an invalid offset {offset}"))?;
+        let (number, endianness) =
+            parse_type(number).with_context(|| format!("Line {line_number} is invalid"))?;
+        fields.push(Field {
+            offset,
+            number,
+            endianness,
+            name: name.to_owned(),
+        });
+    }
+    anyhow::ensure!(!fields.is_empty(), "The template has no fields");
+    Ok(fields)
+}
+
+/// Templates describing fixed-format binary payloads of topics matching their topic filter.
+///
+/// Every field is decoded into a number of a JSON object with the field name as key.
+pub struct BinaryTemplates {
+    templates: Vec<(String, Vec<Field>)>,
+}
+
+impl BinaryTemplates {
+    /// Read the templates which are given as their topic filter and file path
+    pub fn new(templates: Vec<(String, PathBuf)>) -> anyhow::Result<Self> {
+        let templates = templates
+            .into_iter()
+            .map(|(filter, path)| Ok((filter, read(&path)?)))
+            .collect::<anyhow::Result<_>>()?;
+        Ok(Self { templates })
+    }
+
+    /// Decode the payload with the first template matching the topic.
+    ///
+    /// Returns `None` when no template is responsible for the topic.
+    pub fn decode(&self, topic: &str, payload: &[u8]) -> Option<anyhow::Result<serde_json::Value>> {
+        let (_, fields) = self
+            .templates
+            .iter()
+            .find(|(filter, _)| topic_filter::matches(filter, topic))?;
+        Some(decode(fields, payload))
+    }
+}
+
+fn read(path: &Path) -> anyhow::Result<Vec<Field>> {
+    let content = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read binary template {}", path.display()))?;
+    parse_template(&content).with_context(|| format!("Invalid binary template {}", path.display()))
+}
+
+fn decode(fields: &[Field], payload: &[u8]) -> anyhow::Result<serde_json::Value> {
+    let mut object = serde_json::Map::new();
+    for field in fields {
+        let number = payload
+            .get(field.offset..)
+            .and_then(|data| field.number.read(data, field.endianness))
+            .with_context(|| {
+                format!(
+                    "Binary template field {} at offset {} does not fit into the payload of {} bytes",
+                    field.name,
+                    field.offset,
+                    payload.len()
+                )
+            })?;
+        object.insert(field.name.clone(), serde_json::Value::Number(number));
+    }
+    Ok(serde_json::Value::Object(object))
+}
+
+#[test]
+fn read_works() {
+    let data = [0x01, 0x02, 0x03, 0x04];
+    assert_eq!(
+        NumberType::U16.read(&data, Endianness::Big),
+        Some(0x0102.into())
+    );
+    assert_eq!(
+        NumberType::U16.read(&data, Endianness::Little),
+        Some(0x0201.into())
+    );
+    assert_eq!(
+        NumberType::I8.read(&[0xff], Endianness::Big),
+        Some((-1).into())
+    );
+    assert_eq!(
+        NumberType::F32.read(&3.3_f32.to_le_bytes(), Endianness::Little),
+        serde_json::Number::from_f64(3.3)
+    );
+    assert_eq!(NumberType::U64.read(&data, Endianness::Big), None);
+}
+
+#[test]
+fn parse_type_works() {
+    assert_eq!(parse_type("u8").unwrap(), (NumberType::U8, Endianness::Big));
+    assert_eq!(
+        parse_type("i16le").unwrap(),
+        (NumberType::I16, Endianness::Little)
+    );
+    assert_eq!(
+        parse_type("f64be").unwrap(),
+        (NumberType::F64, Endianness::Big)
+    );
+    assert!(parse_type("u16").is_err());
+    assert!(parse_type("u24le").is_err());
+}
+
+#[test]
+fn parse_template_works() {
+    let fields =
+        parse_template("# offset type name\n0 u16be temperature\n\n0x2 i8 rssi\n").unwrap();
+    assert_eq!(
+        fields,
+        [
+            Field {
+                offset: 0,
+                number: NumberType::U16,
+                endianness: Endianness::Big,
+                name: "temperature".to_owned(),
+            },
+            Field {
+                offset: 2,
+                number: NumberType::I8,
+                endianness: Endianness::Big,
+                name: "rssi".to_owned(),
+            },
+        ]
+    );
+    assert!(parse_template("# only a comment").is_err());
+    assert!(parse_template("0 u8").is_err());
+    assert!(parse_template("x u8 foo").is_err());
+}
+
+#[test]
+fn decode_works() {
+    let templates = BinaryTemplates {
+        templates: vec![(
+            "sensor/+/raw".to_owned(),
+            parse_template("0 u16be temperature\n2 i8 rssi").unwrap(),
+        )],
+    };
+    assert_eq!(
+        templates
+            .decode("sensor/a/raw", &[0x00, 0xd7, 0xb5])
+            .unwrap()
+            .unwrap(),
+        serde_json::json!({"temperature": 215, "rssi": -75})
+    );
+    assert!(templates.decode("sensor/a/raw", &[0x00]).unwrap().is_err());
+    assert!(templates.decode("other", &[]).is_none());
+}
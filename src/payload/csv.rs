@@ -0,0 +1,95 @@
+/// Delimiters checked in order. The first one resulting in the same amount of columns in every line is used.
+const DELIMITERS: [char; 3] = [',', ';', '\t'];
+
+/// String payload of comma, semicolon or tab separated values like `21.5,40,1013`.
+///
+/// Quoted values containing the delimiter are not supported.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Csv<'a> {
+    /// The first line when it contains no numbers while the values do
+    pub header: Option<Vec<&'a str>>,
+    pub rows: Vec<Vec<&'a str>>,
+}
+
+impl<'a> Csv<'a> {
+    /// Detect CSV-like payloads. At least two columns and a number in the last row are required.
+    pub fn parse(text: &'a str) -> Option<Self> {
+        let lines = text
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .collect::<Vec<_>>();
+        let first = lines.first()?;
+        let mut rows = DELIMITERS.into_iter().find_map(|delimiter| {
+            let columns = first.split(delimiter).count();
+            if columns < 2 {
+                return None;
+            }
+            let rows = lines
+                .iter()
+                .map(|line| line.split(delimiter).map(cell).collect::<Vec<_>>())
+                .collect::<Vec<_>>();
+            rows.iter().all(|row| row.len() == columns).then_some(rows)
+        })?;
+
+        let has_number = |row: &[&str]| row.iter().any(|cell| cell.parse::<f64>().is_ok());
+        if !has_number(rows.last()?) {
+            return None;
+        }
+        let header = if rows.len() > 1 && !has_number(&rows[0]) {
+            Some(rows.remove(0))
+        } else {
+            None
+        };
+        Some(Self { header, rows })
+    }
+
+    pub fn columns(&self) -> usize {
+        self.rows.first().map_or(0, Vec::len)
+    }
+
+    /// The value of the column in the last row which is the most recent sample
+    pub fn last_value(&self, column: usize) -> Option<&'a str> {
+        self.rows.last()?.get(column).copied()
+    }
+}
+
+fn cell(value: &str) -> &str {
+    let value = value.trim();
+    value
+        .strip_prefix('"')
+        .and_then(|value| value.strip_suffix('"'))
+        .unwrap_or(value)
+}
+
+#[test]
+fn parse_single_line() {
+    let csv = Csv::parse("21.5, 40,\"ok\"").unwrap();
+    assert_eq!(csv.header, None);
+    assert_eq!(csv.rows, [["21.5", "40", "ok"]]);
+    assert_eq!(csv.columns(), 3);
+    assert_eq!(csv.last_value(1), Some("40"));
+    assert_eq!(csv.last_value(3), None);
+}
+
+#[test]
+fn parse_with_header() {
+    let csv = Csv::parse("time;temperature\n12:00;21.5\n12:01;21.7\n").unwrap();
+    assert_eq!(csv.header, Some(vec!["time", "temperature"]));
+    assert_eq!(csv.rows.len(), 2);
+    assert_eq!(csv.last_value(1), Some("21.7"));
+}
+
+#[test]
+fn parse_tabs() {
+    let csv = Csv::parse("1\t2\n3\t4").unwrap();
+    assert_eq!(csv.rows, [["1", "2"], ["3", "4"]]);
+}
+
+#[test]
+fn parse_rejects_text() {
+    assert_eq!(Csv::parse(""), None);
+    assert_eq!(Csv::parse("42"), None);
+    assert_eq!(Csv::parse("Hello, world"), None);
+    assert_eq!(Csv::parse("1,2\n3,4,5"), None);
+}
@@ -0,0 +1,223 @@
+use aes_gcm::aead::{Aead, KeyInit};
+use anyhow::Context;
+use cbc::cipher::block_padding::Pkcs7;
+use cbc::cipher::{BlockDecryptMut, KeyIvInit};
+
+use crate::mqtt::topic_filter;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Algorithm {
+    Aes128Cbc,
+    Aes256Cbc,
+    Aes128Gcm,
+    Aes256Gcm,
+    ChaCha20Poly1305,
+}
+
+impl Algorithm {
+    const fn key_len(self) -> usize {
+        match self {
+            Self::Aes128Cbc | Self::Aes128Gcm => 16,
+            Self::Aes256Cbc | Self::Aes256Gcm | Self::ChaCha20Poly1305 => 32,
+        }
+    }
+
+    const fn iv_len(self) -> usize {
+        match self {
+            Self::Aes128Cbc | Self::Aes256Cbc => 16,
+            Self::Aes128Gcm | Self::Aes256Gcm | Self::ChaCha20Poly1305 => 12,
+        }
+    }
+}
+
+impl core::str::FromStr for Algorithm {
+    type Err = anyhow::Error;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        Ok(match input {
+            "aes-128-cbc" => Self::Aes128Cbc,
+            "aes-256-cbc" => Self::Aes256Cbc,
+            "aes-128-gcm" => Self::Aes128Gcm,
+            "aes-256-gcm" => Self::Aes256Gcm,
+            "chacha20-poly1305" => Self::ChaCha20Poly1305,
+            _ => anyhow::bail!("Unknown algorithm {input}. Supported are aes-128-cbc, aes-256-cbc, aes-128-gcm, aes-256-gcm and chacha20-poly1305"),
+        })
+    }
+}
+
+/// Symmetric key to decrypt payloads of topics matching the filter.
+///
+/// Parsed from `FILTER=ALGORITHM:KEY[:IV]` with the key and the optional fixed IV / nonce in hex.
+/// Without a fixed IV the payload is expected to start with the IV followed by the ciphertext.
+#[derive(Debug)]
+pub struct Decryption {
+    filter: String,
+    algorithm: Algorithm,
+    key: Vec<u8>,
+    iv: Option<Vec<u8>>,
+}
+
+impl core::str::FromStr for Decryption {
+    type Err = anyhow::Error;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        let (filter, spec) = input
+            .rsplit_once('=')
+            .context("Expected the form FILTER=ALGORITHM:KEY[:IV]")?;
+        anyhow::ensure!(!filter.is_empty(), "The topic filter can not be empty");
+        let mut parts = spec.split(':');
+        let algorithm = parts.next().unwrap_or_default().parse::<Algorithm>()?;
+        let key = decode_hex(parts.next().context("Missing the key")?).context("Invalid key")?;
+        anyhow::ensure!(
+            key.len() == algorithm.key_len(),
+            "The key needs to be {} bytes long",
+            algorithm.key_len()
+        );
+        let iv = parts
+            .next()
+            .map(|iv| decode_hex(iv).context("Invalid IV"))
+            .transpose()?;
+        if let Some(iv) = &iv {
+            anyhow::ensure!(
+                iv.len() == algorithm.iv_len(),
+                "The IV needs to be {} bytes long",
+                algorithm.iv_len()
+            );
+        }
+        anyhow::ensure!(
+            parts.next().is_none(),
+            "Expected the form FILTER=ALGORITHM:KEY[:IV]"
+        );
+        Ok(Self {
+            filter: filter.to_owned(),
+            algorithm,
+            key,
+            iv,
+        })
+    }
+}
+
+impl Decryption {
+    pub fn matches(&self, topic: &str) -> bool {
+        topic_filter::matches(&self.filter, topic)
+    }
+
+    pub fn decrypt(&self, payload: &[u8]) -> anyhow::Result<Vec<u8>> {
+        let (iv, ciphertext) = if let Some(iv) = &self.iv {
+            (iv.as_slice(), payload)
+        } else {
+            let iv_len = self.algorithm.iv_len();
+            anyhow::ensure!(
+                payload.len() >= iv_len,
+                "Payload is too short to contain the IV"
+            );
+            payload.split_at(iv_len)
+        };
+        let key = self.key.as_slice();
+        let plaintext = match self.algorithm {
+            Algorithm::Aes128Cbc => cbc::Decryptor::<aes::Aes128>::new_from_slices(key, iv)?
+                .decrypt_padded_vec_mut::<Pkcs7>(ciphertext)
+                .ok(),
+            Algorithm::Aes256Cbc => cbc::Decryptor::<aes::Aes256>::new_from_slices(key, iv)?
+                .decrypt_padded_vec_mut::<Pkcs7>(ciphertext)
+                .ok(),
+            Algorithm::Aes128Gcm => aes_gcm::Aes128Gcm::new_from_slice(key)?
+                .decrypt(iv.into(), ciphertext)
+                .ok(),
+            Algorithm::Aes256Gcm => aes_gcm::Aes256Gcm::new_from_slice(key)?
+                .decrypt(iv.into(), ciphertext)
+                .ok(),
+            Algorithm::ChaCha20Poly1305 => chacha20poly1305::ChaCha20Poly1305::new_from_slice(key)?
+                .decrypt(iv.into(), ciphertext)
+                .ok(),
+        };
+        plaintext.context("Failed to decrypt the payload")
+    }
+}
+
+fn decode_hex(hex: &str) -> anyhow::Result<Vec<u8>> {
+    // from_str_radix would also accept a sign like +f
+    anyhow::ensure!(
+        hex.chars().all(|char| char.is_ascii_hexdigit()),
+        "Not a valid hex digit"
+    );
+    anyhow::ensure!(hex.len() % 2 == 0, "Hex needs an even amount of digits");
+    (0..hex.len())
+        .step_by(2)
+        .map(|index| {
+            u8::from_str_radix(&hex[index..index + 2], 16).context("Not a valid hex digit")
+        })
+        .collect()
+}
+
+#[test]
+fn decode_hex_works() {
+    assert_eq!(decode_hex("00ff10").unwrap(), [0x00, 0xff, 0x10]);
+    assert!(decode_hex("0").is_err());
+    assert!(decode_hex("zz").is_err());
+    assert!(decode_hex("+f").is_err());
+    assert!(decode_hex("ä0").is_err());
+}
+
+#[test]
+fn parse_works() {
+    let decryption = "foo/#=aes-128-cbc:000102030405060708090a0b0c0d0e0f"
+        .parse::<Decryption>()
+        .unwrap();
+    assert_eq!(decryption.filter, "foo/#");
+    assert_eq!(decryption.algorithm, Algorithm::Aes128Cbc);
+    assert_eq!(decryption.key.len(), 16);
+    assert!(decryption.iv.is_none());
+}
+
+#[test]
+fn parse_rejects_wrong_key_length() {
+    assert!("foo=aes-256-gcm:00ff".parse::<Decryption>().is_err());
+    assert!("foo=rot13:00ff".parse::<Decryption>().is_err());
+}
+
+#[test]
+fn decrypts_aes_cbc_with_prefixed_iv() {
+    use cbc::cipher::BlockEncryptMut;
+    let key = [42; 16];
+    let iv = [7; 16];
+    let mut payload = iv.to_vec();
+    payload.extend(
+        cbc::Encryptor::<aes::Aes128>::new(&key.into(), &iv.into())
+            .encrypt_padded_vec_mut::<Pkcs7>(b"Hello World"),
+    );
+    let decryption = Decryption {
+        filter: "#".to_owned(),
+        algorithm: Algorithm::Aes128Cbc,
+        key: key.to_vec(),
+        iv: None,
+    };
+    assert_eq!(decryption.decrypt(&payload).unwrap(), b"Hello World");
+}
+
+#[test]
+fn decrypts_aes_gcm_with_fixed_nonce() {
+    let key = [42; 32];
+    let nonce = [7; 12];
+    let payload = aes_gcm::Aes256Gcm::new(&key.into())
+        .encrypt(&nonce.into(), b"Hello World".as_slice())
+        .unwrap();
+    let decryption = Decryption {
+        filter: "#".to_owned(),
+        algorithm: Algorithm::Aes256Gcm,
+        key: key.to_vec(),
+        iv: Some(nonce.to_vec()),
+    };
+    assert_eq!(decryption.decrypt(&payload).unwrap(), b"Hello World");
+}
+
+#[test]
+fn wrong_key_fails() {
+    let decryption = Decryption {
+        filter: "#".to_owned(),
+        algorithm: Algorithm::ChaCha20Poly1305,
+        key: vec![0; 32],
+        iv: None,
+    };
+    assert!(decryption.decrypt(&[1; 40]).is_err());
+}
@@ -0,0 +1,73 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Weak};
+
+use super::Payload;
+
+/// Bigger payloads are rarely identical and not worth the lookup
+const MAX_PAYLOAD_SIZE: usize = 1024;
+/// Limits the memory the interner itself needs
+const MAX_ENTRIES: usize = 100_000;
+
+/// Identical payloads like `online` share their memory instead of being stored for each message.
+#[derive(Default)]
+pub struct Interner {
+    payloads: HashMap<Box<[u8]>, Weak<Payload>>,
+}
+
+impl Interner {
+    /// Returns the payload and whether an existing one is shared
+    pub fn intern(
+        &mut self,
+        bytes: Vec<u8>,
+        create: impl FnOnce(Vec<u8>) -> Payload,
+    ) -> (Arc<Payload>, bool) {
+        if bytes.len() > MAX_PAYLOAD_SIZE {
+            return (Arc::new(create(bytes)), false);
+        }
+        if let Some(existing) = self.payloads.get(bytes.as_slice()).and_then(Weak::upgrade) {
+            return (existing, true);
+        }
+        if self.payloads.len() >= MAX_ENTRIES {
+            // Forget payloads which are not in use anymore
+            self.payloads
+                .retain(|_, payload| payload.strong_count() > 0);
+        }
+        let key = (self.payloads.len() < MAX_ENTRIES).then(|| bytes.clone().into_boxed_slice());
+        let payload = Arc::new(create(bytes));
+        if let Some(key) = key {
+            self.payloads.insert(key, Arc::downgrade(&payload));
+        }
+        (payload, false)
+    }
+}
+
+#[test]
+fn identical_payloads_are_shared() {
+    let mut interner = Interner::default();
+    let (first, shared) = interner.intern(b"online".to_vec(), Payload::unlimited);
+    assert!(!shared);
+    let (second, shared) = interner.intern(b"online".to_vec(), Payload::unlimited);
+    assert!(shared);
+    assert!(Arc::ptr_eq(&first, &second));
+    let (other, shared) = interner.intern(b"offline".to_vec(), Payload::unlimited);
+    assert!(!shared);
+    assert!(!Arc::ptr_eq(&first, &other));
+}
+
+#[test]
+fn unused_payloads_are_not_shared() {
+    let mut interner = Interner::default();
+    let (first, _) = interner.intern(b"online".to_vec(), Payload::unlimited);
+    drop(first);
+    let (_, shared) = interner.intern(b"online".to_vec(), Payload::unlimited);
+    assert!(!shared);
+}
+
+#[test]
+fn big_payloads_are_not_shared() {
+    let mut interner = Interner::default();
+    let big = vec![b'a'; MAX_PAYLOAD_SIZE + 1];
+    let (_first, _) = interner.intern(big.clone(), Payload::unlimited);
+    let (_, shared) = interner.intern(big, Payload::unlimited);
+    assert!(!shared);
+}
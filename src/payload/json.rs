@@ -1,44 +1,141 @@
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::Text;
 use serde_json::Value;
 use tui_tree_widget::TreeItem;
 
 use crate::payload::JsonSelector;
 
-pub fn tree_items(root: &Value) -> Vec<TreeItem<'_, JsonSelector>> {
+/// Keys which values differ from the previous payload
+pub const STYLE_CHANGED: Style = Style::new().fg(Color::Yellow).add_modifier(Modifier::BOLD);
+
+/// When the previous payload is given keys with a different value are highlighted
+pub fn tree_items<'a>(
+    root: &'a Value,
+    previous: Option<&Value>,
+) -> Vec<TreeItem<'a, JsonSelector>> {
     match root {
-        Value::Object(object) => from_object(object),
-        Value::Array(array) => from_array(array),
+        Value::Object(object) => from_object(object, previous),
+        Value::Array(array) => from_array(array, previous),
         _ => vec![TreeItem::new_leaf(JsonSelector::None, root.to_string())],
     }
 }
 
-fn recurse(key: JsonSelector, value: &Value) -> TreeItem<JsonSelector> {
+/// `previous` is the previous value of the parent
+fn recurse<'a>(
+    key: JsonSelector,
+    value: &'a Value,
+    previous: Option<&Value>,
+) -> TreeItem<'a, JsonSelector> {
+    let previous_value = previous.and_then(|previous| key.apply_json(previous));
+    let style = if previous.is_some() && previous_value != Some(value) {
+        STYLE_CHANGED
+    } else {
+        Style::new()
+    };
     match value {
         Value::Object(object) => {
-            let text = key.to_string();
-            TreeItem::new(key, text, from_object(object)).unwrap()
+            let text = Text::styled(key.to_string(), style);
+            TreeItem::new(key, text, from_object(object, previous_value)).unwrap()
         }
         Value::Array(array) => {
-            let text = key.to_string();
-            TreeItem::new(key, text, from_array(array)).unwrap()
+            let text = Text::styled(key.to_string(), style);
+            TreeItem::new(key, text, from_array(array, previous_value)).unwrap()
         }
         _ => {
-            let text = format!("{key}: {value}");
+            let text = Text::styled(format!("{key}: {value}"), style);
             TreeItem::new_leaf(key, text)
         }
     }
 }
 
-fn from_object(object: &serde_json::Map<String, Value>) -> Vec<TreeItem<'_, JsonSelector>> {
+fn from_object<'a>(
+    object: &'a serde_json::Map<String, Value>,
+    previous: Option<&Value>,
+) -> Vec<TreeItem<'a, JsonSelector>> {
     object
         .iter()
-        .map(|(key, value)| recurse(JsonSelector::ObjectKey(key.clone()), value))
+        .map(|(key, value)| recurse(JsonSelector::ObjectKey(key.clone()), value, previous))
         .collect()
 }
 
-fn from_array(array: &[Value]) -> Vec<TreeItem<'_, JsonSelector>> {
+fn from_array<'a>(array: &'a [Value], previous: Option<&Value>) -> Vec<TreeItem<'a, JsonSelector>> {
     array
         .iter()
         .enumerate()
-        .map(|(index, value)| recurse(JsonSelector::ArrayIndex(index), value))
+        .map(|(index, value)| recurse(JsonSelector::ArrayIndex(index), value, previous))
         .collect()
 }
+
+/// The texts of all tree items in the order they are shown together with their selector
+pub fn tree_texts(root: &Value) -> Vec<(Vec<JsonSelector>, String)> {
+    fn recurse(
+        selector: &mut Vec<JsonSelector>,
+        value: &Value,
+        texts: &mut Vec<(Vec<JsonSelector>, String)>,
+    ) {
+        let children: Vec<(JsonSelector, &Value)> = match value {
+            Value::Object(object) => object
+                .iter()
+                .map(|(key, value)| (JsonSelector::ObjectKey(key.clone()), value))
+                .collect(),
+            Value::Array(array) => array
+                .iter()
+                .enumerate()
+                .map(|(index, value)| (JsonSelector::ArrayIndex(index), value))
+                .collect(),
+            _ => return,
+        };
+        for (key, value) in children {
+            let text = if matches!(value, Value::Object(_) | Value::Array(_)) {
+                key.to_string()
+            } else {
+                format!("{key}: {value}")
+            };
+            selector.push(key);
+            texts.push((selector.clone(), text));
+            recurse(selector, value, texts);
+            selector.pop();
+        }
+    }
+
+    if !matches!(root, Value::Object(_) | Value::Array(_)) {
+        return vec![(vec![JsonSelector::None], root.to_string())];
+    }
+    let mut texts = Vec::new();
+    recurse(&mut Vec::new(), root, &mut texts);
+    texts
+}
+
+#[test]
+fn tree_texts_works() {
+    let json = serde_json::json!({"a": {"b": 42}, "c": [true]});
+    let texts = tree_texts(&json)
+        .into_iter()
+        .map(|(selector, text)| (selector.len(), text))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        texts,
+        [
+            (1, "a".to_owned()),
+            (2, "b: 42".to_owned()),
+            (1, "c".to_owned()),
+            (2, "0: true".to_owned()),
+        ]
+    );
+}
+
+#[test]
+fn tree_items_highlight_changes() {
+    let previous = serde_json::json!({"a": 1, "b": {"c": 2, "d": 3}});
+    let json = serde_json::json!({"a": 1, "b": {"c": 2, "d": 4}, "e": 5});
+    let items = tree_items(&json, Some(&previous));
+    let changed = |item: &TreeItem<'_, JsonSelector>| format!("{item:?}").contains("Yellow");
+    assert!(!changed(&items[0]));
+    assert!(changed(&items[1]));
+    assert!(!changed(&items[1].children()[0]));
+    assert!(changed(&items[1].children()[1]));
+    assert!(changed(&items[2]));
+
+    let items = tree_items(&json, None);
+    assert!(!items.iter().any(changed));
+}
@@ -1,4 +1,6 @@
-#[derive(Default, Clone, PartialEq, Eq, Hash)]
+use std::fmt::Write;
+
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash)]
 pub enum JsonSelector {
     ObjectKey(String),
     ArrayIndex(usize),
@@ -7,7 +9,7 @@ pub enum JsonSelector {
 }
 
 impl JsonSelector {
-    fn apply_json<'v>(&self, root: &'v serde_json::Value) -> Option<&'v serde_json::Value> {
+    pub fn apply_json<'v>(&self, root: &'v serde_json::Value) -> Option<&'v serde_json::Value> {
         use serde_json::Value;
         match (root, self) {
             (Value::Object(object), Self::ObjectKey(key)) => object.get(key),
@@ -27,7 +29,7 @@ impl JsonSelector {
         Some(current)
     }
 
-    fn apply_messagepack<'v>(&self, root: &'v rmpv::Value) -> Option<&'v rmpv::Value> {
+    pub fn apply_messagepack<'v>(&self, root: &'v rmpv::Value) -> Option<&'v rmpv::Value> {
         use rmpv::Value;
         match (root, self) {
             (Value::Array(array), Self::ArrayIndex(index)) => array.get(*index),
@@ -55,6 +57,47 @@ impl JsonSelector {
         }
         Some(current)
     }
+
+    /// jq path like `.sensors[2].temp` which is also understood by the assert subcommand
+    pub fn jq_path(selector: &[Self]) -> String {
+        let mut path = String::new();
+        for select in selector {
+            match select {
+                Self::ObjectKey(key) if is_identifier(key) => _ = write!(path, ".{key}"),
+                Self::ObjectKey(key) => {
+                    let quoted = serde_json::to_string(key).expect("strings are valid JSON");
+                    _ = write!(path, "[{quoted}]");
+                }
+                Self::ArrayIndex(index) => _ = write!(path, "[{index}]"),
+                Self::None => {}
+            }
+        }
+        if path.is_empty() {
+            path.push('.');
+        }
+        path
+    }
+
+    /// JSON Pointer (RFC 6901) like `/sensors/2/temp`
+    pub fn json_pointer(selector: &[Self]) -> String {
+        let mut pointer = String::new();
+        for select in selector {
+            if !matches!(select, Self::None) {
+                let token = select.to_string().replace('~', "~0").replace('/', "~1");
+                _ = write!(pointer, "/{token}");
+            }
+        }
+        pointer
+    }
+}
+
+/// Keys which jq accepts without quotes
+fn is_identifier(key: &str) -> bool {
+    let mut chars = key.chars();
+    chars
+        .next()
+        .is_some_and(|first| first.is_ascii_alphabetic() || first == '_')
+        && chars.all(|char| char.is_ascii_alphanumeric() || char == '_')
 }
 
 impl std::fmt::Display for JsonSelector {
@@ -88,6 +131,24 @@ fn display_none() {
     assert_eq!(result, "");
 }
 
+#[test]
+fn paths_work() {
+    let selector = [
+        JsonSelector::ObjectKey("sensors".to_owned()),
+        JsonSelector::ArrayIndex(2),
+        JsonSelector::ObjectKey("temp".to_owned()),
+    ];
+    assert_eq!(JsonSelector::jq_path(&selector), ".sensors[2].temp");
+    assert_eq!(JsonSelector::json_pointer(&selector), "/sensors/2/temp");
+
+    let selector = [JsonSelector::ObjectKey("with space/~".to_owned())];
+    assert_eq!(JsonSelector::jq_path(&selector), r#"["with space/~"]"#);
+    assert_eq!(JsonSelector::json_pointer(&selector), "/with space~1~0");
+
+    assert_eq!(JsonSelector::jq_path(&[]), ".");
+    assert_eq!(JsonSelector::json_pointer(&[]), "");
+}
+
 #[cfg(test)]
 mod json_tests {
     use serde_json::Value;
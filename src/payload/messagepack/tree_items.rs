@@ -1,49 +1,110 @@
+use ratatui::style::Style;
+use ratatui::text::Text;
 use rmpv::Value;
 use tui_tree_widget::TreeItem;
 
 use super::map_key;
+use crate::payload::json::STYLE_CHANGED;
 use crate::payload::JsonSelector;
 
-pub fn tree_items(root: &Value) -> Vec<TreeItem<'_, JsonSelector>> {
+/// When the previous payload is given keys with a different value are highlighted
+pub fn tree_items<'a>(
+    root: &'a Value,
+    previous: Option<&Value>,
+) -> Vec<TreeItem<'a, JsonSelector>> {
     match root {
-        Value::Map(object) => from_map(object),
-        Value::Array(array) => from_array(array),
+        Value::Map(object) => from_map(object, previous),
+        Value::Array(array) => from_array(array, previous),
         _ => vec![TreeItem::new_leaf(JsonSelector::None, root.to_string())],
     }
 }
 
-fn recurse(key: JsonSelector, value: &Value) -> TreeItem<JsonSelector> {
+/// `previous` is the previous value of the parent
+fn recurse<'a>(
+    key: JsonSelector,
+    value: &'a Value,
+    previous: Option<&Value>,
+) -> TreeItem<'a, JsonSelector> {
+    let previous_value = previous.and_then(|previous| key.apply_messagepack(previous));
+    let style = if previous.is_some() && previous_value != Some(value) {
+        STYLE_CHANGED
+    } else {
+        Style::new()
+    };
     match value {
         Value::Map(object) => {
-            let text = key.to_string();
-            TreeItem::new(key, text, from_map(object)).unwrap()
+            let text = Text::styled(key.to_string(), style);
+            TreeItem::new(key, text, from_map(object, previous_value)).unwrap()
         }
         Value::Array(array) => {
-            let text = key.to_string();
-            TreeItem::new(key, text, from_array(array)).unwrap()
+            let text = Text::styled(key.to_string(), style);
+            TreeItem::new(key, text, from_array(array, previous_value)).unwrap()
         }
         _ => {
-            let text = format!("{key}: {value}");
+            let text = Text::styled(format!("{key}: {value}"), style);
             TreeItem::new_leaf(key, text)
         }
     }
 }
 
-fn from_map(object: &[(Value, Value)]) -> Vec<TreeItem<'_, JsonSelector>> {
+fn from_map<'a>(
+    object: &'a [(Value, Value)],
+    previous: Option<&Value>,
+) -> Vec<TreeItem<'a, JsonSelector>> {
     object
         .iter()
-        .map(|(key, value)| recurse(JsonSelector::ObjectKey(map_key(key)), value))
+        .map(|(key, value)| recurse(JsonSelector::ObjectKey(map_key(key)), value, previous))
         .collect()
 }
 
-fn from_array(array: &[Value]) -> Vec<TreeItem<'_, JsonSelector>> {
+fn from_array<'a>(array: &'a [Value], previous: Option<&Value>) -> Vec<TreeItem<'a, JsonSelector>> {
     array
         .iter()
         .enumerate()
-        .map(|(index, value)| recurse(JsonSelector::ArrayIndex(index), value))
+        .map(|(index, value)| recurse(JsonSelector::ArrayIndex(index), value, previous))
         .collect()
 }
 
+/// The texts of all tree items in the order they are shown together with their selector
+pub fn tree_texts(root: &Value) -> Vec<(Vec<JsonSelector>, String)> {
+    fn recurse(
+        selector: &mut Vec<JsonSelector>,
+        value: &Value,
+        texts: &mut Vec<(Vec<JsonSelector>, String)>,
+    ) {
+        let children: Vec<(JsonSelector, &Value)> = match value {
+            Value::Map(object) => object
+                .iter()
+                .map(|(key, value)| (JsonSelector::ObjectKey(map_key(key)), value))
+                .collect(),
+            Value::Array(array) => array
+                .iter()
+                .enumerate()
+                .map(|(index, value)| (JsonSelector::ArrayIndex(index), value))
+                .collect(),
+            _ => return,
+        };
+        for (key, value) in children {
+            let text = if matches!(value, Value::Map(_) | Value::Array(_)) {
+                key.to_string()
+            } else {
+                format!("{key}: {value}")
+            };
+            selector.push(key);
+            texts.push((selector.clone(), text));
+            recurse(selector, value, texts);
+            selector.pop();
+        }
+    }
+
+    if !matches!(root, Value::Map(_) | Value::Array(_)) {
+        return vec![(vec![JsonSelector::None], root.to_string())];
+    }
+    let mut texts = Vec::new();
+    recurse(&mut Vec::new(), root, &mut texts);
+    texts
+}
+
 #[test]
 fn value_to_string_is_same_as_variant_to_string() {
     let int: rmpv::Integer = 42.into();
@@ -1,12 +1,31 @@
+use std::sync::Arc;
+
+use anyhow::Context;
 use serde::Serialize;
 
+use self::binary_template::BinaryTemplates;
+use self::decryption::Decryption;
+use self::script::ScriptDecoders;
+use crate::cli::DecodingArgs;
+use crate::transform::Transform;
+
+pub use self::binary_template::{Endianness, NumberType};
+pub use self::csv::Csv;
+pub use self::interner::Interner;
 pub use self::json::tree_items as tree_items_from_json;
+pub use self::json::tree_texts as tree_texts_from_json;
 pub use self::json_selector::JsonSelector;
 pub use self::messagepack::tree_items::tree_items as tree_items_from_messagepack;
+pub use self::messagepack::tree_items::tree_texts as tree_texts_from_messagepack;
 
+mod binary_template;
+mod csv;
+mod decryption;
+mod interner;
 mod json;
 mod json_selector;
 mod messagepack;
+mod script;
 
 #[derive(Debug, Clone, PartialEq, Serialize)]
 #[serde(untagged)]
@@ -44,6 +63,141 @@ impl Payload {
     }
 }
 
+/// Custom processing of received payloads before they are displayed
+#[derive(Default)]
+pub struct Decoding {
+    decryption: Vec<Decryption>,
+    transform: Option<Transform>,
+    scripts: Option<ScriptDecoders>,
+    binary_templates: Option<BinaryTemplates>,
+}
+
+impl Decoding {
+    pub fn new(
+        DecodingArgs {
+            decrypt,
+            transform,
+            decoder: scripts,
+            binary_template: binary_templates,
+        }: DecodingArgs,
+    ) -> anyhow::Result<Self> {
+        let decryption = decrypt
+            .iter()
+            .map(|arg| {
+                arg.parse()
+                    .with_context(|| format!("Invalid decryption argument {arg}"))
+            })
+            .collect::<anyhow::Result<_>>()?;
+        let scripts = if scripts.is_empty() {
+            None
+        } else {
+            Some(ScriptDecoders::new(scripts)?)
+        };
+        let binary_templates = if binary_templates.is_empty() {
+            None
+        } else {
+            Some(BinaryTemplates::new(binary_templates)?)
+        };
+        Ok(Self {
+            decryption,
+            transform: transform.map(Transform::new),
+            scripts,
+            binary_templates,
+        })
+    }
+
+    /// Returns the size of the processed payload and the payload itself.
+    ///
+    /// Failing steps are reported and skipped.
+    pub fn decode(
+        &self,
+        topic: &str,
+        payload: Vec<u8>,
+        size_limit: usize,
+        on_error: impl FnMut(anyhow::Error),
+    ) -> (usize, Payload) {
+        match self.process(topic, payload, on_error) {
+            (size, Ok(json)) => (size, Payload::Json(json)),
+            (size, Err(payload)) => (size, Payload::truncated(payload, size_limit)),
+        }
+    }
+
+    /// Like [`Self::decode`] but identical payloads share their memory.
+    ///
+    /// Returns the size, the payload and whether the payload is shared with an earlier one.
+    pub fn decode_interned(
+        &self,
+        topic: &str,
+        payload: Vec<u8>,
+        size_limit: usize,
+        on_error: impl FnMut(anyhow::Error),
+        interner: &mut Interner,
+    ) -> (usize, Arc<Payload>, bool) {
+        match self.process(topic, payload, on_error) {
+            (size, Ok(json)) => (size, Arc::new(Payload::Json(json)), false),
+            (size, Err(payload)) => {
+                let (payload, shared) =
+                    interner.intern(payload, |payload| Payload::truncated(payload, size_limit));
+                (size, payload, shared)
+            }
+        }
+    }
+
+    /// Returns the size of the processed payload and either the JSON of a decoder script or binary template or the processed bytes
+    fn process(
+        &self,
+        topic: &str,
+        payload: Vec<u8>,
+        mut on_error: impl FnMut(anyhow::Error),
+    ) -> (usize, Result<serde_json::Value, Vec<u8>>) {
+        let payload = match self
+            .decryption
+            .iter()
+            .find(|decryption| decryption.matches(topic))
+            .map(|decryption| decryption.decrypt(&payload))
+        {
+            Some(Ok(decrypted)) => decrypted,
+            Some(Err(err)) => {
+                on_error(err);
+                payload
+            }
+            None => payload,
+        };
+        let payload = match self
+            .transform
+            .as_ref()
+            .map(|transform| transform.run(topic, &payload))
+        {
+            Some(Ok(transformed)) => transformed,
+            Some(Err(err)) => {
+                on_error(err);
+                payload
+            }
+            None => payload,
+        };
+        let size = payload.len();
+        match self
+            .scripts
+            .as_ref()
+            .and_then(|scripts| scripts.decode(topic, &payload))
+        {
+            Some(Ok(json)) => return (size, Ok(json)),
+            Some(Err(err)) => on_error(err),
+            None => {}
+        }
+        match self
+            .binary_templates
+            .as_ref()
+            .and_then(|templates| templates.decode(topic, &payload))
+        {
+            Some(Ok(json)) => return (size, Ok(json)),
+            Some(Err(err)) => on_error(err),
+            None => {}
+        }
+        (size, Err(payload))
+    }
+}
+
 impl std::fmt::Display for Payload {
     fn fmt(&self, fmt: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
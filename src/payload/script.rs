@@ -0,0 +1,118 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::Context;
+use rhai::{Blob, Engine, Scope, AST};
+
+use crate::mqtt::topic_filter;
+
+/// Limits of a script run per payload so a runaway script fails instead of stalling the decoding
+const MAX_OPERATIONS: u64 = 1_000_000;
+const MAX_CALL_LEVELS: usize = 32;
+const MAX_EXPR_DEPTH: usize = 64;
+const MAX_FUNCTION_EXPR_DEPTH: usize = 32;
+
+/// [Rhai](https://rhai.rs) scripts decoding payloads of topics matching their topic filter.
+///
+/// The script has access to the raw payload as `payload` (a blob of bytes) and the `topic`.
+/// The value of the last expression is the decoded payload and is shown like JSON.
+pub struct ScriptDecoders {
+    engine: Engine,
+    scripts: Vec<(String, AST)>,
+}
+
+impl ScriptDecoders {
+    /// Compile the scripts which are given as their topic filter and file path
+    pub fn new(scripts: Vec<(String, PathBuf)>) -> anyhow::Result<Self> {
+        let engine = engine();
+        let scripts = scripts
+            .into_iter()
+            .map(|(filter, path)| {
+                let ast = compile(&engine, &path)?;
+                Ok((filter, ast))
+            })
+            .collect::<anyhow::Result<_>>()?;
+        Ok(Self { engine, scripts })
+    }
+
+    /// Decode the payload with the first script matching the topic.
+    ///
+    /// Returns `None` when no script is responsible for the topic.
+    pub fn decode(&self, topic: &str, payload: &[u8]) -> Option<anyhow::Result<serde_json::Value>> {
+        let (_, ast) = self
+            .scripts
+            .iter()
+            .find(|(filter, _)| topic_filter::matches(filter, topic))?;
+        let mut scope = Scope::new();
+        scope.push_constant("topic", topic.to_owned());
+        scope.push_constant("payload", Blob::from(payload));
+        let result = self
+            .engine
+            .eval_ast_with_scope::<rhai::Dynamic>(&mut scope, ast)
+            .map_err(|err| anyhow::anyhow!("Decoder script failed: {err}"))
+            .and_then(|value| {
+                rhai::serde::from_dynamic(&value).map_err(|err| {
+                    anyhow::anyhow!("Decoder script returned an invalid value: {err}")
+                })
+            });
+        Some(result)
+    }
+}
+
+fn engine() -> Engine {
+    let mut engine = Engine::new();
+    engine.set_max_operations(MAX_OPERATIONS);
+    engine.set_max_call_levels(MAX_CALL_LEVELS);
+    engine.set_max_expr_depths(MAX_EXPR_DEPTH, MAX_FUNCTION_EXPR_DEPTH);
+    engine
+}
+
+fn compile(engine: &Engine, path: &Path) -> anyhow::Result<AST> {
+    let script = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read decoder script {}", path.display()))?;
+    engine.compile(script).map_err(|err| {
+        anyhow::anyhow!("Failed to compile decoder script {}: {err}", path.display())
+    })
+}
+
+#[cfg(test)]
+fn decoders_from_source(filter: &str, source: &str) -> ScriptDecoders {
+    let engine = engine();
+    let ast = engine.compile(source).unwrap();
+    ScriptDecoders {
+        engine,
+        scripts: vec![(filter.to_owned(), ast)],
+    }
+}
+
+#[test]
+fn decodes_matching_topic() {
+    let decoders = decoders_from_source(
+        "sensor/+/raw",
+        "#{ temperature: (payload[0] * 256 + payload[1]) / 10.0, topic: topic }",
+    );
+    let value = decoders.decode("sensor/a/raw", &[0, 215]).unwrap().unwrap();
+    assert_eq!(
+        value,
+        serde_json::json!({"temperature": 21.5, "topic": "sensor/a/raw"})
+    );
+}
+
+#[test]
+fn ignores_other_topics() {
+    let decoders = decoders_from_source("sensor/+/raw", "42");
+    assert!(decoders.decode("sensor/a/json", b"").is_none());
+}
+
+#[test]
+fn script_error_is_error() {
+    let decoders = decoders_from_source("#", "payload[42]");
+    assert!(decoders.decode("foo", b"").unwrap().is_err());
+}
+
+#[test]
+fn runaway_script_is_error() {
+    let decoders = decoders_from_source("#", "loop {}");
+    assert!(decoders.decode("foo", b"").unwrap().is_err());
+    let decoders = decoders_from_source("#", "fn f(x) { f(x) } f(1)");
+    assert!(decoders.decode("foo", b"").unwrap().is_err());
+}
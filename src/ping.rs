@@ -0,0 +1,90 @@
+use std::num::NonZeroUsize;
+use std::thread::sleep;
+use std::time::{Duration, Instant};
+
+use rumqttc::{Client, Connection, Event, Outgoing, Packet};
+
+use crate::cli::Broker;
+use crate::mqtt::{certificate, encryption};
+
+/// Warn about certificates which expire within this amount of days
+const EXPIRY_WARNING_DAYS: i64 = 30;
+
+fn print_tls_info(broker: &Broker) -> anyhow::Result<()> {
+    let chain = encryption::peer_certificates();
+    anyhow::ensure!(
+        !chain.is_empty(),
+        "The connection to {broker} does not use TLS"
+    );
+    let now = chrono::Utc::now().naive_utc();
+    println!("Certificate chain of {broker}:");
+    for (index, der) in chain.iter().enumerate() {
+        let Some(certificate) = certificate::parse(der) else {
+            println!("{index}: Failed to parse the certificate");
+            continue;
+        };
+        let subject = &certificate.subject;
+        println!("{index}: {subject}");
+        println!("   Issuer: {}", certificate.issuer);
+        println!(
+            "   Valid:  {} until {} UTC",
+            certificate.not_before.format("%Y-%m-%d %H:%M:%S"),
+            certificate.not_after.format("%Y-%m-%d %H:%M:%S"),
+        );
+        if !certificate.names.is_empty() {
+            println!("   Names:  {}", certificate.names.join(", "));
+        }
+
+        let days = (certificate.not_after - now).num_days();
+        if certificate.not_after < now {
+            eprintln!(
+                "Warning: The certificate {subject} expired {} days ago",
+                -days
+            );
+        } else if certificate.not_before > now {
+            eprintln!("Warning: The certificate {subject} is not valid yet");
+        } else if days < EXPIRY_WARNING_DAYS {
+            eprintln!("Warning: The certificate {subject} expires in {days} days");
+        }
+    }
+    Ok(())
+}
+
+pub fn show(
+    client: &Client,
+    mut connection: Connection,
+    broker: &Broker,
+    count: NonZeroUsize,
+    tls_info: bool,
+) -> anyhow::Result<()> {
+    if tls_info {
+        print_tls_info(broker)?;
+    }
+
+    let mut sent = None;
+    let mut received = 0;
+    for notification in connection.iter() {
+        match notification {
+            Ok(Event::Outgoing(Outgoing::Disconnect)) => break,
+            Ok(Event::Outgoing(Outgoing::PingReq)) => sent = Some(Instant::now()),
+            Ok(Event::Incoming(Packet::PingResp)) => {
+                if let Some(sent) = sent.take() {
+                    println!(
+                        "Ping to {broker}: {:.1}ms",
+                        sent.elapsed().as_secs_f64() * 1000.0
+                    );
+                    received += 1;
+                    if received >= count.get() {
+                        client.disconnect()?;
+                    }
+                }
+            }
+            Ok(_) => {}
+            Err(err) => {
+                eprintln!("Connection Error: {err}");
+                sleep(Duration::from_millis(25));
+            }
+        }
+    }
+    Ok(())
+}
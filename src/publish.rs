@@ -1,27 +1,280 @@
-use rumqttc::{Client, Connection};
+use std::io::BufRead;
+use std::path::Path;
+use std::thread::{self, sleep};
+use std::time::{Duration, Instant};
 
-pub fn eventloop(client: &Client, mut connection: Connection, verbose: bool) {
-    for notification in connection.iter() {
-        match notification.expect("connection error") {
-            rumqttc::Event::Outgoing(outgoing) => {
+use anyhow::Context;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use rumqttc::{Client, Connection, QoS, RecvTimeoutError};
+use serde::{Deserialize, Serialize};
+
+use crate::exit::{self, chatter};
+use crate::mqtt::{Inflight, InflightChange};
+
+pub struct Message {
+    pub topic: String,
+    pub qos: QoS,
+    pub retain: bool,
+    pub payload: Vec<u8>,
+}
+
+impl Message {
+    /// Reads the payload from stdin when not given
+    pub fn from_args(topic: String, payload: Option<String>, qos: QoS, retain: bool) -> Self {
+        let payload = payload.map_or_else(
+            || {
+                use std::io::Read;
+                let mut buffer = Vec::new();
+                std::io::stdin()
+                    .read_to_end(&mut buffer)
+                    .expect("Should be able to read the payload from stdin");
+                buffer
+            },
+            String::into_bytes,
+        );
+        Self {
+            topic,
+            qos,
+            retain,
+            payload,
+        }
+    }
+
+    /// The file content is published as it is which is useful for binary payloads like firmware images
+    pub fn from_file(topic: String, path: &Path, qos: QoS, retain: bool) -> anyhow::Result<Self> {
+        let payload =
+            std::fs::read(path).with_context(|| format!("Failed to read {}", path.display()))?;
+        Ok(Self {
+            topic,
+            qos,
+            retain,
+            payload,
+        })
+    }
+
+    /// Replace the payload with its base64 decoded content.
+    ///
+    /// Whitespace like the line breaks of the `base64` command is ignored.
+    pub fn decode_base64(&mut self) -> anyhow::Result<()> {
+        let encoded = self
+            .payload
+            .iter()
+            .copied()
+            .filter(|byte| !byte.is_ascii_whitespace())
+            .collect::<Vec<_>>();
+        self.payload = BASE64
+            .decode(encoded)
+            .context("The payload is not valid base64")?;
+        Ok(())
+    }
+}
+
+#[derive(Deserialize)]
+struct FileEntry {
+    topic: String,
+    /// Strings are published as is, everything else as JSON
+    payload: serde_json::Value,
+    retain: Option<bool>,
+    qos: Option<u8>,
+}
+
+/// Read newline-delimited JSON with one message per line.
+///
+/// Retain and `QoS` can be omitted per line in which case the given defaults are used.
+pub fn read_file(path: &Path, qos: QoS, retain: bool) -> anyhow::Result<Vec<Message>> {
+    let file =
+        std::fs::File::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+    let mut messages = Vec::new();
+    for (index, line) in std::io::BufReader::new(file).lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let line_number = index.saturating_add(1);
+        let entry = serde_json::from_str::<FileEntry>(&line)
+            .with_context(|| format!("Failed to parse line {line_number}"))?;
+        let qos = match entry.qos {
+            Some(qos) => {
+                rumqttc::qos(qos).with_context(|| format!("Invalid QoS on line {line_number}"))?
+            }
+            None => qos,
+        };
+        let payload = match entry.payload {
+            serde_json::Value::String(str) => str.into_bytes(),
+            json => json.to_string().into_bytes(),
+        };
+        messages.push(Message {
+            topic: entry.topic,
+            qos,
+            retain: entry.retain.unwrap_or(retain),
+            payload,
+        });
+    }
+    Ok(messages)
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "snake_case")]
+enum Status {
+    /// At most once has no acknowledgement, it was sent to the broker
+    Sent,
+    /// `PubAck` on at least once, `PubComp` on exactly once
+    Acknowledged,
+    Timeout,
+}
+
+#[derive(Serialize)]
+struct Report<'a> {
+    topic: &'a str,
+    qos: u8,
+    status: Status,
+}
+
+impl Report<'_> {
+    fn print(&self) {
+        let json = serde_json::to_string(self).expect("Should be able to format report as JSON");
+        println!("{json}");
+    }
+}
+
+/// The request channel is bounded so publish while the connection is progressing
+fn spawn_publisher(client: Client, messages: Vec<Message>) {
+    thread::Builder::new()
+        .name("publish".to_owned())
+        .spawn(move || {
+            for message in messages {
+                let result =
+                    client.publish(message.topic, message.qos, message.retain, message.payload);
+                if result.is_err() {
+                    break;
+                }
+            }
+        })
+        .expect("should be able to spawn a thread");
+}
+
+fn print_inflight(inflight: &Inflight) {
+    eprintln!("in flight: {}", inflight.len());
+    for line in inflight.lines() {
+        eprintln!("  {line}");
+    }
+}
+
+/// Publish all messages in order and wait until the broker acknowledged all of them
+pub fn publish(
+    client: &Client,
+    mut connection: Connection,
+    messages: Vec<Message>,
+    timeout: Duration,
+    verbose: bool,
+) -> anyhow::Result<()> {
+    let mut inflight = Inflight::default();
+    for message in &messages {
+        inflight.on_requested(message.topic.clone(), message.qos);
+    }
+    let total = messages.len();
+
+    spawn_publisher(client.clone(), messages);
+
+    // Sent or acknowledged, depending on the QoS
+    let mut succeeded: usize = 0;
+    let start = Instant::now();
+    let mut done = total == 0;
+    if done {
+        client.disconnect()?;
+    }
+    loop {
+        let remaining = timeout.saturating_sub(start.elapsed());
+        let notification = match connection.recv_timeout(remaining) {
+            Ok(notification) => notification,
+            Err(RecvTimeoutError::Disconnected) => break,
+            Err(RecvTimeoutError::Timeout) if done => break,
+            Err(RecvTimeoutError::Timeout) => {
                 if verbose {
-                    println!("outgoing {outgoing:?}");
+                    print_inflight(&inflight);
+                }
+                for (topic, qos) in inflight.pending() {
+                    Report {
+                        topic,
+                        qos: qos as u8,
+                        status: Status::Timeout,
+                    }
+                    .print();
                 }
+                let code = if succeeded == 0 {
+                    exit::Code::Timeout
+                } else {
+                    exit::Code::Partial
+                };
+                return Err(exit::fail(
+                    code,
+                    format!(
+                        "The broker acknowledged {succeeded} of {total} publishes within {} seconds",
+                        timeout.as_secs_f32()
+                    ),
+                ));
+            }
+        };
 
-                if outgoing == rumqttc::Outgoing::Disconnect {
+        let change = match notification {
+            Ok(rumqttc::Event::Outgoing(outgoing)) => {
+                if verbose {
+                    eprintln!("outgoing {outgoing:?}");
+                }
+                if matches!(outgoing, rumqttc::Outgoing::Disconnect) {
                     break;
                 }
+                inflight.on_outgoing(&outgoing)
             }
-            rumqttc::Event::Incoming(packet) => {
+            Ok(rumqttc::Event::Incoming(packet)) => {
                 if verbose {
-                    println!("incoming {packet:?}");
+                    eprintln!("incoming {packet:?}");
                 }
+                inflight.on_incoming(&packet)
+            }
+            Err(err) => {
+                // rumqttc reconnects and resends not yet acknowledged publishes
+                chatter!("Connection Error: {err}");
+                sleep(Duration::from_millis(25));
+                None
+            }
+        };
+        if verbose && change.is_some() {
+            print_inflight(&inflight);
+        }
 
-                if let rumqttc::Packet::PubAck(_) = packet {
-                    // There was published something -> success -> disconnect
-                    client.disconnect().unwrap();
-                }
+        if let Some(InflightChange::Finished { topic, qos }) = change {
+            succeeded = succeeded.saturating_add(1);
+            let status = if qos == QoS::AtMostOnce {
+                Status::Sent
+            } else {
+                Status::Acknowledged
+            };
+            Report {
+                topic: &topic,
+                qos: qos as u8,
+                status,
+            }
+            .print();
+            if !done && inflight.pending().next().is_none() {
+                done = true;
+                client.disconnect()?;
             }
         }
     }
+    Ok(())
+}
+
+#[test]
+fn decode_base64_works() {
+    let mut message = Message {
+        topic: "foo".to_owned(),
+        qos: QoS::AtMostOnce,
+        retain: false,
+        payload: b"AAEC/w==\n".to_vec(),
+    };
+    message.decode_base64().unwrap();
+    assert_eq!(message.payload, [0, 1, 2, 255]);
+    assert!(message.decode_base64().is_err());
 }
@@ -1,11 +1,43 @@
+use std::collections::HashSet;
+use std::num::NonZeroUsize;
 use std::thread::sleep;
 use std::time::Duration;
 
 use rumqttc::{Client, Connection};
+use serde::Serialize;
 
+use crate::exit::{self, chatter};
 use crate::payload::Payload;
 
-pub fn show(client: &Client, mut connection: Connection, ignore_retained: bool, pretty: bool) {
+pub enum Amount {
+    /// Return after the given amount of messages
+    Count(NonZeroUsize),
+    /// Return after each of the given topics received one message
+    PerTopic(HashSet<String>),
+}
+
+impl Amount {
+    const fn is_single(&self) -> bool {
+        matches!(self, Self::Count(count) if count.get() == 1)
+    }
+}
+
+#[derive(Serialize)]
+struct JsonMessage {
+    topic: String,
+    size: usize,
+    payload: Payload,
+}
+
+pub fn show(
+    client: &Client,
+    mut connection: Connection,
+    ignore_retained: bool,
+    pretty: bool,
+    mut amount: Amount,
+) -> anyhow::Result<()> {
+    let single = amount.is_single();
+    let mut messages = Vec::new();
     let mut done = false;
     for notification in connection.iter() {
         match notification {
@@ -21,24 +53,73 @@ pub fn show(client: &Client, mut connection: Connection, ignore_retained: bool,
                 if ignore_retained && publish.retain {
                     continue;
                 }
-                eprintln!("{}", publish.topic);
-                if pretty {
-                    let payload = Payload::unlimited(publish.payload.into());
-                    println!("{payload:#}");
+                if let Amount::PerTopic(remaining) = &mut amount {
+                    if !remaining.remove(&publish.topic) {
+                        // Already got a message of this topic
+                        continue;
+                    }
+                }
+
+                if single {
+                    chatter!("{}", publish.topic);
+                    if pretty {
+                        let payload = Payload::unlimited(publish.payload.into());
+                        println!("{payload:#}");
+                    } else {
+                        use std::io::Write;
+                        std::io::stdout()
+                            .write_all(&publish.payload)
+                            .expect("Should be able to write payload to stdout");
+                    }
                 } else {
-                    use std::io::Write;
-                    std::io::stdout()
-                        .write_all(&publish.payload)
-                        .expect("Should be able to write payload to stdout");
+                    messages.push(JsonMessage {
+                        topic: publish.topic,
+                        size: publish.payload.len(),
+                        payload: Payload::unlimited(publish.payload.into()),
+                    });
+                }
+
+                done = match &amount {
+                    Amount::Count(count) => messages.len() >= count.get() || single,
+                    Amount::PerTopic(remaining) => remaining.is_empty(),
                 };
-                done = true;
-                client.disconnect().unwrap();
+                if done {
+                    client.disconnect().unwrap();
+                }
             }
             Ok(rumqttc::Event::Incoming(_)) => {}
             Err(err) => {
-                eprintln!("Connection Error: {err}");
+                chatter!("Connection Error: {err}");
                 sleep(Duration::from_millis(25));
             }
         }
     }
+
+    if !single {
+        let json = if pretty {
+            serde_json::to_string_pretty(&messages)
+        } else {
+            serde_json::to_string(&messages)
+        }
+        .expect("Should be able to format messages as JSON");
+        println!("{json}");
+    }
+
+    // Disconnected by a signal before everything arrived
+    if done {
+        Ok(())
+    } else if messages.is_empty() {
+        Err(exit::fail(
+            exit::Code::NoMatch,
+            "Disconnected before a message was received",
+        ))
+    } else {
+        Err(exit::fail(
+            exit::Code::Partial,
+            format!(
+                "Disconnected after receiving only {} messages",
+                messages.len()
+            ),
+        ))
+    }
 }
@@ -0,0 +1,111 @@
+use std::time::Duration;
+
+use rumqttc::v5::mqttbytes::v5::{Packet, PublishProperties};
+use rumqttc::v5::mqttbytes::QoS;
+use rumqttc::v5::{AsyncClient, Event, EventLoop};
+
+use crate::payload::Payload;
+
+pub struct Request {
+    pub topic: String,
+    pub payload: Vec<u8>,
+    pub response_topic: Option<String>,
+    pub qos: QoS,
+}
+
+/// Publish the request and wait for the response with the same correlation data.
+///
+/// Returns the payload of the response.
+async fn request(
+    client: &AsyncClient,
+    eventloop: &mut EventLoop,
+    request: Request,
+) -> anyhow::Result<Vec<u8>> {
+    let response_topic = request
+        .response_topic
+        .unwrap_or_else(|| format!("mqttui/response/{:x}", rand::random::<u64>()));
+    let correlation_data = rand::random::<u128>().to_be_bytes().to_vec();
+    client.subscribe(&response_topic, request.qos).await?;
+
+    let mut published = false;
+    loop {
+        let event = match eventloop.poll().await {
+            Ok(event) => event,
+            Err(err) => {
+                // rumqttc reconnects
+                eprintln!("Connection Error: {err}");
+                tokio::time::sleep(Duration::from_millis(25)).await;
+                continue;
+            }
+        };
+        match event {
+            // Only publish once the response can be received
+            Event::Incoming(Packet::SubAck(_)) if !published => {
+                published = true;
+                client
+                    .publish_with_properties(
+                        request.topic.clone(),
+                        request.qos,
+                        false,
+                        request.payload.clone(),
+                        PublishProperties {
+                            response_topic: Some(response_topic.clone()),
+                            correlation_data: Some(correlation_data.clone().into()),
+                            ..PublishProperties::default()
+                        },
+                    )
+                    .await?;
+            }
+            Event::Incoming(Packet::Publish(publish)) => {
+                // Responders should return the correlation data but not all do
+                let correlates = publish
+                    .properties
+                    .as_ref()
+                    .and_then(|properties| properties.correlation_data.as_ref())
+                    .map_or(true, |data| *data == correlation_data);
+                if publish.topic == response_topic.as_bytes() && !publish.retain && correlates {
+                    return Ok(publish.payload.to_vec());
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+pub async fn show(
+    client: AsyncClient,
+    mut eventloop: EventLoop,
+    request: Request,
+    timeout: Duration,
+    pretty: bool,
+) -> anyhow::Result<()> {
+    let topic = request.topic.clone();
+    let result =
+        tokio::time::timeout(timeout, self::request(&client, &mut eventloop, request)).await;
+
+    // Send the disconnect to the broker
+    client.disconnect().await?;
+    let _ = tokio::time::timeout(Duration::from_secs(1), async {
+        while let Ok(event) = eventloop.poll().await {
+            if matches!(event, Event::Outgoing(rumqttc::Outgoing::Disconnect)) {
+                break;
+            }
+        }
+    })
+    .await;
+
+    let Ok(payload) = result else {
+        anyhow::bail!(
+            "No response to the request on {topic} within {} seconds",
+            timeout.as_secs_f32()
+        );
+    };
+    let payload = payload?;
+    if pretty {
+        println!("{:#}", Payload::unlimited(payload));
+    } else {
+        use std::io::Write;
+        std::io::stdout().write_all(&payload)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,296 @@
+use std::io::{BufRead, BufReader, Write};
+use std::os::unix::fs::FileTypeExt;
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, RwLock};
+use std::thread::{self, sleep};
+use std::time::Duration;
+
+use anyhow::Context;
+use rumqttc::{Client, Connection};
+use serde::Deserialize;
+use serde_json::json;
+
+use crate::exit::chatter;
+use crate::interactive::{HistoryLimit, MqttHistory};
+use crate::mqtt::{HistoryEntry, Subscriptions, Time};
+use crate::payload::Decoding;
+
+/// Clients served at the same time. Further clients are refused.
+const MAX_CLIENTS: usize = 64;
+
+type History = Arc<RwLock<MqttHistory>>;
+
+/// One request per line, answered with one JSON line
+#[derive(Deserialize)]
+#[serde(tag = "command", rename_all = "kebab-case")]
+enum Request {
+    /// All topics with messages
+    Topics,
+    /// All messages of the topic
+    Get { topic: String },
+    Publish {
+        topic: String,
+        payload: String,
+        #[serde(default)]
+        qos: u8,
+        #[serde(default)]
+        retain: bool,
+    },
+}
+
+fn process(history: &History, client: &Client, line: &str) -> anyhow::Result<serde_json::Value> {
+    let request = serde_json::from_str::<Request>(line).context("Invalid request")?;
+    let result = match request {
+        Request::Topics => json!(history
+            .read()
+            .expect("mqtt thread panicked")
+            .get_all_topics()),
+        Request::Get { topic } => history
+            .read()
+            .expect("mqtt thread panicked")
+            .get(&topic)
+            .map(|entries| entries.iter().map(entry_json).collect::<Vec<_>>())
+            .map(serde_json::Value::Array)
+            .with_context(|| format!("No messages on topic {topic}"))?,
+        Request::Publish {
+            topic,
+            payload,
+            qos,
+            retain,
+        } => {
+            client.publish(topic, rumqttc::qos(qos)?, retain, payload)?;
+            serde_json::Value::Null
+        }
+    };
+    Ok(result)
+}
+
+fn entry_json(entry: &HistoryEntry) -> serde_json::Value {
+    json!({
+        "time": entry.time,
+        "qos": entry.qos as u8,
+        "size": entry.payload_size,
+        "payload": *entry.payload,
+    })
+}
+
+fn handle_client(history: &History, client: &Client, stream: UnixStream) -> std::io::Result<()> {
+    let mut writer = stream.try_clone()?;
+    for line in BufReader::new(stream).lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let response = match process(history, client, &line) {
+            Ok(result) => json!({ "ok": true, "result": result }),
+            Err(err) => json!({ "ok": false, "error": format!("{err:#}") }),
+        };
+        writeln!(writer, "{response}")?;
+    }
+    Ok(())
+}
+
+fn mqtt_logic(
+    client: &Client,
+    mut connection: Connection,
+    mut subscriptions: Subscriptions,
+    decoding: &Decoding,
+    history: &History,
+) {
+    for notification in connection.iter() {
+        match notification {
+            Ok(rumqttc::Event::Outgoing(rumqttc::Outgoing::Subscribe(pkid))) => {
+                subscriptions.on_outgoing(pkid);
+            }
+            Ok(rumqttc::Event::Outgoing(rumqttc::Outgoing::Disconnect)) => break,
+            // The first ConnAck was handled when connecting
+            Ok(rumqttc::Event::Incoming(rumqttc::Packet::ConnAck(connack)))
+                if !connack.session_present =>
+            {
+                if let Err(err) = subscriptions.resubscribe(client) {
+                    chatter!("Failed to subscribe again after reconnecting: {err}");
+                }
+            }
+            Ok(rumqttc::Event::Incoming(rumqttc::Packet::SubAck(suback))) => {
+                if let Some((topic, false)) = subscriptions.on_suback(&suback) {
+                    chatter!("The broker rejected the subscription to {topic}");
+                }
+            }
+            Ok(rumqttc::Event::Incoming(rumqttc::Packet::Publish(publish))) => {
+                if publish.dup {
+                    continue;
+                }
+                let (payload_size, payload) =
+                    decoding.decode(&publish.topic, publish.payload.into(), usize::MAX, |err| {
+                        chatter!("{err:#}");
+                    });
+                let entry = HistoryEntry {
+                    qos: publish.qos,
+                    time: Time::new_now(publish.retain),
+                    payload_size,
+                    payload: Arc::new(payload),
+                };
+                history
+                    .write()
+                    .expect("mqtt thread panicked")
+                    .add(publish.topic, entry);
+            }
+            Ok(_) => {}
+            Err(err) => {
+                chatter!("Connection Error: {err}");
+                sleep(Duration::from_millis(25));
+            }
+        }
+    }
+}
+
+pub fn serve(
+    client: &Client,
+    connection: Connection,
+    subscriptions: Subscriptions,
+    decoding: Decoding,
+    socket: &Path,
+    history_limit: HistoryLimit,
+) -> anyhow::Result<()> {
+    // Remove a leftover socket of a previous run. Other files are not touched.
+    if std::fs::symlink_metadata(socket).is_ok_and(|meta| meta.file_type().is_socket()) {
+        std::fs::remove_file(socket)
+            .with_context(|| format!("Failed to remove old socket {}", socket.display()))?;
+    }
+    let listener = UnixListener::bind(socket)
+        .with_context(|| format!("Failed to listen on socket {}", socket.display()))?;
+
+    let history = Arc::new(RwLock::new(MqttHistory::with_limit(Some(history_limit))));
+    let disconnected = Arc::new(AtomicBool::new(false));
+    {
+        let client = client.clone();
+        let history = Arc::clone(&history);
+        let disconnected = Arc::clone(&disconnected);
+        let socket = socket.to_path_buf();
+        thread::Builder::new()
+            .name("mqtt connection".to_owned())
+            .spawn(move || {
+                mqtt_logic(&client, connection, subscriptions, &decoding, &history);
+                disconnected.store(true, Ordering::Relaxed);
+                // Wake up the accept loop so it notices the end of the connection
+                _ = UnixStream::connect(socket);
+            })
+            .expect("should be able to spawn a thread");
+    }
+
+    let clients = Arc::new(AtomicUsize::new(0));
+    for stream in listener.incoming() {
+        if disconnected.load(Ordering::Relaxed) {
+            break;
+        }
+        let mut stream = match stream {
+            Ok(stream) => stream,
+            // Like running out of file descriptors. Retrying right away would only spin.
+            Err(err) => {
+                chatter!("Socket Error: {err}");
+                sleep(Duration::from_millis(100));
+                continue;
+            }
+        };
+        if clients.load(Ordering::Relaxed) >= MAX_CLIENTS {
+            let response = json!({ "ok": false, "error": "Too many clients are connected" });
+            _ = writeln!(stream, "{response}");
+            continue;
+        }
+        clients.fetch_add(1, Ordering::Relaxed);
+        let history = Arc::clone(&history);
+        let client = client.clone();
+        let clients = Arc::clone(&clients);
+        thread::Builder::new()
+            .name("serve client".to_owned())
+            .spawn(move || {
+                if let Err(err) = handle_client(&history, &client, stream) {
+                    chatter!("Socket Error: {err}");
+                }
+                clients.fetch_sub(1, Ordering::Relaxed);
+            })
+            .expect("should be able to spawn a thread");
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+fn example() -> (History, Client, Connection) {
+    let history = Arc::new(RwLock::new(MqttHistory::new()));
+    history.write().unwrap().add(
+        "foo/bar".to_owned(),
+        HistoryEntry::example("42", Time::Retained),
+    );
+    let (client, connection) =
+        Client::new(rumqttc::MqttOptions::new("test", "localhost", 1883), 10);
+    (history, client, connection)
+}
+
+#[test]
+fn topics_works() {
+    let (history, client, _connection) = example();
+    let result = process(&history, &client, r#"{"command":"topics"}"#).unwrap();
+    assert_eq!(result, json!(["foo/bar"]));
+}
+
+#[test]
+fn get_works() {
+    let (history, client, _connection) = example();
+    let result = process(&history, &client, r#"{"command":"get","topic":"foo/bar"}"#).unwrap();
+    assert_eq!(
+        result,
+        json!([{ "time": null, "qos": 0, "size": 2, "payload": 42 }])
+    );
+}
+
+#[test]
+fn get_unknown_topic_fails() {
+    let (history, client, _connection) = example();
+    let result = process(&history, &client, r#"{"command":"get","topic":"nope"}"#);
+    assert!(result.is_err());
+}
+
+#[test]
+fn publish_works() {
+    let (history, client, _connection) = example();
+    let result = process(
+        &history,
+        &client,
+        r#"{"command":"publish","topic":"foo","payload":"bar","retain":true}"#,
+    )
+    .unwrap();
+    assert_eq!(result, serde_json::Value::Null);
+}
+
+#[test]
+fn invalid_request_fails() {
+    let (history, client, _connection) = example();
+    assert!(process(&history, &client, r#"{"command":"nope"}"#).is_err());
+    assert!(process(&history, &client, "not json").is_err());
+}
+
+#[test]
+fn history_is_limited() {
+    let history = Arc::new(RwLock::new(MqttHistory::with_limit(Some(HistoryLimit {
+        messages: 2,
+        compaction: false,
+    }))));
+    for value in 0..5 {
+        history.write().unwrap().add(
+            "foo".to_owned(),
+            HistoryEntry::example(&value.to_string(), Time::Retained),
+        );
+    }
+    let (client, _connection) =
+        Client::new(rumqttc::MqttOptions::new("test", "localhost", 1883), 10);
+    let result = process(&history, &client, r#"{"command":"get","topic":"foo"}"#).unwrap();
+    let payloads = result
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|entry| entry["payload"].clone())
+        .collect::<Vec<_>>();
+    assert_eq!(payloads, [json!(3), json!(4)]);
+}
@@ -0,0 +1,45 @@
+use rumqttc::Client;
+
+/// Disconnect from the broker on SIGINT or SIGTERM instead of leaving the connection dangling.
+///
+/// The subcommands end on the outgoing disconnect.
+/// When that takes longer than the grace period or a second signal arrives the process is terminated.
+#[cfg(unix)]
+pub fn disconnect_on_signal(client: Client) -> anyhow::Result<()> {
+    use std::thread::{self, sleep};
+    use std::time::Duration;
+
+    use signal_hook::consts::{SIGINT, SIGTERM};
+    use signal_hook::iterator::Signals;
+
+    /// Time to finish outstanding work after the disconnect was requested
+    const GRACE_PERIOD: Duration = Duration::from_secs(5);
+
+    let mut signals = Signals::new([SIGINT, SIGTERM])?;
+    thread::Builder::new()
+        .name("signals".to_owned())
+        .spawn(move || {
+            let mut signals = signals.forever();
+            let Some(signal) = signals.next() else {
+                return;
+            };
+            _ = client.disconnect();
+            thread::Builder::new()
+                .name("grace period".to_owned())
+                .spawn(move || {
+                    sleep(GRACE_PERIOD);
+                    std::process::exit(128 + signal);
+                })
+                .expect("should be able to spawn a thread");
+            if let Some(signal) = signals.next() {
+                std::process::exit(128 + signal);
+            }
+        })?;
+    Ok(())
+}
+
+#[cfg(not(unix))]
+#[allow(clippy::needless_pass_by_value, clippy::unnecessary_wraps)]
+pub fn disconnect_on_signal(_client: Client) -> anyhow::Result<()> {
+    Ok(())
+}
@@ -0,0 +1,296 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::thread::{self, sleep};
+use std::time::{Duration, Instant};
+
+use anyhow::Context;
+use rand::Rng;
+use rumqttc::{Client, Connection, QoS};
+use serde::Deserialize;
+
+use crate::payload::Payload;
+
+/// Replaced by the number of the device when there are multiple instances
+#[allow(clippy::literal_string_with_formatting_args)]
+const INSTANCE_PLACEHOLDER: &str = "{instance}";
+
+const fn default_decimals() -> usize {
+    1
+}
+
+const fn default_instances() -> usize {
+    1
+}
+
+/// Creates the values which are put into the templates
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case", deny_unknown_fields)]
+enum Generator {
+    /// Changes by up to the step on every message while staying within min and max
+    RandomWalk {
+        start: f64,
+        step: f64,
+        min: f64,
+        max: f64,
+        #[serde(default = "default_decimals")]
+        decimals: usize,
+    },
+    Random {
+        min: f64,
+        max: f64,
+        #[serde(default = "default_decimals")]
+        decimals: usize,
+    },
+    /// Repeats the values in order
+    Cycle(Vec<String>),
+}
+
+impl Generator {
+    fn validate(&self) -> anyhow::Result<()> {
+        match self {
+            Self::RandomWalk { step, min, max, .. } => {
+                anyhow::ensure!(*step >= 0.0, "The step can not be negative");
+                anyhow::ensure!(min <= max, "The min can not be greater than the max");
+            }
+            Self::Random { min, max, .. } => {
+                anyhow::ensure!(min <= max, "The min can not be greater than the max");
+            }
+            Self::Cycle(values) => {
+                anyhow::ensure!(!values.is_empty(), "There have to be values to cycle");
+            }
+        }
+        Ok(())
+    }
+
+    fn next(&mut self, rng: &mut impl Rng) -> String {
+        match self {
+            Self::RandomWalk {
+                start,
+                step,
+                min,
+                max,
+                decimals,
+            } => {
+                let value = *start;
+                *start = (*start + rng.gen_range(-*step..=*step)).clamp(*min, *max);
+                format!("{value:.decimals$}")
+            }
+            Self::Random { min, max, decimals } => {
+                let value = rng.gen_range(*min..=*max);
+                format!("{value:.decimals$}")
+            }
+            Self::Cycle(values) => {
+                let value = values.first().cloned().unwrap_or_default();
+                values.rotate_left(1);
+                value
+            }
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct DeviceConfig {
+    topic: String,
+    /// Seconds between the messages
+    interval: f32,
+    payload: String,
+    #[serde(default)]
+    values: BTreeMap<String, Generator>,
+    #[serde(default)]
+    retain: bool,
+    #[serde(default)]
+    qos: u8,
+    /// Amount of devices created from this config. `{instance}` is replaced with their number.
+    #[serde(default = "default_instances")]
+    instances: usize,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Scenario {
+    devices: Vec<DeviceConfig>,
+}
+
+pub struct Device {
+    topic: String,
+    payload: String,
+    values: BTreeMap<String, Generator>,
+    retain: bool,
+    qos: QoS,
+    interval: Duration,
+    next: Instant,
+}
+
+impl Device {
+    fn render(&mut self, rng: &mut impl Rng) -> (String, String) {
+        let mut topic = self.topic.clone();
+        let mut payload = self.payload.clone();
+        for (name, generator) in &mut self.values {
+            let placeholder = format!("{{{name}}}");
+            let value = generator.next(rng);
+            topic = topic.replace(&placeholder, &value);
+            payload = payload.replace(&placeholder, &value);
+        }
+        (topic, payload)
+    }
+}
+
+fn devices(scenario: Scenario, start: Instant) -> anyhow::Result<Vec<Device>> {
+    anyhow::ensure!(
+        !scenario.devices.is_empty(),
+        "The scenario contains no devices"
+    );
+    let mut devices = Vec::new();
+    for config in scenario.devices {
+        let topic = &config.topic;
+        anyhow::ensure!(
+            config.interval > 0.0,
+            "The interval of {topic} has to be positive"
+        );
+        let qos = rumqttc::qos(config.qos).with_context(|| format!("Invalid QoS of {topic}"))?;
+        for (name, generator) in &config.values {
+            generator
+                .validate()
+                .with_context(|| format!("Invalid value {name} of {topic}"))?;
+            let placeholder = format!("{{{name}}}");
+            anyhow::ensure!(
+                config.topic.contains(&placeholder) || config.payload.contains(&placeholder),
+                "The value {name} of {topic} is not used as {placeholder} in the topic or payload"
+            );
+        }
+        let interval = Duration::from_secs_f32(config.interval);
+        for instance in 1..=config.instances {
+            let number = instance.to_string();
+            // Spread the instances over the interval to not publish them all at once
+            #[allow(clippy::cast_precision_loss)]
+            let offset = interval.mul_f64((instance - 1) as f64 / config.instances as f64);
+            devices.push(Device {
+                topic: config.topic.replace(INSTANCE_PLACEHOLDER, &number),
+                payload: config.payload.replace(INSTANCE_PLACEHOLDER, &number),
+                values: config.values.clone(),
+                retain: config.retain,
+                qos,
+                interval,
+                next: start + offset,
+            });
+        }
+    }
+    Ok(devices)
+}
+
+pub fn read(path: &Path) -> anyhow::Result<Vec<Device>> {
+    let content = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+    let scenario = serde_json::from_str(&content)
+        .with_context(|| format!("Invalid scenario {}", path.display()))?;
+    devices(scenario, Instant::now())
+}
+
+/// Publish the messages of the devices when they are due until the client disconnects
+fn spawn_publisher(client: Client, mut devices: Vec<Device>, verbose: bool) {
+    thread::Builder::new()
+        .name("simulate".to_owned())
+        .spawn(move || {
+            let mut rng = rand::thread_rng();
+            loop {
+                let device = devices
+                    .iter_mut()
+                    .min_by_key(|device| device.next)
+                    .expect("Scenario contains devices");
+                sleep(device.next.saturating_duration_since(Instant::now()));
+                device.next += device.interval;
+                let (topic, payload) = device.render(&mut rng);
+                if verbose {
+                    let size = payload.len();
+                    let shown = Payload::unlimited(payload.clone().into_bytes());
+                    println!("{topic:50} Payload({size:>3}): {shown}");
+                }
+                if client
+                    .publish(topic, device.qos, device.retain, payload)
+                    .is_err()
+                {
+                    break;
+                }
+            }
+        })
+        .expect("should be able to spawn a thread");
+}
+
+pub fn simulate(client: &Client, mut connection: Connection, devices: Vec<Device>, verbose: bool) {
+    eprintln!("Simulating {} devices until stopped", devices.len());
+    spawn_publisher(client.clone(), devices, verbose);
+    for notification in connection.iter() {
+        match notification {
+            Ok(rumqttc::Event::Outgoing(rumqttc::Outgoing::Disconnect)) => break,
+            Ok(_) => {}
+            Err(err) => {
+                eprintln!("Connection Error: {err}");
+                sleep(Duration::from_millis(25));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+fn scenario(json: &str) -> anyhow::Result<Vec<Device>> {
+    devices(serde_json::from_str(json)?, Instant::now())
+}
+
+#[test]
+fn scenario_renders_templates() {
+    let mut devices = scenario(
+        r#"{"devices": [{
+            "topic": "sim/{instance}/lamp",
+            "interval": 2,
+            "payload": "{\"state\": \"{state}\"}",
+            "values": {"state": {"cycle": ["on", "off"]}},
+            "instances": 2
+        }]}"#,
+    )
+    .unwrap();
+    assert_eq!(devices.len(), 2);
+    assert!(devices[0].next < devices[1].next);
+    let mut rng = rand::thread_rng();
+    assert_eq!(
+        devices[1].render(&mut rng),
+        ("sim/2/lamp".to_owned(), r#"{"state": "on"}"#.to_owned())
+    );
+    assert_eq!(devices[1].render(&mut rng).1, r#"{"state": "off"}"#);
+    assert_eq!(devices[1].render(&mut rng).1, r#"{"state": "on"}"#);
+}
+
+#[test]
+fn random_walk_stays_within_bounds() {
+    let mut generator = Generator::RandomWalk {
+        start: 21.0,
+        step: 5.0,
+        min: 20.0,
+        max: 22.0,
+        decimals: 2,
+    };
+    let mut rng = rand::thread_rng();
+    assert_eq!(generator.next(&mut rng), "21.00");
+    for _ in 0..100 {
+        let value = generator.next(&mut rng).parse::<f64>().unwrap();
+        assert!((20.0..=22.0).contains(&value), "{value}");
+    }
+}
+
+#[test]
+fn scenario_errors() {
+    assert!(scenario(r#"{"devices": []}"#).is_err());
+    assert!(scenario(r#"{"devices": [{"topic": "a", "interval": 0, "payload": "1"}]}"#).is_err());
+    assert!(scenario(
+        r#"{"devices": [{"topic": "a", "interval": 1, "payload": "1", "values": {"unused": {"cycle": ["x"]}}}]}"#
+    )
+    .is_err());
+    assert!(
+        scenario(r#"{"devices": [{"topic": "a", "interval": 1, "payload": "1", "qos": 3}]}"#)
+            .is_err()
+    );
+    assert!(scenario(
+        r#"{"devices": [{"topic": "a", "interval": 1, "payload": "{x}", "values": {"x": {"random": {"min": 2, "max": 1}}}}]}"#
+    )
+    .is_err());
+}
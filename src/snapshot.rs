@@ -0,0 +1,149 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::time::Duration;
+
+use anyhow::Context;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use rumqttc::{Client, Connection, QoS};
+use serde::{Deserialize, Serialize};
+
+use crate::diff::collect_retained;
+use crate::publish::Message;
+
+/// Retained payloads by their topic as stored in a snapshot file
+pub type Snapshot = BTreeMap<String, StoredPayload>;
+
+/// Payloads are stored as text when they are valid UTF-8 and base64 encoded otherwise
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum StoredPayload {
+    Text(String),
+    Binary { base64: String },
+}
+
+impl StoredPayload {
+    pub fn new(payload: Vec<u8>) -> Self {
+        String::from_utf8(payload).map_or_else(
+            |err| Self::Binary {
+                base64: BASE64.encode(err.into_bytes()),
+            },
+            Self::Text,
+        )
+    }
+
+    pub fn to_bytes(&self) -> anyhow::Result<Vec<u8>> {
+        match self {
+            Self::Text(text) => Ok(text.as_bytes().to_vec()),
+            Self::Binary { base64 } => BASE64
+                .decode(base64)
+                .context("Binary payload is not valid base64"),
+        }
+    }
+}
+
+pub fn read(path: &Path) -> anyhow::Result<Snapshot> {
+    let content = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read snapshot {}", path.display()))?;
+    serde_json::from_str(&content).with_context(|| format!("Invalid snapshot {}", path.display()))
+}
+
+/// Retained messages to publish in order to restore the snapshot, optionally below the prefix
+pub fn restore_messages(snapshot: Snapshot, prefix: Option<&str>) -> anyhow::Result<Vec<Message>> {
+    let prefix = prefix.map(|prefix| prefix.trim_end_matches('/'));
+    snapshot
+        .into_iter()
+        .map(|(topic, payload)| {
+            let payload = payload
+                .to_bytes()
+                .with_context(|| format!("Invalid payload of {topic}"))?;
+            let topic = prefix.map_or_else(|| topic.clone(), |prefix| format!("{prefix}/{topic}"));
+            Ok(Message {
+                topic,
+                qos: QoS::AtLeastOnce,
+                retain: true,
+                payload,
+            })
+        })
+        .collect()
+}
+
+/// Collect the retained messages until none arrived for the settle duration and write them as JSON
+pub fn snapshot(
+    client: &Client,
+    connection: Connection,
+    settle: Duration,
+    out: Option<&Path>,
+) -> anyhow::Result<()> {
+    let snapshot = collect_retained(client, connection, settle)
+        .into_iter()
+        .map(|(topic, payload)| (topic, StoredPayload::new(payload)))
+        .collect::<Snapshot>();
+    let json =
+        serde_json::to_string_pretty(&snapshot).expect("Should be able to format snapshot as JSON");
+    if let Some(out) = out {
+        std::fs::write(out, json + "\n")
+            .with_context(|| format!("Failed to write snapshot to {}", out.display()))?;
+        eprintln!(
+            "Saved {} retained topics to {}",
+            snapshot.len(),
+            out.display()
+        );
+    } else {
+        println!("{json}");
+    }
+    Ok(())
+}
+
+#[test]
+fn stored_payload_roundtrip() {
+    let snapshot = Snapshot::from([
+        ("text".to_owned(), StoredPayload::new(b"21.5".to_vec())),
+        ("binary".to_owned(), StoredPayload::new(vec![0xff, 0x00])),
+    ]);
+    let json = serde_json::to_string(&snapshot).unwrap();
+    assert_eq!(json, r#"{"binary":{"base64":"/wA="},"text":"21.5"}"#);
+    let parsed = serde_json::from_str::<Snapshot>(&json).unwrap();
+    assert_eq!(parsed, snapshot);
+    assert_eq!(parsed["binary"].to_bytes().unwrap(), [0xff, 0x00]);
+    assert_eq!(parsed["text"].to_bytes().unwrap(), b"21.5");
+}
+
+#[test]
+fn restore_messages_with_prefix() {
+    let snapshot = Snapshot::from([
+        ("foo/bar".to_owned(), StoredPayload::Text("A".to_owned())),
+        (
+            "foo/baz".to_owned(),
+            StoredPayload::Binary {
+                base64: "AQI=".to_owned(),
+            },
+        ),
+    ]);
+    let messages = restore_messages(snapshot, Some("backup/")).unwrap();
+    let messages = messages
+        .iter()
+        .map(|message| {
+            (
+                message.topic.as_str(),
+                message.payload.as_slice(),
+                message.retain,
+            )
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        messages,
+        [
+            ("backup/foo/bar", b"A".as_slice(), true),
+            ("backup/foo/baz", [1, 2].as_slice(), true),
+        ]
+    );
+
+    let invalid = Snapshot::from([(
+        "foo".to_owned(),
+        StoredPayload::Binary {
+            base64: "!".to_owned(),
+        },
+    )]);
+    assert!(restore_messages(invalid, None).is_err());
+}
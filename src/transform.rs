@@ -0,0 +1,87 @@
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+use anyhow::Context;
+
+/// External command every received payload is piped through before it is displayed.
+///
+/// The payload is passed via stdin and the command's stdout is used as the new payload.
+/// The topic of the message is available to the command via the `MQTT_TOPIC` environment variable.
+#[derive(Debug, Clone)]
+pub struct Transform {
+    command: String,
+}
+
+impl Transform {
+    pub const fn new(command: String) -> Self {
+        Self { command }
+    }
+
+    fn shell(&self) -> Command {
+        #[cfg(windows)]
+        {
+            let mut command = Command::new("cmd");
+            command.arg("/C").arg(&self.command);
+            command
+        }
+        #[cfg(not(windows))]
+        {
+            let mut command = Command::new("sh");
+            command.arg("-c").arg(&self.command);
+            command
+        }
+    }
+
+    pub fn run(&self, topic: &str, payload: &[u8]) -> anyhow::Result<Vec<u8>> {
+        let mut child = self
+            .shell()
+            .env("MQTT_TOPIC", topic)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .with_context(|| format!("Failed to run transform command {}", self.command))?;
+
+        let mut stdin = child.stdin.take().expect("stdin should be piped");
+        // Write in parallel as the command might fill its stdout before reading all of stdin
+        let output = std::thread::scope(|scope| {
+            scope.spawn(move || {
+                // Commands are free to not read their stdin
+                _ = stdin.write_all(payload);
+            });
+            child.wait_with_output()
+        })?;
+
+        anyhow::ensure!(
+            output.status.success(),
+            "Transform command failed with {}: {}",
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim(),
+        );
+        Ok(output.stdout)
+    }
+}
+
+#[cfg(unix)]
+#[test]
+fn transforms_payload() {
+    let transform = Transform::new("tr a-z A-Z".to_owned());
+    let result = transform.run("foo", b"Hello World").unwrap();
+    assert_eq!(result, b"HELLO WORLD");
+}
+
+#[cfg(unix)]
+#[test]
+fn provides_topic() {
+    let transform = Transform::new("printf %s \"$MQTT_TOPIC\"".to_owned());
+    let result = transform.run("foo/bar", b"").unwrap();
+    assert_eq!(result, b"foo/bar");
+}
+
+#[cfg(unix)]
+#[test]
+fn failing_command_errors() {
+    let transform = Transform::new("echo nope >&2; exit 1".to_owned());
+    let error = transform.run("foo", b"").unwrap_err().to_string();
+    assert!(error.contains("nope"), "{error}");
+}